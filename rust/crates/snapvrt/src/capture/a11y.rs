@@ -0,0 +1,116 @@
+use std::collections::HashMap;
+
+use anyhow::{Context, Result};
+use serde_json::{Value, json};
+
+use crate::cdp::CdpConnection;
+
+/// Text runs: their content already shows in the screenshot (and usually
+/// in the parent's name), so they are left out of the tree.
+const TEXT_ROLES: [&str; 3] = ["StaticText", "InlineTextBox", "LineBreak"];
+
+/// Layout wrappers, left out unless named (their children are kept).
+const LAYOUT_ROLES: [&str; 2] = ["generic", "none"];
+
+/// Node properties worth tracking: ARIA states and relations that change
+/// behavior without necessarily changing pixels.
+const PROPERTIES: [&str; 14] = [
+    "checked",
+    "disabled",
+    "expanded",
+    "haspopup",
+    "invalid",
+    "level",
+    "modal",
+    "multiselectable",
+    "pressed",
+    "readonly",
+    "required",
+    "selected",
+    "valuemax",
+    "valuemin",
+];
+
+/// The page's accessibility tree as indented text, one node per line:
+/// `role "name" [value] property=value...`.
+pub async fn snapshot(conn: &mut CdpConnection) -> Result<String> {
+    let result = conn
+        .call("Accessibility.getFullAXTree", json!({}))
+        .await
+        .context("Failed to read the accessibility tree")?;
+    let nodes = result["nodes"]
+        .as_array()
+        .context("Unexpected Accessibility.getFullAXTree result")?;
+    Ok(format_tree(nodes))
+}
+
+fn format_tree(nodes: &[Value]) -> String {
+    let by_id: HashMap<&str, &Value> = nodes
+        .iter()
+        .filter_map(|n| Some((n["nodeId"].as_str()?, n)))
+        .collect();
+    let mut out = String::new();
+    for root in nodes.iter().filter(|n| n.get("parentId").is_none()) {
+        write_node(root, 0, &by_id, &mut out);
+    }
+    out
+}
+
+fn write_node(node: &Value, depth: usize, by_id: &HashMap<&str, &Value>, out: &mut String) {
+    let role = node["role"]["value"].as_str().unwrap_or_default();
+    let name = node["name"]["value"].as_str().unwrap_or_default();
+    let hidden = node["ignored"].as_bool().unwrap_or(false)
+        || TEXT_ROLES.contains(&role)
+        || (LAYOUT_ROLES.contains(&role) && name.is_empty());
+    let shown = !hidden;
+    if shown {
+        out.push_str(&"  ".repeat(depth));
+        out.push_str(role);
+        if !name.is_empty() {
+            out.push_str(&format!(" {name:?}"));
+        }
+        if let Some(value) = node["value"]["value"].as_str().filter(|v| !v.is_empty()) {
+            out.push_str(&format!(" [{value:?}]"));
+        }
+        for property in node["properties"].as_array().into_iter().flatten() {
+            let key = property["name"].as_str().unwrap_or_default();
+            if PROPERTIES.contains(&key) {
+                let value = &property["value"]["value"];
+                match value.as_str() {
+                    Some(s) => out.push_str(&format!(" {key}={s}")),
+                    None => out.push_str(&format!(" {key}={value}")),
+                }
+            }
+        }
+        out.push('\n');
+    }
+    let child_depth = if shown { depth + 1 } else { depth };
+    for child in node["childIds"].as_array().into_iter().flatten() {
+        if let Some(child) = child.as_str().and_then(|id| by_id.get(id)) {
+            write_node(child, child_depth, by_id, out);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tree_skips_ignored_and_layout_nodes() {
+        let nodes = json!([
+            {"nodeId": "1", "role": {"value": "RootWebArea"}, "name": {"value": "Button"}, "childIds": ["2"]},
+            {"nodeId": "2", "parentId": "1", "role": {"value": "generic"}, "childIds": ["3", "4"]},
+            {"nodeId": "3", "parentId": "2", "role": {"value": "button"}, "name": {"value": "Save"},
+             "properties": [{"name": "disabled", "value": {"value": true}}, {"name": "focusable", "value": {"value": true}}],
+             "childIds": ["5"]},
+            {"nodeId": "4", "parentId": "2", "ignored": true, "role": {"value": "none"}},
+            {"nodeId": "5", "parentId": "3", "role": {"value": "StaticText"}, "name": {"value": "Save"}}
+        ]);
+        let text = format_tree(nodes.as_array().unwrap());
+        assert_eq!(
+            text,
+            "RootWebArea \"Button\"\n  button \"Save\" disabled=true\n"
+        );
+    }
+}
@@ -0,0 +1,70 @@
+use std::sync::{Mutex, OnceLock};
+
+use super::environment::Environment;
+
+/// What one capture worker is doing right now.
+#[derive(Clone, Debug)]
+pub struct WorkerActivity {
+    pub story: String,
+    pub stage: &'static str,
+}
+
+/// Live state of a capture run: per-worker progress (written by runner
+/// workers, read by the terminal progress display) and the rendering
+/// environment, known once Chrome is up.
+#[derive(Debug, Default)]
+pub struct Activity {
+    workers: Mutex<Vec<Option<WorkerActivity>>>,
+    environment: OnceLock<Environment>,
+}
+
+impl Activity {
+    fn update(&self, worker: usize, f: impl FnOnce(&mut Option<WorkerActivity>)) {
+        let Ok(mut workers) = self.workers.lock() else {
+            return;
+        };
+        if workers.len() <= worker {
+            workers.resize(worker + 1, None);
+        }
+        f(&mut workers[worker]);
+    }
+
+    /// Worker `worker` picked up `story`.
+    pub fn begin(&self, worker: usize, story: &str) {
+        self.update(worker, |slot| {
+            *slot = Some(WorkerActivity {
+                story: story.to_string(),
+                stage: "session",
+            })
+        });
+    }
+
+    /// Worker `worker` entered pipeline stage `stage`.
+    pub fn stage(&self, worker: usize, stage: &'static str) {
+        self.update(worker, |slot| {
+            if let Some(a) = slot {
+                a.stage = stage;
+            }
+        });
+    }
+
+    /// Worker `worker` is idle.
+    pub fn finish(&self, worker: usize) {
+        self.update(worker, |slot| *slot = None);
+    }
+
+    pub fn set_environment(&self, env: Environment) {
+        let _ = self.environment.set(env);
+    }
+
+    /// Environment fingerprint of the run (set before any capture starts;
+    /// `None` if it could not be determined).
+    pub fn environment(&self) -> Option<&Environment> {
+        self.environment.get()
+    }
+
+    /// Current state of every worker, idle ones as `None`.
+    pub fn snapshot(&self) -> Vec<Option<WorkerActivity>> {
+        self.workers.lock().map(|w| w.clone()).unwrap_or_default()
+    }
+}
@@ -0,0 +1,221 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use serde_json::json;
+
+use crate::cdp::CdpConnection;
+use crate::cdp::events::Subscription;
+use crate::store;
+
+/// Report written after a run with `css_coverage`, under `.snapvrt/`.
+pub const OUTPUT_FILE: &str = "css-coverage.json";
+
+/// Unused selectors listed per stylesheet in the report.
+const MAX_UNUSED_LISTED: usize = 500;
+
+/// Rule usage of one stylesheet on one page.
+pub struct SheetUsage {
+    /// Source URL, or `inline:<hash>` for `<style>` elements.
+    key: String,
+    text: String,
+    /// `(start, end)` UTF-16 offsets of each style rule, and whether it
+    /// matched anything.
+    rules: Vec<(usize, usize, bool)>,
+}
+
+/// Stylesheets recorded per page; later ones are left out of the report.
+const MAX_SHEETS: usize = 10_000;
+
+/// Start tracking rule usage on a fresh tab, before navigating. Returns the
+/// subscription recording the page's stylesheets, for [`collect`].
+pub async fn start(conn: &mut CdpConnection) -> Result<Subscription> {
+    let sheets = conn.subscribe(&["CSS.styleSheetAdded"], MAX_SHEETS);
+    conn.call("DOM.enable", json!({}))
+        .await
+        .context("Failed to enable DOM domain")?;
+    conn.call("CSS.enable", json!({}))
+        .await
+        .context("Failed to enable CSS domain")?;
+    conn.call("CSS.startRuleUsageTracking", json!({}))
+        .await
+        .context("Failed to start CSS rule usage tracking")?;
+    Ok(sheets)
+}
+
+/// Stop tracking and collect the rule usage of every stylesheet the page
+/// loaded (user-agent and DevTools sheets are skipped).
+pub async fn collect(conn: &mut CdpConnection, sheets: Subscription) -> Result<Vec<SheetUsage>> {
+    let result = conn
+        .call("CSS.stopRuleUsageTracking", json!({}))
+        .await
+        .context("Failed to stop CSS rule usage tracking")?;
+    let mut rules: BTreeMap<String, Vec<(usize, usize, bool)>> = BTreeMap::new();
+    for usage in result["ruleUsage"].as_array().into_iter().flatten() {
+        let (Some(id), Some(start), Some(end)) = (
+            usage["styleSheetId"].as_str(),
+            usage["startOffset"].as_f64(),
+            usage["endOffset"].as_f64(),
+        ) else {
+            continue;
+        };
+        let used = usage["used"].as_bool().unwrap_or(false);
+        rules
+            .entry(id.to_string())
+            .or_default()
+            .push((start as usize, end as usize, used));
+    }
+
+    let headers: Vec<(String, String)> = conn
+        .events(sheets)
+        .map(|e| &e.params["header"])
+        .filter(|h| h["origin"].as_str() == Some("regular"))
+        .filter_map(|h| {
+            let id = h["styleSheetId"].as_str()?.to_string();
+            let inline = h["isInline"].as_bool().unwrap_or(false);
+            let url = h["sourceURL"].as_str().unwrap_or_default().to_string();
+            Some((
+                id,
+                if inline || url.is_empty() {
+                    String::new()
+                } else {
+                    url
+                },
+            ))
+        })
+        .collect();
+    let mut usage = Vec::new();
+    for (id, url) in headers {
+        let Some(rules) = rules.remove(&id) else {
+            continue;
+        };
+        let text = conn
+            .call("CSS.getStyleSheetText", json!({ "styleSheetId": id }))
+            .await?["text"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string();
+        let key = if url.is_empty() {
+            format!("inline:{}", &store::hash_bytes(text.as_bytes())[..12])
+        } else {
+            url
+        };
+        usage.push(SheetUsage { key, text, rules });
+    }
+    Ok(usage)
+}
+
+/// Stylesheet text and whether each `(start, end)` rule was used.
+type MergedSheet = (String, BTreeMap<(usize, usize), bool>);
+
+/// Rule usage merged across every captured story: a rule counts as used
+/// when any story used it.
+#[derive(Default)]
+pub struct CssCoverage {
+    sheets: BTreeMap<String, MergedSheet>,
+}
+
+#[derive(Serialize)]
+struct SheetReport {
+    stylesheet: String,
+    rules: usize,
+    unused: usize,
+    unused_bytes: usize,
+    /// Selectors of unused rules, in stylesheet order.
+    unused_rules: Vec<String>,
+}
+
+impl CssCoverage {
+    pub fn add(&mut self, usage: Vec<SheetUsage>) {
+        for sheet in usage {
+            let (_, rules) = self
+                .sheets
+                .entry(sheet.key)
+                .or_insert_with(|| (sheet.text, BTreeMap::new()));
+            for (start, end, used) in sheet.rules {
+                *rules.entry((start, end)).or_default() |= used;
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sheets.is_empty()
+    }
+
+    /// Per stylesheet, most unused bytes first.
+    fn report(&self) -> Vec<SheetReport> {
+        let mut report: Vec<SheetReport> = self
+            .sheets
+            .iter()
+            .map(|(key, (text, rules))| {
+                let utf16: Vec<u16> = text.encode_utf16().collect();
+                let unused: Vec<(usize, usize)> = rules
+                    .iter()
+                    .filter(|(_, used)| !**used)
+                    .map(|(range, _)| *range)
+                    .collect();
+                SheetReport {
+                    stylesheet: key.clone(),
+                    rules: rules.len(),
+                    unused: unused.len(),
+                    unused_bytes: unused.iter().map(|(start, end)| end - start).sum(),
+                    unused_rules: unused
+                        .iter()
+                        .take(MAX_UNUSED_LISTED)
+                        .map(|&(start, end)| selector(&utf16, start, end))
+                        .collect(),
+                }
+            })
+            .collect();
+        report.sort_by_key(|s| std::cmp::Reverse(s.unused_bytes));
+        report
+    }
+
+    /// Write `.snapvrt/css-coverage.json`. Returns `(unused, total)` rules.
+    pub fn write(&self) -> Result<(usize, usize)> {
+        let report = self.report();
+        let totals = report.iter().fold((0, 0), |(unused, total), s| {
+            (unused + s.unused, total + s.rules)
+        });
+        let path = Path::new(store::BASE_DIR).join(OUTPUT_FILE);
+        let content = serde_json::to_string_pretty(&json!({ "stylesheets": report }))?;
+        std::fs::write(&path, content)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(totals)
+    }
+}
+
+/// Selector of the rule at `start..end`: the text before its `{`, with
+/// whitespace collapsed.
+fn selector(utf16: &[u16], start: usize, end: usize) -> String {
+    let rule = String::from_utf16_lossy(&utf16[start.min(utf16.len())..end.min(utf16.len())]);
+    let head = rule.split('{').next().unwrap_or_default();
+    head.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(key: &str, text: &str, rules: Vec<(usize, usize, bool)>) -> SheetUsage {
+        SheetUsage {
+            key: key.into(),
+            text: text.into(),
+            rules,
+        }
+    }
+
+    #[test]
+    fn rules_used_by_any_story_count_as_used() {
+        let css = ".a { color: red }\n.b,\n.c { color: blue }\n";
+        let rules = |a, b| vec![(0, 17, a), (18, 40, b)];
+        let mut coverage = CssCoverage::default();
+        coverage.add(vec![usage("app.css", css, rules(true, false))]);
+        coverage.add(vec![usage("app.css", css, rules(false, false))]);
+        let report = coverage.report();
+        assert_eq!(report.len(), 1);
+        assert_eq!((report[0].rules, report[0].unused), (2, 1));
+        assert_eq!(report[0].unused_rules, [".b, .c"]);
+    }
+}
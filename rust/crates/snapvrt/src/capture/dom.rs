@@ -0,0 +1,139 @@
+use std::collections::BTreeMap;
+
+use anyhow::{Context, Result};
+use serde_json::{Value, json};
+
+use crate::cdp::CdpConnection;
+
+const ELEMENT_NODE: i64 = 1;
+const TEXT_NODE: i64 = 3;
+
+/// Elements whose content isn't rendered markup.
+const SKIPPED_ELEMENTS: [&str; 5] = ["SCRIPT", "STYLE", "NOSCRIPT", "TEMPLATE", "LINK"];
+
+/// The story document's `<body>` as normalized text, one node per line:
+/// elements as `<tag attr="value">` with sorted attributes, text nodes with
+/// collapsed whitespace, indented by depth. Scripts, styles and comments
+/// are left out.
+pub async fn snapshot(conn: &mut CdpConnection) -> Result<String> {
+    let result = conn
+        .call(
+            "DOMSnapshot.captureSnapshot",
+            json!({ "computedStyles": [] }),
+        )
+        .await
+        .context("Failed to capture the DOM snapshot")?;
+    let strings: Vec<&str> = result["strings"]
+        .as_array()
+        .context("Unexpected DOMSnapshot.captureSnapshot result")?
+        .iter()
+        .map(|s| s.as_str().unwrap_or_default())
+        .collect();
+    let nodes = &result["documents"][0]["nodes"];
+    Ok(format_document(nodes, &strings))
+}
+
+fn format_document(nodes: &Value, strings: &[&str]) -> String {
+    let column = |key: &str| nodes[key].as_array().cloned().unwrap_or_default();
+    let (parents, types, names, values, attributes) = (
+        column("parentIndex"),
+        column("nodeType"),
+        column("nodeName"),
+        column("nodeValue"),
+        column("attributes"),
+    );
+    let string = |v: &Value| {
+        v.as_i64()
+            .and_then(|i| usize::try_from(i).ok())
+            .and_then(|i| strings.get(i).copied())
+            .unwrap_or_default()
+    };
+
+    let mut children: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for (i, parent) in parents.iter().enumerate() {
+        if let Some(parent) = parent.as_i64().and_then(|p| usize::try_from(p).ok()) {
+            children.entry(parent).or_default().push(i);
+        }
+    }
+    let Some(body) = (0..names.len()).find(|&i| string(&names[i]) == "BODY") else {
+        return String::new();
+    };
+
+    let mut out = String::new();
+    let mut stack = vec![(body, 0usize)];
+    while let Some((node, depth)) = stack.pop() {
+        let indent = "  ".repeat(depth);
+        match types[node].as_i64() {
+            Some(ELEMENT_NODE) => {
+                let name = string(&names[node]);
+                if SKIPPED_ELEMENTS.contains(&name) {
+                    continue;
+                }
+                let pairs = attributes[node].as_array().cloned().unwrap_or_default();
+                let mut attrs: Vec<(&str, &str)> = pairs
+                    .chunks(2)
+                    .map(|pair| {
+                        (
+                            string(&pair[0]),
+                            pair.get(1).map(string).unwrap_or_default(),
+                        )
+                    })
+                    .collect();
+                attrs.sort();
+                out.push_str(&format!("{indent}<{}", name.to_ascii_lowercase()));
+                for (key, value) in attrs {
+                    out.push_str(&format!(" {key}={value:?}"));
+                }
+                out.push_str(">\n");
+                if let Some(kids) = children.get(&node) {
+                    stack.extend(kids.iter().rev().map(|&kid| (kid, depth + 1)));
+                }
+            }
+            Some(TEXT_NODE) => {
+                let text = string(&values[node])
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                if !text.is_empty() {
+                    out.push_str(&format!("{indent}{text:?}\n"));
+                }
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn body_is_normalized() {
+        let strings = [
+            "#document",
+            "HTML",
+            "BODY",
+            "DIV",
+            "class",
+            "b a",
+            "id",
+            "root",
+            "#text",
+            "  Hello\n  world ",
+            "SCRIPT",
+            "x()",
+        ];
+        let nodes = json!({
+            "parentIndex": [-1, 0, 1, 2, 3, 2, 5],
+            "nodeType": [9, 1, 1, 1, 3, 1, 3],
+            "nodeName": [0, 1, 2, 3, 8, 10, 8],
+            "nodeValue": [-1, -1, -1, -1, 9, -1, 11],
+            "attributes": [[], [], [], [6, 7, 4, 5], [], [], []]
+        });
+        assert_eq!(
+            format_document(&nodes, &strings),
+            "<body>\n  <div class=\"b a\" id=\"root\">\n    \"Hello world\"\n"
+        );
+    }
+}
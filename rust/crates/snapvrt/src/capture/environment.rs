@@ -0,0 +1,62 @@
+use serde::{Deserialize, Serialize};
+
+/// Rendering environment a set of snapshots was captured in. Differences
+/// (another Chrome build, OS fonts, device scale) cause diffs that have
+/// nothing to do with the code under test.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Environment {
+    /// Browser product, e.g. `HeadlessChrome/139.0.7258.138`.
+    pub chrome: String,
+    /// `navigator.platform`, e.g. `Linux x86_64`.
+    pub platform: String,
+    /// `window.devicePixelRatio`.
+    pub device_scale: f64,
+    /// Hash of text metrics across common font families; changes when the
+    /// installed fonts or font configuration differ.
+    pub fonts: String,
+}
+
+impl Environment {
+    /// Human-readable `field: old -> new` lines for fields that differ.
+    pub fn differences(&self, other: &Environment) -> Vec<String> {
+        let mut out = Vec::new();
+        let mut check = |name: &str, a: String, b: String| {
+            if a != b {
+                out.push(format!("{name}: {a} -> {b}"));
+            }
+        };
+        check("chrome", self.chrome.clone(), other.chrome.clone());
+        check("platform", self.platform.clone(), other.platform.clone());
+        check(
+            "device scale",
+            self.device_scale.to_string(),
+            other.device_scale.to_string(),
+        );
+        check("fonts", self.fonts.clone(), other.fonts.clone());
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env(chrome: &str, fonts: &str) -> Environment {
+        Environment {
+            chrome: chrome.to_string(),
+            platform: "Linux x86_64".to_string(),
+            device_scale: 1.0,
+            fonts: fonts.to_string(),
+        }
+    }
+
+    #[test]
+    fn lists_only_changed_fields() {
+        let a = env("Chrome/139", "abc");
+        assert!(a.differences(&a.clone()).is_empty());
+        assert_eq!(
+            a.differences(&env("Chrome/141", "def")),
+            vec!["chrome: Chrome/139 -> Chrome/141", "fonts: abc -> def"]
+        );
+    }
+}
@@ -0,0 +1,143 @@
+use std::path::Path;
+
+use anyhow::{Context, Result, bail};
+use base64::Engine;
+
+/// Font file extensions picked up from `capture.font_dir`, with their
+/// `@font-face` `format()`.
+const FORMATS: [(&str, &str); 4] = [
+    ("woff2", "woff2"),
+    ("woff", "woff"),
+    ("ttf", "truetype"),
+    ("otf", "opentype"),
+];
+
+/// Style suffixes of font file names and the weight they stand for, longest
+/// first so `ExtraBold` is not read as `Bold`.
+const WEIGHTS: [(&str, u16); 12] = [
+    ("extralight", 200),
+    ("ultralight", 200),
+    ("extrabold", 800),
+    ("ultrabold", 800),
+    ("semibold", 600),
+    ("demibold", 600),
+    ("regular", 400),
+    ("medium", 500),
+    ("light", 300),
+    ("black", 900),
+    ("heavy", 900),
+    ("thin", 100),
+];
+
+/// Family, weight and style of a font file, from its path relative to the
+/// bundle directory: `Inter/SemiBoldItalic.woff2` or `Inter-SemiBoldItalic.woff2`.
+/// Files without a style suffix are the family's regular face.
+fn face(relative: &Path) -> Option<(String, u16, bool)> {
+    let stem = relative.file_stem()?.to_str()?;
+    let (family, style) = match relative.parent().and_then(|p| p.to_str()) {
+        Some(dir) if !dir.is_empty() => (dir.to_string(), stem),
+        _ => match stem.split_once('-') {
+            Some((family, style)) => (family.to_string(), style),
+            None => (stem.to_string(), ""),
+        },
+    };
+    let mut style = style.to_ascii_lowercase();
+    let italic = style.contains("italic") || style.contains("oblique");
+    style = style.replace("italic", "").replace("oblique", "");
+    let style = style.trim_matches(['-', '_', ' ']);
+    let weight = if style.is_empty() {
+        400
+    } else if style == "bold" {
+        700
+    } else {
+        WEIGHTS
+            .iter()
+            .find(|(name, _)| style == *name)
+            .map(|(_, weight)| *weight)
+            .or_else(|| style.parse().ok().filter(|w| (1..=1000).contains(w)))?
+    };
+    Some((family, weight, italic))
+}
+
+/// `@font-face` rules for every font in `dir` (one level of family
+/// subdirectories), with the files inlined as data URLs. Inlining needs no
+/// server, so it works the same for local and Docker Chrome and on HTTPS
+/// pages. Declared families take precedence over installed fonts of the
+/// same name.
+pub fn load(dir: &Path) -> Result<String> {
+    let mut files = Vec::new();
+    let entries =
+        std::fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))?;
+    for entry in entries {
+        let path = entry?.path();
+        if path.is_dir() {
+            for inner in std::fs::read_dir(&path)? {
+                files.push(inner?.path());
+            }
+        } else {
+            files.push(path);
+        }
+    }
+    files.sort();
+
+    let mut css = String::new();
+    for path in files {
+        let ext = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        let Some((_, format)) = FORMATS.iter().find(|(e, _)| *e == ext) else {
+            continue;
+        };
+        let relative = path.strip_prefix(dir).unwrap_or(&path);
+        let Some((family, weight, italic)) = face(relative) else {
+            bail!(
+                "capture.font_dir: cannot tell the weight of {} \
+                 (use e.g. Family-Bold.{ext} or Family/700.{ext})",
+                relative.display()
+            );
+        };
+        let bytes =
+            std::fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let data = base64::engine::general_purpose::STANDARD.encode(bytes);
+        css.push_str(&format!(
+            "@font-face {{ font-family: \"{}\"; font-weight: {weight}; font-style: {}; \
+             font-display: block; src: url(data:font/{ext};base64,{data}) format(\"{format}\"); }}\n",
+            family.replace(['"', '\\'], ""),
+            if italic { "italic" } else { "normal" },
+        ));
+    }
+    if css.is_empty() {
+        bail!(
+            "capture.font_dir: no .woff2, .woff, .ttf or .otf files in {}",
+            dir.display()
+        );
+    }
+    Ok(css)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn faces_from_file_names() {
+        let face = |p: &str| face(Path::new(p));
+        assert_eq!(face("Inter-Bold.woff2"), Some(("Inter".into(), 700, false)));
+        assert_eq!(
+            face("Inter-ExtraBoldItalic.woff2"),
+            Some(("Inter".into(), 800, true))
+        );
+        assert_eq!(face("Arial.ttf"), Some(("Arial".into(), 400, false)));
+        assert_eq!(
+            face("Open Sans/Italic.woff2"),
+            Some(("Open Sans".into(), 400, true))
+        );
+        assert_eq!(
+            face("Open Sans/300.woff2"),
+            Some(("Open Sans".into(), 300, false))
+        );
+        assert_eq!(face("Inter-Wide.woff2"), None);
+    }
+}
@@ -0,0 +1,258 @@
+use std::collections::BTreeMap;
+
+use serde_json::{Value, json};
+
+use crate::report::html::epoch_days_to_ymd;
+
+/// Headers carrying credentials, written to HAR files as `REDACTED`: the
+/// files are meant to be shared and attached to CI runs.
+const REDACTED_HEADERS: [&str; 4] = [
+    "authorization",
+    "proxy-authorization",
+    "cookie",
+    "set-cookie",
+];
+
+/// One request assembled from `Network.*` events.
+#[derive(Default)]
+struct Entry<'a> {
+    sent: Option<&'a Value>,
+    response: Option<&'a Value>,
+    /// Monotonic timestamp (seconds) of `loadingFinished` / `loadingFailed`.
+    end: Option<f64>,
+    encoded_length: Option<f64>,
+    error: Option<&'a str>,
+}
+
+/// Build a HAR 1.2 log from buffered CDP events (`(method, params)`), in
+/// request order. Requests still in flight are included with status 0 and a
+/// `pending` comment — usually the ones a stuck capture waited on.
+pub fn build(events: &[(&str, &Value)]) -> Value {
+    let mut order: Vec<&str> = Vec::new();
+    let mut entries: BTreeMap<&str, Entry> = BTreeMap::new();
+    for (method, params) in events {
+        let Some(id) = params["requestId"].as_str() else {
+            continue;
+        };
+        match *method {
+            "Network.requestWillBeSent" => {
+                // A redirect reuses the request ID; keep the first hop.
+                if !entries.contains_key(id) {
+                    order.push(id);
+                }
+                entries.entry(id).or_default().sent.get_or_insert(params);
+            }
+            "Network.responseReceived" => {
+                entries.entry(id).or_default().response = Some(&params["response"]);
+            }
+            "Network.loadingFinished" => {
+                let e = entries.entry(id).or_default();
+                e.end = params["timestamp"].as_f64();
+                e.encoded_length = params["encodedDataLength"].as_f64();
+            }
+            "Network.loadingFailed" => {
+                let e = entries.entry(id).or_default();
+                e.end = params["timestamp"].as_f64();
+                e.error = params["errorText"].as_str();
+            }
+            _ => {}
+        }
+    }
+
+    let entries: Vec<Value> = order
+        .iter()
+        .filter_map(|id| har_entry(&entries[id]))
+        .collect();
+
+    json!({
+        "log": {
+            "version": "1.2",
+            "creator": { "name": "snapvrt", "version": env!("CARGO_PKG_VERSION") },
+            "pages": [],
+            "entries": entries,
+        }
+    })
+}
+
+fn har_entry(e: &Entry) -> Option<Value> {
+    let sent = e.sent?;
+    let request = &sent["request"];
+    let start = sent["timestamp"].as_f64().unwrap_or(0.0);
+    let total_ms = e.end.map(|end| ((end - start) * 1000.0).max(0.0));
+
+    // Phase timings relative to `response.timing.requestTime`, when known.
+    let timing = e.response.map(|r| &r["timing"]);
+    let phase = |key: &str| timing.and_then(|t| t[key].as_f64()).filter(|v| *v >= 0.0);
+    let send = match (phase("sendStart"), phase("sendEnd")) {
+        (Some(a), Some(b)) => b - a,
+        _ => 0.0,
+    };
+    let wait = match (phase("sendEnd"), phase("receiveHeadersEnd")) {
+        (Some(a), Some(b)) => b - a,
+        _ => 0.0,
+    };
+    let receive = match (total_ms, phase("receiveHeadersEnd")) {
+        (Some(total), Some(headers_end)) => (total - headers_end).max(0.0),
+        _ => 0.0,
+    };
+
+    let response = match e.response {
+        Some(r) => json!({
+            "status": r["status"].as_u64().unwrap_or(0),
+            "statusText": r["statusText"].as_str().unwrap_or(""),
+            "httpVersion": r["protocol"].as_str().unwrap_or("unknown"),
+            "cookies": [],
+            "headers": headers(&r["headers"]),
+            "content": {
+                "size": e.encoded_length.unwrap_or(-1.0),
+                "mimeType": r["mimeType"].as_str().unwrap_or(""),
+            },
+            "redirectURL": "",
+            "headersSize": -1,
+            "bodySize": e.encoded_length.unwrap_or(-1.0),
+        }),
+        None => json!({
+            "status": 0,
+            "statusText": "",
+            "httpVersion": "unknown",
+            "cookies": [],
+            "headers": [],
+            "content": { "size": 0, "mimeType": "" },
+            "redirectURL": "",
+            "headersSize": -1,
+            "bodySize": -1,
+        }),
+    };
+
+    let comment = match (e.error, total_ms) {
+        (Some(error), _) => error.to_string(),
+        (None, None) => "pending".to_string(),
+        (None, Some(_)) => String::new(),
+    };
+
+    Some(json!({
+        "startedDateTime": iso_timestamp(sent["wallTime"].as_f64().unwrap_or(0.0)),
+        "time": total_ms.unwrap_or(0.0),
+        "request": {
+            "method": request["method"].as_str().unwrap_or("GET"),
+            "url": request["url"].as_str().unwrap_or(""),
+            "httpVersion": "unknown",
+            "cookies": [],
+            "headers": headers(&request["headers"]),
+            "queryString": [],
+            "headersSize": -1,
+            "bodySize": -1,
+        },
+        "response": response,
+        "cache": {},
+        "timings": {
+            "blocked": -1,
+            "dns": -1,
+            "connect": -1,
+            "send": send,
+            "wait": wait,
+            "receive": receive,
+            "ssl": -1,
+        },
+        "_resourceType": sent["type"].as_str().unwrap_or(""),
+        "comment": comment,
+    }))
+}
+
+/// CDP header object -> HAR `[{name, value}]`, credentials redacted.
+fn headers(headers: &Value) -> Vec<Value> {
+    headers
+        .as_object()
+        .map(|h| {
+            h.iter()
+                .map(|(name, value)| {
+                    let redact = REDACTED_HEADERS
+                        .iter()
+                        .any(|r| name.eq_ignore_ascii_case(r));
+                    let value = if redact {
+                        "REDACTED"
+                    } else {
+                        value.as_str().unwrap_or("")
+                    };
+                    json!({ "name": name, "value": value })
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Epoch seconds -> `YYYY-MM-DDTHH:MM:SS.mmmZ`.
+fn iso_timestamp(epoch_secs: f64) -> String {
+    let millis = (epoch_secs * 1000.0).max(0.0) as u64;
+    let secs = millis / 1000;
+    let (y, mo, d) = epoch_days_to_ymd(secs / 86400);
+    let (h, m, s) = ((secs / 3600) % 24, (secs / 60) % 60, secs % 60);
+    format!(
+        "{y:04}-{mo:02}-{d:02}T{h:02}:{m:02}:{s:02}.{:03}Z",
+        millis % 1000
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_entries_in_request_order() {
+        let sent_a = json!({
+            "requestId": "a", "timestamp": 10.0, "wallTime": 1_700_000_000.5, "type": "Document",
+            "request": { "method": "GET", "url": "http://sb/iframe.html", "headers": {} },
+        });
+        let response_a = json!({
+            "requestId": "a",
+            "response": { "status": 200, "statusText": "OK", "mimeType": "text/html", "headers": {} },
+        });
+        let finished_a =
+            json!({ "requestId": "a", "timestamp": 10.25, "encodedDataLength": 512.0 });
+        let sent_b = json!({
+            "requestId": "b", "timestamp": 10.3, "wallTime": 1_700_000_000.8,
+            "request": { "method": "GET", "url": "http://sb/slow.json", "headers": {} },
+        });
+
+        let har = build(&[
+            ("Network.requestWillBeSent", &sent_a),
+            ("Network.requestWillBeSent", &sent_b),
+            ("Network.responseReceived", &response_a),
+            ("Network.loadingFinished", &finished_a),
+        ]);
+        let entries = har["log"]["entries"].as_array().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0]["response"]["status"], 200);
+        assert_eq!(entries[0]["time"], 250.0);
+        assert_eq!(entries[0]["startedDateTime"], "2023-11-14T22:13:20.500Z");
+        assert_eq!(entries[1]["request"]["url"], "http://sb/slow.json");
+        assert_eq!(entries[1]["comment"], "pending");
+    }
+
+    #[test]
+    fn credentials_are_redacted() {
+        let har_headers = headers(&json!({
+            "Authorization": "Basic dXNlcjpwYXNz",
+            "cookie": "session=abc",
+            "Proxy-Authorization": "Bearer t",
+            "Set-Cookie": "session=def",
+            "Accept": "text/html",
+        }));
+        let value = |name: &str| {
+            har_headers
+                .iter()
+                .find(|h| h["name"] == name)
+                .map(|h| h["value"].clone())
+                .unwrap()
+        };
+        for name in [
+            "Authorization",
+            "cookie",
+            "Proxy-Authorization",
+            "Set-Cookie",
+        ] {
+            assert_eq!(value(name), "REDACTED", "{name}");
+        }
+        assert_eq!(value("Accept"), "text/html");
+    }
+}
@@ -0,0 +1,142 @@
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+use crate::cdp::Chrome;
+use crate::cdp::chrome::redacted;
+
+/// How often a remote Chrome is polled.
+const INTERVAL: Duration = Duration::from_secs(5);
+
+/// Consecutive failed polls before the remote Chrome counts as unresponsive.
+const MAX_FAILURES: u32 = 3;
+
+/// Something wrong with the remote Chrome, as seen by [`RemoteHealth`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Problem {
+    /// `/json/version` reports a different browser than at startup: the
+    /// remote recycled its browser, and every tab of this run is gone.
+    Restarted,
+    /// `/json/version` failed `MAX_FAILURES` times in a row.
+    Unresponsive,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Restarted => f.write_str("Remote Chrome restarted mid-run"),
+            Self::Unresponsive => f.write_str("Remote Chrome stopped responding"),
+        }
+    }
+}
+
+#[derive(Default)]
+struct State {
+    failures: u32,
+    restarted: bool,
+}
+
+/// Polls a remote Chrome's `GET /json/version` in the background, to tell
+/// captures failing because the shared browser restarted or went away from
+/// captures failing on their own. Stops when dropped.
+pub struct RemoteHealth {
+    state: Arc<Mutex<State>>,
+    task: JoinHandle<()>,
+}
+
+impl RemoteHealth {
+    /// Start polling `chrome`, remembering which browser answers now.
+    pub async fn start(chrome: &Chrome) -> Result<Self> {
+        let client = chrome.http_client();
+        let version_url = chrome.version_url();
+        let initial = browser_id(&client, &version_url).await?;
+        debug!(browser_id = %initial, "watching remote Chrome");
+        let state = Arc::new(Mutex::new(State::default()));
+        let task = tokio::spawn({
+            let state = state.clone();
+            async move {
+                loop {
+                    tokio::time::sleep(INTERVAL).await;
+                    let current = browser_id(&client, &version_url).await;
+                    let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+                    match current {
+                        Ok(id) if id == initial => {
+                            if state.failures >= MAX_FAILURES {
+                                warn!("remote Chrome is responding again");
+                            }
+                            state.failures = 0;
+                        }
+                        Ok(id) => {
+                            warn!(
+                                before = %initial,
+                                after = %id,
+                                "remote Chrome restarted mid-run"
+                            );
+                            state.restarted = true;
+                            break;
+                        }
+                        Err(e) => {
+                            state.failures += 1;
+                            debug!(
+                                error = %format!("{e:#}"),
+                                failures = state.failures,
+                                "remote Chrome health check failed"
+                            );
+                            if state.failures == MAX_FAILURES {
+                                warn!(
+                                    "remote Chrome has not answered /json/version for {}s",
+                                    (INTERVAL * MAX_FAILURES).as_secs()
+                                );
+                            }
+                        }
+                    }
+                }
+            }
+        });
+        Ok(Self { state, task })
+    }
+
+    /// The current problem, if any. A restart is permanent; unresponsiveness
+    /// clears once the same browser answers again.
+    pub fn problem(&self) -> Option<Problem> {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if state.restarted {
+            Some(Problem::Restarted)
+        } else if state.failures >= MAX_FAILURES {
+            Some(Problem::Unresponsive)
+        } else {
+            None
+        }
+    }
+}
+
+impl Drop for RemoteHealth {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// The id identifying one browser process: the last segment of
+/// `webSocketDebuggerUrl` (`ws://host/devtools/browser/<id>`).
+async fn browser_id(client: &reqwest::Client, version_url: &str) -> Result<String> {
+    let resp: serde_json::Value = client
+        .get(version_url)
+        .timeout(INTERVAL)
+        .send()
+        .await
+        .context("GET /json/version failed")?
+        .error_for_status()?
+        .json()
+        .await
+        .context("Failed to parse /json/version response")?;
+    resp["webSocketDebuggerUrl"]
+        .as_str()
+        .and_then(|url| redacted(url).rsplit('/').next())
+        .filter(|id| !id.is_empty())
+        .map(str::to_string)
+        .context("No webSocketDebuggerUrl in /json/version response")
+}
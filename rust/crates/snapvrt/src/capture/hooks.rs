@@ -0,0 +1,227 @@
+use std::borrow::Cow;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+
+use super::pipeline::CaptureRequest;
+use super::scripts;
+use super::strategy::Page;
+use crate::config::capture::{BuiltinHook, CaptureConfig};
+
+/// A step added to the capture pipeline at fixed points.
+///
+/// Hooks supply JS expressions rather than driving the page themselves, so
+/// the pipeline can run them through either backend (DevTools or
+/// WebDriver). Expressions returning a promise are awaited.
+pub trait PipelineHook: Send + Sync {
+    /// Name shown in errors.
+    fn name(&self) -> &str;
+
+    /// Rewrite the story URL before the page navigates to it.
+    fn before_navigation(&self, _url: &str) -> Option<String> {
+        None
+    }
+
+    /// Script run once the page has loaded and the network is idle, before
+    /// animations are disabled and the readiness checks.
+    fn after_navigation(&self, _req: &CaptureRequest) -> Option<Cow<'_, str>> {
+        None
+    }
+
+    /// Script run once the story is ready (play function finished, fonts
+    /// loaded), before focus, scrolling and measuring the clip.
+    fn after_ready(&self, _req: &CaptureRequest) -> Option<Cow<'_, str>> {
+        None
+    }
+
+    /// Script run after the clip is measured, right before the screenshot.
+    fn before_screenshot(&self, _req: &CaptureRequest) -> Option<Cow<'_, str>> {
+        None
+    }
+}
+
+impl PipelineHook for BuiltinHook {
+    fn name(&self) -> &str {
+        match self {
+            Self::HideScrollbars => "hide-scrollbars",
+            Self::BlurInputs => "blur-inputs",
+            Self::StopVideos => "stop-videos",
+        }
+    }
+
+    fn after_navigation(&self, _req: &CaptureRequest) -> Option<Cow<'_, str>> {
+        match self {
+            // Before the clip is measured, since scrollbars take up space.
+            Self::HideScrollbars => Some(scripts::HIDE_SCROLLBARS_JS.into()),
+            // Before focus variants press Tab, so only autofocus is undone.
+            Self::BlurInputs => Some(scripts::BLUR_INPUTS_JS.into()),
+            Self::StopVideos => None,
+        }
+    }
+
+    fn before_screenshot(&self, _req: &CaptureRequest) -> Option<Cow<'_, str>> {
+        match self {
+            Self::StopVideos => Some(scripts::STOP_VIDEOS_JS.into()),
+            Self::HideScrollbars | Self::BlurInputs => None,
+        }
+    }
+}
+
+/// `capture.hook_script`: a project JS file defining `beforeCapture(story)`,
+/// called in the page once the story is ready, with the story's metadata.
+/// Plain scripts run inside a function; ES modules are imported.
+struct ScriptHook {
+    name: String,
+    source: String,
+    module: bool,
+}
+
+impl ScriptHook {
+    fn load(path: &Path) -> Result<Self> {
+        let source = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read hook_script {}", path.display()))?;
+        Ok(Self {
+            name: path.display().to_string(),
+            module: is_module(&source),
+            source,
+        })
+    }
+}
+
+/// Whether `source` uses ES module syntax: a line starting with `export`,
+/// or with a static `import` (dynamic `import(...)` works in scripts too).
+fn is_module(source: &str) -> bool {
+    source.lines().map(str::trim_start).any(|line| {
+        line.starts_with("export ")
+            || line.starts_with("export{")
+            || line
+                .strip_prefix("import")
+                .is_some_and(|rest| rest.starts_with([' ', '{', '*', '"', '\'']))
+    })
+}
+
+impl PipelineHook for ScriptHook {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn after_ready(&self, req: &CaptureRequest) -> Option<Cow<'_, str>> {
+        // The source goes in last, so placeholders in it are left alone.
+        let (template, source) = if self.module {
+            let literal = serde_json::Value::from(self.source.as_str()).to_string();
+            (scripts::HOOK_MODULE_JS_TEMPLATE, literal)
+        } else {
+            (scripts::HOOK_SCRIPT_JS_TEMPLATE, self.source.clone())
+        };
+        let js = template
+            .replace("STORY_PLACEHOLDER", &req.story.to_string())
+            .replace("SOURCE_PLACEHOLDER", &source);
+        Some(js.into())
+    }
+}
+
+/// The hooks of a run, in the order they run at each stage.
+#[derive(Clone, Default)]
+pub struct Hooks(Arc<Vec<Box<dyn PipelineHook>>>);
+
+impl Hooks {
+    /// The built-in hooks enabled by `capture.hooks`, then the
+    /// `capture.hook_script`, if any.
+    pub fn from_config(config: &CaptureConfig) -> Result<Self> {
+        let mut hooks: Vec<Box<dyn PipelineHook>> = config
+            .hooks()
+            .iter()
+            .map(|&hook| Box::new(hook) as Box<dyn PipelineHook>)
+            .collect();
+        if let Some(path) = &config.hook_script {
+            hooks.push(Box::new(ScriptHook::load(path)?));
+        }
+        Ok(Self(Arc::new(hooks)))
+    }
+
+    /// The URL to navigate to for `url`, after every hook's rewrite.
+    pub fn before_navigation(&self, url: &str) -> String {
+        self.0.iter().fold(url.to_string(), |url, hook| {
+            hook.before_navigation(&url).unwrap_or(url)
+        })
+    }
+
+    pub async fn after_navigation(&self, page: &mut impl Page, req: &CaptureRequest) -> Result<()> {
+        for hook in self.0.iter() {
+            if let Some(js) = hook.after_navigation(req) {
+                page.eval_value(&js)
+                    .await
+                    .with_context(|| format!("Hook {} failed after navigation", hook.name()))?;
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn after_ready(&self, page: &mut impl Page, req: &CaptureRequest) -> Result<()> {
+        for hook in self.0.iter() {
+            if let Some(js) = hook.after_ready(req) {
+                page.eval_value(&js)
+                    .await
+                    .with_context(|| format!("Hook {} failed after ready", hook.name()))?;
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn before_screenshot(
+        &self,
+        page: &mut impl Page,
+        req: &CaptureRequest,
+    ) -> Result<()> {
+        for hook in self.0.iter() {
+            if let Some(js) = hook.before_screenshot(req) {
+                page.eval_value(&js)
+                    .await
+                    .with_context(|| format!("Hook {} failed before screenshot", hook.name()))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Query(&'static str);
+
+    impl PipelineHook for Query {
+        fn name(&self) -> &str {
+            "query"
+        }
+
+        fn before_navigation(&self, url: &str) -> Option<String> {
+            Some(format!("{url}&{}", self.0))
+        }
+    }
+
+    #[test]
+    fn module_syntax_is_detected() {
+        assert!(is_module("export function beforeCapture(story) {}"));
+        assert!(is_module("import { theme } from 'https://cdn/theme.js';\n"));
+        assert!(is_module("  export default async (story) => {};"));
+        assert!(!is_module("function beforeCapture(story) {}"));
+        assert!(!is_module("module.exports.beforeCapture = () => {};"));
+        assert!(!is_module("const { x } = await import('./x.js');"));
+        assert!(!is_module("// exported from elsewhere\nimportant();"));
+    }
+
+    #[test]
+    fn url_rewrites_chain_in_order() {
+        let hooks = Hooks(Arc::new(vec![
+            Box::new(Query("a=1")),
+            Box::new(BuiltinHook::HideScrollbars),
+            Box::new(Query("b=2")),
+        ]));
+        assert_eq!(
+            hooks.before_navigation("http://sb/iframe.html?id=x"),
+            "http://sb/iframe.html?id=x&a=1&b=2"
+        );
+    }
+}
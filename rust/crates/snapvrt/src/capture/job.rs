@@ -1,4 +1,13 @@
-use crate::storybook::{Story, normalize_for_filter};
+use serde_json::{Value, json};
+
+use crate::config::StoryConfig;
+use crate::storybook::{Filter, Story};
+
+/// Default snapshot ID layout (`store.id_template`).
+pub const DEFAULT_ID_TEMPLATE: &str = "{source}/{viewport}/{title}/{name}";
+
+/// Placeholders accepted in `store.id_template`.
+const ID_PLACEHOLDERS: [&str; 5] = ["source", "viewport", "title", "name", "id"];
 
 /// A single capture job.
 #[derive(Clone)]
@@ -15,27 +24,192 @@ pub struct CaptureJob {
     pub width: u32,
     /// Viewport height in CSS pixels.
     pub height: u32,
+    /// Snapshot ID rendered from `store.id_template` at plan time.
+    pub id: String,
+    /// `[story."<id>"]` settings for this story.
+    pub settings: StoryConfig,
+    /// Args permutation label (`size=sm,disabled=true`), if any.
+    pub args: Option<String>,
+    /// Capture the whole page instead of the story root (sitemap pages).
+    pub whole_page: bool,
+    /// Focus variant: Tab presses before capture (0 = plain snapshot).
+    pub focus: u32,
 }
 
 impl CaptureJob {
     /// Hierarchical snapshot ID used as a relative path.
-    /// Layout: `{source}/{viewport}/{title_path}/{name}`.
-    /// Title slashes become directory separators, spaces become underscores.
     pub fn snapshot_id(&self) -> String {
-        let title_path = self.story.title.replace(' ', "_");
-        let name_part = self.story.name.replace(' ', "_");
-        format!("{}/{}/{title_path}/{name_part}", self.source, self.viewport)
-    }
-
-    /// Check if this job matches a case-insensitive filter pattern.
-    /// Strips `.png` suffix from pattern (user may copy from HTML review page).
-    /// Normalizes spaces/underscores so both terminal output and raw story
-    /// fields can be used interchangeably in filters.
-    pub fn matches_filter(&self, pattern: &str) -> bool {
-        let pattern = pattern.strip_suffix(".png").unwrap_or(pattern);
-        let p = normalize_for_filter(pattern);
-        self.story.matches_filter(pattern)
-            || normalize_for_filter(&self.viewport).contains(&p)
-            || normalize_for_filter(&self.snapshot_id()).contains(&p)
+        self.id.clone()
+    }
+
+    /// Story metadata passed to `capture.hook_script`.
+    pub fn meta(&self) -> Value {
+        json!({
+            "id": self.id,
+            "storyId": self.story.id,
+            "title": self.story.title,
+            "name": self.story.name,
+            "tags": self.story.tags,
+            "source": self.source,
+            "viewport": self.viewport,
+            "width": self.width,
+            "height": self.height,
+            "args": self.args,
+            "focus": self.focus,
+            "url": self.url,
+        })
+    }
+
+    /// Check if this job matches a `--filter` expression.
+    pub fn matches_filter(&self, filter: &Filter) -> bool {
+        self.story.matches_filter(filter, &self.viewport, &self.id)
+    }
+}
+
+/// Snapshot ID of an args permutation (`<id>__<label>`).
+pub fn args_variant_id(id: &str, label: &str) -> String {
+    format!("{id}__{label}")
+}
+
+/// Snapshot ID of focus variant `n` (`<id>__focus-<n>`).
+pub fn focus_variant_id(id: &str, n: u32) -> String {
+    format!("{id}__focus-{n}")
+}
+
+/// Render a snapshot ID from `template`.
+///
+/// Placeholders: `{source}`, `{viewport}`, `{title}`, `{name}`, `{id}` (the
+/// Storybook story ID). Title slashes become directory separators; spaces in
+/// title and name become underscores.
+pub fn render_snapshot_id(template: &str, source: &str, viewport: &str, story: &Story) -> String {
+    let mut out = String::with_capacity(template.len() + 32);
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let Some(end) = after.find('}') else {
+            out.push_str(&rest[start..]);
+            return out;
+        };
+        match &after[..end] {
+            "source" => out.push_str(source),
+            "viewport" => out.push_str(viewport),
+            "title" => out.push_str(&story.title.replace(' ', "_")),
+            "name" => out.push_str(&story.name.replace(' ', "_")),
+            "id" => out.push_str(&story.id),
+            other => {
+                out.push('{');
+                out.push_str(other);
+                out.push('}');
+            }
+        }
+        rest = &after[end + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// The component title of a snapshot ID rendered from `template`, as a
+/// path (`Forms/Button`). Falls back to the ID's directory when `{title}`
+/// isn't a path segment of its own.
+pub fn snapshot_title(template: &str, id: &str) -> String {
+    let segments: Vec<&str> = template.split('/').collect();
+    let parts: Vec<&str> = id.split('/').collect();
+    if let Some(at) = segments.iter().position(|s| *s == "{title}") {
+        let after = segments.len() - at - 1;
+        if parts.len() > at + after {
+            return parts[at..parts.len() - after].join("/");
+        }
+    }
+    id.rsplit_once('/').map_or("", |(dir, _)| dir).to_string()
+}
+
+/// Check that `template` only uses known placeholders, identifies the story,
+/// and stays inside the store directory.
+pub fn validate_id_template(template: &str) -> Result<(), String> {
+    let mut rest = template;
+    let mut names = Vec::new();
+    while let Some(start) = rest.find('{') {
+        let after = &rest[start + 1..];
+        let end = after
+            .find('}')
+            .ok_or_else(|| format!("unclosed '{{' in id_template \"{template}\""))?;
+        let name = &after[..end];
+        if !ID_PLACEHOLDERS.contains(&name) {
+            return Err(format!(
+                "unknown placeholder {{{name}}} in id_template \"{template}\" (expected one of: {})",
+                ID_PLACEHOLDERS.map(|p| format!("{{{p}}}")).join(", ")
+            ));
+        }
+        names.push(name);
+        rest = &after[end + 1..];
+    }
+    if !names.iter().any(|n| *n == "name" || *n == "id") {
+        return Err(format!(
+            "id_template \"{template}\" must contain {{name}} or {{id}}"
+        ));
+    }
+    if template.starts_with('/') || template.split('/').any(|seg| seg == "..") {
+        return Err(format!(
+            "id_template \"{template}\" must be a relative path without '..'"
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn story() -> Story {
+        Story {
+            id: "forms-button--primary".into(),
+            name: "Primary Large".into(),
+            title: "Forms/Button".into(),
+            tags: Vec::new(),
+            url: None,
+        }
+    }
+
+    #[test]
+    fn default_template_matches_legacy_layout() {
+        let id = render_snapshot_id(DEFAULT_ID_TEMPLATE, "storybook", "laptop", &story());
+        assert_eq!(id, "storybook/laptop/Forms/Button/Primary_Large");
+    }
+
+    #[test]
+    fn custom_template() {
+        let id = render_snapshot_id(
+            "{source}/{title}/{name}@{viewport}",
+            "ui",
+            "mobile",
+            &story(),
+        );
+        assert_eq!(id, "ui/Forms/Button/Primary_Large@mobile");
+        assert_eq!(
+            snapshot_title("{source}/{title}/{name}@{viewport}", &id),
+            "Forms/Button"
+        );
+        assert_eq!(
+            snapshot_title(
+                DEFAULT_ID_TEMPLATE,
+                "storybook/laptop/Forms/Button/Primary_Large"
+            ),
+            "Forms/Button"
+        );
+        assert_eq!(
+            snapshot_title("{source}/{id}", "ui/forms-button--primary"),
+            "ui"
+        );
+    }
+
+    #[test]
+    fn template_validation() {
+        assert!(validate_id_template(DEFAULT_ID_TEMPLATE).is_ok());
+        assert!(validate_id_template("{source}/{id}").is_ok());
+        assert!(validate_id_template("{source}/{viewport}").is_err());
+        assert!(validate_id_template("{source}/{nmae}").is_err());
+        assert!(validate_id_template("{source}/{name").is_err());
+        assert!(validate_id_template("../{name}").is_err());
     }
 }
@@ -0,0 +1,71 @@
+use tracing::warn;
+
+/// cgroup v2 and v1 memory limit files, as seen from inside a container.
+const CGROUP_LIMIT_FILES: [&str; 2] = [
+    "/sys/fs/cgroup/memory.max",
+    "/sys/fs/cgroup/memory/memory.limit_in_bytes",
+];
+
+/// Memory the browser process, GPU process and snapvrt itself take before
+/// any tab is open.
+const BASE_MB: u64 = 400;
+
+/// Limits at or above this are "unlimited" (cgroup v1 reports a huge
+/// page-aligned number instead of `max`).
+const UNLIMITED_BYTES: u64 = 1 << 60;
+
+/// Memory limit (bytes) of the cgroup snapvrt runs in, if any — in Docker
+/// or Kubernetes, the container's limit.
+pub fn container_limit() -> Option<u64> {
+    CGROUP_LIMIT_FILES
+        .iter()
+        .find_map(|path| std::fs::read_to_string(path).ok())
+        .and_then(|content| parse_limit(&content))
+}
+
+fn parse_limit(content: &str) -> Option<u64> {
+    let bytes: u64 = content.trim().parse().ok()?;
+    (bytes < UNLIMITED_BYTES).then_some(bytes)
+}
+
+/// Tabs that fit in `limit` bytes at `tab_mb` each, at least one.
+fn tabs_that_fit(limit: u64, tab_mb: u32) -> usize {
+    let available_mb = (limit / (1024 * 1024)).saturating_sub(BASE_MB);
+    (available_mb / u64::from(tab_mb).max(1)).max(1) as usize
+}
+
+/// Lower `parallel` to what the container's memory limit can hold at
+/// `tab_mb` per tab, so the kernel doesn't OOM-kill Chrome mid-run.
+/// `tab_mb = 0` disables the cap.
+pub fn cap_parallel(parallel: usize, tab_mb: u32) -> usize {
+    if tab_mb == 0 {
+        return parallel;
+    }
+    let Some(limit) = container_limit() else {
+        return parallel;
+    };
+    let fit = tabs_that_fit(limit, tab_mb);
+    if fit >= parallel {
+        return parallel;
+    }
+    warn!(
+        "Memory limit is {} MB: capturing with {fit} tab(s) instead of {parallel} \
+         (capture.tab_memory_mb = {tab_mb}; 0 disables this)",
+        limit / (1024 * 1024)
+    );
+    fit
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parallel_fits_the_container_limit() {
+        assert_eq!(parse_limit("max\n"), None);
+        assert_eq!(parse_limit("9223372036854771712\n"), None);
+        let two_gb = parse_limit("2147483648\n").unwrap();
+        assert_eq!(tabs_that_fit(two_gb, 300), 5);
+        assert_eq!(tabs_that_fit(256 * 1024 * 1024, 300), 1);
+    }
+}
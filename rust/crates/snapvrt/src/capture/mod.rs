@@ -1,11 +1,27 @@
+pub mod a11y;
+pub mod activity;
+pub mod css_coverage;
+pub mod dom;
+pub mod environment;
+pub mod font_bundle;
+pub mod har;
+pub mod health;
+pub mod hooks;
 pub mod job;
+pub mod memory;
 pub mod pipeline;
 pub mod plan;
+pub mod popups;
+pub mod renderer;
 pub mod runner;
 pub mod scripts;
+pub mod stability;
 pub mod strategy;
 pub mod timing;
+pub mod webdriver;
 
-pub use self::plan::CapturePlan;
+pub use self::activity::Activity;
+pub use self::environment::Environment;
+pub use self::plan::{CapturePlan, Selection};
 pub use self::runner::CaptureOutcome;
 pub use self::timing::CaptureTimings;
@@ -1,28 +1,85 @@
+use std::collections::BTreeMap;
 use std::time::{Duration, Instant};
 
 use anyhow::{Context, Result};
-use tracing::debug;
+use serde_json::json;
+use tracing::{debug, warn};
 
+use super::a11y;
+use super::css_coverage::{self, SheetUsage};
+use super::dom;
+use super::environment::Environment;
+use super::har;
+use super::health::{Problem, RemoteHealth};
+use super::hooks::Hooks;
+use super::popups::PopupCloser;
 use super::scripts;
-use super::strategy::{self, Screenshot};
-use super::timing::CaptureTimings;
-use crate::cdp::{CdpConnection, Chrome};
-use crate::config::CaptureConfig;
+use super::strategy::{self, FontControl, Screenshot};
+use super::timing::{CaptureTimings, PerfMetrics};
+use crate::cdp::chrome::redacted;
+use crate::cdp::connection::origin;
+use crate::cdp::events::Subscription;
+use crate::cdp::{CdpConnection, Chrome, ChromeToken, ClipRect};
+use crate::compare::diff::Mask;
+use crate::config::{
+    CaptureConfig, DialogAction, DomMode, Isolation, Popups, StoryConfig, TokenPlacement,
+};
+use crate::store;
+
+/// Most Audits issues buffered per page for the mixed-content check.
+const MAX_ISSUES: usize = 100;
 
 /// Delay after viewport resize to let the page reflow.
 const VIEWPORT_RESIZE_SETTLE: Duration = Duration::from_millis(500);
 
+/// Budget for collecting error artifacts from a failed page.
+const ERROR_ARTIFACT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Most recent console messages kept in error artifacts.
+const MAX_CONSOLE_MESSAGES: usize = 200;
+
 /// Parameters for a single capture operation.
 pub struct CaptureRequest {
     pub url: String,
     pub width: u32,
     pub height: u32,
+    /// Per-story settings (`[story."<id>"]`).
+    pub settings: StoryConfig,
+    /// Tab presses before capture (focus variants); 0 for none.
+    pub focus_presses: u32,
+    /// Clip to the whole document instead of the story root.
+    pub whole_page: bool,
+    /// Take a single screenshot, skipping the stability check
+    /// (`screenshot = "adaptive"` for a snapshot known to be stable).
+    pub single_screenshot: bool,
+    /// Story metadata for the hooks ([`CaptureJob::meta`]).
+    ///
+    /// [`CaptureJob::meta`]: super::job::CaptureJob::meta
+    pub story: serde_json::Value,
 }
 
 /// Result of a capture operation.
 pub struct CaptureResult {
+    /// Encoded screenshot: PNG, or JPEG/WebP per `screenshot_format`.
     pub png: Vec<u8>,
     pub timings: CaptureTimings,
+    /// Bounds of `ignore_selectors` elements, relative to the screenshot.
+    pub masks: Vec<Mask>,
+    /// Text snapshots taken with the screenshot, by kind (`a11y`, `dom`).
+    pub texts: BTreeMap<String, String>,
+}
+
+/// Best-effort page state collected after a capture fails.
+#[derive(Default)]
+pub struct ErrorArtifacts {
+    /// Viewport screenshot (PNG).
+    pub screenshot: Option<Vec<u8>>,
+    /// `document.documentElement.outerHTML`.
+    pub html: Option<String>,
+    /// Recent console messages and exceptions.
+    pub console: Vec<String>,
+    /// Network log (HAR JSON), when `har` is enabled.
+    pub har: Option<String>,
 }
 
 // ---------------------------------------------------------------------------
@@ -32,40 +89,295 @@ pub struct CaptureResult {
 /// CDP renderer: owns a Chrome instance and produces `CdpSession`s.
 pub struct CdpRenderer {
     chrome: Chrome,
+    /// Browser-level connection creating a browser context per session
+    /// (`isolation = "context"`).
+    contexts: Option<tokio::sync::Mutex<CdpConnection>>,
+    /// Closes tabs the pages open (`popups = "close"`).
+    _popups: Option<PopupCloser>,
+    /// Watches a remote `chrome_url` for restarts and outages.
+    health: Option<RemoteHealth>,
     screenshot: Screenshot,
+    fonts: FontControl,
+    hooks: Hooks,
+    media_time: Option<f64>,
+    hide_scrollbar_gutters: bool,
+    deterministic_canvas: bool,
+    record_har: bool,
+    a11y: bool,
+    dom: DomMode,
+    css_coverage: bool,
+    perf_metrics: bool,
+    device_scale: f64,
+    ignore_selectors: Vec<String>,
+    pause_before_screenshot: bool,
+    cdp_timeout: Option<Duration>,
+    dialogs: DialogAction,
+    bypass_service_workers: bool,
+    disable_cache: bool,
+    headers: BTreeMap<String, String>,
+    ignore_https_errors: bool,
 }
 
 impl CdpRenderer {
-    pub async fn launch(config: &CaptureConfig) -> Result<Self> {
+    /// `headers` are sent with every request to the origin of the page
+    /// being captured, and to no other. `instance`
+    /// numbers the browsers of an `isolation = "browser"` run, which each
+    /// get their own subdirectory of `profile_dir`.
+    pub async fn launch(
+        config: &CaptureConfig,
+        headers: &BTreeMap<String, String>,
+        instance: usize,
+    ) -> Result<Self> {
+        let profile_dir = match (&config.profile_dir, config.isolation()) {
+            (Some(dir), Isolation::Browser) => Some(dir.join(format!("worker-{instance}"))),
+            (dir, _) => dir.clone(),
+        };
         let chrome = match &config.chrome_url {
-            Some(url) => Chrome::connect(url)
-                .await
-                .with_context(|| format!("Failed to connect to remote Chrome at {url}"))?,
-            None => Chrome::launch().await.context("Failed to launch Chrome")?,
+            Some(url) => {
+                let token =
+                    config
+                        .chrome_token
+                        .clone()
+                        .map(|token| match config.chrome_token_in() {
+                            TokenPlacement::Query => ChromeToken::Query(token),
+                            TokenPlacement::Header => ChromeToken::Bearer(token),
+                        });
+                Chrome::connect(url, token)
+                    .await
+                    .with_context(|| format!("Failed to connect to remote Chrome at {url}"))?
+            }
+            None => Chrome::launch(
+                !config.debug,
+                config.ignore_https_errors(),
+                &config.chrome_args(),
+                profile_dir.as_deref(),
+            )
+            .await
+            .context("Failed to launch Chrome")?,
+        };
+        let contexts = match config.isolation() {
+            Isolation::Context => {
+                let url = chrome.browser_ws_url().await?;
+                let mut conn = chrome
+                    .open(&url)
+                    .await
+                    .context("Failed to connect to the browser target")?;
+                conn.set_command_timeout(config.cdp_timeout());
+                Some(tokio::sync::Mutex::new(conn))
+            }
+            Isolation::Tab | Isolation::Browser => None,
+        };
+        let popups = match config.popups() {
+            Popups::Close => Some(PopupCloser::start(&chrome).await?),
+            Popups::Keep => None,
+        };
+        let health = match chrome.is_remote() {
+            true => Some(RemoteHealth::start(&chrome).await?),
+            false => None,
         };
         let screenshot = Screenshot::from_config(config);
-        Ok(Self { chrome, screenshot })
+        Ok(Self {
+            chrome,
+            contexts,
+            _popups: popups,
+            health,
+            screenshot,
+            fonts: FontControl::from_config(config)?,
+            hooks: Hooks::from_config(config)?,
+            media_time: config.media_time,
+            hide_scrollbar_gutters: config.hide_scrollbar_gutters(),
+            deterministic_canvas: config.deterministic_canvas(),
+            record_har: config.har(),
+            a11y: config.a11y(),
+            dom: config.dom(),
+            css_coverage: config.css_coverage(),
+            perf_metrics: config.perf_metrics(),
+            device_scale: config.device_scale(),
+            ignore_selectors: config.ignore_selectors().to_vec(),
+            pause_before_screenshot: config.debug,
+            cdp_timeout: config.cdp_timeout(),
+            dialogs: config.dialogs(),
+            bypass_service_workers: config.bypass_service_workers(),
+            disable_cache: config.disable_cache(),
+            headers: headers.clone(),
+            ignore_https_errors: config.ignore_https_errors(),
+        })
+    }
+
+    /// What is wrong with the remote Chrome, if anything (always None for a
+    /// locally launched one).
+    pub fn remote_problem(&self) -> Option<Problem> {
+        self.health.as_ref().and_then(RemoteHealth::problem)
+    }
+
+    /// Fingerprint the rendering environment (browser build, platform,
+    /// device scale, fonts) using a scratch tab.
+    pub async fn environment(&self) -> Result<Environment> {
+        let chrome = self.chrome.version().await?;
+        let mut session = self.new_session().await?;
+        // Emulate the configured scale so `devicePixelRatio` matches captures.
+        session
+            .conn
+            .set_viewport(800, 600, self.device_scale)
+            .await?;
+        let result = session.conn.eval(scripts::ENV_FINGERPRINT_JS).await;
+        let _ = self.close_session(session).await;
+
+        let raw = result?;
+        let json: serde_json::Value = raw["result"]["value"]
+            .as_str()
+            .and_then(|s| serde_json::from_str(s).ok())
+            .context("Unexpected environment fingerprint result")?;
+        let fonts = json["fonts"].as_str().unwrap_or_default();
+        let fonts_hash = crate::store::hash_bytes(fonts.as_bytes());
+        Ok(Environment {
+            chrome,
+            platform: json["platform"].as_str().unwrap_or_default().to_string(),
+            device_scale: json["dpr"].as_f64().unwrap_or(1.0),
+            fonts: fonts_hash[..12].to_string(),
+        })
     }
 
-    /// Close a session: drop the WebSocket connection, then close the tab.
+    /// Close a session: drop the WebSocket connection, then close the tab
+    /// (or dispose of its browser context, which closes the tab with it).
     pub async fn close_session(&self, session: CdpSession) -> Result<()> {
         let target_id = session.target_id;
         // Drop the WebSocket connection before closing the tab.
         drop(session.conn);
-        self.chrome.close_tab(&target_id).await
+        match (&self.contexts, session.context_id) {
+            (Some(browser), Some(context_id)) => {
+                browser
+                    .lock()
+                    .await
+                    .call(
+                        "Target.disposeBrowserContext",
+                        json!({ "browserContextId": context_id }),
+                    )
+                    .await
+                    .context("Failed to dispose browser context")?;
+                debug!(target_id, "browser context disposed");
+                Ok(())
+            }
+            _ => self.chrome.close_tab(&target_id).await,
+        }
+    }
+
+    /// Connect to a tab's WebSocket. Remote Chrome, often behind a proxy, can
+    /// drop a connection attempt while the tab is fine, so those are retried.
+    async fn connect_tab(&self, ws_url: &str) -> Result<CdpConnection> {
+        let attempts: u32 = if self.chrome.is_remote() { 3 } else { 1 };
+        let mut attempt = 1;
+        loop {
+            match self.chrome.open(ws_url).await {
+                Ok(conn) => return Ok(conn),
+                Err(e) if attempt < attempts => {
+                    debug!(error = %format!("{e:#}"), attempt, "tab connection failed, retrying");
+                    tokio::time::sleep(Duration::from_millis(250 * u64::from(attempt))).await;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Open a blank tab in a new browser context.
+    async fn create_context_tab(
+        &self,
+        browser: &tokio::sync::Mutex<CdpConnection>,
+    ) -> Result<(String, String, String)> {
+        let mut browser = browser.lock().await;
+        let context = browser
+            .call("Target.createBrowserContext", json!({}))
+            .await
+            .context("Failed to create browser context")?;
+        let context_id = context["browserContextId"]
+            .as_str()
+            .context("No browserContextId in Target.createBrowserContext response")?
+            .to_string();
+        let target = browser
+            .call(
+                "Target.createTarget",
+                json!({ "url": "about:blank", "browserContextId": context_id }),
+            )
+            .await
+            .context("Failed to create tab in browser context")?;
+        let target_id = target["targetId"]
+            .as_str()
+            .context("No targetId in Target.createTarget response")?
+            .to_string();
+        let ws_url = self.chrome.page_ws_url(&target_id);
+        Ok((target_id, ws_url, context_id))
     }
 
     pub async fn new_session(&self) -> Result<CdpSession> {
-        let (target_id, ws_url) = self.chrome.create_tab().await?;
-        debug!(target_id = %target_id, ws_url = %ws_url, "connecting to tab");
-        let mut conn = CdpConnection::connect(&ws_url).await?;
+        let (target_id, ws_url, context_id) = match &self.contexts {
+            Some(browser) => {
+                let (target_id, ws_url, context_id) = self.create_context_tab(browser).await?;
+                (target_id, ws_url, Some(context_id))
+            }
+            None => {
+                let (target_id, ws_url) = self.chrome.create_tab().await?;
+                (target_id, ws_url, None)
+            }
+        };
+        debug!(target_id = %target_id, ws_url = %redacted(&ws_url), "connecting to tab");
+        let mut conn = self.connect_tab(&ws_url).await?;
+        conn.set_command_timeout(self.cdp_timeout);
         debug!(target_id = %target_id, "enabling domains");
         conn.enable_domains().await?;
+        conn.answer_dialogs(self.dialogs == DialogAction::Accept);
+        if !self.headers.is_empty() {
+            conn.add_headers_to_paused_requests(self.headers.clone());
+        }
+        // The launch switch only covers local Chrome; this also covers remote.
+        if self.ignore_https_errors {
+            conn.ignore_certificate_errors().await?;
+        }
+        if self.bypass_service_workers {
+            conn.call("Network.setBypassServiceWorker", json!({ "bypass": true }))
+                .await
+                .context("Failed to bypass service workers")?;
+        }
+        if self.disable_cache {
+            conn.call("Network.setCacheDisabled", json!({ "cacheDisabled": true }))
+                .await
+                .context("Failed to disable the HTTP cache")?;
+        }
+        self.fonts.setup(&mut conn).await?;
+        let css_sheets = match self.css_coverage {
+            true => Some(css_coverage::start(&mut conn).await?),
+            false => None,
+        };
+        if self.perf_metrics {
+            conn.call("Performance.enable", json!({}))
+                .await
+                .context("Failed to enable Performance domain")?;
+        }
+        if self.deterministic_canvas {
+            conn.add_init_script(scripts::DETERMINISTIC_CANVAS_JS)
+                .await?;
+        }
         debug!(target_id = %target_id, "session ready");
         Ok(CdpSession {
             conn,
             screenshot: self.screenshot,
+            fonts: self.fonts.clone(),
+            hooks: self.hooks.clone(),
+            media_time: self.media_time,
+            send_headers: !self.headers.is_empty(),
+            headers_origin: None,
+            hide_scrollbar_gutters: self.hide_scrollbar_gutters,
+            deterministic_canvas: self.deterministic_canvas,
+            record_har: self.record_har,
+            a11y: self.a11y,
+            dom: self.dom,
+            css_sheets,
+            perf_metrics: self.perf_metrics,
+            device_scale: self.device_scale,
+            ignore_selectors: self.ignore_selectors.clone(),
+            pause_before_screenshot: self.pause_before_screenshot,
             target_id,
+            context_id,
         })
     }
 }
@@ -74,7 +386,27 @@ impl CdpRenderer {
 pub struct CdpSession {
     conn: CdpConnection,
     screenshot: Screenshot,
+    fonts: FontControl,
+    hooks: Hooks,
+    media_time: Option<f64>,
+    /// Whether the source has headers to add to the story origin's requests.
+    send_headers: bool,
+    /// Origin whose requests currently get the headers.
+    headers_origin: Option<String>,
+    hide_scrollbar_gutters: bool,
+    deterministic_canvas: bool,
+    record_har: bool,
+    a11y: bool,
+    dom: DomMode,
+    /// Stylesheets of the page, when `css_coverage` is enabled.
+    css_sheets: Option<Subscription>,
+    perf_metrics: bool,
+    device_scale: f64,
+    ignore_selectors: Vec<String>,
+    pause_before_screenshot: bool,
     target_id: String,
+    /// Browser context the tab was opened in (`isolation = "context"`).
+    context_id: Option<String>,
 }
 
 impl CdpSession {
@@ -82,41 +414,128 @@ impl CdpSession {
         &self.target_id
     }
 
+    /// Network activity of the current page as HAR JSON, when `har` is enabled.
+    pub fn har(&self) -> Option<String> {
+        if !self.record_har {
+            return None;
+        }
+        let har = har::build(&self.conn.network_events());
+        serde_json::to_string_pretty(&har).ok()
+    }
+
+    /// Rule usage of the page's stylesheets, when `css_coverage` is
+    /// enabled. Stops tracking, so call once, after the capture.
+    pub async fn css_usage(&mut self) -> Result<Option<Vec<SheetUsage>>> {
+        let Some(sheets) = self.css_sheets else {
+            return Ok(None);
+        };
+        css_coverage::collect(&mut self.conn, sheets)
+            .await
+            .map(Some)
+    }
+
+    /// Collect a screenshot, the page HTML and console output from the
+    /// current page state. Never fails: pieces that cannot be collected
+    /// within `ERROR_ARTIFACT_TIMEOUT` are left empty.
+    pub async fn error_artifacts(&mut self) -> ErrorArtifacts {
+        let har = self.har();
+        let conn = &mut self.conn;
+        let mut console = conn.console_messages();
+        if console.len() > MAX_CONSOLE_MESSAGES {
+            console.drain(..console.len() - MAX_CONSOLE_MESSAGES);
+        }
+        let mut artifacts = ErrorArtifacts {
+            console,
+            har,
+            ..Default::default()
+        };
+
+        let collect = async {
+            artifacts.screenshot = conn.capture_viewport_screenshot().await.ok();
+            artifacts.html = conn
+                .eval("document.documentElement.outerHTML")
+                .await
+                .ok()
+                .and_then(|v| v["result"]["value"].as_str().map(str::to_string));
+        };
+        if tokio::time::timeout(ERROR_ARTIFACT_TIMEOUT, collect)
+            .await
+            .is_err()
+        {
+            debug!("error artifact collection timed out");
+        }
+        artifacts
+    }
+
     /// Full capture pipeline.
     ///
     /// Pipeline stages:
-    /// 1. Set viewport
-    /// 2. Navigate
+    /// 1. Set viewport (and throttle the network for stories with `network`)
+    /// 2. Navigate (to the URL as rewritten by the hooks)
     /// 3. Wait load event
-    /// 4. Wait for network idle
-    /// 5. Disable animations
-    /// 6. Wait ready (fonts + DOM)
-    /// 7. Wait for story root selector
-    /// 8. Get clip bounds
+    /// 4. Wait for network idle, then run the after-navigation hooks
+    /// 5. Disable animations (apply the font override, pin media)
+    /// 6. Wait ready (play function, fonts + DOM, then any required font
+    ///    families), then run the after-ready hooks
+    /// 7. Wait for story root selector, then press Tab (focus variants) and
+    ///    apply `scroll_to`
+    /// 8. Hide scrollbar gutters, get clip bounds (and `ignore_selectors`
+    ///    masks), then run the before-screenshot hooks
     /// 9. Take screenshot (strategy)
-    pub async fn capture(&mut self, req: &CaptureRequest) -> Result<CaptureResult> {
+    ///
+    /// `on_stage` is called with each stage name (as in `CaptureTimings`)
+    /// as it starts, for progress display.
+    pub async fn capture(
+        &mut self,
+        req: &CaptureRequest,
+        on_stage: impl Fn(&'static str),
+    ) -> Result<CaptureResult> {
         let conn = &mut self.conn;
         let t0 = Instant::now();
 
         // 1. Set viewport
+        on_stage("viewport");
         debug!(width = req.width, height = req.height, "1/9 set_viewport");
-        conn.set_viewport(req.width, req.height).await?;
+        conn.set_viewport(req.width, req.height, self.device_scale)
+            .await?;
+        if let Some(network) = &req.settings.network {
+            let c = network.conditions();
+            debug!(?c, "1/9 emulate_network_conditions");
+            conn.emulate_network_conditions(
+                c.latency_ms,
+                c.download_bytes_per_sec(),
+                c.upload_bytes_per_sec(),
+            )
+            .await?;
+        }
         let t1 = Instant::now();
 
         // 2. Navigate
-        debug!(url = %req.url, "2/9 navigate");
-        conn.navigate(&req.url).await?;
+        on_stage("navigate");
+        let url = self.hooks.before_navigation(&req.url);
+        debug!(url = %url, "2/9 navigate");
+        if self.send_headers
+            && let Some(origin) = origin(&url)
+            && self.headers_origin.as_deref() != Some(origin)
+        {
+            conn.pause_requests_to(origin).await?;
+            self.headers_origin = Some(origin.to_string());
+        }
+        conn.navigate(&url).await?;
         let t2 = Instant::now();
 
         // 3. Wait for page load
+        on_stage("page_load");
         debug!("3/9 wait_page_load");
         conn.wait_page_load().await?;
         let t3 = Instant::now();
         debug!(elapsed_ms = (t3 - t2).as_millis() as u64, "3/9 page loaded");
 
         // 4. Wait for network idle
+        on_stage("network");
         debug!("4/9 network_wait");
         conn.wait_network_idle().await?;
+        self.hooks.after_navigation(conn, req).await?;
         let t4 = Instant::now();
         debug!(
             elapsed_ms = (t4 - t3).as_millis() as u64,
@@ -124,19 +543,37 @@ impl CdpSession {
         );
 
         // 5. Disable animations
+        on_stage("animation");
         debug!("5/9 disable_animations");
         strategy::disable_animations(conn).await?;
+        self.fonts.apply_override(conn).await?;
+        if let Some(time) = self.media_time {
+            strategy::pin_media(conn, time).await?;
+        }
         let t5 = Instant::now();
 
-        // 6. Wait for ready (fonts + DOM stable)
+        // 6. Wait for ready (play function, then fonts + DOM stable)
+        on_stage("ready");
         debug!("6/9 wait_ready");
+        let play = conn.eval_async(scripts::WAIT_FOR_PLAY_JS).await?;
+        debug!(phase = %play["result"]["value"], "6/9 play function");
         conn.eval_async(scripts::WAIT_FOR_READY_JS).await?;
+        self.fonts.wait_loaded(conn).await?;
+        self.hooks.after_ready(conn, req).await?;
         let t6 = Instant::now();
         debug!(elapsed_ms = (t6 - t5).as_millis() as u64, "6/9 ready");
 
         // 7. Wait for story root selector (poll until visible with non-zero dimensions)
+        on_stage("selector");
         debug!("7/9 wait_story_root");
-        conn.eval_async(scripts::WAIT_FOR_STORY_ROOT_JS).await?;
+        if !req.whole_page {
+            conn.eval_async(scripts::WAIT_FOR_STORY_ROOT_JS).await?;
+        }
+        let focus_scroll = strategy::focus(conn, req.focus_presses).await?;
+        let window_scroll = match req.settings.scroll_to {
+            Some(_) => strategy::scroll(conn, &req.settings).await?,
+            None => focus_scroll,
+        };
         let t7 = Instant::now();
         debug!(
             elapsed_ms = (t7 - t6).as_millis() as u64,
@@ -144,8 +581,17 @@ impl CdpSession {
         );
 
         // 8. Get clip bounds
+        on_stage("clip");
+        if self.hide_scrollbar_gutters {
+            let gutters = strategy::hide_scrollbar_gutters(conn).await?;
+            debug!(gutters, "8/9 hid scrollbar gutters");
+        }
         debug!("8/9 get_clip");
-        let mut clip = strategy::get_clip(conn).await?;
+        let mut clip = if req.whole_page {
+            strategy::get_page_clip(conn).await?
+        } else {
+            strategy::get_clip(conn).await?
+        };
 
         // Clamp clip width to viewport.
         let vp_w = req.width as f64;
@@ -158,22 +604,48 @@ impl CdpSession {
             clip.w = vp_w;
         }
 
+        // A scrolled window shows what is on screen: keep the clip inside
+        // the viewport (growing it would undo the scroll).
+        if window_scroll.is_some() {
+            let top = clip.y.max(0.0);
+            clip.h = (clip.y + clip.h).min(req.height as f64) - top;
+            clip.y = top;
+        }
+
         // Ensure minimum dimensions (defensive).
         clip.w = clip.w.max(1.0);
         clip.h = clip.h.max(1.0);
 
-        // Resize viewport for tall content.
-        let resized = clip.h > req.height as f64;
+        // Resize viewport for tall content, unless the screenshot can
+        // capture beyond the viewport.
+        let resized = clip.h > req.height as f64 && !self.screenshot.beyond_viewport();
         if resized {
             let new_h = clip.h.ceil() as u32;
             debug!(
                 original_h = req.height,
                 new_h, "resizing viewport for tall content"
             );
-            conn.set_viewport(req.width, new_h).await?;
+            conn.set_viewport(req.width, new_h, self.device_scale)
+                .await?;
             tokio::time::sleep(VIEWPORT_RESIZE_SETTLE).await;
         }
 
+        // Measure masks after any resize so they match the screenshot layout.
+        // Measured in CSS pixels; the screenshot has `device_scale` times as many.
+        let masks: Vec<Mask> = strategy::get_ignore_masks(conn, &self.ignore_selectors, &clip)
+            .await?
+            .into_iter()
+            .map(|m| m.scaled(self.device_scale))
+            .collect();
+
+        // Clip bounds are viewport-relative; the screenshot clip is in page
+        // coordinates.
+        if let Some((x, y)) = window_scroll {
+            clip.x += x;
+            clip.y += y;
+        }
+        self.hooks.before_screenshot(conn, req).await?;
+
         let t8 = Instant::now();
         debug!(
             x = clip.x,
@@ -181,22 +653,63 @@ impl CdpSession {
             w = clip.w,
             h = clip.h,
             resized,
+            masks = masks.len(),
             "8/9 clip bounds"
         );
 
+        if self.pause_before_screenshot {
+            pause(req, &clip).await?;
+        }
+
         // 9. Take screenshot (strategy)
+        on_stage("screenshot");
         debug!("9/9 screenshot");
-        let png = self.screenshot.take(conn, &clip).await?;
+        if self.deterministic_canvas {
+            // Let canvas/WebGL content composite one more frame.
+            conn.eval_async(scripts::SETTLE_FRAMES_JS).await?;
+        }
+        let (png, screenshots) = self
+            .screenshot
+            .take(conn, &clip, req.single_screenshot)
+            .await?;
         let t9 = Instant::now();
+        let perf = if self.perf_metrics {
+            let result = conn
+                .call("Performance.getMetrics", json!({}))
+                .await
+                .context("Failed to get performance metrics")?;
+            Some(PerfMetrics::from_cdp(&result))
+        } else {
+            None
+        };
         debug!(
             bytes = png.len(),
             elapsed_ms = (t9 - t8).as_millis() as u64,
             "9/9 screenshot done"
         );
+        if req.url.starts_with("https://") {
+            match mixed_content(conn).await {
+                Ok(resources) => {
+                    for resource in resources {
+                        warn!(story = %req.url, "mixed content: {resource}");
+                    }
+                }
+                Err(e) => debug!(error = %format!("{e:#}"), "mixed content check failed"),
+            }
+        }
 
         // Restore original viewport if resized.
         if resized {
-            conn.set_viewport(req.width, req.height).await?;
+            conn.set_viewport(req.width, req.height, self.device_scale)
+                .await?;
+        }
+
+        let mut texts = BTreeMap::new();
+        if self.a11y {
+            texts.insert(store::A11Y_TEXT.to_string(), a11y::snapshot(conn).await?);
+        }
+        if self.dom != DomMode::Off {
+            texts.insert(store::DOM_TEXT.to_string(), dom::snapshot(conn).await?);
         }
 
         let timings = CaptureTimings {
@@ -210,9 +723,61 @@ impl CdpSession {
             clip: t8 - t7,
             screenshot: t9 - t8,
             total: t9 - t0,
+            screenshots,
             compare: Duration::ZERO,
+            identical: false,
+            perf,
         };
 
-        Ok(CaptureResult { png, timings })
+        Ok(CaptureResult {
+            png,
+            timings,
+            masks,
+            texts,
+        })
     }
 }
+
+/// Debug mode: wait for Enter so the page can be inspected in the visible
+/// Chrome window before the screenshot is taken.
+async fn pause(req: &CaptureRequest, clip: &ClipRect) -> Result<()> {
+    eprintln!(
+        "\r\x1b[2K\n  Paused before screenshot of {}\n  clip: x={} y={} {}x{}\n  Inspect the Chrome window, then press Enter to continue...",
+        req.url, clip.x, clip.y, clip.w, clip.h
+    );
+    tokio::task::spawn_blocking(|| {
+        let mut line = String::new();
+        std::io::stdin().read_line(&mut line).map(|_| ())
+    })
+    .await
+    .context("Pause task panicked")?
+    .context("Failed to read from stdin")
+}
+
+/// Insecure resources an HTTPS page requested, as `<status> <url> (<type>)`.
+/// Chrome blocks most of them, so the capture is missing content that a
+/// plain-HTTP Storybook shows. Audits reports issues collected so far when
+/// enabled, so this works after the page has loaded.
+async fn mixed_content(conn: &mut CdpConnection) -> Result<Vec<String>> {
+    let issues = conn.subscribe(&["Audits.issueAdded"], MAX_ISSUES);
+    conn.call("Audits.enable", json!({}))
+        .await
+        .context("Failed to enable Audits domain")?;
+    Ok(conn
+        .events(issues)
+        .filter(|e| e.params["issue"]["code"].as_str() == Some("MixedContentIssue"))
+        .map(|e| {
+            let details = &e.params["issue"]["details"]["mixedContentIssueDetails"];
+            let status = match details["resolutionStatus"].as_str() {
+                Some("MixedContentBlocked") => "blocked",
+                Some("MixedContentAutomaticallyUpgraded") => "upgraded",
+                _ => "loaded",
+            };
+            format!(
+                "{status} {} ({})",
+                details["insecureURL"].as_str().unwrap_or_default(),
+                details["resourceType"].as_str().unwrap_or("resource")
+            )
+        })
+        .collect())
+}
@@ -1,22 +1,73 @@
-use anyhow::Result;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::sync::Arc;
+
+use anyhow::{Result, bail};
 use tokio::sync::mpsc;
+use tracing::warn;
 
-use super::job::CaptureJob;
+use super::activity::Activity;
+use super::job::{CaptureJob, args_variant_id, focus_variant_id, render_snapshot_id};
 use super::runner::{CaptureOutcome, capture_all};
-use crate::config::{CaptureConfig, ResolvedRunConfig};
-use crate::storybook::Storybook;
+use crate::config::{CaptureConfig, ResolvedRunConfig, matches_pattern, settings_for};
+use crate::storybook::{Filter, SourceKind, StaticServer, Storybook, static_dir};
+
+/// Which planned snapshots a run captures: `--filter` and `--ids-from`.
+#[derive(Clone, Debug, Default)]
+pub struct Selection {
+    pub filter: Option<Filter>,
+    /// Exact snapshot IDs (`--ids-from`).
+    pub ids: Option<BTreeSet<String>>,
+}
+
+impl Selection {
+    /// Whether the run covers only some of the snapshots.
+    pub fn is_partial(&self) -> bool {
+        self.filter.is_some() || self.ids.is_some()
+    }
+
+    fn keeps(&self, job: &CaptureJob) -> bool {
+        self.filter.as_ref().is_none_or(|f| job.matches_filter(f))
+            && self.ids.as_ref().is_none_or(|ids| ids.contains(&job.id))
+    }
+}
 
 /// Plans and executes a capture run: discovery, job building, filtering, capture.
 pub struct CapturePlan {
     config: CaptureConfig,
+    headers: BTreeMap<String, String>,
     jobs: Vec<CaptureJob>,
+    activity: Arc<Activity>,
+    /// Serves a `storybook-static` directory for the duration of the run.
+    server: Option<StaticServer>,
 }
 
 impl CapturePlan {
-    /// Discover stories, build the job list (stories x viewports), filter.
-    pub async fn plan(config: &ResolvedRunConfig, filter: Option<&str>) -> Result<Self> {
-        let local = config.capture.chrome_url.is_none();
-        let storybook = Storybook::new(&config.storybook_url, local)?;
+    /// Discover stories, build the job list (stories x viewports), and keep
+    /// the selected jobs.
+    pub async fn plan(config: &ResolvedRunConfig, selection: &Selection) -> Result<Self> {
+        let local = config.capture.local_browser();
+        let server = match static_dir(&config.storybook_url) {
+            Some(dir) => {
+                let server = StaticServer::start(&dir, !local).await?;
+                println!("Serving {} at {}", dir.display(), server.url());
+                Some(server)
+            }
+            None => None,
+        };
+        let url = server
+            .as_ref()
+            .map_or(config.storybook_url.as_str(), |s| s.url());
+        let storybook = Storybook::new(
+            config.source_kind,
+            url,
+            local,
+            &config.headers,
+            config.capture.ignore_https_errors(),
+        )?
+        .with_page_options(config.pages.clone())
+        .with_nextjs_options(config.nextjs.clone())
+        .with_wait(config.capture.wait_for_storybook())
+        .with_offline(config.capture.offline);
         let stories: Vec<_> = storybook
             .discover()
             .await?
@@ -28,7 +79,10 @@ impl CapturePlan {
             println!("No stories found at {}", storybook.url());
             return Ok(Self {
                 config: config.capture.clone(),
+                headers: config.headers.clone(),
                 jobs: Vec::new(),
+                activity: Arc::default(),
+                server,
             });
         }
 
@@ -46,30 +100,98 @@ impl CapturePlan {
         );
         println!();
 
+        for id in config.stories.keys() {
+            if !stories.iter().any(|s| matches_pattern(id, &s.id)) {
+                warn!(story = %id, "[story] settings match no discovered story");
+            }
+        }
+
+        let template = config.store.id_template();
+        let mut seen = HashSet::new();
         let mut jobs: Vec<CaptureJob> = Vec::new();
         for story in &stories {
+            let settings = settings_for(&config.stories, &story.id)
+                .cloned()
+                .unwrap_or_default();
+            let combinations = settings.arg_combinations();
+            if !combinations.is_empty() && config.source_kind != SourceKind::Storybook {
+                bail!(
+                    "story.\"{}\": args permutations are only supported for Storybook sources",
+                    story.id
+                );
+            }
+            let focus_steps = settings
+                .focus_steps
+                .or(config.capture.focus_steps)
+                .unwrap_or(0);
             for (vp_name, vp) in &viewports {
-                jobs.push(CaptureJob {
+                let id = render_snapshot_id(template, &config.source_name, vp_name, story);
+                if !seen.insert(id.clone()) {
+                    bail!(
+                        "store.id_template \"{template}\" maps several snapshots to \"{id}\". \
+                         Include {{viewport}} (and {{name}} or {{id}}) to keep IDs unique."
+                    );
+                }
+                let job = CaptureJob {
                     source: config.source_name.clone(),
                     story: story.clone(),
                     viewport: vp_name.clone(),
                     url: storybook.story_url(story),
                     width: vp.width,
                     height: vp.height,
-                });
+                    id,
+                    settings: settings.clone(),
+                    args: None,
+                    whole_page: config.source_kind.captures_pages(),
+                    focus: 0,
+                };
+                // Args permutations replace the plain snapshot.
+                let bases = if combinations.is_empty() {
+                    vec![job]
+                } else {
+                    combinations
+                        .iter()
+                        .map(|args| CaptureJob {
+                            id: args_variant_id(&job.id, &args.label()),
+                            url: format!("{}&args={}", job.url, args.url_param()),
+                            args: Some(args.label()),
+                            ..job.clone()
+                        })
+                        .collect()
+                };
+                for job in bases {
+                    // Focus variants: the same page after 1..=N Tab presses.
+                    let variants: Vec<CaptureJob> = (1..=focus_steps)
+                        .map(|n| CaptureJob {
+                            id: focus_variant_id(&job.id, n),
+                            focus: n,
+                            ..job.clone()
+                        })
+                        .collect();
+                    jobs.push(job);
+                    jobs.extend(variants);
+                }
             }
         }
 
-        if let Some(pattern) = filter {
-            jobs.retain(|job| job.matches_filter(pattern));
+        if let Some(ids) = &selection.ids {
+            for id in ids.iter().filter(|id| !jobs.iter().any(|j| &j.id == *id)) {
+                warn!(snapshot = %id, "--ids-from lists a snapshot this run doesn't plan");
+            }
+        }
+        if selection.is_partial() {
+            jobs.retain(|job| selection.keeps(job));
             if jobs.is_empty() {
-                println!("No snapshots match filter");
+                println!("No snapshots match the selection");
             }
         }
 
         Ok(Self {
             config: config.capture.clone(),
+            headers: config.headers.clone(),
             jobs,
+            activity: Arc::default(),
+            server,
         })
     }
 
@@ -77,13 +199,45 @@ impl CapturePlan {
         self.jobs.len()
     }
 
+    /// Queue every job `runs` times (for repeated-capture analysis).
+    pub fn repeat(&mut self, runs: usize) {
+        let jobs = std::mem::take(&mut self.jobs);
+        self.jobs = (0..runs.max(1))
+            .flat_map(|_| jobs.iter().cloned())
+            .collect();
+    }
+
+    pub fn jobs(&self) -> &[CaptureJob] {
+        &self.jobs
+    }
+
     /// Return the snapshot names (IDs) for all jobs in this run.
     pub fn job_names(&self) -> Vec<String> {
         self.jobs.iter().map(|j| j.snapshot_id()).collect()
     }
 
+    /// Live per-worker state of the run, for progress display.
+    pub fn activity(&self) -> Arc<Activity> {
+        self.activity.clone()
+    }
+
     /// Launch Chrome and start capturing. Consumes self.
     pub async fn execute(self) -> Result<mpsc::Receiver<(CaptureJob, CaptureOutcome)>> {
-        capture_all(self.jobs, &self.config).await
+        let mut results =
+            capture_all(self.jobs, &self.config, &self.headers, self.activity).await?;
+        let Some(server) = self.server else {
+            return Ok(results);
+        };
+        // Keep the static server up until every capture has been delivered.
+        let (tx, rx) = mpsc::channel(1);
+        tokio::spawn(async move {
+            let _server = server;
+            while let Some(result) = results.recv().await {
+                if tx.send(result).await.is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(rx)
     }
 }
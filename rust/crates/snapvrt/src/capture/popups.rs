@@ -0,0 +1,58 @@
+use anyhow::{Context, Result};
+use serde_json::json;
+use tokio::task::JoinHandle;
+use tracing::debug;
+
+use crate::cdp::Chrome;
+use crate::cdp::events::Reply;
+
+/// Closes tabs that pages open (`window.open`, clicks on `target="_blank"`
+/// links) as soon as Chrome reports them, so they don't pile up and hold
+/// memory for the rest of the run. Stops when dropped.
+pub struct PopupCloser {
+    task: JoinHandle<()>,
+}
+
+impl PopupCloser {
+    /// Watch `chrome` for new targets.
+    pub async fn start(chrome: &Chrome) -> Result<Self> {
+        let mut conn = chrome
+            .open(&chrome.browser_ws_url().await?)
+            .await
+            .context("Failed to connect to the browser target")?;
+        conn.on_event("Target.targetCreated", |event| {
+            let info = &event.params["targetInfo"];
+            // Our own tabs have no opener.
+            let opener = info["openerId"].as_str().filter(|id| !id.is_empty())?;
+            if info["type"].as_str() != Some("page") {
+                return None;
+            }
+            let target_id = info["targetId"].as_str()?;
+            debug!(
+                target_id,
+                opener,
+                url = info["url"].as_str().unwrap_or_default(),
+                "closing popup"
+            );
+            Some(Reply {
+                method: "Target.closeTarget",
+                params: json!({ "targetId": target_id }),
+            })
+        });
+        conn.call("Target.setDiscoverTargets", json!({ "discover": true }))
+            .await
+            .context("Failed to watch for new targets")?;
+        let task = tokio::spawn(async move {
+            if let Err(e) = conn.listen().await {
+                debug!(error = %format!("{e:#}"), "popup closer stopped");
+            }
+        });
+        Ok(Self { task })
+    }
+}
+
+impl Drop for PopupCloser {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
@@ -0,0 +1,123 @@
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+
+use super::css_coverage::SheetUsage;
+use super::environment::Environment;
+use super::health::Problem;
+use super::pipeline::{CaptureRequest, CaptureResult, CdpRenderer, CdpSession, ErrorArtifacts};
+use super::webdriver::{WebDriverRenderer, WebDriverSession};
+use crate::config::CaptureConfig;
+
+/// Capture backend: Chrome DevTools (local or `chrome_url`), or a WebDriver
+/// server (`webdriver_url`) where DevTools can't be reached.
+pub enum Renderer {
+    Cdp(Box<CdpRenderer>),
+    WebDriver(Box<WebDriverRenderer>),
+}
+
+/// A page to capture in, from [`Renderer::new_session`].
+pub enum Session {
+    Cdp(Box<CdpSession>),
+    WebDriver(Box<WebDriverSession>),
+}
+
+impl Renderer {
+    /// Launch the configured backend. `instance` numbers the renderers of a
+    /// run (see [`CdpRenderer::launch`]).
+    pub async fn launch(
+        config: &CaptureConfig,
+        headers: &BTreeMap<String, String>,
+        instance: usize,
+    ) -> Result<Self> {
+        Ok(match &config.webdriver_url {
+            Some(url) => Self::WebDriver(Box::new(
+                WebDriverRenderer::launch(config, headers, url).await?,
+            )),
+            None => Self::Cdp(Box::new(
+                CdpRenderer::launch(config, headers, instance).await?,
+            )),
+        })
+    }
+
+    pub async fn environment(&self) -> Result<Environment> {
+        match self {
+            Self::Cdp(r) => r.environment().await,
+            Self::WebDriver(r) => r.environment().await,
+        }
+    }
+
+    pub async fn new_session(&self) -> Result<Session> {
+        Ok(match self {
+            Self::Cdp(r) => Session::Cdp(Box::new(r.new_session().await?)),
+            Self::WebDriver(r) => Session::WebDriver(Box::new(r.new_session().await?)),
+        })
+    }
+
+    pub async fn close_session(&self, session: Session) -> Result<()> {
+        match (self, session) {
+            (Self::Cdp(r), Session::Cdp(s)) => r.close_session(*s).await,
+            (Self::WebDriver(r), Session::WebDriver(s)) => r.close_session(*s).await,
+            _ => unreachable!("session from another renderer"),
+        }
+    }
+
+    /// What is wrong with a remote Chrome, if anything.
+    pub fn remote_problem(&self) -> Option<Problem> {
+        match self {
+            Self::Cdp(r) => r.remote_problem(),
+            Self::WebDriver(_) => None,
+        }
+    }
+
+    /// Release what dropping can't (WebDriver sessions on a remote server).
+    pub async fn shutdown(&self) {
+        match self {
+            Self::Cdp(_) => {}
+            Self::WebDriver(r) => r.shutdown().await,
+        }
+    }
+}
+
+impl Session {
+    pub fn target_id(&self) -> &str {
+        match self {
+            Self::Cdp(s) => s.target_id(),
+            Self::WebDriver(s) => s.target_id(),
+        }
+    }
+
+    pub async fn capture(
+        &mut self,
+        req: &CaptureRequest,
+        on_stage: impl Fn(&'static str),
+    ) -> Result<CaptureResult> {
+        match self {
+            Self::Cdp(s) => s.capture(req, on_stage).await,
+            Self::WebDriver(s) => s.capture(req, on_stage).await,
+        }
+    }
+
+    /// See [`CdpSession::har`]; always None over WebDriver.
+    pub fn har(&self) -> Option<String> {
+        match self {
+            Self::Cdp(s) => s.har(),
+            Self::WebDriver(_) => None,
+        }
+    }
+
+    /// See [`CdpSession::css_usage`]; always None over WebDriver.
+    pub async fn css_usage(&mut self) -> Result<Option<Vec<SheetUsage>>> {
+        match self {
+            Self::Cdp(s) => s.css_usage().await,
+            Self::WebDriver(_) => Ok(None),
+        }
+    }
+
+    pub async fn error_artifacts(&mut self) -> ErrorArtifacts {
+        match self {
+            Self::Cdp(s) => s.error_artifacts().await,
+            Self::WebDriver(s) => s.error_artifacts().await,
+        }
+    }
+}
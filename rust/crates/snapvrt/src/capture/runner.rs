@@ -1,36 +1,153 @@
+use std::collections::{BTreeMap, HashSet};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::{Duration, Instant};
 
 use anyhow::Result;
 use tokio::sync::{Mutex, mpsc};
-use tracing::{Instrument, debug, debug_span, info_span, warn};
+use tracing::{Instrument, debug, debug_span, info, info_span, warn};
 
+use super::activity::Activity;
+use super::css_coverage::{self, CssCoverage};
+use super::health::Problem;
 use super::job::CaptureJob;
-use super::pipeline::{CaptureRequest, CdpRenderer};
-use super::timing::CaptureTimings;
-use crate::config::CaptureConfig;
+use super::memory;
+use super::pipeline::{CaptureRequest, CaptureResult};
+use super::renderer::{Renderer, Session};
+use super::stability::StabilityHistory;
+use crate::cdp::ConnectionLost;
+use crate::config::capture::ScreenshotKind;
+use crate::config::{CaptureConfig, CaptureOrder, Isolation};
+use crate::interrupt;
+use crate::store;
 
 /// Per-capture timeout. Covers navigate + load + network idle + ready + screenshot.
 /// Must exceed the sum of individual stage timeouts (network: 10s, ready JS: 10s,
 /// stable screenshot: ~600ms) plus time for Chrome to actually load the page.
 const CAPTURE_TIMEOUT: Duration = Duration::from_secs(30);
 
+/// Cache file holding each snapshot's last capture time in ms, for `slowest-first`.
+const TIMINGS_CACHE: &str = "timings.json";
+
 /// Per-snapshot capture outcome.
 pub enum CaptureOutcome {
-    Ok(Vec<u8>, CaptureTimings),
+    Ok(Box<CaptureResult>),
     Err(String),
 }
 
-/// Drain remaining jobs from the queue, reporting each as a Chrome crash error.
+/// Drain remaining jobs from the queue, reporting each as failed for `reason`.
 async fn drain_crashed(
     queue: &Mutex<Vec<CaptureJob>>,
     tx: &mpsc::Sender<(CaptureJob, CaptureOutcome)>,
+    reason: &str,
 ) {
     while let Some(job) = queue.lock().await.pop() {
-        let _ = tx
-            .send((job, CaptureOutcome::Err("Chrome process crashed".into())))
-            .await;
+        let _ = tx.send((job, CaptureOutcome::Err(reason.into()))).await;
+    }
+}
+
+/// Prefix a capture error with what is wrong with the remote Chrome, so a
+/// run against a recycled browser doesn't read as many broken stories.
+fn with_problem(renderer: &Renderer, msg: String) -> String {
+    match renderer.remote_problem() {
+        Some(problem) => format!("{problem}: {msg}"),
+        None => msg,
+    }
+}
+
+/// Arrange `jobs` so that popping from the end yields them in `order`.
+fn schedule(jobs: &mut [CaptureJob], order: CaptureOrder, timings: &BTreeMap<String, u64>) {
+    match order {
+        CaptureOrder::Discovery => {}
+        // Unknown (new) snapshots count as slowest: they may well be.
+        CaptureOrder::SlowestFirst => {
+            jobs.sort_by_key(|job| timings.get(&job.id).copied().unwrap_or(u64::MAX))
+        }
+        CaptureOrder::Title => {
+            let key = |job: &CaptureJob| {
+                (
+                    job.story.title.clone(),
+                    job.story.id.clone(),
+                    job.id.clone(),
+                )
+            };
+            jobs.sort_by_key(|job| std::cmp::Reverse(key(job)));
+        }
+    }
+}
+
+fn read_timings() -> BTreeMap<String, u64> {
+    store::read_cache(TIMINGS_CACHE)
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+/// Merge this run's capture times into the timing history.
+fn write_timings(run: BTreeMap<String, u64>) {
+    let mut timings = read_timings();
+    timings.extend(run);
+    let result = serde_json::to_string(&timings)
+        .map_err(anyhow::Error::from)
+        .and_then(|json| store::write_cache(TIMINGS_CACHE, &json));
+    if let Err(e) = result {
+        warn!(error = %format!("{e:#}"), "failed to save capture timings");
+    }
+}
+
+/// Write the run's CSS coverage report, if any stylesheet was tracked.
+fn write_coverage(coverage: &CssCoverage) {
+    if coverage.is_empty() {
+        return;
+    }
+    match coverage.write() {
+        Ok((unused, total)) => info!(
+            unused,
+            total,
+            "CSS coverage: {unused} of {total} rules unused, see {}/{}",
+            store::BASE_DIR,
+            css_coverage::OUTPUT_FILE
+        ),
+        Err(e) => warn!(error = %format!("{e:#}"), "failed to save CSS coverage"),
+    }
+}
+
+/// Carry the timing history of renamed snapshots (`(from, to)` pairs) over
+/// to their new IDs, so `slowest-first` ordering survives `snapvrt mv`.
+pub fn rename_timings(pairs: &[(String, String)]) {
+    let mut timings = read_timings();
+    let moved: Vec<(&String, u64)> = pairs
+        .iter()
+        .filter_map(|(from, to)| timings.remove(from).map(|ms| (to, ms)))
+        .collect();
+    if moved.is_empty() {
+        return;
+    }
+    timings.extend(moved.into_iter().map(|(to, ms)| (to.clone(), ms)));
+    let result = serde_json::to_string(&timings)
+        .map_err(anyhow::Error::from)
+        .and_then(|json| store::write_cache(TIMINGS_CACHE, &json));
+    if let Err(e) = result {
+        warn!(error = %format!("{e:#}"), "failed to save capture timings");
+    }
+}
+
+/// Save best-effort page state for a failed capture to `errors/<id>/`.
+/// Returns a suffix for the error message pointing at the artifacts.
+async fn save_error_artifacts(session: &mut Session, job: &CaptureJob, msg: &str) -> String {
+    let artifacts = session.error_artifacts().await;
+    match store::write_error_artifacts(
+        &job.id,
+        msg,
+        artifacts.screenshot.as_deref(),
+        artifacts.html.as_deref(),
+        &artifacts.console,
+        artifacts.har.as_deref(),
+    ) {
+        Ok(dir) => format!(" — see {dir}"),
+        Err(e) => {
+            warn!(error = %format!("{e:#}"), "failed to save error artifacts");
+            String::new()
+        }
     }
 }
 
@@ -40,28 +157,61 @@ async fn drain_crashed(
 ///
 /// Returns a `Receiver` — results stream in as captures complete.
 pub async fn capture_all(
-    jobs: Vec<CaptureJob>,
+    mut jobs: Vec<CaptureJob>,
     config: &CaptureConfig,
+    headers: &BTreeMap<String, String>,
+    activity: Arc<Activity>,
 ) -> Result<mpsc::Receiver<(CaptureJob, CaptureOutcome)>> {
     if jobs.is_empty() {
         let (_tx, rx) = mpsc::channel(1);
         return Ok(rx);
     }
 
-    let parallel = config.parallel();
-    let renderer = CdpRenderer::launch(config).await?;
-    capture_all_with(renderer, jobs, parallel).await
+    // Chrome shares snapvrt's container only when launched locally.
+    let parallel = match config.local_browser() {
+        true => memory::cap_parallel(config.parallel(), config.tab_memory_mb()),
+        false => config.parallel(),
+    };
+    schedule(&mut jobs, config.order(), &read_timings());
+    // Debug mode pauses for user input mid-capture, so no timeout.
+    let timeout = (!config.debug).then_some(CAPTURE_TIMEOUT);
+    let renderer = Renderer::launch(config, headers, 0).await?;
+    match renderer.environment().await {
+        Ok(env) => activity.set_environment(env),
+        Err(e) => warn!(error = %format!("{e:#}"), "failed to fingerprint environment"),
+    }
+    let mut renderers = vec![renderer];
+    // Over WebDriver, every capture gets its own browser session instead.
+    if config.isolation() == Isolation::Browser && config.webdriver_url.is_none() {
+        let workers = jobs.len().min(parallel.max(1));
+        let more = (1..workers).map(|instance| Renderer::launch(config, headers, instance));
+        renderers.extend(futures::future::try_join_all(more).await?);
+    }
+    let stability = (config.screenshot == Some(ScreenshotKind::Adaptive)).then(|| {
+        let history = StabilityHistory::load();
+        debug!(
+            single = history.single_count(),
+            "adaptive screenshots: known-stable snapshots"
+        );
+        history
+    });
+    capture_all_with(renderers, jobs, parallel, timeout, stability, activity).await
 }
 
 /// Capture orchestration: creates parallel workers with a shared work queue.
 ///
 /// Each capture gets a fresh tab to avoid browser-level WS mutex contention.
+/// With several renderers (`isolation = "browser"`), worker `i` uses
+/// renderer `i % renderers.len()`.
 ///
 /// Returns a `Receiver` immediately — captures stream in via the channel.
 async fn capture_all_with(
-    renderer: CdpRenderer,
+    renderers: Vec<Renderer>,
     jobs: Vec<CaptureJob>,
     parallel: usize,
+    timeout: Option<Duration>,
+    stability: Option<StabilityHistory>,
+    activity: Arc<Activity>,
 ) -> Result<mpsc::Receiver<(CaptureJob, CaptureOutcome)>> {
     let job_count = jobs.len();
     let worker_count = job_count.min(parallel.max(1));
@@ -75,9 +225,14 @@ async fn capture_all_with(
     /// Consecutive session-creation failures before we declare Chrome dead.
     const MAX_SESSION_FAILURES: u32 = 3;
 
-    let renderer = Arc::new(renderer);
+    let renderers: Vec<Arc<Renderer>> = renderers.into_iter().map(Arc::new).collect();
     let queue = Arc::new(Mutex::new(jobs));
     let chrome_dead = Arc::new(AtomicBool::new(false));
+    // Jobs already requeued once after losing their tab's connection.
+    let retried = Arc::new(std::sync::Mutex::new(HashSet::new()));
+    let timings = Arc::new(std::sync::Mutex::new(BTreeMap::new()));
+    let coverage = Arc::new(std::sync::Mutex::new(CssCoverage::default()));
+    let stability = stability.map(|history| Arc::new(std::sync::Mutex::new(history)));
 
     let (tx, rx) = mpsc::channel(parallel.max(1) * 2);
 
@@ -86,8 +241,13 @@ async fn capture_all_with(
     for idx in 0..worker_count {
         let queue = queue.clone();
         let tx = tx.clone();
-        let renderer = renderer.clone();
+        let renderer = renderers[idx % renderers.len()].clone();
         let chrome_dead = chrome_dead.clone();
+        let retried = retried.clone();
+        let activity = activity.clone();
+        let timings = timings.clone();
+        let coverage = coverage.clone();
+        let stability = stability.clone();
         let span = info_span!("worker", id = idx);
         set.spawn(
             async move {
@@ -95,10 +255,31 @@ async fn capture_all_with(
                 let mut consecutive_session_failures: u32 = 0;
 
                 loop {
+                    // After Ctrl-C, leave the rest of the queue uncaptured.
+                    if interrupt::requested() {
+                        debug!("interrupted, exiting");
+                        break;
+                    }
+
                     // If another worker detected Chrome is dead, drain and exit.
                     if chrome_dead.load(Ordering::Relaxed) {
                         debug!("chrome is dead, draining remaining jobs");
-                        drain_crashed(&queue, &tx).await;
+                        drain_crashed(&queue, &tx, "Chrome process crashed").await;
+                        break;
+                    }
+
+                    // The remote browser was recycled: every tab, context and
+                    // watcher of this run is gone with it.
+                    if renderer.remote_problem() == Some(Problem::Restarted) {
+                        warn!("remote Chrome restarted mid-run, aborting remaining captures");
+                        chrome_dead.store(true, Ordering::Relaxed);
+                        drain_crashed(
+                            &queue,
+                            &tx,
+                            "Remote Chrome restarted mid-run; re-run, or raise the remote \
+                             browser's session timeout",
+                        )
+                        .await;
                         break;
                     }
 
@@ -116,6 +297,7 @@ async fn capture_all_with(
                         }
                     };
                     debug!(job = %job.snapshot_id(), remaining, "picked job");
+                    activity.begin(idx, &job.id);
 
                     // Create a fresh session (tab) for each capture.
                     let t_create = Instant::now();
@@ -136,10 +318,14 @@ async fn capture_all_with(
                                 consecutive = consecutive_session_failures,
                                 "failed to create session"
                             );
+                            activity.finish(idx);
                             let _ = tx
                                 .send((
                                     job,
-                                    CaptureOutcome::Err(format!("Session creation failed: {e:#}")),
+                                    CaptureOutcome::Err(with_problem(
+                                        &renderer,
+                                        format!("Session creation failed: {e:#}"),
+                                    )),
                                 ))
                                 .await;
 
@@ -150,7 +336,8 @@ async fn capture_all_with(
                                      aborting remaining captures"
                                 );
                                 chrome_dead.store(true, Ordering::Relaxed);
-                                drain_crashed(&queue, &tx).await;
+                                let reason = with_problem(&renderer, "Chrome process crashed".into());
+                                drain_crashed(&queue, &tx, &reason).await;
                                 break;
                             }
                             continue;
@@ -161,40 +348,103 @@ async fn capture_all_with(
                         url: job.url.clone(),
                         width: job.width,
                         height: job.height,
+                        settings: job.settings.clone(),
+                        focus_presses: job.focus,
+                        whole_page: job.whole_page,
+                        single_screenshot: stability.as_ref().is_some_and(|history| {
+                            history
+                                .lock()
+                                .unwrap_or_else(|e| e.into_inner())
+                                .single(&job.id)
+                        }),
+                        story: job.meta(),
                     };
                     let capture_span = debug_span!("capture", job = %job.snapshot_id());
-                    let outcome = match tokio::time::timeout(
-                        CAPTURE_TIMEOUT,
-                        session.capture(&req).instrument(capture_span),
-                    )
-                    .await
-                    {
+                    let capture = session
+                        .capture(&req, |stage| activity.stage(idx, stage))
+                        .instrument(capture_span);
+                    let capture = async {
+                        match timeout {
+                            Some(t) => tokio::time::timeout(t, capture).await,
+                            None => Ok(capture.await),
+                        }
+                    };
+                    let result = tokio::select! {
+                        result = capture => result,
+                        () = interrupt::wait() => {
+                            debug!("interrupted, abandoning capture");
+                            if let Err(e) = renderer.close_session(session).await {
+                                warn!(error = %format!("{e:#}"), "failed to close tab");
+                            }
+                            activity.finish(idx);
+                            break;
+                        }
+                    };
+                    let outcome = match result {
                         Ok(Ok(result)) => {
-                            debug!(
-                                elapsed_ms = result.timings.total.as_millis() as u64,
-                                "captured ok"
-                            );
-                            CaptureOutcome::Ok(result.png, result.timings)
+                            let elapsed_ms = result.timings.total.as_millis() as u64;
+                            debug!(elapsed_ms, "captured ok");
+                            timings
+                                .lock()
+                                .unwrap_or_else(|e| e.into_inner())
+                                .insert(job.id.clone(), elapsed_ms);
+                            if let Some(history) = &stability {
+                                history.lock().unwrap_or_else(|e| e.into_inner()).record(
+                                    &job.id,
+                                    req.single_screenshot,
+                                    result.timings.screenshots,
+                                );
+                            }
+                            if let Some(har) = session.har()
+                                && let Err(e) = store::write_har(&job.id, &har)
+                            {
+                                warn!(error = %format!("{e:#}"), "failed to save HAR");
+                            }
+                            match session.css_usage().await {
+                                Ok(Some(usage)) => coverage
+                                    .lock()
+                                    .unwrap_or_else(|e| e.into_inner())
+                                    .add(usage),
+                                Ok(None) => {}
+                                Err(e) => {
+                                    warn!(error = %format!("{e:#}"), "failed to collect CSS coverage")
+                                }
+                            }
+                            CaptureOutcome::Ok(Box::new(result))
+                        }
+                        // A dropped tab connection is usually transient (a proxy
+                        // or remote browser recycling sockets): retry once in a
+                        // fresh tab.
+                        Ok(Err(e))
+                            if e.downcast_ref::<ConnectionLost>().is_some()
+                                && renderer.remote_problem() != Some(Problem::Restarted)
+                                && retried
+                                    .lock()
+                                    .unwrap_or_else(|e| e.into_inner())
+                                    .insert(job.id.clone()) =>
+                        {
+                            warn!(error = %format!("{e:#}"), "connection lost, retrying capture");
+                            let _ = renderer.close_session(session).await;
+                            activity.finish(idx);
+                            queue.lock().await.push(job);
+                            continue;
                         }
                         Ok(Err(e)) => {
                             warn!(error = %format!("{e:#}"), "capture failed");
-                            CaptureOutcome::Err(format!("{e:#}"))
+                            let msg = with_problem(&renderer, format!("{e:#}"));
+                            let note = save_error_artifacts(&mut session, &job, &msg).await;
+                            CaptureOutcome::Err(format!("{msg}{note}"))
                         }
                         Err(_) => {
                             warn!("capture timed out after 30s");
-                            // Timeout — close the tab and continue with next job.
-                            let _ = renderer.close_session(session).await;
-                            let _ = tx
-                                .send((
-                                    job,
-                                    CaptureOutcome::Err("Capture timed out after 30s".into()),
-                                ))
-                                .await;
-                            continue;
+                            let msg = with_problem(&renderer, "Capture timed out after 30s".into());
+                            let note = save_error_artifacts(&mut session, &job, &msg).await;
+                            CaptureOutcome::Err(format!("{msg}{note}"))
                         }
                     };
 
                     // Close the tab after capture.
+                    activity.stage(idx, "close");
                     let t_close = Instant::now();
                     if let Err(e) = renderer.close_session(session).await {
                         warn!(error = %format!("{e:#}"), "failed to close tab");
@@ -205,6 +455,7 @@ async fn capture_all_with(
                         );
                     }
 
+                    activity.finish(idx);
                     if tx.send((job, outcome)).await.is_err() {
                         warn!("channel send failed (receiver dropped), stopping");
                         break; // receiver dropped, stop capturing
@@ -216,13 +467,9 @@ async fn capture_all_with(
         );
     }
 
-    // Original sender not needed — channel closes when session task clones drop.
-    drop(tx);
-    debug!("original tx dropped, channel will close when all workers finish");
-
-    // Keep Chrome alive until all captures finish.
+    // Keep Chrome alive until all captures finish. This task holds the
+    // original sender, so the channel closes only once Chrome is torn down.
     tokio::spawn(async move {
-        let _renderer = renderer;
         debug!("renderer keep-alive task started");
         while let Some(result) = set.join_next().await {
             match result {
@@ -231,7 +478,82 @@ async fn capture_all_with(
             }
         }
         debug!("all workers done, dropping renderer");
+        for renderer in &renderers {
+            renderer.shutdown().await;
+        }
+        drop(renderers);
+        if let Ok(timings) = Arc::try_unwrap(timings) {
+            write_timings(timings.into_inner().unwrap_or_else(|e| e.into_inner()));
+        }
+        if let Some(Ok(history)) = stability.map(Arc::try_unwrap) {
+            history
+                .into_inner()
+                .unwrap_or_else(|e| e.into_inner())
+                .save();
+        }
+        if let Ok(coverage) = Arc::try_unwrap(coverage) {
+            write_coverage(&coverage.into_inner().unwrap_or_else(|e| e.into_inner()));
+        }
+        drop(tx);
     });
 
     Ok(rx)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::StoryConfig;
+    use crate::storybook::Story;
+
+    fn job(title: &str, id: &str) -> CaptureJob {
+        CaptureJob {
+            source: "storybook".into(),
+            story: Story {
+                id: id.into(),
+                name: id.into(),
+                title: title.into(),
+                tags: Vec::new(),
+                url: None,
+            },
+            viewport: "laptop".into(),
+            url: String::new(),
+            width: 1366,
+            height: 768,
+            id: id.into(),
+            settings: StoryConfig::default(),
+            args: None,
+            whole_page: false,
+            focus: 0,
+        }
+    }
+
+    fn popped(mut jobs: Vec<CaptureJob>) -> Vec<String> {
+        std::iter::from_fn(|| jobs.pop()).map(|j| j.id).collect()
+    }
+
+    #[test]
+    fn schedule_orders_pops() {
+        let jobs = || {
+            vec![
+                job("B", "b--1"),
+                job("A", "a--1"),
+                job("B", "b--2"),
+                job("A", "a--2"),
+            ]
+        };
+        let timings = BTreeMap::from([
+            ("a--1".into(), 100),
+            ("b--1".into(), 900),
+            ("a--2".into(), 50),
+        ]);
+
+        let mut slowest = jobs();
+        schedule(&mut slowest, CaptureOrder::SlowestFirst, &timings);
+        assert_eq!(popped(slowest), ["b--2", "b--1", "a--1", "a--2"]);
+
+        let mut by_title = jobs();
+        schedule(&mut by_title, CaptureOrder::Title, &timings);
+        assert_eq!(popped(by_title), ["a--1", "a--2", "b--1", "b--2"]);
+    }
+}
@@ -73,6 +73,120 @@ pub(crate) const INJECT_CSS_JS_TEMPLATE: &str = r#"
 })()
 "#;
 
+/// CSS forcing a font stack onto every element (`FONT_STACK_PLACEHOLDER`).
+pub(crate) const FONT_OVERRIDE_CSS_TEMPLATE: &str = r#"
+*,
+*::before,
+*::after {
+  font-family: FONT_STACK_PLACEHOLDER !important;
+}
+"#;
+
+/// Poll `document.fonts.check` until every family in `FAMILIES_PLACEHOLDER`
+/// (a JSON array) is available (100ms interval, 10s timeout).
+pub(crate) const WAIT_FOR_FONTS_JS_TEMPLATE: &str = r#"
+(function waitForFonts() {
+    return new Promise(function(resolve, reject) {
+        var TIMEOUT = 10000;
+        var INTERVAL = 100;
+        var families = FAMILIES_PLACEHOLDER;
+        function missing() {
+            return families.filter(function(f) {
+                var spec = '16px "' + f.replace(/"/g, '\\"') + '"';
+                // Start loading declared faces not yet used by the page.
+                document.fonts.load(spec).catch(function() {});
+                return !document.fonts.check(spec);
+            });
+        }
+        var timer = setTimeout(function() {
+            reject(new Error('Fonts not loaded after 10s: ' + missing().join(', ')));
+        }, TIMEOUT);
+        function check() {
+            if (missing().length === 0) {
+                clearTimeout(timer);
+                resolve('fonts');
+                return;
+            }
+            setTimeout(check, INTERVAL);
+        }
+        check();
+    });
+})()
+"#;
+
+/// Scroll per `SCROLL_PLACEHOLDER` (`{selector}`, `{y, container}` or
+/// `{y}`), let scroll handlers render, and resolve with the window scroll
+/// offset as a JSON string.
+pub(crate) const SCROLL_JS_TEMPLATE: &str = r#"
+(function scrollForCapture() {
+    return new Promise(function(resolve, reject) {
+        var opts = SCROLL_PLACEHOLDER;
+        if (opts.selector) {
+            var el = document.querySelector(opts.selector);
+            if (!el) {
+                reject(new Error('scroll_to selector "' + opts.selector + '" not found'));
+                return;
+            }
+            el.scrollIntoView({ block: 'start', inline: 'nearest', behavior: 'instant' });
+        } else if (opts.container) {
+            var container = document.querySelector(opts.container);
+            if (!container) {
+                reject(new Error('scroll_container "' + opts.container + '" not found'));
+                return;
+            }
+            container.scrollTo({ top: opts.y, behavior: 'instant' });
+        } else {
+            window.scrollTo({ top: opts.y, behavior: 'instant' });
+        }
+        // Two frames for sticky positioning and virtualized lists, then a
+        // short settle for content rendered from scroll handlers.
+        requestAnimationFrame(function() {
+            requestAnimationFrame(function() {
+                setTimeout(function() {
+                    resolve(JSON.stringify({ x: window.scrollX, y: window.scrollY }));
+                }, 100);
+            });
+        });
+    });
+})()
+"#;
+
+/// Init script for `deterministic_canvas`: replaces `Math.random` with a
+/// fixed-seed PRNG (mulberry32) and forces `preserveDrawingBuffer` on WebGL
+/// contexts, so the drawn frame is still there when the screenshot is taken.
+pub(crate) const DETERMINISTIC_CANVAS_JS: &str = r#"
+(function() {
+    var seed = 0x2f6e2b1;
+    Math.random = function() {
+        seed = (seed + 0x6d2b79f5) | 0;
+        var t = Math.imul(seed ^ (seed >>> 15), 1 | seed);
+        t = (t + Math.imul(t ^ (t >>> 7), 61 | t)) ^ t;
+        return ((t ^ (t >>> 14)) >>> 0) / 4294967296;
+    };
+    var getContext = HTMLCanvasElement.prototype.getContext;
+    HTMLCanvasElement.prototype.getContext = function(type, attributes) {
+        if (type === 'webgl' || type === 'webgl2' || type === 'experimental-webgl') {
+            attributes = Object.assign({}, attributes, { preserveDrawingBuffer: true });
+        }
+        return getContext.call(this, type, attributes);
+    };
+})();
+"#;
+
+/// Wait two frames (for focus styles or canvas content to render) and resolve with the window
+/// scroll offset as a JSON string.
+pub(crate) const SETTLE_FRAMES_JS: &str = r#"
+(function settleFrames() {
+    return new Promise(function(resolve) {
+        requestAnimationFrame(function() {
+            requestAnimationFrame(function() {
+                resolve(JSON.stringify({ x: window.scrollX, y: window.scrollY }));
+            });
+        });
+    });
+})()
+"#;
+
 /// JavaScript that finishes or cancels in-progress animations via the Web
 /// Animations API. Complements CSS injection (which prevents new CSS
 /// animations) by handling JS-driven animations (framer-motion, GSAP, etc.).
@@ -94,13 +208,194 @@ pub(crate) const FINISH_ANIMATIONS_JS: &str = r#"
 })()
 "#;
 
+/// Pause every `<video>`/`<audio>` element and seek it to `TIME_PLACEHOLDER`
+/// seconds (clamped to its duration), resolving once all have seeked.
+/// Media that never seeks (no source, load error) is skipped after 5s.
+pub(crate) const PIN_MEDIA_JS_TEMPLATE: &str = r#"
+(function pinMedia() {
+    var TIME = TIME_PLACEHOLDER;
+    var TIMEOUT = 5000;
+    var media = Array.from(document.querySelectorAll('video, audio'));
+    var pinned = media.map(function(m) {
+        return new Promise(function(resolve) {
+            m.autoplay = false;
+            m.loop = false;
+            m.pause();
+            function seek() {
+                var target = isFinite(m.duration) ? Math.min(TIME, m.duration) : TIME;
+                if (Math.abs(m.currentTime - target) < 0.001 && m.readyState >= 2) {
+                    resolve();
+                    return;
+                }
+                m.addEventListener('seeked', function() { resolve(); }, { once: true });
+                m.currentTime = target;
+            }
+            if (m.readyState >= 1) {
+                seek();
+            } else {
+                m.addEventListener('loadedmetadata', seek, { once: true });
+            }
+        });
+    });
+    return Promise.race([
+        Promise.all(pinned),
+        new Promise(function(resolve) { setTimeout(resolve, TIMEOUT); }),
+    ]).then(function() { return media.length; });
+})()
+"#;
+
+/// `hide-scrollbars` hook: hide scrollbars, on the page and inside
+/// scrollable elements, without disabling scrolling.
+pub(crate) const HIDE_SCROLLBARS_JS: &str = r#"
+(function hideScrollbars() {
+    const style = document.createElement('style');
+    style.textContent = '* { scrollbar-width: none !important; } '
+        + '::-webkit-scrollbar { display: none !important; }';
+    document.head.appendChild(style);
+})()
+"#;
+
+/// `hide_scrollbar_gutters`: the layout space taken by scrollbars, as a JSON
+/// array of `[width, height]` gutters: the page's, then each element's that
+/// has one.
+pub(crate) const MEASURE_SCROLLBAR_GUTTERS_JS: &str = r#"
+(function measureScrollbarGutters() {
+    const doc = document.documentElement;
+    const gutters = [[window.innerWidth - doc.clientWidth, window.innerHeight - doc.clientHeight]];
+    for (const el of document.body ? document.body.querySelectorAll('*') : []) {
+        const s = window.getComputedStyle(el);
+        const width = el.offsetWidth - el.clientWidth
+            - parseFloat(s.borderLeftWidth) - parseFloat(s.borderRightWidth);
+        const height = el.offsetHeight - el.clientHeight
+            - parseFloat(s.borderTopWidth) - parseFloat(s.borderBottomWidth);
+        if (width > 0 || height > 0) gutters.push([width, height]);
+    }
+    return JSON.stringify(gutters);
+})()
+"#;
+
+/// `blur-inputs` hook: take focus away from an input, textarea or editable
+/// element the story focused, so no focus ring shows.
+pub(crate) const BLUR_INPUTS_JS: &str = r#"
+(function blurInputs() {
+    const el = document.activeElement;
+    if (el && el.matches('input, textarea, select, [contenteditable]')) {
+        el.blur();
+    }
+})()
+"#;
+
+/// `stop-videos` hook: stop every `<video>` and rewind it to its first
+/// frame, resolving once all have seeked (or after 5s).
+pub(crate) const STOP_VIDEOS_JS: &str = r#"
+(function stopVideos() {
+    const videos = Array.from(document.querySelectorAll('video'));
+    const stopped = videos.map((v) => new Promise((resolve) => {
+        v.autoplay = false;
+        v.pause();
+        if (v.currentTime === 0) {
+            resolve();
+            return;
+        }
+        v.addEventListener('seeked', () => resolve(), { once: true });
+        v.currentTime = 0;
+    }));
+    return Promise.race([
+        Promise.all(stopped),
+        new Promise((resolve) => setTimeout(resolve, 5000)),
+    ]).then(() => videos.length);
+})()
+"#;
+
+/// Run a `capture.hook_script` (`SOURCE_PLACEHOLDER`): call the
+/// `beforeCapture` function it defines, or exports through `module.exports`,
+/// with the story metadata (`STORY_PLACEHOLDER`), awaiting its result.
+pub(crate) const HOOK_SCRIPT_JS_TEMPLATE: &str = r#"
+(async function hookScript(story) {
+    const module = { exports: {} };
+    const exports = module.exports;
+SOURCE_PLACEHOLDER
+    ;
+    const hook = module.exports.beforeCapture
+        || (typeof beforeCapture === 'function' ? beforeCapture : null);
+    if (typeof hook !== 'function') {
+        throw new Error('hook_script defines no beforeCapture function');
+    }
+    await hook(story);
+    return true;
+})(STORY_PLACEHOLDER)
+"#;
+
+/// `hook_script` written as an ES module: imported from a `blob:` URL (the
+/// source as a JS string literal), then its `beforeCapture` export, or its
+/// default export, is called with the story.
+pub(crate) const HOOK_MODULE_JS_TEMPLATE: &str = r#"
+(async function hookModule(story) {
+    const url = URL.createObjectURL(
+        new Blob([SOURCE_PLACEHOLDER], { type: 'text/javascript' }));
+    try {
+        const module = await import(url);
+        const hook = module.beforeCapture || module.default;
+        if (typeof hook !== 'function') {
+            throw new Error('hook_script exports no beforeCapture function');
+        }
+        await hook(story);
+    } finally {
+        URL.revokeObjectURL(url);
+    }
+    return true;
+})(STORY_PLACEHOLDER)
+"#;
+
+/// Wait for the story's `play` function to finish (50ms interval, 10s timeout).
+///
+/// Reads the render phase Storybook 7+ tracks on
+/// `__STORYBOOK_PREVIEW__.currentRender`. Resolves immediately for stories
+/// without a play function (or older Storybooks), and rejects with the error
+/// Storybook displays when the play function threw.
+pub(crate) const WAIT_FOR_PLAY_JS: &str = r#"
+(function waitForPlay() {
+    return new Promise(function(resolve, reject) {
+        var TIMEOUT = 10000;
+        var INTERVAL = 50;
+        var DONE = ['played', 'completed', 'finished', 'aborted'];
+        var timer = setTimeout(function() {
+            reject(new Error('Play function did not finish after 10s'));
+        }, TIMEOUT);
+        function check() {
+            var preview = window.__STORYBOOK_PREVIEW__;
+            var render = preview && preview.currentRender;
+            if (!render || !render.story || !render.story.playFunction) {
+                clearTimeout(timer);
+                resolve('no-play');
+                return;
+            }
+            if (render.phase === 'errored') {
+                clearTimeout(timer);
+                var message = document.querySelector('#error-message');
+                reject(new Error('Play function threw: ' +
+                    ((message && message.textContent.trim()) || 'unknown error')));
+                return;
+            }
+            if (DONE.indexOf(render.phase) !== -1) {
+                clearTimeout(timer);
+                resolve(render.phase);
+                return;
+            }
+            setTimeout(check, INTERVAL);
+        }
+        check();
+    });
+})()
+"#;
+
 /// Poll for the story root selector to exist with non-zero dimensions (100ms interval, 10s timeout).
 pub(crate) const WAIT_FOR_STORY_ROOT_JS: &str = r#"
 (function waitForStoryRoot() {
     return new Promise(function(resolve, reject) {
         var TIMEOUT = 10000;
         var INTERVAL = 100;
-        var selector = '#storybook-root > *, #root > *';
+        var selector = '#storybook-root > *, #root > *, .ladle-main > *';
         var timer = setTimeout(function() {
             reject(new Error('Story root selector "' + selector + '" not found or has zero dimensions after 10s'));
         }, TIMEOUT);
@@ -121,14 +416,28 @@ pub(crate) const WAIT_FOR_STORY_ROOT_JS: &str = r#"
 })()
 "#;
 
+/// Bounds of the whole document, for pages captured without a story root.
+pub(crate) const GET_PAGE_BOUNDS_JS: &str = r#"
+(function() {
+    var doc = document.documentElement;
+    var body = document.body;
+    return JSON.stringify({
+        x: 0,
+        y: 0,
+        width: doc.clientWidth,
+        height: Math.max(doc.scrollHeight, body ? body.scrollHeight : 0)
+    });
+})()
+"#;
+
 /// Visible-child-union walk of Storybook root container.
 ///
-/// Walks visible children of `#storybook-root` or `#root` and unions their
-/// rects. Catches absolutely-positioned children that overflow body.
+/// Walks visible children of `#storybook-root`, `#root` or Ladle's
+/// `.ladle-main` and unions their rects. Catches absolutely-positioned children that overflow body.
 /// Falls back to body rect if no root container found.
 pub(crate) const GET_STORY_ROOT_BOUNDS_JS: &str = r#"
 (function() {
-    var selector = '#storybook-root > *, #root > *';
+    var selector = '#storybook-root > *, #root > *, .ladle-main > *';
 
     function hasOverflow(el) {
         var s = window.getComputedStyle(el);
@@ -248,3 +557,70 @@ pub(crate) const GET_STORY_ROOT_BOUNDS_JS: &str = r#"
     });
 })()
 "#;
+
+/// Bounding rects (viewport CSS pixels) of visible elements matching any of
+/// the selectors in `SELECTORS_PLACEHOLDER` (a JSON array). Invalid selectors
+/// are skipped.
+pub(crate) const GET_IGNORE_RECTS_JS_TEMPLATE: &str = r#"
+(function() {
+    var selectors = SELECTORS_PLACEHOLDER;
+    var rects = [];
+    selectors.forEach(function(sel) {
+        var els;
+        try { els = document.querySelectorAll(sel); } catch(e) { return; }
+        els.forEach(function(el) {
+            var r = el.getBoundingClientRect();
+            if (r.width > 0 && r.height > 0) {
+                rects.push({ x: r.x, y: r.y, width: r.width, height: r.height });
+            }
+        });
+    });
+    return JSON.stringify(rects);
+})()
+"#;
+
+/// JavaScript returning a JSON string with `navigator.platform`,
+/// `devicePixelRatio` and canvas text widths across common font families
+/// (hashed into the environment fingerprint's font signature).
+pub(crate) const ENV_FINGERPRINT_JS: &str = r#"
+(() => {
+  const ctx = document.createElement('canvas').getContext('2d');
+  const text = 'The quick brown fox jumps over the lazy dog 0123456789 \u00e9\u00df\u6f22\u5b57';
+  const families = [
+    'sans-serif', 'serif', 'monospace', 'system-ui', 'cursive', 'fantasy',
+    'Arial', 'Helvetica', '"Times New Roman"', '"Courier New"', 'Roboto', 'Inter',
+  ];
+  const widths = families.map((f) => {
+    ctx.font = '16px ' + f;
+    return f + '=' + ctx.measureText(text).width.toFixed(3);
+  });
+  return JSON.stringify({
+    platform: navigator.platform,
+    dpr: window.devicePixelRatio,
+    fonts: widths.join(';'),
+  });
+})()
+"#;
+
+/// JavaScript that waits until no new resources have been fetched for 100ms,
+/// for WebDriver sessions that can't watch network events. Resolves after
+/// 10s regardless, like the CDP network idle wait.
+pub(crate) const WAIT_FOR_NETWORK_QUIET_JS: &str = r#"
+(function waitForNetworkQuiet() {
+    return new Promise((resolve) => {
+        const SETTLE_MS = 100;
+        const deadline = Date.now() + 10000;
+        let last = -1;
+        function check() {
+            const count = performance.getEntriesByType('resource').length;
+            if (count === last || Date.now() > deadline) {
+                resolve(count);
+                return;
+            }
+            last = count;
+            setTimeout(check, SETTLE_MS);
+        }
+        check();
+    });
+})()
+"#;
@@ -0,0 +1,117 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::store;
+
+/// Cache file holding each snapshot's stability history, for
+/// `screenshot = "adaptive"`.
+const STABILITY_CACHE: &str = "stability.json";
+
+/// Consecutive stable captures that settled at once before a snapshot gets
+/// a single screenshot.
+const CLEAN_RUNS: u32 = 3;
+
+/// Single screenshots before a snapshot is checked for stability again, in
+/// case it became noisy.
+const RECHECK_EVERY: u32 = 20;
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+struct Entry {
+    /// Consecutive stable captures whose first two screenshots matched.
+    clean: u32,
+    /// Single-screenshot captures since the last stability check.
+    singles: u32,
+}
+
+/// Per-snapshot record of how many screenshots the stable strategy needed,
+/// so `adaptive` can skip the stability check for snapshots that never
+/// needed it and keep it for noisy ones.
+#[derive(Debug, Default)]
+pub struct StabilityHistory {
+    entries: BTreeMap<String, Entry>,
+}
+
+impl StabilityHistory {
+    pub fn load() -> Self {
+        let entries = store::read_cache(STABILITY_CACHE)
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+        Self { entries }
+    }
+
+    /// Whether `id` can be captured with a single screenshot. New snapshots
+    /// and noisy ones get the stability check.
+    pub fn single(&self, id: &str) -> bool {
+        self.entries
+            .get(id)
+            .is_some_and(|e| e.clean >= CLEAN_RUNS && e.singles < RECHECK_EVERY)
+    }
+
+    /// Record a capture of `id` that took `screenshots` screenshots, `single`
+    /// or with the stability check.
+    pub fn record(&mut self, id: &str, single: bool, screenshots: u32) {
+        let entry = self.entries.entry(id.to_string()).or_default();
+        if single {
+            entry.singles += 1;
+        } else if screenshots <= 2 {
+            entry.clean += 1;
+            entry.singles = 0;
+        } else {
+            *entry = Entry::default();
+        }
+    }
+
+    /// Snapshots that currently get a single screenshot.
+    pub fn single_count(&self) -> usize {
+        self.entries.keys().filter(|id| self.single(id)).count()
+    }
+
+    pub fn save(&self) {
+        let result = serde_json::to_string(&self.entries)
+            .map_err(anyhow::Error::from)
+            .and_then(|json| store::write_cache(STABILITY_CACHE, &json));
+        if let Err(e) = result {
+            warn!(error = %format!("{e:#}"), "failed to save stability history");
+        }
+    }
+}
+
+/// Carry the stability history of renamed snapshots (`(from, to)` pairs)
+/// over to their new IDs.
+pub fn rename(pairs: &[(String, String)]) {
+    let mut history = StabilityHistory::load();
+    let moved: Vec<(String, Entry)> = pairs
+        .iter()
+        .filter_map(|(from, to)| history.entries.remove(from).map(|e| (to.clone(), e)))
+        .collect();
+    if moved.is_empty() {
+        return;
+    }
+    history.entries.extend(moved);
+    history.save();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn goes_single_after_clean_runs_and_back_when_noisy() {
+        let mut history = StabilityHistory::default();
+        assert!(!history.single("a"));
+        for _ in 0..CLEAN_RUNS {
+            history.record("a", false, 2);
+        }
+        assert!(history.single("a"));
+
+        for _ in 0..RECHECK_EVERY {
+            history.record("a", true, 1);
+        }
+        assert!(!history.single("a"), "due for a stability check");
+        history.record("a", false, 3);
+        assert!(!history.single("a"), "noisy snapshots keep the check");
+        assert_eq!(history.single_count(), 0);
+    }
+}
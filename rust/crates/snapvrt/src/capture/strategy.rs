@@ -1,40 +1,252 @@
+use std::sync::Arc;
 use std::time::Duration;
 
 use anyhow::{Context, Result};
 use serde::Deserialize;
+use serde_json::Value;
 
-use super::scripts;
-use crate::cdp::{CdpConnection, ClipRect};
-use crate::config::capture::{CaptureConfig, ScreenshotKind};
+use super::{font_bundle, scripts};
+use crate::cdp::{CdpConnection, ClipRect, ImageEncoding, ScreenshotParams};
+use crate::compare::diff::Mask;
+use crate::config::capture::{CaptureConfig, ScreenshotFormat, ScreenshotKind, StabilityMetric};
+use crate::config::{ScrollTo, StoryConfig};
+use crate::webdriver::{self, WebDriver};
+
+// ---------------------------------------------------------------------------
+// Page
+// ---------------------------------------------------------------------------
+
+/// A page the capture scripts run in: a CDP tab, or a WebDriver session.
+pub trait Page {
+    /// Evaluate a JS expression, awaiting it when it is a promise, and
+    /// return its value.
+    async fn eval_value(&mut self, expression: &str) -> Result<Value>;
+
+    /// Press Tab, moving keyboard focus.
+    async fn press_tab(&mut self) -> Result<()>;
+}
+
+impl Page for CdpConnection {
+    async fn eval_value(&mut self, expression: &str) -> Result<Value> {
+        let mut result = self.eval_async(expression).await?;
+        Ok(result["result"]["value"].take())
+    }
+
+    async fn press_tab(&mut self) -> Result<()> {
+        self.press_key("Tab", "Tab", 9).await
+    }
+}
+
+impl Page for WebDriver {
+    async fn eval_value(&mut self, expression: &str) -> Result<Value> {
+        self.eval(expression).await
+    }
+
+    async fn press_tab(&mut self) -> Result<()> {
+        self.press_key(webdriver::TAB).await
+    }
+}
 
 // ---------------------------------------------------------------------------
 // disable_animations
 // ---------------------------------------------------------------------------
 
 /// Disable CSS animations/transitions and finish Web Animations API animations.
-pub async fn disable_animations(conn: &mut CdpConnection) -> Result<()> {
+pub async fn disable_animations(page: &mut impl Page) -> Result<()> {
+    inject_css(page, scripts::DISABLE_ANIMATIONS_CSS).await?;
+    page.eval_value(scripts::FINISH_ANIMATIONS_JS).await?;
+    Ok(())
+}
+
+/// Pause media elements and seek them to `time` seconds, so videos show the
+/// same frame every capture (CSS animation disabling does not reach them).
+pub async fn pin_media(page: &mut impl Page, time: f64) -> Result<()> {
+    let js = scripts::PIN_MEDIA_JS_TEMPLATE.replace("TIME_PLACEHOLDER", &time.to_string());
+    page.eval_value(&js).await?;
+    Ok(())
+}
+
+/// Hide scrollbars if any take up layout space, so a platform with classic
+/// scrollbars captures the layout of one with overlay scrollbars. Returns
+/// the number of scrollbar gutters found.
+pub async fn hide_scrollbar_gutters(page: &mut impl Page) -> Result<u64> {
+    let result = page
+        .eval_value(scripts::MEASURE_SCROLLBAR_GUTTERS_JS)
+        .await
+        .context("Failed to measure scrollbar gutters")?;
+    let gutters = count_gutters(&result)?;
+    if gutters > 0 {
+        page.eval_value(scripts::HIDE_SCROLLBARS_JS).await?;
+        // Let the content reflow into the freed space.
+        page.eval_value(scripts::SETTLE_FRAMES_JS).await?;
+    }
+    Ok(gutters)
+}
+
+/// Gutters over a pixel wide (or high) in `MEASURE_SCROLLBAR_GUTTERS_JS`'s
+/// result; thinner ones are rounding in the layout sizes.
+fn count_gutters(result: &Value) -> Result<u64> {
+    let json_str = result
+        .as_str()
+        .context("Scrollbar gutters: no string value returned")?;
+    let gutters: Vec<[f64; 2]> =
+        serde_json::from_str(json_str).context("Failed to parse scrollbar gutters JSON")?;
+    Ok(gutters.iter().filter(|[w, h]| *w > 1.0 || *h > 1.0).count() as u64)
+}
+
+/// Append a `<style>` element with `css` to the page.
+async fn inject_css(page: &mut impl Page, css: &str) -> Result<()> {
     let inject_css_js =
-        scripts::INJECT_CSS_JS_TEMPLATE.replace("CSS_PLACEHOLDER", &css_for_template_literal());
-    conn.eval(&inject_css_js).await?;
-    conn.eval(scripts::FINISH_ANIMATIONS_JS).await?;
+        scripts::INJECT_CSS_JS_TEMPLATE.replace("CSS_PLACEHOLDER", &css_for_template_literal(css));
+    page.eval_value(&inject_css_js).await?;
     Ok(())
 }
 
 /// Escape the CSS for safe embedding in a JS template literal.
-fn css_for_template_literal() -> String {
-    scripts::DISABLE_ANIMATIONS_CSS
-        .replace('\\', "\\\\")
+fn css_for_template_literal(css: &str) -> String {
+    css.replace('\\', "\\\\")
         .replace('`', "\\`")
         .replace("${", "\\${")
 }
 
+// ---------------------------------------------------------------------------
+// FontControl
+// ---------------------------------------------------------------------------
+
+/// URL patterns of web font services blocked by `block_remote_fonts`.
+/// Fonts served by Storybook itself are left alone.
+const REMOTE_FONT_PATTERNS: [&str; 9] = [
+    "*://fonts.googleapis.com/*",
+    "*://fonts.gstatic.com/*",
+    "*://use.typekit.net/*",
+    "*://p.typekit.net/*",
+    "*://fast.fonts.net/*",
+    "*://fonts.bunny.net/*",
+    "*://use.fontawesome.com/*",
+    "*://kit.fontawesome.com/*",
+    "*://ka-f.fontawesome.com/*",
+];
+
+/// Project-level control over font loading and substitution.
+#[derive(Clone, Debug, Default)]
+pub struct FontControl {
+    block_remote: bool,
+    override_stack: Option<String>,
+    wait_for: Vec<String>,
+    /// `@font-face` rules of `font_dir`, read once per run.
+    bundle: Option<Arc<str>>,
+}
+
+impl FontControl {
+    pub fn from_config(config: &CaptureConfig) -> Result<Self> {
+        let bundle = match &config.font_dir {
+            Some(dir) => Some(Arc::from(font_bundle::load(dir)?)),
+            None => None,
+        };
+        Ok(Self {
+            block_remote: config.block_remote_fonts(),
+            override_stack: config.font_override.clone(),
+            wait_for: config.wait_for_fonts().to_vec(),
+            bundle,
+        })
+    }
+
+    /// Per-tab setup, before any navigation.
+    pub async fn setup(&self, conn: &mut CdpConnection) -> Result<()> {
+        if self.block_remote {
+            conn.block_urls(&REMOTE_FONT_PATTERNS).await?;
+        }
+        Ok(())
+    }
+
+    /// Declare the bundled fonts and force the override font stack, if
+    /// configured. Runs before the ready wait so `document.fonts.ready`
+    /// covers the substituted fonts.
+    pub async fn apply_override(&self, page: &mut impl Page) -> Result<()> {
+        if let Some(css) = &self.bundle {
+            inject_css(page, css).await?;
+        }
+        if let Some(stack) = &self.override_stack {
+            let css = scripts::FONT_OVERRIDE_CSS_TEMPLATE.replace("FONT_STACK_PLACEHOLDER", stack);
+            inject_css(page, &css).await?;
+        }
+        Ok(())
+    }
+
+    /// Wait until every configured font family is loaded.
+    pub async fn wait_loaded(&self, page: &mut impl Page) -> Result<()> {
+        if self.wait_for.is_empty() {
+            return Ok(());
+        }
+        let families_json =
+            serde_json::to_string(&self.wait_for).context("Failed to serialize font families")?;
+        let js =
+            scripts::WAIT_FOR_FONTS_JS_TEMPLATE.replace("FAMILIES_PLACEHOLDER", &families_json);
+        page.eval_value(&js).await?;
+        Ok(())
+    }
+}
+
+// ---------------------------------------------------------------------------
+// scroll
+// ---------------------------------------------------------------------------
+
+/// Apply the story's `scroll_to`. Returns the window scroll offset `(x, y)`
+/// when the window itself ended up scrolled, `None` otherwise (including
+/// when only an overflow container moved).
+pub async fn scroll(page: &mut impl Page, settings: &StoryConfig) -> Result<Option<(f64, f64)>> {
+    let opts = match (&settings.scroll_to, &settings.scroll_container) {
+        (None, _) => return Ok(None),
+        (Some(ScrollTo::Selector(selector)), _) => serde_json::json!({ "selector": selector }),
+        (Some(ScrollTo::Offset(y)), Some(container)) => {
+            serde_json::json!({ "y": y, "container": container })
+        }
+        (Some(ScrollTo::Offset(y)), None) => serde_json::json!({ "y": y }),
+    };
+    let js = scripts::SCROLL_JS_TEMPLATE.replace("SCROLL_PLACEHOLDER", &opts.to_string());
+    let result = page.eval_value(&js).await?;
+    parse_window_offset(&result)
+}
+
+/// Press Tab `presses` times so focus rings and skip links show, then let
+/// them render. Returns the window scroll offset like `scroll`, since
+/// focusing can scroll the focused element into view.
+pub async fn focus(page: &mut impl Page, presses: u32) -> Result<Option<(f64, f64)>> {
+    if presses == 0 {
+        return Ok(None);
+    }
+    for _ in 0..presses {
+        page.press_tab().await?;
+    }
+    let result = page.eval_value(scripts::SETTLE_FRAMES_JS).await?;
+    parse_window_offset(&result)
+}
+
+/// Parse a `{x, y}` window scroll offset; `None` when not scrolled.
+fn parse_window_offset(result: &Value) -> Result<Option<(f64, f64)>> {
+    let json_str = result
+        .as_str()
+        .context("Scroll offset: no string value returned")?;
+    let offset: serde_json::Value =
+        serde_json::from_str(json_str).context("Failed to parse scroll offset JSON")?;
+    let x = offset["x"].as_f64().unwrap_or(0.0);
+    let y = offset["y"].as_f64().unwrap_or(0.0);
+    Ok((x != 0.0 || y != 0.0).then_some((x, y)))
+}
+
 // ---------------------------------------------------------------------------
 // get_clip
 // ---------------------------------------------------------------------------
 
 /// Get the clip region by walking visible children of the Storybook root.
-pub async fn get_clip(conn: &mut CdpConnection) -> Result<ClipRect> {
-    let result = conn.eval(scripts::GET_STORY_ROOT_BOUNDS_JS).await?;
+pub async fn get_clip(page: &mut impl Page) -> Result<ClipRect> {
+    let result = page.eval_value(scripts::GET_STORY_ROOT_BOUNDS_JS).await?;
+    parse_bounds_result(&result)
+}
+
+/// Get the clip region covering the whole document (sitemap pages).
+pub async fn get_page_clip(page: &mut impl Page) -> Result<ClipRect> {
+    let result = page.eval_value(scripts::GET_PAGE_BOUNDS_JS).await?;
     parse_bounds_result(&result)
 }
 
@@ -46,8 +258,8 @@ struct ClipBounds {
     height: f64,
 }
 
-fn parse_bounds_result(result: &serde_json::Value) -> Result<ClipRect> {
-    let json_str = result["result"]["value"]
+fn parse_bounds_result(result: &Value) -> Result<ClipRect> {
+    let json_str = result
         .as_str()
         .context("Clip bounds: no string value returned")?;
     let bounds: ClipBounds =
@@ -61,52 +273,245 @@ fn parse_bounds_result(result: &serde_json::Value) -> Result<ClipRect> {
     })
 }
 
+// ---------------------------------------------------------------------------
+// get_ignore_masks
+// ---------------------------------------------------------------------------
+
+/// Measure elements matching `selectors` and return their bounds relative to
+/// `clip`, clamped to it. Empty when no selectors are configured.
+pub async fn get_ignore_masks(
+    page: &mut impl Page,
+    selectors: &[String],
+    clip: &ClipRect,
+) -> Result<Vec<Mask>> {
+    if selectors.is_empty() {
+        return Ok(Vec::new());
+    }
+    let selectors_json =
+        serde_json::to_string(selectors).context("Failed to serialize ignore selectors")?;
+    let js =
+        scripts::GET_IGNORE_RECTS_JS_TEMPLATE.replace("SELECTORS_PLACEHOLDER", &selectors_json);
+    let result = page.eval_value(&js).await?;
+    let json_str = result
+        .as_str()
+        .context("Ignore rects: no string value returned")?;
+    let rects: Vec<ClipBounds> =
+        serde_json::from_str(json_str).context("Failed to parse ignore rects JSON")?;
+
+    Ok(rects
+        .iter()
+        .filter_map(|r| clip_relative_mask(r, clip))
+        .collect())
+}
+
+/// Translate a viewport rect into clip coordinates, expanded to whole pixels
+/// and clamped to the clip. `None` when it lies outside the clip.
+fn clip_relative_mask(r: &ClipBounds, clip: &ClipRect) -> Option<Mask> {
+    let x0 = (r.x - clip.x).floor().max(0.0);
+    let y0 = (r.y - clip.y).floor().max(0.0);
+    let x1 = (r.x + r.width - clip.x).ceil().min(clip.w.ceil());
+    let y1 = (r.y + r.height - clip.y).ceil().min(clip.h.ceil());
+    if x1 <= x0 || y1 <= y0 {
+        return None;
+    }
+    Some(Mask {
+        x: x0 as u32,
+        y: y0 as u32,
+        width: (x1 - x0) as u32,
+        height: (y1 - y0) as u32,
+    })
+}
+
 // ---------------------------------------------------------------------------
 // Screenshot
 // ---------------------------------------------------------------------------
 
-/// How the final screenshot is taken.
+/// Default JPEG/WebP quality when `screenshot_quality` is unset.
+const DEFAULT_QUALITY: u8 = 90;
+
+/// When two consecutive screenshots count as the same frame.
+#[derive(Clone, Copy)]
+enum Sameness {
+    /// Byte-identical.
+    Bytes,
+    /// At most `tolerance` (a fraction) of the decoded pixels differ.
+    Pixels { tolerance: f64 },
+}
+
+/// Whether the final screenshot is checked for stability.
 #[derive(Clone, Copy)]
-pub enum Screenshot {
-    /// Take up to N screenshots, returning when two consecutive are byte-identical.
-    Stable { max_attempts: u32, delay: Duration },
+enum Mode {
+    /// Take up to N screenshots, returning when two consecutive show the
+    /// same frame.
+    Stable {
+        max_attempts: u32,
+        delay: Duration,
+        same: Sameness,
+    },
     /// Single screenshot, no stability check.
     Single,
 }
 
+/// How the final screenshot is taken.
+#[derive(Clone, Copy)]
+pub struct Screenshot {
+    mode: Mode,
+    params: ScreenshotParams,
+}
+
 impl Screenshot {
     pub fn from_config(config: &CaptureConfig) -> Self {
         let kind = config.screenshot.unwrap_or_default();
         let attempts = config.stability_attempts.unwrap_or(3);
         let delay_ms = config.stability_delay_ms.unwrap_or(100);
 
-        match kind {
-            ScreenshotKind::Stable => Self::Stable {
+        let same = match config.stability_metric.unwrap_or_default() {
+            StabilityMetric::Bytes => Sameness::Bytes,
+            StabilityMetric::Pixels => Sameness::Pixels {
+                tolerance: config.stability_tolerance.unwrap_or(0.0),
+            },
+        };
+        let mode = match kind {
+            ScreenshotKind::Stable | ScreenshotKind::Adaptive => Mode::Stable {
                 max_attempts: attempts,
                 delay: Duration::from_millis(delay_ms),
+                same,
             },
-            ScreenshotKind::Single => Self::Single,
-        }
+            ScreenshotKind::Single => Mode::Single,
+        };
+        let quality = Some(
+            config
+                .screenshot_quality
+                .unwrap_or(DEFAULT_QUALITY)
+                .min(100),
+        );
+        let encoding = match config.screenshot_format.unwrap_or_default() {
+            ScreenshotFormat::Png => ImageEncoding::PNG,
+            ScreenshotFormat::Jpeg => ImageEncoding {
+                format: "jpeg",
+                quality,
+            },
+            ScreenshotFormat::Webp => ImageEncoding {
+                format: "webp",
+                quality,
+            },
+        };
+        let params = ScreenshotParams {
+            encoding,
+            beyond_viewport: config.capture_beyond_viewport(),
+            from_surface: config.from_surface,
+        };
+        Self { mode, params }
+    }
+
+    /// Whether content taller than the viewport is captured directly, so
+    /// the pipeline need not grow the viewport first.
+    pub fn beyond_viewport(&self) -> bool {
+        self.params.beyond_viewport
     }
 
-    pub async fn take(&self, conn: &mut CdpConnection, clip: &ClipRect) -> Result<Vec<u8>> {
-        match *self {
-            Self::Stable {
+    /// Screenshots taken at most, and the delay between them, before
+    /// settling for the last one. `single` (adaptive mode) takes one.
+    pub fn attempts(&self, single: bool) -> (u32, Duration) {
+        if single {
+            return (1, Duration::ZERO);
+        }
+        match self.mode {
+            Mode::Stable {
                 max_attempts,
                 delay,
-            } => {
-                let mut prev = conn.capture_screenshot(clip).await?;
-                for _ in 1..max_attempts {
-                    tokio::time::sleep(delay).await;
-                    let curr = conn.capture_screenshot(clip).await?;
-                    if curr == prev {
-                        return Ok(curr);
-                    }
-                    prev = curr;
-                }
-                Ok(prev)
+                ..
+            } => (max_attempts.max(1), delay),
+            Mode::Single => (1, Duration::ZERO),
+        }
+    }
+
+    /// Whether two consecutive screenshots show the same frame.
+    pub fn same_frame(&self, a: &[u8], b: &[u8]) -> bool {
+        if a == b {
+            return true;
+        }
+        match self.mode {
+            Mode::Stable {
+                same: Sameness::Pixels { tolerance },
+                ..
+            } => pixels_within(a, b, tolerance),
+            _ => false,
+        }
+    }
+
+    /// Take the screenshot, returning it with the number of screenshots
+    /// taken. `single` skips the stability check (adaptive mode).
+    pub async fn take(
+        &self,
+        conn: &mut CdpConnection,
+        clip: &ClipRect,
+        single: bool,
+    ) -> Result<(Vec<u8>, u32)> {
+        let (max_attempts, delay) = self.attempts(single);
+        let mut prev = conn.capture_screenshot(clip, &self.params).await?;
+        for attempt in 2..=max_attempts {
+            tokio::time::sleep(delay).await;
+            let curr = conn.capture_screenshot(clip, &self.params).await?;
+            if self.same_frame(&curr, &prev) {
+                return Ok((curr, attempt));
             }
-            Self::Single => conn.capture_screenshot(clip).await,
+            prev = curr;
         }
+        Ok((prev, max_attempts))
+    }
+}
+
+/// Whether two encoded images decode to the same size with at most
+/// `tolerance` of their pixels differing. Undecodable images never match.
+fn pixels_within(a: &[u8], b: &[u8], tolerance: f64) -> bool {
+    let (Ok(a), Ok(b)) = (image::load_from_memory(a), image::load_from_memory(b)) else {
+        return false;
+    };
+    let (a, b) = (a.to_rgba8(), b.to_rgba8());
+    if a.dimensions() != b.dimensions() {
+        return false;
+    }
+    let total = a.pixels().len().max(1);
+    let differing = a.pixels().zip(b.pixels()).filter(|(p, q)| p != q).count();
+    differing as f64 / total as f64 <= tolerance
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png(pixels: &[[u8; 4]]) -> Vec<u8> {
+        let mut image = image::RgbaImage::new(pixels.len() as u32, 1);
+        for (i, p) in pixels.iter().enumerate() {
+            image.put_pixel(i as u32, 0, image::Rgba(*p));
+        }
+        let mut out = Vec::new();
+        image
+            .write_to(&mut std::io::Cursor::new(&mut out), image::ImageFormat::Png)
+            .unwrap();
+        out
+    }
+
+    #[test]
+    fn gutters_count_only_when_over_a_pixel() {
+        let count = |json: &str| count_gutters(&Value::from(json)).unwrap();
+        assert_eq!(count("[[0, 0]]"), 0);
+        assert_eq!(count("[[0.5, 1], [1, 0.25]]"), 0);
+        assert_eq!(count("[[15, 0], [0.5, 0], [0, 17]]"), 2);
+        assert!(count_gutters(&Value::from(3)).is_err());
+    }
+
+    #[test]
+    fn pixels_within_counts_differing_pixels() {
+        let white = [255, 255, 255, 255];
+        let black = [0, 0, 0, 255];
+        let a = png(&[white, white, white, white]);
+        let b = png(&[white, white, white, black]);
+        assert!(pixels_within(&a, &a, 0.0));
+        assert!(!pixels_within(&a, &b, 0.0));
+        assert!(pixels_within(&a, &b, 0.25));
+        assert!(!pixels_within(&a, &png(&[white, white]), 1.0));
+        assert!(!pixels_within(&a, b"not an image", 1.0));
     }
 }
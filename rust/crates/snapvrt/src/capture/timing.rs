@@ -1,6 +1,10 @@
 use std::time::Duration;
 
+use serde::Serialize;
+use serde_json::Value;
+
 /// Per-stage timing breakdown for a single snapshot.
+#[derive(Clone, Default)]
 pub struct CaptureTimings {
     pub viewport: Duration,
     pub navigate: Duration,
@@ -12,6 +16,69 @@ pub struct CaptureTimings {
     pub clip: Duration,
     pub screenshot: Duration,
     pub total: Duration,
+    /// Screenshots taken before one was accepted (1 without a stability
+    /// check, 2 when the first two matched).
+    pub screenshots: u32,
     /// Time spent on image comparison. Zero when no reference exists.
     pub compare: Duration,
+    /// Byte-identical to the reference: nothing decoded, compared or written.
+    pub identical: bool,
+    /// Page metrics at screenshot time, with `capture.perf_metrics`.
+    pub perf: Option<PerfMetrics>,
+}
+
+/// `Performance.getMetrics` counters of a story's page, taken right after
+/// the screenshot. Cumulative since the tab opened, so they cover loading
+/// and rendering the story.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct PerfMetrics {
+    /// Full or partial page layouts.
+    pub layout_count: u64,
+    /// Style recalculations.
+    pub style_recalc_count: u64,
+    pub layout_ms: f64,
+    pub script_ms: f64,
+    /// Used JS heap, in bytes.
+    pub js_heap_used: u64,
+    pub dom_nodes: u64,
+}
+
+impl PerfMetrics {
+    /// Parse a `Performance.getMetrics` result. Missing metrics are zero.
+    pub fn from_cdp(result: &Value) -> Self {
+        let mut perf = Self::default();
+        for metric in result["metrics"].as_array().into_iter().flatten() {
+            let value = metric["value"].as_f64().unwrap_or(0.0);
+            match metric["name"].as_str().unwrap_or_default() {
+                "LayoutCount" => perf.layout_count = value as u64,
+                "RecalcStyleCount" => perf.style_recalc_count = value as u64,
+                "LayoutDuration" => perf.layout_ms = value * 1000.0,
+                "ScriptDuration" => perf.script_ms = value * 1000.0,
+                "JSHeapUsedSize" => perf.js_heap_used = value as u64,
+                "Nodes" => perf.dom_nodes = value as u64,
+                _ => {}
+            }
+        }
+        perf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn perf_metrics_from_cdp_converts_seconds_to_ms() {
+        let result = serde_json::json!({"metrics": [
+            {"name": "LayoutCount", "value": 12.0},
+            {"name": "ScriptDuration", "value": 0.0425},
+            {"name": "JSHeapUsedSize", "value": 2097152.0},
+            {"name": "Timestamp", "value": 1234.5},
+        ]});
+        let perf = PerfMetrics::from_cdp(&result);
+        assert_eq!(perf.layout_count, 12);
+        assert!((perf.script_ms - 42.5).abs() < 1e-9);
+        assert_eq!(perf.js_heap_used, 2 * 1024 * 1024);
+        assert_eq!(perf.style_recalc_count, 0);
+    }
 }
@@ -0,0 +1,425 @@
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use serde_json::{Value, json};
+use tracing::{debug, warn};
+
+use super::environment::Environment;
+use super::hooks::Hooks;
+use super::pipeline::{CaptureRequest, CaptureResult, ErrorArtifacts};
+use super::scripts;
+use super::strategy::{self, FontControl, Page, Screenshot};
+use super::timing::CaptureTimings;
+use crate::cdp::ClipRect;
+use crate::compare::diff::Mask;
+use crate::config::{CaptureConfig, DialogAction, DomMode, Isolation};
+use crate::webdriver::WebDriver;
+
+/// Limit for page loads and scripts; the readiness scripts time out on
+/// their own well before it.
+const SCRIPT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Time for the page to lay out again after the window is resized.
+const RESIZE_SETTLE: Duration = Duration::from_millis(500);
+
+/// Time allowed for collecting error artifacts.
+const ERROR_ARTIFACT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// WebDriver renderer: starts browser sessions on a WebDriver server (e.g. a
+/// Selenium Grid) and runs the capture pipeline stages that don't need
+/// Chrome DevTools.
+pub struct WebDriverRenderer {
+    server: String,
+    capabilities: Value,
+    /// Sessions kept between captures (`isolation = "tab"`); `None` starts a
+    /// fresh browser per capture.
+    idle: Option<Mutex<Vec<WebDriver>>>,
+    screenshot: Screenshot,
+    fonts: FontControl,
+    hooks: Hooks,
+    media_time: Option<f64>,
+    hide_scrollbar_gutters: bool,
+    ignore_selectors: Vec<String>,
+}
+
+impl WebDriverRenderer {
+    /// Connect to the WebDriver server at `server` and start a first
+    /// session, so a wrong URL or unavailable browser fails the run early.
+    pub async fn launch(
+        config: &CaptureConfig,
+        headers: &BTreeMap<String, String>,
+        server: &str,
+    ) -> Result<Self> {
+        warn_unsupported(config, headers);
+        let renderer = Self {
+            server: server.to_string(),
+            capabilities: capabilities(config),
+            idle: (config.isolation() == Isolation::Tab).then(|| Mutex::new(Vec::new())),
+            screenshot: Screenshot::from_config(config),
+            fonts: FontControl::from_config(config)?,
+            hooks: Hooks::from_config(config)?,
+            media_time: config.media_time,
+            hide_scrollbar_gutters: config.hide_scrollbar_gutters(),
+            ignore_selectors: config.ignore_selectors().to_vec(),
+        };
+        let session = renderer
+            .new_session()
+            .await
+            .with_context(|| format!("Failed to start a WebDriver session at {server}"))?;
+        renderer.close_session(session).await?;
+        Ok(renderer)
+    }
+
+    /// Fingerprint the rendering environment (browser build, platform,
+    /// device scale, fonts) using a scratch session.
+    pub async fn environment(&self) -> Result<Environment> {
+        let mut session = self.new_session().await?;
+        let browser = session.driver.browser().to_string();
+        let result = async {
+            session.driver.set_viewport(800, 600).await?;
+            session.driver.eval_value(scripts::ENV_FINGERPRINT_JS).await
+        }
+        .await;
+        let _ = self.close_session(session).await;
+
+        let json: Value = result?
+            .as_str()
+            .and_then(|s| serde_json::from_str(s).ok())
+            .context("Unexpected environment fingerprint result")?;
+        let fonts = json["fonts"].as_str().unwrap_or_default();
+        let fonts_hash = crate::store::hash_bytes(fonts.as_bytes());
+        Ok(Environment {
+            chrome: browser,
+            platform: json["platform"].as_str().unwrap_or_default().to_string(),
+            device_scale: json["dpr"].as_f64().unwrap_or(1.0),
+            fonts: fonts_hash[..12].to_string(),
+        })
+    }
+
+    /// Reuse an idle session, or start one.
+    pub async fn new_session(&self) -> Result<WebDriverSession> {
+        let idle = self
+            .idle
+            .as_ref()
+            .and_then(|idle| idle.lock().unwrap_or_else(|e| e.into_inner()).pop());
+        let driver = match idle {
+            Some(driver) => driver,
+            None => {
+                let driver = WebDriver::start(&self.server, self.capabilities.clone()).await?;
+                driver.set_timeouts(SCRIPT_TIMEOUT, SCRIPT_TIMEOUT).await?;
+                driver
+            }
+        };
+        Ok(WebDriverSession {
+            driver,
+            screenshot: self.screenshot,
+            fonts: self.fonts.clone(),
+            hooks: self.hooks.clone(),
+            media_time: self.media_time,
+            hide_scrollbar_gutters: self.hide_scrollbar_gutters,
+            ignore_selectors: self.ignore_selectors.clone(),
+        })
+    }
+
+    /// Return a session to the pool after clearing its page, or end it.
+    pub async fn close_session(&self, session: WebDriverSession) -> Result<()> {
+        let driver = session.driver;
+        if let Some(idle) = &self.idle {
+            match driver.navigate("about:blank").await {
+                Ok(()) => {
+                    idle.lock().unwrap_or_else(|e| e.into_inner()).push(driver);
+                    return Ok(());
+                }
+                Err(e) => debug!(error = %format!("{e:#}"), "session unusable, ending it"),
+            }
+        }
+        driver.quit().await
+    }
+
+    /// End the pooled sessions, freeing their Grid slots.
+    pub async fn shutdown(&self) {
+        let Some(idle) = &self.idle else {
+            return;
+        };
+        let drivers = std::mem::take(&mut *idle.lock().unwrap_or_else(|e| e.into_inner()));
+        for driver in drivers {
+            if let Err(e) = driver.quit().await {
+                warn!(error = %format!("{e:#}"), "failed to end WebDriver session");
+            }
+        }
+    }
+}
+
+/// WebDriver session: owns one browser window.
+pub struct WebDriverSession {
+    driver: WebDriver,
+    screenshot: Screenshot,
+    fonts: FontControl,
+    hooks: Hooks,
+    media_time: Option<f64>,
+    hide_scrollbar_gutters: bool,
+    ignore_selectors: Vec<String>,
+}
+
+impl WebDriverSession {
+    pub fn target_id(&self) -> &str {
+        self.driver.id()
+    }
+
+    /// Collect a screenshot and the page HTML from the current page state.
+    /// Never fails; console output is not available over WebDriver.
+    pub async fn error_artifacts(&mut self) -> ErrorArtifacts {
+        let mut artifacts = ErrorArtifacts::default();
+        let driver = &mut self.driver;
+        let collect = async {
+            artifacts.screenshot = driver.screenshot().await.ok();
+            artifacts.html = driver
+                .eval_value("document.documentElement.outerHTML")
+                .await
+                .ok()
+                .and_then(|v| v.as_str().map(str::to_string));
+        };
+        if tokio::time::timeout(ERROR_ARTIFACT_TIMEOUT, collect)
+            .await
+            .is_err()
+        {
+            debug!("error artifact collection timed out");
+        }
+        artifacts
+    }
+
+    /// The capture pipeline over WebDriver: the stages of the CDP pipeline,
+    /// with page load and network idle detected from inside the page, and
+    /// the viewport screenshot cropped to the clip.
+    pub async fn capture(
+        &mut self,
+        req: &CaptureRequest,
+        on_stage: impl Fn(&'static str),
+    ) -> Result<CaptureResult> {
+        let driver = &mut self.driver;
+        let t0 = Instant::now();
+
+        on_stage("viewport");
+        driver.set_viewport(req.width, req.height).await?;
+        if req.settings.network.is_some() {
+            warn!(story = %req.url, "network emulation needs Chrome DevTools, ignored");
+        }
+        let t1 = Instant::now();
+
+        // Navigation returns once the load event fired.
+        on_stage("navigate");
+        let url = self.hooks.before_navigation(&req.url);
+        debug!(url = %url, "navigate");
+        driver.navigate(&url).await?;
+        let t2 = Instant::now();
+        on_stage("page_load");
+        let t3 = Instant::now();
+
+        on_stage("network");
+        driver
+            .eval_value(scripts::WAIT_FOR_NETWORK_QUIET_JS)
+            .await?;
+        self.hooks.after_navigation(driver, req).await?;
+        let t4 = Instant::now();
+
+        on_stage("animation");
+        strategy::disable_animations(driver).await?;
+        self.fonts.apply_override(driver).await?;
+        if let Some(time) = self.media_time {
+            strategy::pin_media(driver, time).await?;
+        }
+        let t5 = Instant::now();
+
+        on_stage("ready");
+        driver.eval_value(scripts::WAIT_FOR_PLAY_JS).await?;
+        driver.eval_value(scripts::WAIT_FOR_READY_JS).await?;
+        self.fonts.wait_loaded(driver).await?;
+        self.hooks.after_ready(driver, req).await?;
+        let t6 = Instant::now();
+
+        on_stage("selector");
+        if !req.whole_page {
+            driver.eval_value(scripts::WAIT_FOR_STORY_ROOT_JS).await?;
+        }
+        let focus_scroll = strategy::focus(driver, req.focus_presses).await?;
+        let window_scroll = match req.settings.scroll_to {
+            Some(_) => strategy::scroll(driver, &req.settings).await?,
+            None => focus_scroll,
+        };
+        let t7 = Instant::now();
+
+        // The screenshot shows the viewport, so the clip stays
+        // viewport-relative.
+        on_stage("clip");
+        if self.hide_scrollbar_gutters {
+            let gutters = strategy::hide_scrollbar_gutters(driver).await?;
+            debug!(gutters, "hid scrollbar gutters");
+        }
+        let mut clip = if req.whole_page {
+            strategy::get_page_clip(driver).await?
+        } else {
+            strategy::get_clip(driver).await?
+        };
+        clip.w = clip.w.min(req.width as f64).max(1.0);
+        if window_scroll.is_some() {
+            let top = clip.y.max(0.0);
+            clip.h = (clip.y + clip.h).min(req.height as f64) - top;
+            clip.y = top;
+        }
+        clip.h = clip.h.max(1.0);
+        let bottom = (clip.y + clip.h).ceil() as u32;
+        let resized = bottom > req.height;
+        if resized {
+            debug!(
+                original_h = req.height,
+                new_h = bottom,
+                "resizing window for tall content"
+            );
+            driver.set_viewport(req.width, bottom).await?;
+            tokio::time::sleep(RESIZE_SETTLE).await;
+        }
+        let dpr = driver
+            .eval_value("window.devicePixelRatio")
+            .await?
+            .as_f64()
+            .unwrap_or(1.0);
+        let masks: Vec<Mask> = strategy::get_ignore_masks(driver, &self.ignore_selectors, &clip)
+            .await?
+            .into_iter()
+            .map(|m| m.scaled(dpr))
+            .collect();
+        self.hooks.before_screenshot(driver, req).await?;
+        let t8 = Instant::now();
+
+        on_stage("screenshot");
+        let (attempts, delay) = self.screenshot.attempts(req.single_screenshot);
+        let mut shot = driver.screenshot().await?;
+        let mut screenshots = 1;
+        while screenshots < attempts {
+            tokio::time::sleep(delay).await;
+            let next = driver.screenshot().await?;
+            screenshots += 1;
+            let same = self.screenshot.same_frame(&next, &shot);
+            shot = next;
+            if same {
+                break;
+            }
+        }
+        let png = crop(&shot, &clip, dpr)?;
+        let t9 = Instant::now();
+
+        let timings = CaptureTimings {
+            viewport: t1 - t0,
+            navigate: t2 - t1,
+            page_load: t3 - t2,
+            network: t4 - t3,
+            animation: t5 - t4,
+            ready: t6 - t5,
+            selector: t7 - t6,
+            clip: t8 - t7,
+            screenshot: t9 - t8,
+            total: t9 - t0,
+            screenshots,
+            compare: Duration::ZERO,
+            identical: false,
+            perf: None,
+        };
+        Ok(CaptureResult {
+            png,
+            timings,
+            masks,
+            texts: BTreeMap::new(),
+        })
+    }
+}
+
+/// Crop a viewport screenshot to `clip` (CSS pixels, `dpr` device pixels
+/// each) and encode it as PNG.
+fn crop(screenshot: &[u8], clip: &ClipRect, dpr: f64) -> Result<Vec<u8>> {
+    let image = image::load_from_memory(screenshot).context("Failed to decode screenshot")?;
+    let x = ((clip.x * dpr).floor().max(0.0) as u32).min(image.width() - 1);
+    let y = ((clip.y * dpr).floor().max(0.0) as u32).min(image.height() - 1);
+    let w = ((clip.w * dpr).round() as u32).clamp(1, image.width() - x);
+    let h = ((clip.h * dpr).round() as u32).clamp(1, image.height() - y);
+    let mut png = Vec::new();
+    image
+        .crop_imm(x, y, w, h)
+        .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+        .context("Failed to encode screenshot")?;
+    Ok(png)
+}
+
+/// `alwaysMatch` capabilities for a new session.
+fn capabilities(config: &CaptureConfig) -> Value {
+    let browser = config.webdriver_browser();
+    let mut caps = json!({
+        "browserName": browser,
+        "acceptInsecureCerts": config.ignore_https_errors(),
+        "unhandledPromptBehavior": match config.dialogs() {
+            DialogAction::Dismiss => "dismiss",
+            DialogAction::Accept => "accept",
+        },
+    });
+    let headless = !config.debug;
+    match browser.to_ascii_lowercase().as_str() {
+        name @ ("chrome" | "chromium" | "microsoftedge" | "msedge") => {
+            let mut args = config.chrome_args();
+            args.push("--hide-scrollbars".to_string());
+            if headless {
+                args.push("--headless=new".to_string());
+            }
+            let key = match name {
+                "microsoftedge" | "msedge" => "ms:edgeOptions",
+                _ => "goog:chromeOptions",
+            };
+            caps[key] = json!({ "args": args });
+        }
+        "firefox" if headless => caps["moz:firefoxOptions"] = json!({ "args": ["-headless"] }),
+        _ => {}
+    }
+    caps
+}
+
+/// Warn about configured features that need Chrome DevTools.
+fn warn_unsupported(config: &CaptureConfig, headers: &BTreeMap<String, String>) {
+    let unsupported = [
+        ("har", config.har()),
+        ("a11y", config.a11y()),
+        ("dom", config.dom() != DomMode::Off),
+        ("css_coverage", config.css_coverage()),
+        ("perf_metrics", config.perf_metrics()),
+        ("deterministic_canvas", config.deterministic_canvas()),
+        ("block_remote_fonts", config.block_remote_fonts()),
+        ("bypass_service_workers", config.bypass_service_workers()),
+        ("disable_cache", config.disable_cache()),
+        ("headers", !headers.is_empty()),
+    ];
+    for (name, _) in unsupported.iter().filter(|(_, enabled)| *enabled) {
+        warn!("{name} needs Chrome DevTools and is ignored with capture.webdriver_url");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crop_scales_clip_to_device_pixels_and_clamps() {
+        let image = image::RgbaImage::new(200, 100);
+        let mut png = Vec::new();
+        image
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        let clip = ClipRect {
+            x: 10.0,
+            y: 5.0,
+            w: 40.0,
+            h: 80.0,
+        };
+        let cropped = image::load_from_memory(&crop(&png, &clip, 2.0).unwrap()).unwrap();
+        // 80x160 device pixels from (20, 10), clamped to the 100px height.
+        assert_eq!((cropped.width(), cropped.height()), (80, 90));
+    }
+}
@@ -1,4 +1,4 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicU64, Ordering};
 
 use anyhow::{Context, Result, bail};
@@ -6,31 +6,86 @@ use tokio::io::AsyncBufReadExt;
 use tokio::process::{Child, Command};
 use tracing::{debug, info};
 
+use super::CdpConnection;
+
 static BROWSER_COUNTER: AtomicU64 = AtomicU64::new(0);
 
+/// Temp user-data dirs are `<prefix><pid>-<n>` in the system temp dir.
+const DATA_DIR_PREFIX: &str = "snapvrt-";
+
+/// How long dropping a local Chrome waits for the killed process to exit.
+const EXIT_WAIT: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// API token of a managed Chrome service (browserless, browsercloud), sent
+/// with every JSON API request and WebSocket upgrade.
+#[derive(Clone, Debug)]
+pub enum ChromeToken {
+    /// Appended to every URL as `?token=...`.
+    Query(String),
+    /// Sent as `Authorization: Bearer ...`.
+    Bearer(String),
+}
+
 /// Chrome process lifecycle: launch (or connect to remote), create tabs, kill.
 pub struct Chrome {
     /// None when connected to a remote Chrome we don't own.
     child: Option<Child>,
     /// host:port for HTTP JSON API and building per-tab WebSocket URLs.
     host_port: String,
-    /// Temp data dir, cleaned up on drop (only for local Chrome).
+    /// Reached over TLS (`https://` / `wss://`), e.g. a remote Chrome behind
+    /// a TLS-terminating proxy.
+    tls: bool,
+    /// Token for a managed remote Chrome service.
+    token: Option<ChromeToken>,
+    /// Client for the HTTP JSON API, sending the bearer token if any.
+    http: reqwest::Client,
+    /// Temp data dir, cleaned up on drop (only for local Chrome with no
+    /// persistent profile).
     data_dir: Option<PathBuf>,
 }
 
 impl Chrome {
     /// Launch a local Chrome with `--remote-debugging-port=0` (auto-assign).
     /// Parses `DevTools listening on ws://...` from stderr.
-    pub async fn launch() -> Result<Self> {
+    ///
+    /// `headless = false` opens a visible window (for `test --debug`).
+    /// `ignore_cert_errors` accepts invalid TLS certificates. `extra_args`
+    /// are appended to the default flags. `profile_dir` is a persistent
+    /// user-data dir, kept after Chrome exits; without it Chrome gets a temp
+    /// one, removed on drop.
+    pub async fn launch(
+        headless: bool,
+        ignore_cert_errors: bool,
+        extra_args: &[String],
+        profile_dir: Option<&Path>,
+    ) -> Result<Self> {
         let id = BROWSER_COUNTER.fetch_add(1, Ordering::Relaxed);
-        let data_dir = std::env::temp_dir().join(format!("snapvrt-{}-{id}", std::process::id()));
+        if id == 0 {
+            remove_stale_data_dirs();
+        }
+        let (user_data_dir, data_dir) = match profile_dir {
+            Some(dir) => {
+                std::fs::create_dir_all(dir)
+                    .with_context(|| format!("Failed to create {}", dir.display()))?;
+                let dir = std::path::absolute(dir)
+                    .with_context(|| format!("Failed to resolve {}", dir.display()))?;
+                (dir, None)
+            }
+            None => {
+                let dir = std::env::temp_dir()
+                    .join(format!("{DATA_DIR_PREFIX}{}-{id}", std::process::id()));
+                (dir.clone(), Some(dir))
+            }
+        };
 
         let chrome_path = find_chrome()?;
         info!(path = %chrome_path, "launching local Chrome");
 
         let mut child = Command::new(chrome_path)
+            .args(headless.then_some("--headless=new"))
+            .args(ignore_cert_errors.then_some("--ignore-certificate-errors"))
+            .args(extra_args)
             .args([
-                "--headless=new",
                 "--disable-gpu",
                 "--no-first-run",
                 "--no-default-browser-check",
@@ -46,7 +101,7 @@ impl Chrome {
                 "--hide-scrollbars",
                 "--remote-debugging-port=0",
             ])
-            .arg(format!("--user-data-dir={}", data_dir.display()))
+            .arg(format!("--user-data-dir={}", user_data_dir.display()))
             .stderr(std::process::Stdio::piped())
             .stdout(std::process::Stdio::null())
             .stdin(std::process::Stdio::null())
@@ -85,53 +140,156 @@ impl Chrome {
         Ok(Self {
             child: Some(child),
             host_port,
-            data_dir: Some(data_dir),
+            tls: false,
+            token: None,
+            http: reqwest::Client::new(),
+            data_dir,
         })
     }
 
     /// Connect to a remote Chrome instance (e.g. running in Docker).
     ///
-    /// `base_url` is `http://host:port` — we hit `/json/version` to verify
-    /// connectivity, then use the HTTP JSON API for tab management.
-    pub async fn connect(base_url: &str) -> Result<Self> {
+    /// `base_url` is `http://host:port` (or `https://`, making the DevTools
+    /// WebSockets `wss://`) — we hit `/json/version` to verify connectivity,
+    /// then use the HTTP JSON API for tab management. `token` authenticates
+    /// with a managed Chrome service.
+    pub async fn connect(base_url: &str, token: Option<ChromeToken>) -> Result<Self> {
         let base = base_url.trim_end_matches('/');
         let version_url = format!("{base}/json/version");
 
         // Extract the host:port the user gave us — this is what we'll use for
         // all HTTP and WebSocket connections, regardless of what Chrome reports
         // internally (e.g. Docker container address).
-        let caller_host_port = base
-            .split("://")
-            .nth(1)
-            .context("Invalid chrome_url: no scheme")?
-            .to_string();
+        let (scheme, caller_host_port) = base
+            .split_once("://")
+            .context("Invalid chrome_url: no scheme")?;
+        let tls = match scheme {
+            "http" => false,
+            "https" => true,
+            _ => bail!("Invalid chrome_url: expected http:// or https://, got {scheme}://"),
+        };
+
+        let mut http = reqwest::Client::builder();
+        if let Some(ChromeToken::Bearer(token)) = &token {
+            let mut value = reqwest::header::HeaderValue::from_str(&format!("Bearer {token}"))
+                .context("Invalid chrome_token")?;
+            value.set_sensitive(true);
+            let mut headers = reqwest::header::HeaderMap::new();
+            headers.insert(reqwest::header::AUTHORIZATION, value);
+            http = http.default_headers(headers);
+        }
+        let chrome = Self {
+            child: None,
+            host_port: caller_host_port.to_string(),
+            tls,
+            token,
+            http: http.build().context("Failed to build HTTP client")?,
+            data_dir: None,
+        };
 
         info!(url = %version_url, "connecting to remote Chrome");
-        reqwest::get(&version_url)
+        chrome
+            .http
+            .get(chrome.version_url())
+            .send()
             .await
+            .map_err(reqwest::Error::without_url)
             .with_context(|| format!("Failed to reach Chrome at {version_url}"))?
             .error_for_status()
-            .context("Chrome /json/version returned error")?;
+            .context("Chrome /json/version returned error (wrong chrome_token?)")?;
 
         debug!("remote Chrome is reachable");
 
-        Ok(Self {
-            child: None,
-            host_port: caller_host_port,
-            data_dir: None,
-        })
+        Ok(chrome)
+    }
+
+    /// Browser product string from `GET /json/version` (e.g. `HeadlessChrome/139.0.7258.138`).
+    pub async fn version(&self) -> Result<String> {
+        let url = self.version_url();
+        let resp: serde_json::Value = self
+            .http
+            .get(&url)
+            .send()
+            .await
+            .map_err(reqwest::Error::without_url)
+            .context("GET /json/version failed")?
+            .json()
+            .await
+            .context("Failed to parse /json/version response")?;
+        resp["Browser"]
+            .as_str()
+            .map(str::to_string)
+            .context("No Browser in /json/version response")
+    }
+
+    /// Browser-level WebSocket URL from `GET /json/version`, for commands
+    /// that aren't scoped to a tab (browser contexts, targets).
+    pub async fn browser_ws_url(&self) -> Result<String> {
+        let url = self.version_url();
+        let resp: serde_json::Value = self
+            .http
+            .get(&url)
+            .send()
+            .await
+            .map_err(reqwest::Error::without_url)
+            .context("GET /json/version failed")?
+            .json()
+            .await
+            .context("Failed to parse /json/version response")?;
+        let reported = resp["webSocketDebuggerUrl"]
+            .as_str()
+            .context("No webSocketDebuggerUrl in /json/version response")?;
+        // Keep our host:port; Chrome reports its own (e.g. inside Docker).
+        let path = reported
+            .split("://")
+            .nth(1)
+            .and_then(|rest| rest.split_once('/'))
+            .map(|(_, path)| path.split('?').next().unwrap_or(path))
+            .context("Invalid webSocketDebuggerUrl")?;
+        Ok(self.ws_url(&format!("/{path}")))
+    }
+
+    /// `GET /json/version` endpoint, also used to health-check remote Chrome.
+    pub fn version_url(&self) -> String {
+        self.http_url("/json/version")
+    }
+
+    /// Connected with [`Self::connect`] rather than launched by us.
+    pub fn is_remote(&self) -> bool {
+        self.child.is_none()
+    }
+
+    /// Client for [`Self::version_url`] and other JSON API requests.
+    pub fn http_client(&self) -> reqwest::Client {
+        self.http.clone()
+    }
+
+    /// Open a CDP WebSocket to one of this browser's targets.
+    pub async fn open(&self, ws_url: &str) -> Result<CdpConnection> {
+        let bearer = match &self.token {
+            Some(ChromeToken::Bearer(token)) => Some(token.as_str()),
+            _ => None,
+        };
+        CdpConnection::connect(ws_url, bearer).await
+    }
+
+    /// Per-target WebSocket URL.
+    pub fn page_ws_url(&self, target_id: &str) -> String {
+        self.ws_url(&format!("/devtools/page/{target_id}"))
     }
 
     /// Create a new tab via `PUT /json/new` (HTTP JSON API, no browser WS needed).
     /// Returns `(target_id, ws_url)` where `ws_url` is the per-target WebSocket.
     pub async fn create_tab(&self) -> Result<(String, String)> {
-        let url = format!("http://{}/json/new?about:blank", self.host_port);
-        debug!(url = %url, "PUT /json/new");
+        let url = self.http_url("/json/new?about:blank");
+        debug!(url = %redacted(&url), "PUT /json/new");
 
-        let resp: serde_json::Value = reqwest::Client::new()
+        let resp: serde_json::Value = self
+            .http
             .put(&url)
             .send()
             .await
+            .map_err(reqwest::Error::without_url)
             .context("PUT /json/new failed")?
             .json()
             .await
@@ -142,7 +300,7 @@ impl Chrome {
             .context("No id in /json/new response")?
             .to_string();
 
-        let ws_url = format!("ws://{}/devtools/page/{target_id}", self.host_port);
+        let ws_url = self.page_ws_url(&target_id);
         debug!(target_id = %target_id, "tab created");
 
         Ok((target_id, ws_url))
@@ -150,14 +308,40 @@ impl Chrome {
 
     /// Close a tab via `GET /json/close/<id>` (HTTP JSON API, no browser WS needed).
     pub async fn close_tab(&self, target_id: &str) -> Result<()> {
-        let url = format!("http://{}/json/close/{target_id}", self.host_port);
-        reqwest::get(&url)
+        let url = self.http_url(&format!("/json/close/{target_id}"));
+        self.http
+            .get(&url)
+            .send()
             .await
+            .map_err(reqwest::Error::without_url)
             .with_context(|| format!("GET /json/close/{target_id} failed"))?;
         debug!(target_id, "tab closed");
         Ok(())
     }
 
+    /// `http(s)://host:port<path>` for the HTTP JSON API.
+    fn http_url(&self, path: &str) -> String {
+        let scheme = if self.tls { "https" } else { "http" };
+        self.with_token(format!("{scheme}://{}{path}", self.host_port))
+    }
+
+    /// `ws(s)://host:port<path>` for DevTools WebSockets.
+    fn ws_url(&self, path: &str) -> String {
+        let scheme = if self.tls { "wss" } else { "ws" };
+        self.with_token(format!("{scheme}://{}{path}", self.host_port))
+    }
+
+    /// Append a query-string token to `url`.
+    fn with_token(&self, url: String) -> String {
+        match &self.token {
+            Some(ChromeToken::Query(token)) => {
+                let sep = if url.contains('?') { '&' } else { '?' };
+                format!("{url}{sep}token={token}")
+            }
+            _ => url,
+        }
+    }
+
     /// Kill the Chrome process (no-op for remote connections).
     pub fn kill(&mut self) {
         if let Some(ref mut child) = self.child {
@@ -169,13 +353,69 @@ impl Chrome {
 impl Drop for Chrome {
     fn drop(&mut self) {
         self.kill();
+        // Chrome writes to its profile until it exits; removing the data dir
+        // earlier leaves part of it behind.
+        if let Some(ref mut child) = self.child {
+            let deadline = std::time::Instant::now() + EXIT_WAIT;
+            while matches!(child.try_wait(), Ok(None)) && std::time::Instant::now() < deadline {
+                std::thread::sleep(std::time::Duration::from_millis(20));
+            }
+        }
         if let Some(ref data_dir) = self.data_dir {
             let _ = std::fs::remove_dir_all(data_dir);
         }
     }
 }
 
+/// Remove temp user-data dirs left behind by snapvrt processes that no
+/// longer run (killed, OOM-killed, crashed), so they don't pile up in /tmp.
+fn remove_stale_data_dirs() {
+    let Ok(entries) = std::fs::read_dir(std::env::temp_dir()) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let Some(pid) = name.to_str().and_then(data_dir_pid) else {
+            continue;
+        };
+        if pid == std::process::id() || process_alive(pid) {
+            continue;
+        }
+        match std::fs::remove_dir_all(entry.path()) {
+            Ok(()) => debug!(dir = %entry.path().display(), "removed stale Chrome data dir"),
+            Err(e) => {
+                debug!(dir = %entry.path().display(), error = %e, "failed to remove stale Chrome data dir")
+            }
+        }
+    }
+}
+
+/// The snapvrt process ID in a temp data dir name (`snapvrt-<pid>-<n>`).
+fn data_dir_pid(name: &str) -> Option<u32> {
+    let (pid, n) = name.strip_prefix(DATA_DIR_PREFIX)?.split_once('-')?;
+    n.parse::<u64>().ok()?;
+    pid.parse().ok()
+}
+
+fn process_alive(pid: u32) -> bool {
+    let proc = Path::new("/proc");
+    if proc.is_dir() {
+        return proc.join(pid.to_string()).exists();
+    }
+    std::process::Command::new("kill")
+        .args(["-0", &pid.to_string()])
+        .stderr(std::process::Stdio::null())
+        .status()
+        .is_ok_and(|s| s.success())
+}
+
 /// Extract `host:port` from a WebSocket URL like `ws://127.0.0.1:9222/devtools/browser/...`
+/// `url` without its query string, which may carry a token, for logs and
+/// error messages.
+pub fn redacted(url: &str) -> &str {
+    url.split_once('?').map_or(url, |(base, _)| base)
+}
+
 fn parse_host_port(ws_url: &str) -> Result<String> {
     let after_scheme = ws_url
         .split("://")
@@ -225,3 +465,42 @@ fn find_chrome() -> Result<String> {
 
     bail!("Chrome not found. Tried: {}", candidates.join(", "))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn data_dir_pid_matches_temp_profiles_only() {
+        assert_eq!(data_dir_pid("snapvrt-4242-0"), Some(4242));
+        assert_eq!(data_dir_pid("snapvrt-4242-12"), Some(4242));
+        assert_eq!(data_dir_pid("snapvrt-cache"), None);
+        assert_eq!(data_dir_pid("snapvrt-4242"), None);
+        assert_eq!(data_dir_pid("chrome-4242-0"), None);
+    }
+
+    #[test]
+    fn query_token_is_appended_to_every_url() {
+        let chrome = Chrome {
+            child: None,
+            host_port: "chrome.example:3000".into(),
+            tls: true,
+            token: Some(ChromeToken::Query("s3cret".into())),
+            http: reqwest::Client::new(),
+            data_dir: None,
+        };
+        assert_eq!(
+            chrome.version_url(),
+            "https://chrome.example:3000/json/version?token=s3cret"
+        );
+        let new_tab = chrome.http_url("/json/new?about:blank");
+        assert_eq!(
+            new_tab,
+            "https://chrome.example:3000/json/new?about:blank&token=s3cret"
+        );
+        assert_eq!(
+            redacted(&chrome.page_ws_url("ABC")),
+            "wss://chrome.example:3000/devtools/page/ABC"
+        );
+    }
+}
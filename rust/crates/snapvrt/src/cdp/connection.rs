@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 use std::time::Duration;
 
 use anyhow::{Context, Result, bail};
@@ -6,13 +6,46 @@ use futures::{SinkExt, StreamExt};
 use serde_json::{Value, json};
 use tokio::net::TcpStream;
 use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
 use tokio_tungstenite::{MaybeTlsStream, WebSocketStream, connect_async};
 use tracing::{debug, trace, warn};
 
-/// A CDP event received from the browser.
-struct CdpEvent {
-    method: String,
-    params: Value,
+use super::chrome::redacted;
+use super::events::{CdpEvent, EventRouter, Reply, Subscription};
+
+/// Silence on the WebSocket after which we ping Chrome, and then how long we
+/// wait for the pong before declaring the connection dead.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Buffer sizes of the built-in subscriptions. Network events feed HAR
+/// files, so they get the most room.
+const PAGE_EVENTS: usize = 256;
+const NETWORK_EVENTS: usize = 50_000;
+const CONSOLE_EVENTS: usize = 1_000;
+
+/// The WebSocket to Chrome closed, failed or stopped answering pings: the
+/// tab or the browser went away, rather than the page misbehaving.
+#[derive(Debug, thiserror::Error)]
+#[error("Lost the WebSocket to Chrome ({reason}) while waiting for {waiting_for}")]
+pub struct ConnectionLost {
+    reason: String,
+    waiting_for: String,
+}
+
+impl ConnectionLost {
+    fn new(reason: impl Into<String>, waiting_for: &str) -> Self {
+        Self {
+            reason: reason.into(),
+            waiting_for: waiting_for.to_string(),
+        }
+    }
+}
+
+/// A message read from the WebSocket.
+enum Incoming {
+    /// A command response, with its id.
+    Response(u64, Value),
+    Event(CdpEvent),
 }
 
 /// Per-target WebSocket CDP connection.
@@ -22,28 +55,118 @@ struct CdpEvent {
 pub struct CdpConnection {
     ws: WebSocketStream<MaybeTlsStream<TcpStream>>,
     next_id: u64,
-    event_buffer: Vec<CdpEvent>,
+    events: EventRouter,
+    page_events: Subscription,
+    network_events: Subscription,
+    console_events: Subscription,
+    /// Longest a command may wait for its response; `None` waits forever.
+    command_timeout: Option<Duration>,
 }
 
 impl CdpConnection {
-    /// Connect to a CDP WebSocket URL (browser or per-target).
-    pub async fn connect(url: &str) -> Result<Self> {
-        debug!(url, "connecting CDP WebSocket");
-        let (ws, _) = connect_async(url)
+    /// Connect to a CDP WebSocket URL (browser or per-target), sending
+    /// `bearer` as an `Authorization` token with the upgrade request.
+    pub async fn connect(url: &str, bearer: Option<&str>) -> Result<Self> {
+        let shown = redacted(url);
+        debug!(url = shown, "connecting CDP WebSocket");
+        let mut request = url
+            .into_client_request()
+            .with_context(|| format!("Invalid WebSocket URL {shown}"))?;
+        if let Some(token) = bearer {
+            let value = format!("Bearer {token}")
+                .parse()
+                .context("Invalid chrome_token")?;
+            request.headers_mut().insert("Authorization", value);
+        }
+        let (ws, _) = connect_async(request)
             .await
-            .with_context(|| format!("Failed to connect to {url}"))?;
-        debug!(url, "CDP WebSocket connected");
+            .with_context(|| format!("Failed to connect to {shown}"))?;
+        debug!(url = shown, "CDP WebSocket connected");
 
+        let mut events = EventRouter::default();
         Ok(Self {
             ws,
             next_id: 1,
-            event_buffer: Vec::new(),
+            page_events: events.subscribe(&["Page."], PAGE_EVENTS),
+            network_events: events.subscribe(&["Network."], NETWORK_EVENTS),
+            console_events: events.subscribe(
+                &["Runtime.consoleAPICalled", "Runtime.exceptionThrown"],
+                CONSOLE_EVENTS,
+            ),
+            events,
+            command_timeout: None,
         })
     }
 
+    /// Buffer events matching any of `patterns` (exact methods, or domain
+    /// prefixes like `Network.`), keeping the latest `capacity`, until the
+    /// next navigation. Read them with [`Self::events`].
+    pub fn subscribe(&mut self, patterns: &[&'static str], capacity: usize) -> Subscription {
+        self.events.subscribe(patterns, capacity)
+    }
+
+    /// Buffered events of a subscription, oldest first.
+    pub fn events(&self, sub: Subscription) -> impl Iterator<Item = &CdpEvent> {
+        self.events.events(sub)
+    }
+
+    /// Call `handler` for every event matching `pattern` as soon as it is
+    /// read, during any command or wait. The command it returns, if any, is
+    /// sent without waiting for the response.
+    pub fn on_event(
+        &mut self,
+        pattern: &'static str,
+        handler: impl FnMut(&CdpEvent) -> Option<Reply> + Send + 'static,
+    ) {
+        self.events.on_event(pattern, Box::new(handler));
+    }
+
+    /// Fail commands whose response takes longer than `timeout`.
+    pub fn set_command_timeout(&mut self, timeout: Option<Duration>) {
+        self.command_timeout = timeout;
+    }
+
     /// Send a CDP command and wait for the matching response (by id).
     /// Events received while waiting are buffered for later retrieval.
+    ///
+    /// Fails after the command timeout, so a hung renderer fails the command
+    /// waiting on it rather than the whole capture.
     pub async fn call(&mut self, method: &str, params: Value) -> Result<Value> {
+        let Some(timeout) = self.command_timeout else {
+            return self.call_inner(method, params).await;
+        };
+        match tokio::time::timeout(timeout, self.call_inner(method, params)).await {
+            Ok(result) => result,
+            Err(_) => bail!(
+                "CDP command {method} got no response within {:.1}s (renderer hung?)",
+                timeout.as_secs_f64()
+            ),
+        }
+    }
+
+    async fn call_inner(&mut self, method: &str, params: Value) -> Result<Value> {
+        let id = self.send(method, params).await?;
+
+        // Read messages until we get the matching response.
+        loop {
+            let Incoming::Response(response_id, response) = self.read("response").await? else {
+                continue;
+            };
+            if response_id != id {
+                continue; // Response to an event handler's reply.
+            }
+            if let Some(error) = response.get("error") {
+                bail!(
+                    "CDP error for {method}: {}",
+                    serde_json::to_string(error).unwrap_or_default()
+                );
+            }
+            return Ok(response.get("result").cloned().unwrap_or(Value::Null));
+        }
+    }
+
+    /// Send a command without waiting for its response. Returns its id.
+    async fn send(&mut self, method: &str, params: Value) -> Result<u64> {
         let id = self.next_id;
         self.next_id += 1;
 
@@ -56,81 +179,56 @@ impl CdpConnection {
         self.ws
             .send(Message::Text(msg.to_string().into()))
             .await
+            .map_err(|e| ConnectionLost::new(e.to_string(), method))
             .with_context(|| format!("Failed to send CDP command {method}"))?;
+        Ok(id)
+    }
 
-        // Read messages until we get the matching response.
+    /// Read the next message. Events are dispatched to handlers and
+    /// subscriptions before they are returned.
+    async fn read(&mut self, waiting_for: &str) -> Result<Incoming> {
         loop {
-            let raw = self
-                .ws
-                .next()
-                .await
-                .context("WebSocket closed while waiting for response")?
-                .context("WebSocket error")?;
-
-            let Message::Text(text) = raw else {
-                continue; // Skip binary/ping/pong frames
-            };
-
+            let text = self.next_text(waiting_for).await?;
             let parsed: Value =
                 serde_json::from_str(&text).context("Failed to parse CDP message")?;
 
-            // Check if this is our response (has matching id).
-            if parsed.get("id").and_then(|v| v.as_u64()) == Some(id) {
-                if let Some(error) = parsed.get("error") {
-                    bail!(
-                        "CDP error for {method}: {}",
-                        serde_json::to_string(error).unwrap_or_default()
-                    );
-                }
-                return Ok(parsed.get("result").cloned().unwrap_or(Value::Null));
+            if let Some(id) = parsed.get("id").and_then(|v| v.as_u64()) {
+                return Ok(Incoming::Response(id, parsed));
             }
-
-            // Otherwise it's an event — buffer it.
-            if let Some(event_method) = parsed.get("method").and_then(|v| v.as_str()) {
-                self.event_buffer.push(CdpEvent {
-                    method: event_method.to_string(),
-                    params: parsed.get("params").cloned().unwrap_or(Value::Null),
-                });
+            let Some(method) = parsed.get("method").and_then(|v| v.as_str()) else {
+                continue;
+            };
+            let event = CdpEvent {
+                method: method.to_string(),
+                params: parsed.get("params").cloned().unwrap_or(Value::Null),
+            };
+            for reply in self.events.dispatch(&event) {
+                trace!(event = %event.method, reply = reply.method, "answering event");
+                self.send(reply.method, reply.params).await?;
             }
+            return Ok(Incoming::Event(event));
         }
     }
 
-    /// Wait for a specific CDP event (by method name).
-    /// Checks the buffer first, then reads from WebSocket.
+    /// Wait for a specific CDP event (by method name), which a subscription
+    /// must buffer. Takes the oldest buffered one first, then reads from the
+    /// WebSocket.
     pub async fn wait_event(&mut self, method: &str) -> Result<Value> {
-        // Check buffer first.
-        if let Some(idx) = self.event_buffer.iter().position(|e| e.method == method) {
-            return Ok(self.event_buffer.remove(idx).params);
+        if !self.events.covers(method) {
+            bail!("No subscription buffers {method}");
         }
-
-        // Read from WebSocket until we get the event.
         loop {
-            let raw = self
-                .ws
-                .next()
-                .await
-                .context("WebSocket closed while waiting for event")?
-                .context("WebSocket error")?;
-
-            let Message::Text(text) = raw else {
-                continue;
-            };
-
-            let parsed: Value =
-                serde_json::from_str(&text).context("Failed to parse CDP message")?;
-
-            if let Some(event_method) = parsed.get("method").and_then(|v| v.as_str()) {
-                let params = parsed.get("params").cloned().unwrap_or(Value::Null);
-                if event_method == method {
-                    return Ok(params);
-                }
-                // Buffer other events.
-                self.event_buffer.push(CdpEvent {
-                    method: event_method.to_string(),
-                    params,
-                });
+            if let Some(event) = self.events.take(method) {
+                return Ok(event.params);
             }
-            // Ignore non-event messages (stale responses, etc.)
+            self.read("event").await?;
+        }
+    }
+
+    /// Read events (running their handlers) until the connection closes.
+    pub async fn listen(&mut self) -> Result<()> {
+        loop {
+            self.read("event").await?;
         }
     }
 
@@ -146,11 +244,11 @@ impl CdpConnection {
         let mut pending: HashSet<String> = HashSet::new();
 
         // Process already-buffered network events.
-        for event in &self.event_buffer {
+        for event in self.events.events(self.network_events) {
             Self::track_network(&event.method, &event.params, &mut pending);
         }
         trace!(
-            buffered_events = self.event_buffer.len(),
+            buffered_events = self.events.buffered(),
             pending = pending.len(),
             "network idle: initial state"
         );
@@ -178,9 +276,8 @@ impl CdpConnection {
                     return Ok(());
                 }
                 Ok(result) => {
-                    let (method, params) = result?;
-                    Self::track_network(&method, &params, &mut pending);
-                    self.event_buffer.push(CdpEvent { method, params });
+                    let event = result?;
+                    Self::track_network(&event.method, &event.params, &mut pending);
                 }
             }
         }
@@ -218,22 +315,35 @@ impl CdpConnection {
         Ok(result)
     }
 
-    /// Capture a screenshot of the given clip region and return decoded PNG bytes.
-    pub async fn capture_screenshot(&mut self, clip: &super::ClipRect) -> Result<Vec<u8>> {
+    /// Capture a screenshot of the given clip region and return the decoded
+    /// image bytes in the requested encoding.
+    pub async fn capture_screenshot(
+        &mut self,
+        clip: &super::ClipRect,
+        options: &super::ScreenshotParams,
+    ) -> Result<Vec<u8>> {
+        let encoding = options.encoding;
+        let mut params = json!({
+            "format": encoding.format,
+            "clip": {
+                "x": clip.x,
+                "y": clip.y,
+                "width": clip.w,
+                "height": clip.h,
+                "scale": 1,
+            },
+        });
+        if let Some(quality) = encoding.quality {
+            params["quality"] = json!(quality);
+        }
+        if options.beyond_viewport {
+            params["captureBeyondViewport"] = json!(true);
+        }
+        if let Some(from_surface) = options.from_surface {
+            params["fromSurface"] = json!(from_surface);
+        }
         let result = self
-            .call(
-                "Page.captureScreenshot",
-                json!({
-                    "format": "png",
-                    "clip": {
-                        "x": clip.x,
-                        "y": clip.y,
-                        "width": clip.w,
-                        "height": clip.h,
-                        "scale": 1,
-                    },
-                }),
-            )
+            .call("Page.captureScreenshot", params)
             .await
             .context("Failed to capture screenshot")?;
 
@@ -247,6 +357,133 @@ impl CdpConnection {
             .context("Failed to decode base64 screenshot")
     }
 
+    /// Add `headers` to every request paused by [`Self::pause_requests_to`],
+    /// replacing any the page set under the same names.
+    pub fn add_headers_to_paused_requests(&mut self, headers: BTreeMap<String, String>) {
+        self.on_event("Fetch.requestPaused", move |event| {
+            Some(Reply {
+                method: "Fetch.continueRequest",
+                params: json!({
+                    "requestId": event.params["requestId"],
+                    "headers": with_headers(&event.params["request"]["headers"], &headers),
+                }),
+            })
+        });
+    }
+
+    /// Pause the requests to `origin` (`scheme://host[:port]`), and only
+    /// those, so the headers added to them never reach CDNs, font hosts
+    /// or analytics the page also loads from. Replaces the previous origin.
+    pub async fn pause_requests_to(&mut self, origin: &str) -> Result<()> {
+        self.call(
+            "Fetch.enable",
+            json!({ "patterns": [{ "urlPattern": format!("{origin}/*") }] }),
+        )
+        .await
+        .with_context(|| format!("Failed to intercept requests to {origin}"))?;
+        Ok(())
+    }
+
+    /// Accept invalid TLS certificates for this tab.
+    pub async fn ignore_certificate_errors(&mut self) -> Result<()> {
+        self.call(
+            "Security.setIgnoreCertificateErrors",
+            json!({ "ignore": true }),
+        )
+        .await
+        .context("Failed to ignore certificate errors")?;
+        Ok(())
+    }
+
+    /// Answer every JavaScript dialog the page opens as soon as it is
+    /// reported: with OK (a `prompt` gets its default text) when `accept`,
+    /// otherwise with Cancel. An open dialog blocks the page, including any
+    /// `Runtime.evaluate` waiting on it. Requires `Page.enable`.
+    pub fn answer_dialogs(&mut self, accept: bool) {
+        self.on_event("Page.javascriptDialogOpening", move |event| {
+            debug!(
+                kind = event.params["type"].as_str().unwrap_or_default(),
+                message = event.params["message"].as_str().unwrap_or_default(),
+                accept,
+                "answering JavaScript dialog"
+            );
+            let mut params = json!({ "accept": accept });
+            if accept && let Some(text) = event.params["defaultPrompt"].as_str() {
+                params["promptText"] = json!(text);
+            }
+            Some(Reply {
+                method: "Page.handleJavaScriptDialog",
+                params,
+            })
+        });
+    }
+
+    /// Capture the current viewport (no clip) as PNG. Used for error artifacts.
+    pub async fn capture_viewport_screenshot(&mut self) -> Result<Vec<u8>> {
+        let result = self
+            .call("Page.captureScreenshot", json!({"format": "png"}))
+            .await
+            .context("Failed to capture screenshot")?;
+        let b64_data = result["data"]
+            .as_str()
+            .context("No screenshot data in response")?;
+
+        use base64::Engine;
+        base64::engine::general_purpose::STANDARD
+            .decode(b64_data)
+            .context("Failed to decode base64 screenshot")
+    }
+
+    /// Console messages and uncaught exceptions buffered since the last
+    /// navigation, formatted as `[level] text`. Requires `Runtime.enable`.
+    pub fn console_messages(&self) -> Vec<String> {
+        self.events
+            .events(self.console_events)
+            .filter_map(|event| match event.method.as_str() {
+                "Runtime.consoleAPICalled" => {
+                    let level = event.params["type"].as_str().unwrap_or("log");
+                    let text: Vec<String> = event.params["args"]
+                        .as_array()
+                        .map(|args| {
+                            args.iter()
+                                .map(|a| match &a["value"] {
+                                    Value::String(s) => s.clone(),
+                                    Value::Null => {
+                                        a["description"].as_str().unwrap_or("").to_string()
+                                    }
+                                    v => v.to_string(),
+                                })
+                                .collect()
+                        })
+                        .unwrap_or_default();
+                    Some(format!("[{level}] {}", text.join(" ")))
+                }
+                "Runtime.exceptionThrown" => {
+                    let details = &event.params["exceptionDetails"];
+                    let text = details["exception"]["description"]
+                        .as_str()
+                        .or_else(|| details["text"].as_str())
+                        .unwrap_or("unknown exception");
+                    Some(format!("[exception] {text}"))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// `Network.*` events buffered since the last navigation, as
+    /// `(method, params)` in arrival order.
+    pub fn network_events(&self) -> Vec<(&str, &Value)> {
+        let evicted = self.events.evicted(self.network_events);
+        if evicted > 0 {
+            warn!(evicted, "network log is full, oldest events were dropped");
+        }
+        self.events
+            .events(self.network_events)
+            .map(|e| (e.method.as_str(), &e.params))
+            .collect()
+    }
+
     /// Bail if a `Runtime.evaluate` result contains an exception.
     fn check_js_exception(result: &Value) -> Result<()> {
         if let Some(desc) = result
@@ -263,7 +500,7 @@ impl CdpConnection {
     /// Wait for the page load event to fire.
     pub async fn wait_page_load(&mut self) -> Result<()> {
         debug!(
-            buffered_events = self.event_buffer.len(),
+            buffered_events = self.events.events(self.page_events).count(),
             "waiting for Page.loadEventFired"
         );
         match tokio::time::timeout(
@@ -291,8 +528,8 @@ impl CdpConnection {
     /// navigations on this tab are stale and would pollute wait_page_load /
     /// wait_network_idle.
     pub async fn navigate(&mut self, url: &str) -> Result<()> {
-        let stale = self.event_buffer.len();
-        self.event_buffer.clear();
+        let stale = self.events.buffered();
+        self.events.clear();
         debug!(url, stale_events_cleared = stale, "navigating");
         let result = self
             .call("Page.navigate", json!({"url": url}))
@@ -302,14 +539,14 @@ impl CdpConnection {
         Ok(())
     }
 
-    /// Set the emulated viewport size.
-    pub async fn set_viewport(&mut self, width: u32, height: u32) -> Result<()> {
+    /// Set the emulated viewport size (CSS pixels) and device scale factor.
+    pub async fn set_viewport(&mut self, width: u32, height: u32, scale: f64) -> Result<()> {
         self.call(
             "Emulation.setDeviceMetricsOverride",
             json!({
                 "width": width,
                 "height": height,
-                "deviceScaleFactor": 1,
+                "deviceScaleFactor": scale,
                 "mobile": false,
             }),
         )
@@ -318,7 +555,9 @@ impl CdpConnection {
         Ok(())
     }
 
-    /// Enable the Page and Network CDP domains for this connection.
+    /// Enable the Page, Network, Runtime and Inspector CDP domains for this
+    /// connection. Runtime events (console output, exceptions) feed error
+    /// artifacts; a crashed renderer is logged as soon as Chrome reports it.
     pub async fn enable_domains(&mut self) -> Result<()> {
         self.call("Page.enable", json!({}))
             .await
@@ -326,31 +565,120 @@ impl CdpConnection {
         self.call("Network.enable", json!({}))
             .await
             .context("Failed to enable Network domain")?;
+        self.call("Runtime.enable", json!({}))
+            .await
+            .context("Failed to enable Runtime domain")?;
+        self.on_event("Inspector.targetCrashed", |_| {
+            warn!("page renderer crashed");
+            None
+        });
+        self.call("Inspector.enable", json!({}))
+            .await
+            .context("Failed to enable Inspector domain")?;
+        Ok(())
+    }
+
+    /// Press and release a key as trusted keyboard input (so `:focus-visible`
+    /// applies), e.g. `("Tab", "Tab", 9)`.
+    pub async fn press_key(&mut self, key: &str, code: &str, key_code: u32) -> Result<()> {
+        for event_type in ["keyDown", "keyUp"] {
+            self.call(
+                "Input.dispatchKeyEvent",
+                json!({
+                    "type": event_type,
+                    "key": key,
+                    "code": code,
+                    "windowsVirtualKeyCode": key_code,
+                    "nativeVirtualKeyCode": key_code,
+                }),
+            )
+            .await
+            .with_context(|| format!("Failed to press {key}"))?;
+        }
+        Ok(())
+    }
+
+    /// Run `source` in every document this tab loads, before page scripts.
+    pub async fn add_init_script(&mut self, source: &str) -> Result<()> {
+        self.call(
+            "Page.addScriptToEvaluateOnNewDocument",
+            json!({ "source": source }),
+        )
+        .await
+        .context("Failed to add init script")?;
+        Ok(())
+    }
+
+    /// Fail requests whose URL matches any of `patterns` (`*` wildcards).
+    pub async fn block_urls(&mut self, patterns: &[&str]) -> Result<()> {
+        self.call("Network.setBlockedURLs", json!({"urls": patterns}))
+            .await
+            .context("Failed to set blocked URLs")?;
+        Ok(())
+    }
+
+    /// Throttle this tab's network: added round-trip latency in milliseconds
+    /// and throughput in bytes per second.
+    pub async fn emulate_network_conditions(
+        &mut self,
+        latency_ms: f64,
+        download_throughput: f64,
+        upload_throughput: f64,
+    ) -> Result<()> {
+        self.call(
+            "Network.emulateNetworkConditions",
+            json!({
+                "offline": false,
+                "latency": latency_ms,
+                "downloadThroughput": download_throughput,
+                "uploadThroughput": upload_throughput,
+            }),
+        )
+        .await
+        .context("Failed to emulate network conditions")?;
         Ok(())
     }
 
     /// Read the next CDP event from the WebSocket, skipping non-event messages.
-    async fn read_event(&mut self) -> Result<(String, Value)> {
+    async fn read_event(&mut self) -> Result<CdpEvent> {
         loop {
-            let raw = self
-                .ws
-                .next()
-                .await
-                .context("WebSocket closed while waiting for event")?
-                .context("WebSocket error")?;
-
-            let Message::Text(text) = raw else {
-                continue;
-            };
-
-            let parsed: Value =
-                serde_json::from_str(&text).context("Failed to parse CDP message")?;
+            // Skip non-event messages (stale responses).
+            if let Incoming::Event(event) = self.read("event").await? {
+                return Ok(event);
+            }
+        }
+    }
 
-            if let Some(method) = parsed.get("method").and_then(|v| v.as_str()) {
-                let params = parsed.get("params").cloned().unwrap_or(Value::Null);
-                return Ok((method.to_string(), params));
+    /// Next text frame. After `HEARTBEAT_INTERVAL` of silence, pings Chrome;
+    /// no pong within another interval means the connection is dead.
+    async fn next_text(&mut self, waiting_for: &str) -> Result<String> {
+        let mut pinged = false;
+        loop {
+            let frame = match tokio::time::timeout(HEARTBEAT_INTERVAL, self.ws.next()).await {
+                Ok(frame) => frame,
+                Err(_) if pinged => {
+                    return Err(ConnectionLost::new("no answer to ping", waiting_for).into());
+                }
+                Err(_) => {
+                    trace!(waiting_for, "silent connection, sending ping");
+                    self.ws
+                        .send(Message::Ping(Vec::new().into()))
+                        .await
+                        .map_err(|e| ConnectionLost::new(e.to_string(), waiting_for))?;
+                    pinged = true;
+                    continue;
+                }
+            };
+            let frame = match frame {
+                Some(Ok(frame)) => frame,
+                Some(Err(e)) => return Err(ConnectionLost::new(e.to_string(), waiting_for).into()),
+                None => return Err(ConnectionLost::new("closed", waiting_for).into()),
+            };
+            match frame {
+                Message::Text(text) => return Ok(text.to_string()),
+                // Any frame proves Chrome is alive.
+                _ => pinged = false,
             }
-            // Skip non-event messages (stale responses).
         }
     }
 
@@ -370,3 +698,63 @@ impl CdpConnection {
         }
     }
 }
+
+/// `scheme://host[:port]` of `url`; None without a scheme.
+pub fn origin(url: &str) -> Option<&str> {
+    let host_start = url.find("://")? + 3;
+    let end = url[host_start..]
+        .find(['/', '?', '#'])
+        .map_or(url.len(), |i| host_start + i);
+    Some(&url[..end])
+}
+
+/// `request` headers (a CDP `Headers` object) with `extra` added, as the
+/// `HeaderEntry` list `Fetch.continueRequest` takes.
+fn with_headers(request: &Value, extra: &BTreeMap<String, String>) -> Vec<Value> {
+    let replaced = |name: &str| extra.keys().any(|k| k.eq_ignore_ascii_case(name));
+    let kept = request
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter(|(name, _)| !replaced(name))
+        .map(|(name, value)| json!({ "name": name, "value": value }));
+    let added = extra
+        .iter()
+        .map(|(name, value)| json!({ "name": name, "value": value }));
+    kept.chain(added).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn origin_keeps_scheme_host_and_port() {
+        assert_eq!(
+            origin("http://localhost:6006/iframe.html?id=a"),
+            Some("http://localhost:6006")
+        );
+        assert_eq!(
+            origin("https://sb.example.com"),
+            Some("https://sb.example.com")
+        );
+        assert_eq!(
+            origin("https://sb.example.com?x"),
+            Some("https://sb.example.com")
+        );
+        assert_eq!(origin("about:blank"), None);
+    }
+
+    #[test]
+    fn added_headers_replace_the_page_s_own() {
+        let request = json!({ "Accept": "*/*", "authorization": "Bearer page" });
+        let extra = BTreeMap::from([("Authorization".to_string(), "Basic dTpw".to_string())]);
+        assert_eq!(
+            with_headers(&request, &extra),
+            [
+                json!({ "name": "Accept", "value": "*/*" }),
+                json!({ "name": "Authorization", "value": "Basic dTpw" }),
+            ]
+        );
+    }
+}
@@ -0,0 +1,145 @@
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result, bail};
+use tokio::process::Command;
+use tracing::debug;
+
+use crate::config::CONFIG_DIR;
+
+/// Name of the container managed by `snapvrt chrome up|down`.
+pub const CONTAINER_NAME: &str = "snapvrt-chrome";
+
+/// Pinned headless Chrome image, so every host renders with the same
+/// browser build and fonts.
+pub const IMAGE: &str = "yukinying/chrome-headless-browser-stable:139.0.7258.138";
+
+/// CDP port inside the container.
+const CONTAINER_PORT: u16 = 9222;
+
+/// Chrome flags appended to the image entrypoint.
+const CHROME_ARGS: [&str; 7] = [
+    "--disable-background-networking",
+    "--disable-gpu",
+    "--disable-software-rasterizer",
+    "--disable-extensions",
+    "--no-first-run",
+    "--hide-scrollbars",
+    "--force-color-profile=srgb",
+];
+
+/// Records the URL of the running container; used as `chrome_url` when none
+/// is configured.
+const URL_FILE: &str = "chrome-url";
+
+/// How long `up` waits for Chrome to answer `/json/version`.
+const READY_TIMEOUT: Duration = Duration::from_secs(30);
+const READY_POLL: Duration = Duration::from_millis(200);
+
+/// Run `docker` with `args`, returning trimmed stdout.
+async fn docker(args: &[&str]) -> Result<String> {
+    debug!(?args, "docker");
+    let output = Command::new("docker")
+        .args(args)
+        .output()
+        .await
+        .context("Failed to run docker. Is Docker installed and on PATH?")?;
+    if !output.status.success() {
+        bail!(
+            "docker {} failed: {}",
+            args.first().copied().unwrap_or_default(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Host port the running container publishes CDP on, if it is running.
+async fn running_port() -> Option<u16> {
+    let running = docker(&["inspect", "-f", "{{.State.Running}}", CONTAINER_NAME])
+        .await
+        .ok()?;
+    if running != "true" {
+        return None;
+    }
+    // e.g. "0.0.0.0:9222" (one line per address family).
+    let mapping = docker(&["port", CONTAINER_NAME, &CONTAINER_PORT.to_string()])
+        .await
+        .ok()?;
+    mapping.lines().next()?.rsplit(':').next()?.parse().ok()
+}
+
+/// Start the managed container on host `port` (or reuse a running one),
+/// wait until Chrome answers, and record its URL. Returns the URL.
+pub async fn start(port: u16) -> Result<String> {
+    let port = match running_port().await {
+        Some(existing) => existing,
+        None => {
+            // Clear out a stopped container left with the same name.
+            let _ = docker(&["rm", "-f", CONTAINER_NAME]).await;
+            let publish = format!("{port}:{CONTAINER_PORT}");
+            let mut args = vec![
+                "run",
+                "-d",
+                "--rm",
+                "--name",
+                CONTAINER_NAME,
+                "--label",
+                "snapvrt.managed=true",
+                "-p",
+                &publish,
+                "--shm-size=4g",
+                "--cap-add=SYS_ADMIN",
+                IMAGE,
+            ];
+            args.extend(CHROME_ARGS);
+            docker(&args).await?;
+            port
+        }
+    };
+
+    let url = format!("http://localhost:{port}");
+    wait_ready(&url).await?;
+    let path = Path::new(CONFIG_DIR).join(URL_FILE);
+    std::fs::write(&path, &url).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(url)
+}
+
+/// Stop the managed container and forget its URL.
+/// Returns false when no container was running.
+pub async fn stop() -> Result<bool> {
+    let _ = std::fs::remove_file(Path::new(CONFIG_DIR).join(URL_FILE));
+    if running_port().await.is_none() {
+        return Ok(false);
+    }
+    docker(&["rm", "-f", CONTAINER_NAME]).await?;
+    Ok(true)
+}
+
+/// URL of the container started by `snapvrt chrome up`, if recorded.
+pub fn managed_url() -> Option<String> {
+    std::fs::read_to_string(Path::new(CONFIG_DIR).join(URL_FILE))
+        .ok()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+async fn wait_ready(url: &str) -> Result<()> {
+    let version_url = format!("{url}/json/version");
+    let start = Instant::now();
+    loop {
+        if let Ok(resp) = reqwest::get(&version_url).await
+            && resp.status().is_success()
+        {
+            return Ok(());
+        }
+        if start.elapsed() > READY_TIMEOUT {
+            bail!(
+                "Chrome container did not become ready at {version_url} within {}s. \
+                 Check `docker logs {CONTAINER_NAME}`.",
+                READY_TIMEOUT.as_secs()
+            );
+        }
+        tokio::time::sleep(READY_POLL).await;
+    }
+}
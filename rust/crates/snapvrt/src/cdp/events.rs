@@ -0,0 +1,189 @@
+use std::collections::VecDeque;
+
+use serde_json::Value;
+
+/// A CDP event received from the browser.
+#[derive(Clone, Debug)]
+pub struct CdpEvent {
+    pub method: String,
+    pub params: Value,
+}
+
+/// Handle to the buffer of a subscription made with
+/// [`EventRouter::subscribe`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Subscription(usize);
+
+/// A CDP command an event handler wants sent. It is sent without waiting
+/// for the response.
+#[derive(Debug)]
+pub struct Reply {
+    pub method: &'static str,
+    pub params: Value,
+}
+
+/// Reacts to an event as it arrives, e.g. to answer a dialog that blocks
+/// the page until it is closed.
+pub type Handler = Box<dyn FnMut(&CdpEvent) -> Option<Reply> + Send>;
+
+/// Whether `method` matches `pattern`: an exact method name
+/// (`Page.loadEventFired`), or a domain prefix ending in `.` (`Network.`).
+fn matches(pattern: &str, method: &str) -> bool {
+    if pattern.ends_with('.') {
+        method.starts_with(pattern)
+    } else {
+        method == pattern
+    }
+}
+
+struct Buffer {
+    patterns: Vec<&'static str>,
+    capacity: usize,
+    events: VecDeque<CdpEvent>,
+    /// Oldest events dropped because the buffer was full.
+    evicted: usize,
+}
+
+/// Routes incoming events to subscriptions. Every subscription keeps its own
+/// bounded buffer of the events it matches, so consumers (network idle, HAR,
+/// console output, ...) don't see or disturb each other's events; events no
+/// subscription matches are dropped.
+#[derive(Default)]
+pub struct EventRouter {
+    buffers: Vec<Buffer>,
+    handlers: Vec<(&'static str, Handler)>,
+}
+
+impl EventRouter {
+    /// Buffer events matching any of `patterns`, keeping the latest
+    /// `capacity`.
+    pub fn subscribe(&mut self, patterns: &[&'static str], capacity: usize) -> Subscription {
+        self.buffers.push(Buffer {
+            patterns: patterns.to_vec(),
+            capacity: capacity.max(1),
+            events: VecDeque::new(),
+            evicted: 0,
+        });
+        Subscription(self.buffers.len() - 1)
+    }
+
+    /// Call `handler` for every event matching `pattern`, as it arrives.
+    pub fn on_event(&mut self, pattern: &'static str, handler: Handler) {
+        self.handlers.push((pattern, handler));
+    }
+
+    /// Deliver an event to its handlers and subscriptions. Returns the
+    /// commands the handlers want sent.
+    pub fn dispatch(&mut self, event: &CdpEvent) -> Vec<Reply> {
+        let replies = self
+            .handlers
+            .iter_mut()
+            .filter(|(pattern, _)| matches(pattern, &event.method))
+            .filter_map(|(_, handler)| handler(event))
+            .collect();
+        for buffer in &mut self.buffers {
+            if !buffer.patterns.iter().any(|p| matches(p, &event.method)) {
+                continue;
+            }
+            if buffer.events.len() == buffer.capacity {
+                buffer.events.pop_front();
+                buffer.evicted += 1;
+            }
+            buffer.events.push_back(event.clone());
+        }
+        replies
+    }
+
+    /// Buffered events of a subscription, oldest first.
+    pub fn events(&self, sub: Subscription) -> impl Iterator<Item = &CdpEvent> {
+        self.buffers[sub.0].events.iter()
+    }
+
+    /// Events a subscription lost to its capacity since the last `clear`.
+    pub fn evicted(&self, sub: Subscription) -> usize {
+        self.buffers[sub.0].evicted
+    }
+
+    /// Whether some subscription buffers `method`.
+    pub fn covers(&self, method: &str) -> bool {
+        self.buffers
+            .iter()
+            .any(|b| b.patterns.iter().any(|p| matches(p, method)))
+    }
+
+    /// Remove and return the oldest buffered `method` event from the first
+    /// subscription that buffers it.
+    pub fn take(&mut self, method: &str) -> Option<CdpEvent> {
+        let buffer = self
+            .buffers
+            .iter_mut()
+            .find(|b| b.patterns.iter().any(|p| matches(p, method)))?;
+        let idx = buffer.events.iter().position(|e| e.method == method)?;
+        buffer.events.remove(idx)
+    }
+
+    /// Drop every buffered event (handlers stay registered).
+    pub fn clear(&mut self) {
+        for buffer in &mut self.buffers {
+            buffer.events.clear();
+            buffer.evicted = 0;
+        }
+    }
+
+    /// Events buffered across all subscriptions.
+    pub fn buffered(&self) -> usize {
+        self.buffers.iter().map(|b| b.events.len()).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(method: &str) -> CdpEvent {
+        CdpEvent {
+            method: method.into(),
+            params: Value::Null,
+        }
+    }
+
+    #[test]
+    fn subscriptions_buffer_independently_and_evict_oldest() {
+        let mut router = EventRouter::default();
+        let network = router.subscribe(&["Network."], 2);
+        let load = router.subscribe(&["Page.loadEventFired"], 8);
+        router.on_event(
+            "Page.javascriptDialogOpening",
+            Box::new(|_| {
+                Some(Reply {
+                    method: "Page.handleJavaScriptDialog",
+                    params: Value::Null,
+                })
+            }),
+        );
+
+        for method in [
+            "Network.requestWillBeSent",
+            "Page.loadEventFired",
+            "Network.responseReceived",
+            "Network.loadingFinished",
+            "Runtime.consoleAPICalled",
+        ] {
+            assert!(router.dispatch(&event(method)).is_empty());
+        }
+        let replies = router.dispatch(&event("Page.javascriptDialogOpening"));
+        assert_eq!(replies.len(), 1);
+
+        let methods: Vec<&str> = router.events(network).map(|e| e.method.as_str()).collect();
+        assert_eq!(
+            methods,
+            ["Network.responseReceived", "Network.loadingFinished"]
+        );
+        assert_eq!(router.evicted(network), 1);
+        assert!(!router.covers("Runtime.consoleAPICalled"));
+        assert!(router.take("Page.loadEventFired").is_some());
+        assert_eq!(router.events(load).count(), 0);
+        router.clear();
+        assert_eq!(router.buffered(), 0);
+    }
+}
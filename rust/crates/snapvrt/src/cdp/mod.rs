@@ -1,8 +1,37 @@
 pub mod chrome;
 pub mod connection;
+pub mod docker;
+pub mod events;
 
-pub use self::chrome::Chrome;
-pub use self::connection::CdpConnection;
+pub use self::chrome::{Chrome, ChromeToken};
+pub use self::connection::{CdpConnection, ConnectionLost};
+
+/// Image encoding requested from `Page.captureScreenshot`.
+#[derive(Clone, Copy, Debug)]
+pub struct ImageEncoding {
+    /// `png`, `jpeg` or `webp`.
+    pub format: &'static str,
+    /// Compression quality (0–100); ignored for PNG.
+    pub quality: Option<u8>,
+}
+
+impl ImageEncoding {
+    pub const PNG: Self = Self {
+        format: "png",
+        quality: None,
+    };
+}
+
+/// Parameters for `Page.captureScreenshot` besides the clip.
+#[derive(Clone, Copy, Debug)]
+pub struct ScreenshotParams {
+    pub encoding: ImageEncoding,
+    /// Capture content outside the viewport (`captureBeyondViewport`).
+    pub beyond_viewport: bool,
+    /// Capture from the compositor surface rather than the view
+    /// (`fromSurface`); `None` leaves Chrome's default (true).
+    pub from_surface: Option<bool>,
+}
 
 /// Clip region in CSS pixels (used by `Page.captureScreenshot`).
 pub struct ClipRect {
@@ -1,13 +1,37 @@
+use std::path::PathBuf;
+
 use clap::{Parser, Subcommand};
 
+use crate::commands::{self, FailOn};
+use crate::compare::diff::Mask;
 use crate::config;
 use crate::config::CaptureConfig;
+use crate::config::foreign::ForeignTool;
+use crate::report::ReportTarget;
+use crate::storybook::Filter;
 
 fn parse_threshold(s: &str) -> Result<f64, String> {
     let v: f64 = s.parse().map_err(|e| format!("{e}"))?;
     config::validate_threshold(v)
 }
 
+fn parse_region(s: &str) -> Result<Mask, String> {
+    let parts: Vec<u32> = s
+        .split(',')
+        .map(|p| p.trim().parse::<u32>())
+        .collect::<Result<_, _>>()
+        .map_err(|e| format!("{e}"))?;
+    match parts[..] {
+        [x, y, width, height] if width > 0 && height > 0 => Ok(Mask {
+            x,
+            y,
+            width,
+            height,
+        }),
+        _ => Err(format!("expected X,Y,W,H with a non-zero size, got '{s}'")),
+    }
+}
+
 #[derive(Parser)]
 #[command(
     name = "snapvrt",
@@ -16,18 +40,37 @@ fn parse_threshold(s: &str) -> Result<f64, String> {
 pub struct Cli {
     #[command(subcommand)]
     pub command: Command,
+    /// Wait for another snapvrt process to release .snapvrt/.lock instead of failing
+    #[arg(long, global = true)]
+    pub wait: bool,
+    /// Only print failures and the summary
+    #[arg(long, short = 'q', global = true)]
+    pub quiet: bool,
+    /// Disable colored output (also set by a non-empty NO_COLOR)
+    #[arg(long, global = true)]
+    pub no_color: bool,
+    /// Also write debug-level logs to FILE as JSON lines (e.g. .snapvrt/run.log),
+    /// whatever RUST_LOG says
+    #[arg(long, global = true, value_name = "FILE")]
+    pub log_file: Option<PathBuf>,
 }
 
 #[derive(Subcommand)]
 pub enum Command {
     /// Create .snapvrt/config.toml with default settings
     Init {
-        /// Storybook URL
-        #[arg(long, default_value = "http://localhost:6006")]
-        url: String,
+        /// Storybook URL (default: port detected from package.json, else http://localhost:6006)
+        #[arg(long)]
+        url: Option<String>,
         /// Overwrite existing config and gitignore
         #[arg(long, short = 'f')]
         force: bool,
+        /// Add snapvrt artifacts to the repository root .gitignore without asking
+        #[arg(long, short = 'y')]
+        yes: bool,
+        /// Import config and baselines from another tool (Storybook must be running)
+        #[arg(long, value_enum, value_name = "TOOL")]
+        from: Option<ForeignTool>,
     },
 
     /// Discover, capture, compare, and report visual differences (exit 0/1)
@@ -35,9 +78,14 @@ pub enum Command {
         /// Storybook URL (overrides config)
         #[arg(long)]
         url: Option<String>,
-        /// Only run snapshots whose name contains PATTERN (case-insensitive)
-        #[arg(long, short = 'f')]
-        filter: Option<String>,
+        /// Only run snapshots matching EXPR: patterns (case-insensitive,
+        /// `*` wildcards), `viewport:NAME`, `tag:NAME`, `and`, `or`, `not`
+        #[arg(long, short = 'f', value_name = "EXPR")]
+        filter: Option<Filter>,
+        /// Only run the snapshot IDs listed in FILE, one per line (or a
+        /// JSON array), e.g. from a code-owners script
+        #[arg(long, value_name = "FILE")]
+        ids_from: Option<PathBuf>,
         /// Max allowed diff score (0.0–1.0). Snapshots within threshold pass.
         #[arg(long, value_parser = parse_threshold)]
         threshold: Option<f64>,
@@ -47,6 +95,62 @@ pub enum Command {
         /// Delete orphaned reference snapshots that no longer match any story
         #[arg(long)]
         prune: bool,
+        /// Which outcomes fail the run (exit 1 = diffs, 2 = new, 3 = capture errors)
+        #[arg(long, value_enum, default_value_t = FailOn::Any)]
+        fail_on: FailOn,
+        /// Headful Chrome, one tab at a time, pausing before each screenshot
+        #[arg(long, requires = "filter", conflicts_with = "chrome_url")]
+        debug: bool,
+        /// Fail instead of warning when the browser environment differs from the baselines'
+        #[arg(long)]
+        strict_env: bool,
+        /// Print the snapshots that would be captured (ID, viewport, URL) and
+        /// exit, without launching a browser
+        #[arg(long, conflicts_with_all = ["debug", "prune"])]
+        list: bool,
+        /// Also write a report: json, html, junit, gitlab (Code Quality) or
+        /// bitbucket (Code Insights). Repeatable
+        #[arg(long = "report", value_name = "KIND=PATH")]
+        reports: Vec<ReportTarget>,
+        /// List failed snapshots under their owning team (`[owners]`)
+        #[arg(long)]
+        group_by_owner: bool,
+        #[command(flatten)]
+        capture: CaptureConfig,
+    },
+
+    /// Capture every snapshot several times and report nondeterministic ones (exit 0/1)
+    Flaky {
+        /// Storybook URL (overrides config)
+        #[arg(long)]
+        url: Option<String>,
+        /// Only run snapshots matching EXPR: patterns (case-insensitive,
+        /// `*` wildcards), `viewport:NAME`, `tag:NAME`, `and`, `or`, `not`
+        #[arg(long, short = 'f', value_name = "EXPR")]
+        filter: Option<Filter>,
+        /// Number of captures per snapshot (min 2)
+        #[arg(long, default_value_t = 3)]
+        runs: usize,
+        #[command(flatten)]
+        capture: CaptureConfig,
+    },
+
+    /// Capture every snapshot several times and suggest per-snapshot
+    /// thresholds above its noise floor
+    Calibrate {
+        /// Storybook URL (overrides config)
+        #[arg(long)]
+        url: Option<String>,
+        /// Only run snapshots matching EXPR: patterns (case-insensitive,
+        /// `*` wildcards), `viewport:NAME`, `tag:NAME`, `and`, `or`, `not`
+        #[arg(long, short = 'f', value_name = "EXPR")]
+        filter: Option<Filter>,
+        /// Number of captures per snapshot (min 2)
+        #[arg(long, default_value_t = 5)]
+        runs: usize,
+        /// Write the suggestions to .snapvrt/config.toml as [[diff.override]] entries
+        #[arg(long)]
+        write: bool,
         #[command(flatten)]
         capture: CaptureConfig,
     },
@@ -58,11 +162,23 @@ pub enum Command {
         open: bool,
     },
 
+    /// Compare the failures of two report.json files, e.g. a PR run against
+    /// the main branch's (exit 0/1)
+    CompareRuns {
+        /// Report of the run to compare against (e.g. the nightly main run)
+        base: PathBuf,
+        /// Report of the run being checked (e.g. the pull request run)
+        head: PathBuf,
+        /// Also write the delta (new failures, fixed, still failing) as JSON
+        #[arg(long, short = 'o', value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+
     /// Promote current/ snapshots to reference/ without re-capturing
     Approve {
-        /// Only approve snapshots whose name contains PATTERN (case-insensitive)
-        #[arg(long, short = 'f')]
-        filter: Option<String>,
+        /// Only approve snapshots whose ID matches EXPR (patterns, `and`, `or`, `not`)
+        #[arg(long, short = 'f', value_name = "EXPR")]
+        filter: Option<Filter>,
         /// Only approve new snapshots (no prior reference)
         #[arg(long)]
         new: bool,
@@ -72,9 +188,25 @@ pub enum Command {
         /// Approve all pending snapshots (default when no kind flags)
         #[arg(long)]
         all: bool,
+        /// Only approve the snapshot IDs listed in FILE: a JSON array (the
+        /// HTML report's "Export selection") or one ID per line
+        #[arg(long, value_name = "FILE", visible_alias = "ids-from")]
+        from_file: Option<PathBuf>,
+        /// Approve images even if the latest `snapvrt test` run did not
+        /// capture them (left over from an older run, or edited since)
+        #[arg(long)]
+        force: bool,
+        /// Refuse to approve from a detached HEAD or a working tree with
+        /// uncommitted changes (also `store.require_clean_git`)
+        #[arg(long)]
+        require_clean_git: bool,
+        /// Only approve this rectangle (image pixels) of a single failed
+        /// snapshot, keeping the rest of its reference (repeatable)
+        #[arg(long = "region", value_name = "X,Y,W,H", value_parser = parse_region)]
+        regions: Vec<Mask>,
     },
 
-    /// Delete orphaned reference snapshots that no longer match any story
+    /// Delete reference snapshots and run outputs that no longer match any story
     Prune {
         /// Storybook URL (overrides config)
         #[arg(long)]
@@ -85,22 +217,118 @@ pub enum Command {
         /// Skip confirmation prompt
         #[arg(long, short = 'y')]
         yes: bool,
+        /// Also delete orphans still within store.orphan_grace_days / orphan_grace_runs
+        #[arg(long)]
+        ignore_grace: bool,
+        #[command(flatten)]
+        capture: CaptureConfig,
+    },
+
+    /// Rename reference snapshots after changing store.id_template
+    MigrateIds {
+        /// Storybook URL (overrides config)
+        #[arg(long)]
+        url: Option<String>,
+        /// Template the references were stored with (default: recorded in the manifest)
+        #[arg(long)]
+        from: Option<String>,
+        /// Show the renames without applying them
+        #[arg(long)]
+        dry_run: bool,
         #[command(flatten)]
         capture: CaptureConfig,
     },
 
+    /// Rename reference snapshots after retitling stories, keeping their baselines
+    Mv {
+        /// Snapshot ID, ID prefix, or pattern with * wildcards
+        /// (e.g. "*/Forms/Button/*")
+        from: String,
+        /// New ID, prefix or pattern; each * takes what the matching * in FROM matched
+        to: String,
+        /// Show the renames without applying them
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Resample reference snapshots after changing capture.device_scale
+    MigrateScale {
+        /// Scale the references were captured at (default: recorded in the manifest, else 1)
+        #[arg(long)]
+        from: Option<f64>,
+        /// Scale to resample to (default: capture.device_scale)
+        #[arg(long)]
+        to: Option<f64>,
+        /// Show the new sizes without rewriting files
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Start or stop a pinned headless-Chrome Docker container
+    Chrome {
+        #[command(subcommand)]
+        action: ChromeAction,
+    },
+
+    /// Delete old run outputs (current/difference images, error artifacts, reports)
+    Clean {
+        /// Only delete files last modified longer ago than AGE (e.g. 12h, 7d, 2w)
+        #[arg(long, value_name = "AGE", value_parser = commands::parse_age, default_value = "7d")]
+        older_than: std::time::Duration,
+        /// Delete all run outputs regardless of age
+        #[arg(long, conflicts_with = "older_than")]
+        all: bool,
+        /// List what would be deleted without deleting
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Losslessly recompress existing reference snapshots
+    Optimize {
+        /// Only optimize snapshots whose ID matches EXPR (patterns, `and`, `or`, `not`)
+        #[arg(long, short = 'f', value_name = "EXPR")]
+        filter: Option<Filter>,
+        /// Use zopfli compression (much slower, slightly smaller)
+        #[arg(long)]
+        zopfli: bool,
+        /// Show potential savings without rewriting files
+        #[arg(long)]
+        dry_run: bool,
+    },
+
     /// Discover, capture, and save as reference snapshots
     Update {
         /// Storybook URL (overrides config)
         #[arg(long)]
         url: Option<String>,
-        /// Only run snapshots whose name contains PATTERN (case-insensitive)
-        #[arg(long, short = 'f')]
-        filter: Option<String>,
+        /// Only run snapshots matching EXPR: patterns (case-insensitive,
+        /// `*` wildcards), `viewport:NAME`, `tag:NAME`, `and`, `or`, `not`
+        #[arg(long, short = 'f', value_name = "EXPR")]
+        filter: Option<Filter>,
+        /// Only run the snapshot IDs listed in FILE, one per line (or a
+        /// JSON array), e.g. from a code-owners script
+        #[arg(long, value_name = "FILE")]
+        ids_from: Option<PathBuf>,
         /// Print per-snapshot timing breakdown table
         #[arg(long)]
         timings: bool,
+        /// Keep references whose new capture matches them instead of
+        /// rewriting them
+        #[arg(long)]
+        only_changed: bool,
         #[command(flatten)]
         capture: CaptureConfig,
     },
 }
+
+#[derive(Subcommand)]
+pub enum ChromeAction {
+    /// Start the container; test/update then use it as chrome_url
+    Up {
+        /// Host port for the Chrome DevTools protocol
+        #[arg(long, default_value_t = 9222)]
+        port: u16,
+    },
+    /// Stop and remove the container
+    Down,
+}
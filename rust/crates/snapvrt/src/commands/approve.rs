@@ -1,7 +1,14 @@
-use anyhow::{Result, bail};
+use std::collections::BTreeSet;
+use std::path::Path;
 
+use anyhow::{Context, Result, bail};
+
+use crate::compare::diff::{self, Mask};
+use crate::config;
+use crate::report::run_info::{self, GitState};
+use crate::report::terminal;
 use crate::store;
-use crate::storybook::normalize_for_filter;
+use crate::storybook::Filter;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Kind {
@@ -9,18 +16,66 @@ enum Kind {
     Failed,
 }
 
-pub fn approve(filter: Option<&str>, new_only: bool, failed_only: bool, all: bool) -> Result<()> {
-    let (new_only, failed_only) = if all {
+/// Which pending snapshots [`approve`] takes, and what it checks first.
+#[derive(Debug)]
+pub struct ApproveOptions {
+    /// Only snapshots without a reference.
+    pub new_only: bool,
+    /// Only snapshots with a difference.
+    pub failed_only: bool,
+    /// Every pending snapshot; overrides `new_only` and `failed_only`.
+    pub all: bool,
+    /// Skip the check that the latest run captured each image.
+    pub force: bool,
+    /// Refuse unless the git checkout is clean.
+    pub require_clean_git: bool,
+}
+
+/// Images not captured by the latest run are refused unless `force`d, so a
+/// filtered run can't leave captures of an older Storybook build to be
+/// approved alongside its own.
+///
+/// With `regions`, only those rectangles of a single failed snapshot are
+/// approved: the reference keeps its other pixels, so an intentional change
+/// can be accepted while a regression elsewhere in the image keeps failing.
+///
+/// Each approved reference records the commit checked out; with
+/// `require_clean_git` (or `store.require_clean_git`), approving from a
+/// detached HEAD or with uncommitted changes is refused.
+pub fn approve(
+    filter: Option<&Filter>,
+    selection: Option<&BTreeSet<String>>,
+    options: &ApproveOptions,
+    regions: &[Mask],
+) -> Result<()> {
+    let (new_only, failed_only) = if options.all {
         (false, false)
     } else {
-        (new_only, failed_only)
+        (options.new_only, options.failed_only)
     };
+    if filter.is_some_and(Filter::needs_stories) {
+        bail!("approve matches snapshot IDs only; --filter can't use viewport: or tag:");
+    }
     let ids = store::list_current_ids();
     if ids.is_empty() {
         println!("Nothing to approve — current/ is empty.");
         return Ok(());
     }
 
+    if let Some(selection) = selection {
+        let unknown: Vec<&str> = selection
+            .iter()
+            .filter(|id| !ids.contains(*id))
+            .map(String::as_str)
+            .collect();
+        if !unknown.is_empty() {
+            bail!(
+                "Selected snapshot(s) not pending in current/ (report out of date?): {}",
+                unknown.join(", ")
+            );
+        }
+    }
+
     // Classify each id.
     let classified: Vec<(&str, Kind)> = ids
         .iter()
@@ -48,18 +103,11 @@ pub fn approve(filter: Option<&str>, new_only: bool, failed_only: bool, all: boo
         })
         .collect();
 
-    // Filter by pattern (strip .png suffix — user may copy from HTML review page).
-    // Normalize spaces/underscores so both terminal output and raw names work.
+    // Narrow to `--ids-from`, then to the `--filter` expression.
     let filtered: Vec<(&str, Kind)> = kind_filtered
         .into_iter()
-        .filter(|(id, _)| {
-            filter
-                .map(|pat| {
-                    let pat = pat.strip_suffix(".png").unwrap_or(pat);
-                    normalize_for_filter(id).contains(&normalize_for_filter(pat))
-                })
-                .unwrap_or(true)
-        })
+        .filter(|(id, _)| selection.is_none_or(|s| s.contains(*id)))
+        .filter(|(id, _)| filter.is_none_or(|f| f.matches_id(id)))
         .collect();
 
     if filtered.is_empty() {
@@ -67,35 +115,162 @@ pub fn approve(filter: Option<&str>, new_only: bool, failed_only: bool, all: boo
         return Ok(());
     }
 
+    if !regions.is_empty() {
+        match filtered.as_slice() {
+            [(_, Kind::Failed)] => {}
+            [(id, Kind::New)] => bail!("{id} has no reference to approve regions of"),
+            _ => bail!(
+                "--region approves part of one failed snapshot, but {} matched. \
+                 Narrow the selection with --filter or --ids-from",
+                filtered.len()
+            ),
+        }
+    }
+
+    if !options.force {
+        check_latest_run(&filtered)?;
+    }
+
+    // Approving needs no config file; without one the defaults apply.
+    let (store_config, diff_config) = if config::config_file_exists() {
+        let file_config = config::load()?;
+        (file_config.store, file_config.diff)
+    } else {
+        Default::default()
+    };
+    let git = GitState::read();
+    if options.require_clean_git || store_config.require_clean_git {
+        run_info::check_clean_git(git.as_ref())?;
+    }
+    let commit = git.as_ref().map(GitState::label);
+
+    let mut references = store::References::open_for_writing()?;
+    references.check_id_template(store_config.id_template())?;
+    references.set_id_template(store_config.id_template());
+    if let Some(env) = store::read_current_environment() {
+        references.set_environment(&env);
+    }
+
+    if let [(id, _)] = filtered.as_slice()
+        && !regions.is_empty()
+    {
+        let reference = references
+            .read(id)?
+            .with_context(|| format!("No reference image for {id}"))?;
+        let current = store::read_current(id)
+            .with_context(|| format!("Could not read current image for {id}"))?;
+        let image = diff::composite(&reference, &current, regions, diff_config.max_pixels())
+            .with_context(|| format!("Failed to approve regions of {id}"))?;
+        let image = store::prepare_reference(image, &store_config)?;
+        references.rewrite(id, &image)?;
+        if let Some(commit) = &commit {
+            references.set_commit(id, commit);
+        }
+        references.commit()?;
+        println!("  Approved  {}  {id}", terminal::yellow("PART"));
+        println!();
+        println!(
+            "{} region(s) of {id} approved; rerun `snapvrt test` to check the rest.",
+            regions.len()
+        );
+        return Ok(());
+    }
+
     let mut count_new = 0usize;
     let mut count_failed = 0usize;
 
     for (id, kind) in &filtered {
         let bytes = store::read_current(id);
         match bytes {
-            Some(png) => {
-                store::write_reference(id, &png)?;
+            Some(image) => {
+                let image = store::prepare_reference(image, &store_config)?;
+                let masks = store::read_current_masks(id);
+                references.write(id, &image, masks, &store::read_current_texts(id))?;
+                if let Some(commit) = &commit {
+                    references.set_commit(id, commit);
+                }
                 let label = match kind {
                     Kind::Failed => {
                         count_failed += 1;
-                        "\x1b[31mFAIL\x1b[0m"
+                        terminal::red("FAIL")
                     }
                     Kind::New => {
                         count_new += 1;
-                        "\x1b[33m NEW\x1b[0m"
+                        terminal::yellow(" NEW")
                     }
                 };
                 println!("  Approved  {label}  {id}");
             }
             None => {
-                bail!("Could not read current/{id}.png");
+                bail!("Could not read current image for {id}");
             }
         }
     }
 
+    references.commit()?;
+
     let total = count_new + count_failed;
     println!();
     println!("{total} snapshot(s) approved ({count_new} new, {count_failed} failed).");
 
     Ok(())
 }
+
+/// Fail unless every image to approve was written by the latest run.
+fn check_latest_run(ids: &[(&str, Kind)]) -> Result<()> {
+    let Some(run) = store::RunManifest::read() else {
+        bail!("current/ has no run manifest — rerun `snapvrt test`, or pass --force");
+    };
+    let stale: Vec<String> = ids
+        .iter()
+        .filter_map(|(id, _)| {
+            let image = store::read_current(id).unwrap_or_default();
+            run.stale_reason(id, &image)
+                .map(|reason| format!("  {id} ({reason})"))
+        })
+        .collect();
+    if !stale.is_empty() {
+        bail!(
+            "{} image(s) in current/ were not captured by the latest run ({}):\n{}\n\
+             Rerun `snapvrt test` for them, or pass --force to approve anyway.",
+            stale.len(),
+            run.run_id,
+            stale.join("\n")
+        );
+    }
+    Ok(())
+}
+
+/// Snapshot IDs from a `--from-file`/`--ids-from` selection: a JSON array of
+/// strings, or one ID per line (the report's "Copy IDs").
+pub fn read_selection(path: &Path) -> Result<BTreeSet<String>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    parse_selection(&content).with_context(|| format!("Invalid selection in {}", path.display()))
+}
+
+fn parse_selection(content: &str) -> Result<BTreeSet<String>> {
+    if content.trim_start().starts_with('[') {
+        return Ok(serde_json::from_str(content)?);
+    }
+    Ok(content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(String::from)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selection_accepts_json_or_lines() {
+        let expected: BTreeSet<String> = ["a/b--default", "c"].map(String::from).into();
+        let json = parse_selection(r#"["c", "a/b--default"]"#).unwrap();
+        assert_eq!(json, expected);
+        assert_eq!(parse_selection("a/b--default\n\n  c\n").unwrap(), expected);
+        assert!(parse_selection("[1, 2]").is_err());
+    }
+}
@@ -0,0 +1,180 @@
+use std::collections::BTreeMap;
+
+use anyhow::{Context, Result};
+
+use crate::capture::pipeline::CaptureResult;
+use crate::capture::{CapturePlan, Selection};
+use crate::compare::diff::{self, CompareOptions};
+use crate::compare::metric_score;
+use crate::config::{self, DiffMetric, DiffOverride, ResolvedRunConfig};
+use crate::interrupt;
+use crate::report::terminal;
+
+/// Percentile of the self-diff scores taken as a snapshot's noise floor.
+const NOISE_PERCENTILE: f64 = 0.99;
+
+/// Noise floor of one snapshot across repeated captures.
+struct NoiseFloor {
+    name: String,
+    viewport: String,
+    /// `NOISE_PERCENTILE` of the scores of every pair of captures.
+    p99: f64,
+    max: f64,
+}
+
+/// `snapvrt calibrate` — capture every snapshot `runs` times, diff the
+/// captures of each snapshot against each other and suggest a threshold
+/// for those whose noise floor exceeds their current one. With `write`,
+/// the suggestions are written to the config as `[[diff.override]]`
+/// entries, replacing existing ones for the same snapshot. References are not touched.
+pub async fn calibrate(
+    config: ResolvedRunConfig,
+    selection: &Selection,
+    runs: usize,
+    write: bool,
+) -> Result<()> {
+    let runs = runs.max(2);
+    let plan = CapturePlan::plan(&config, selection).await?;
+    if plan.total() == 0 {
+        return Ok(());
+    }
+    let snapshots = plan.total();
+    let viewports: BTreeMap<String, String> = plan
+        .jobs()
+        .iter()
+        .map(|job| (job.snapshot_id(), job.viewport.clone()))
+        .collect();
+    let mut captured = super::repeat::capture_repeated(plan, runs).await?;
+
+    let metric = config.diff_metric;
+    let options = config.compare_options();
+    let floors = captured
+        .analyze(move |name, runs| {
+            let viewport = viewports.get(&name).cloned().unwrap_or_default();
+            measure(name, viewport, runs, &options, metric)
+        })
+        .await?;
+
+    let mut suggested: Vec<DiffOverride> = Vec::new();
+    for floor in &floors {
+        let current = config.threshold_for(&floor.viewport, &floor.name);
+        if floor.p99 <= current {
+            continue;
+        }
+        let threshold = round_up(floor.p99);
+        println!(
+            "  {}  {}  (p99 {:.4}, max {:.4}, threshold {current:.4} → {threshold:.4})",
+            terminal::yellow("NOISY"),
+            floor.name,
+            floor.p99,
+            floor.max,
+        );
+        suggested.push(DiffOverride {
+            snapshot: floor.name.clone(),
+            threshold,
+        });
+    }
+
+    captured.print_summary(
+        snapshots,
+        runs,
+        &[
+            terminal::green(format!(
+                "{} within threshold",
+                floors.len() - suggested.len()
+            )),
+            terminal::yellow(format!("{} above", suggested.len())),
+        ],
+    );
+    if interrupt::requested() {
+        println!(
+            "Interrupted: {} of {} capture(s) done; not writing suggestions.",
+            captured.done, captured.total
+        );
+        return Ok(());
+    }
+    if suggested.is_empty() {
+        return Ok(());
+    }
+
+    if write {
+        let path = config::write_overrides(&suggested)?;
+        println!();
+        println!(
+            "{} [[diff.override]] entries written to {}.",
+            suggested.len(),
+            path.display()
+        );
+    } else {
+        println!();
+        println!("Suggested overrides (or rerun with --write to write them to the config):");
+        println!();
+        for o in &suggested {
+            println!("[[diff.override]]");
+            println!("snapshot = {}", toml::Value::String(o.snapshot.clone()));
+            println!("threshold = {}", o.threshold);
+            println!();
+        }
+    }
+    Ok(())
+}
+
+/// Diff every pair of captures, masking `ignore_selectors` bounds from
+/// both sides, and take the noise floor of the scores.
+fn measure(
+    name: String,
+    viewport: String,
+    runs: Vec<CaptureResult>,
+    options: &CompareOptions,
+    metric: DiffMetric,
+) -> Result<NoiseFloor> {
+    let mut scores = Vec::new();
+    for (i, first) in runs.iter().enumerate() {
+        for second in &runs[i + 1..] {
+            let mut masks = second.masks.clone();
+            masks.extend_from_slice(&first.masks);
+            let result = diff::compare(&first.png, &second.png, &masks, options)
+                .with_context(|| format!("Failed to compare captures of {name}"))?;
+            scores.push(metric_score(metric, &result));
+        }
+    }
+    let max = scores.iter().copied().fold(0.0, f64::max);
+    Ok(NoiseFloor {
+        name,
+        viewport,
+        p99: percentile(&mut scores, NOISE_PERCENTILE),
+        max,
+    })
+}
+
+/// Nearest-rank percentile `p` (0.0–1.0) of `values`; 0 when empty.
+fn percentile(values: &mut [f64], p: f64) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    values.sort_by(f64::total_cmp);
+    let rank = (p * values.len() as f64).ceil() as usize;
+    values[rank.clamp(1, values.len()) - 1]
+}
+
+/// `score` rounded up to 4 decimals, so the suggested threshold still
+/// covers it once written out.
+fn round_up(score: f64) -> f64 {
+    ((score * 10_000.0).ceil() / 10_000.0).min(1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn noise_floor_is_nearest_rank_p99() {
+        let mut few = [0.002, 0.0, 0.001];
+        assert_eq!(percentile(&mut few, NOISE_PERCENTILE), 0.002);
+
+        let mut many: Vec<f64> = (1..=200).map(|i| f64::from(i) / 1000.0).collect();
+        assert_eq!(percentile(&mut many, NOISE_PERCENTILE), 0.198);
+        assert_eq!(percentile(&mut [], NOISE_PERCENTILE), 0.0);
+        assert_eq!(round_up(0.00123), 0.0013);
+    }
+}
@@ -0,0 +1,22 @@
+use anyhow::Result;
+
+use crate::cdp::docker;
+
+/// `snapvrt chrome up` — start the pinned headless-Chrome container.
+pub async fn chrome_up(port: u16) -> Result<()> {
+    println!("Starting {} ({})...", docker::CONTAINER_NAME, docker::IMAGE);
+    let url = docker::start(port).await?;
+    println!("Chrome running at {url}");
+    println!("test/update use it automatically until `snapvrt chrome down`.");
+    Ok(())
+}
+
+/// `snapvrt chrome down` — stop the container started by `chrome up`.
+pub async fn chrome_down() -> Result<()> {
+    if docker::stop().await? {
+        println!("Stopped {}.", docker::CONTAINER_NAME);
+    } else {
+        println!("{} is not running.", docker::CONTAINER_NAME);
+    }
+    Ok(())
+}
@@ -0,0 +1,110 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use anyhow::Result;
+
+use crate::capture::css_coverage;
+use crate::report::{html, json, terminal};
+use crate::store;
+
+/// Parse an age like `7d`, `12h`, `30m` or `2w`.
+pub fn parse_age(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (num, unit) = s.split_at(split);
+    let n: u64 = num
+        .parse()
+        .map_err(|_| format!("invalid age '{s}' (expected e.g. 7d, 12h, 30m)"))?;
+    let secs = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" | "" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return Err(format!("unknown age unit '{unit}' (use s, m, h, d or w)")),
+    };
+    Ok(Duration::from_secs(n * secs))
+}
+
+/// `snapvrt clean` — delete generated run outputs (current and difference
+/// images, error artifacts, reports). References are never touched.
+///
+/// Without `all`, only files last modified more than `older_than` ago go.
+pub fn clean(older_than: Duration, all: bool, dry_run: bool) -> Result<()> {
+    let mut files = store::output_files();
+    for report in [
+        html::OUTPUT_FILE,
+        json::OUTPUT_FILE,
+        css_coverage::OUTPUT_FILE,
+    ] {
+        let path = Path::new(store::BASE_DIR).join(report);
+        if path.exists() {
+            files.push(path);
+        }
+    }
+
+    let now = SystemTime::now();
+    let stale: Vec<(PathBuf, u64)> = files
+        .into_iter()
+        .filter_map(|path| {
+            let meta = std::fs::metadata(&path).ok()?;
+            let age = meta
+                .modified()
+                .ok()
+                .and_then(|m| now.duration_since(m).ok())
+                .unwrap_or_default();
+            (all || age > older_than).then_some((path, meta.len()))
+        })
+        .collect();
+
+    if stale.is_empty() {
+        println!("Nothing to clean.");
+        return Ok(());
+    }
+
+    let label = if dry_run { "Would remove" } else { "Removed" };
+    let mut removed = 0usize;
+    let mut freed = 0u64;
+    for (path, size) in &stale {
+        if !dry_run && let Err(e) = std::fs::remove_file(path) {
+            terminal::print_error_line(&path.display().to_string(), &e.to_string());
+            continue;
+        }
+        removed += 1;
+        freed += size;
+        terminal::print_info_line(
+            label,
+            &path.display().to_string(),
+            &terminal::format_bytes(*size),
+        );
+    }
+    if !dry_run {
+        store::remove_empty_output_dirs();
+    }
+
+    println!();
+    println!(
+        "{label} {removed} file(s), {}.",
+        terminal::format_bytes(freed)
+    );
+    if dry_run {
+        println!("Dry run — no files deleted.");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_age_units() {
+        assert_eq!(parse_age("7d"), Ok(Duration::from_secs(7 * 86400)));
+        assert_eq!(parse_age("12h"), Ok(Duration::from_secs(12 * 3600)));
+        assert_eq!(parse_age("30m"), Ok(Duration::from_secs(1800)));
+        assert_eq!(parse_age("2w"), Ok(Duration::from_secs(14 * 86400)));
+        assert_eq!(parse_age("3"), Ok(Duration::from_secs(3 * 86400)));
+        assert!(parse_age("d").is_err());
+        assert!(parse_age("5y").is_err());
+    }
+}
@@ -0,0 +1,232 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::report::run_info::RunInfo;
+use crate::report::terminal;
+
+/// The parts of a `report.json` that runs are compared on.
+#[derive(Deserialize)]
+struct SavedReport {
+    #[serde(default)]
+    run: Option<RunInfo>,
+    #[serde(default)]
+    snapshots: Vec<SavedEntry>,
+}
+
+#[derive(Deserialize)]
+struct SavedEntry {
+    id: String,
+    status: String,
+    #[serde(default)]
+    score: Option<f64>,
+}
+
+impl SavedEntry {
+    /// Differences and capture errors; new snapshots have nothing to fail.
+    fn failing(&self) -> bool {
+        matches!(self.status.as_str(), "fail" | "error")
+    }
+}
+
+/// One snapshot whose outcome is compared between the runs.
+#[derive(Debug, PartialEq, Serialize)]
+struct Change {
+    id: String,
+    /// Status in the base run; None when the base run didn't capture it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    base_status: Option<String>,
+    head_status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    base_score: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    head_score: Option<f64>,
+    /// `head_score - base_score`, when both runs scored it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    score_delta: Option<f64>,
+}
+
+impl Change {
+    fn new(base: Option<&SavedEntry>, head: &SavedEntry) -> Self {
+        let base_score = base.and_then(|b| b.score);
+        Self {
+            id: head.id.clone(),
+            base_status: base.map(|b| b.status.clone()),
+            head_status: head.status.clone(),
+            base_score,
+            head_score: head.score,
+            score_delta: base_score.zip(head.score).map(|(b, h)| h - b),
+        }
+    }
+}
+
+/// How the head run's failures differ from the base run's. Snapshots only
+/// the base run captured are left out: a filtered head run says nothing
+/// about them.
+#[derive(Debug, Default, PartialEq, Serialize)]
+struct RunDelta {
+    /// Failing in head, but passing, new or not captured in base.
+    new_failures: Vec<Change>,
+    /// Failing in base, passing in head.
+    fixed: Vec<Change>,
+    /// Failing in both, largest score increase first.
+    still_failing: Vec<Change>,
+}
+
+impl RunDelta {
+    fn new(base: &[SavedEntry], head: &[SavedEntry]) -> Self {
+        let base: BTreeMap<&str, &SavedEntry> = base.iter().map(|e| (e.id.as_str(), e)).collect();
+        let mut delta = Self::default();
+        for entry in head {
+            let before = base.get(entry.id.as_str()).copied();
+            let was_failing = before.is_some_and(SavedEntry::failing);
+            let list = match (was_failing, entry.failing()) {
+                (false, true) => &mut delta.new_failures,
+                (true, true) => &mut delta.still_failing,
+                (true, false) if entry.status == "pass" => &mut delta.fixed,
+                _ => continue,
+            };
+            list.push(Change::new(before, entry));
+        }
+        delta.new_failures.sort_by(|a, b| {
+            let score = |c: &Change| c.head_score.unwrap_or(f64::INFINITY);
+            score(b).total_cmp(&score(a))
+        });
+        delta.still_failing.sort_by(|a, b| {
+            let delta = |c: &Change| c.score_delta.unwrap_or(0.0);
+            delta(b).total_cmp(&delta(a))
+        });
+        delta
+    }
+}
+
+fn read_report(path: &Path) -> Result<SavedReport> {
+    let bytes =
+        std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    serde_json::from_slice(&bytes).with_context(|| {
+        format!(
+            "Failed to parse {} as a snapvrt report.json",
+            path.display()
+        )
+    })
+}
+
+/// `path`, plus the branch and commit the run was made from, if recorded.
+fn describe_run(path: &Path, report: &SavedReport) -> String {
+    let run = report.run.as_ref();
+    let commit = run
+        .and_then(|r| r.git_commit.as_deref())
+        .map(|c| &c[..c.len().min(10)]);
+    match (run.and_then(|r| r.git_branch.as_deref()), commit) {
+        (Some(branch), Some(commit)) => format!("{} ({branch} @ {commit})", path.display()),
+        (None, Some(commit)) => format!("{} ({commit})", path.display()),
+        _ => path.display().to_string(),
+    }
+}
+
+fn format_score(score: Option<f64>) -> String {
+    score.map_or_else(|| "-".to_string(), |s| format!("{s:.4}"))
+}
+
+/// `snapvrt compare-runs` — compare the failures of two `report.json`
+/// files, e.g. a pull request run (`head`) against the nightly run of the
+/// main branch (`base`), and optionally write the delta as JSON.
+/// Returns exit code: 0 = no new failures, 1 = new failures in `head`.
+pub fn compare_runs(base_path: &Path, head_path: &Path, output: Option<&Path>) -> Result<i32> {
+    let base = read_report(base_path)?;
+    let head = read_report(head_path)?;
+    let delta = RunDelta::new(&base.snapshots, &head.snapshots);
+
+    println!("Base: {}", describe_run(base_path, &base));
+    println!("Head: {}", describe_run(head_path, &head));
+
+    let id_width = [&delta.new_failures, &delta.fixed, &delta.still_failing]
+        .iter()
+        .flat_map(|list| list.iter().map(|c| c.id.len()))
+        .max()
+        .unwrap_or(0);
+    for (label, list) in [
+        ("New failures", &delta.new_failures),
+        ("Fixed", &delta.fixed),
+        ("Still failing", &delta.still_failing),
+    ] {
+        if list.is_empty() {
+            continue;
+        }
+        println!();
+        println!("  {label} ({}):", list.len());
+        for change in list {
+            let detail = match (label, change.score_delta) {
+                ("Still failing", Some(delta)) => format!(
+                    "{} → {} ({delta:+.4})",
+                    format_score(change.base_score),
+                    format_score(change.head_score)
+                ),
+                ("Fixed", _) => format!("was {}", format_score(change.base_score)),
+                _ if change.head_status == "error" => "capture error".to_string(),
+                _ => format_score(change.head_score),
+            };
+            println!("    {:id_width$}  {}", change.id, terminal::dim(detail));
+        }
+    }
+
+    println!();
+    println!(
+        "Compared:   {} vs {} snapshots, {}, {}, {}",
+        base.snapshots.len(),
+        head.snapshots.len(),
+        terminal::red(format!("{} new failures", delta.new_failures.len())),
+        terminal::green(format!("{} fixed", delta.fixed.len())),
+        terminal::yellow(format!("{} still failing", delta.still_failing.len())),
+    );
+
+    if let Some(path) = output {
+        let json = serde_json::to_string_pretty(&delta)?;
+        std::fs::write(path, json)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        println!("Delta written to {}", path.display());
+    }
+
+    Ok(if delta.new_failures.is_empty() { 0 } else { 1 })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: &str, status: &str, score: Option<f64>) -> SavedEntry {
+        SavedEntry {
+            id: id.to_string(),
+            status: status.to_string(),
+            score,
+        }
+    }
+
+    #[test]
+    fn sorts_snapshots_into_new_fixed_and_still_failing() {
+        let base = [
+            entry("a", "pass", None),
+            entry("b", "fail", Some(0.1)),
+            entry("c", "fail", Some(0.2)),
+            entry("d", "fail", Some(0.1)),
+            entry("gone", "fail", Some(0.5)),
+        ];
+        let head = [
+            entry("a", "fail", Some(0.3)),
+            entry("b", "pass", None),
+            entry("c", "fail", Some(0.25)),
+            entry("d", "fail", Some(0.4)),
+            entry("e", "error", None),
+            entry("f", "new", None),
+        ];
+        let delta = RunDelta::new(&base, &head);
+        let ids = |list: &[Change]| list.iter().map(|c| c.id.clone()).collect::<Vec<_>>();
+        assert_eq!(ids(&delta.new_failures), ["e", "a"]);
+        assert_eq!(ids(&delta.fixed), ["b"]);
+        assert_eq!(ids(&delta.still_failing), ["d", "c"]);
+        let d = &delta.still_failing[0];
+        assert!((d.score_delta.unwrap() - 0.3).abs() < 1e-9);
+    }
+}
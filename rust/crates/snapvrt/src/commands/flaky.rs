@@ -0,0 +1,210 @@
+use std::collections::BTreeSet;
+
+use anyhow::{Context, Result};
+
+use crate::capture::pipeline::CaptureResult;
+use crate::capture::{CapturePlan, Selection};
+use crate::compare::diff::{self, CompareOptions};
+use crate::compare::render::DiffRender;
+use crate::config::{DiffChannels, ResolvedRunConfig};
+use crate::interrupt;
+use crate::report::terminal;
+
+/// Per-snapshot stability across repeated captures.
+struct Stability {
+    name: String,
+    runs: usize,
+    /// Runs whose pixels differ from the first capture.
+    differing: usize,
+    /// Number of distinct encoded outputs.
+    distinct: usize,
+    max_score: f64,
+    mean_score: f64,
+}
+
+/// `snapvrt flaky` — capture every snapshot `runs` times and report the
+/// ones whose output varies between runs. References are not touched.
+/// Returns exit code: 0 = all stable, 1 = any flaky or errored.
+pub async fn flaky(config: ResolvedRunConfig, selection: &Selection, runs: usize) -> Result<i32> {
+    let runs = runs.max(2);
+    let plan = CapturePlan::plan(&config, selection).await?;
+    if plan.total() == 0 {
+        return Ok(0);
+    }
+    let snapshots = plan.total();
+    let mut captured = super::repeat::capture_repeated(plan, runs).await?;
+
+    let max_pixels = config.max_pixels;
+    let results = captured
+        .analyze(move |name, runs| analyze(name, runs, max_pixels))
+        .await?;
+
+    let mut unstable: Vec<&Stability> = results.iter().filter(|s| s.differing > 0).collect();
+    unstable.sort_by(|a, b| b.max_score.total_cmp(&a.max_score));
+
+    for s in &unstable {
+        println!(
+            "  {}  {}  ({}/{} runs differ, {} distinct, max {:.4}, mean {:.4})",
+            terminal::red("FLAKY"),
+            s.name,
+            s.differing,
+            s.runs - 1,
+            s.distinct,
+            s.max_score,
+            s.mean_score,
+        );
+    }
+
+    captured.print_summary(
+        snapshots,
+        runs,
+        &[
+            terminal::green(format!("{} stable", results.len() - unstable.len())),
+            terminal::red(format!("{} flaky", unstable.len())),
+        ],
+    );
+    if interrupt::requested() {
+        println!(
+            "Interrupted: {} of {} capture(s) done.",
+            captured.done, captured.total
+        );
+    }
+
+    if unstable.is_empty() && captured.errored.is_empty() {
+        Ok(0)
+    } else {
+        Ok(1)
+    }
+}
+
+/// Diff every capture against the first one, masking `ignore_selectors`
+/// bounds from both sides.
+fn analyze(name: String, runs: Vec<CaptureResult>, max_pixels: u64) -> Result<Stability> {
+    let options = CompareOptions {
+        max_pixels,
+        ssim: false,
+        flip: false,
+        render: DiffRender::DEFAULT,
+        blur_sigma: None,
+        channels: DiffChannels::Rgba,
+    };
+    let distinct = runs.iter().map(|r| &r.png).collect::<BTreeSet<_>>().len();
+    let mut differing = 0usize;
+    let mut scores = Vec::new();
+    if let Some((first, rest)) = runs.split_first() {
+        for run in rest {
+            let mut masks = run.masks.clone();
+            masks.extend_from_slice(&first.masks);
+            let result = diff::compare(&first.png, &run.png, &masks, &options)
+                .with_context(|| format!("Failed to compare captures of {name}"))?;
+            if !result.is_match {
+                differing += 1;
+            }
+            scores.push(result.score);
+        }
+    }
+    let max_score = scores.iter().copied().fold(0.0, f64::max);
+    let mean_score = if scores.is_empty() {
+        0.0
+    } else {
+        scores.iter().sum::<f64>() / scores.len() as f64
+    };
+    Ok(Stability {
+        name,
+        runs: runs.len(),
+        differing,
+        distinct,
+        max_score,
+        mean_score,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use image::{Rgba, RgbaImage};
+
+    use super::*;
+    use crate::capture::CaptureTimings;
+    use crate::compare::diff::Mask;
+
+    /// A capture of a 20x20 white image with the pixels in `dark` black.
+    fn capture(dark: &[(u32, u32)], format: image::ImageFormat, masks: &[Mask]) -> CaptureResult {
+        let mut img = RgbaImage::from_pixel(20, 20, Rgba([255, 255, 255, 255]));
+        for &(x, y) in dark {
+            img.put_pixel(x, y, Rgba([0, 0, 0, 255]));
+        }
+        let mut png = Vec::new();
+        img.write_to(&mut std::io::Cursor::new(&mut png), format)
+            .unwrap();
+        CaptureResult {
+            png,
+            timings: CaptureTimings::default(),
+            masks: masks.to_vec(),
+            texts: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn analyze_tells_stable_from_flaky_captures() {
+        let png = image::ImageFormat::Png;
+        let mask = Mask {
+            x: 10,
+            y: 10,
+            width: 5,
+            height: 5,
+        };
+
+        // Same pixels, encoded differently: distinct outputs, but stable.
+        let stable = analyze(
+            "stable".to_string(),
+            vec![
+                capture(&[], png, &[]),
+                capture(&[], png, &[]),
+                capture(&[], image::ImageFormat::Bmp, &[]),
+            ],
+            u64::MAX,
+        )
+        .unwrap();
+        assert_eq!((stable.runs, stable.differing, stable.distinct), (3, 0, 2));
+        assert_eq!(stable.max_score, 0.0);
+
+        // A change inside a masked element doesn't count.
+        let masked = analyze(
+            "masked".to_string(),
+            vec![
+                capture(&[], png, &[mask]),
+                capture(&[(12, 12)], png, &[mask]),
+            ],
+            u64::MAX,
+        )
+        .unwrap();
+        assert_eq!(masked.differing, 0);
+
+        let flaky = analyze(
+            "flaky".to_string(),
+            vec![
+                capture(&[], png, &[]),
+                capture(&[(1, 1), (2, 2)], png, &[]),
+                capture(&[], png, &[]),
+            ],
+            u64::MAX,
+        )
+        .unwrap();
+        assert_eq!((flaky.differing, flaky.distinct), (1, 2));
+        assert_eq!(flaky.max_score, 2.0 / 400.0);
+        assert_eq!(flaky.mean_score, 1.0 / 400.0);
+    }
+
+    #[test]
+    fn analyze_fails_on_an_undecodable_capture() {
+        let mut broken = capture(&[], image::ImageFormat::Png, &[]);
+        broken.png.truncate(20);
+        let runs = vec![capture(&[], image::ImageFormat::Png, &[]), broken];
+        let Err(err) = analyze("broken".to_string(), runs, u64::MAX) else {
+            panic!("a truncated PNG was compared");
+        };
+        assert!(err.to_string().contains("broken"), "{err}");
+    }
+}
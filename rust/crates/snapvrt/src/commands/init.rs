@@ -1,18 +1,211 @@
-use anyhow::{Result, bail};
+use std::collections::BTreeMap;
+use std::io::{IsTerminal, Write};
+use std::path::Path;
 
-use crate::config;
+use anyhow::{Context, Result, bail};
+
+use crate::capture::{CapturePlan, Selection};
+use crate::config::detect::{DEFAULT_STORYBOOK_PORT, detect_project};
+use crate::config::foreign::{ForeignSetup, ForeignTool};
+use crate::config::{self, CaptureConfig, CliOverrides, ResolvedRunConfig};
+use crate::report::terminal;
+use crate::store;
+
+/// Unmatched baseline names listed after an import.
+const UNMATCHED_SHOWN: usize = 10;
 
 /// `snapvrt init` — create .snapvrt/config.toml.
-pub fn init(url: &str, force: bool) -> Result<()> {
+///
+/// Without `--url`, the Storybook port is detected from `package.json`
+/// scripts or `.storybook/main.*`; custom viewports are taken from the
+/// viewport addon config in `.storybook/preview.*`.
+///
+/// With `from`, URL and viewports come from the other tool's config and its
+/// baselines are imported as references (Storybook must be running).
+pub async fn init(
+    url: Option<&str>,
+    force: bool,
+    yes: bool,
+    from: Option<ForeignTool>,
+) -> Result<()> {
     if !force && config::config_file_exists() {
         bail!(".snapvrt/config.toml already exists (use --force to overwrite)");
     }
 
-    config::write_template(url)?;
+    let cwd = std::env::current_dir().context("Failed to read current directory")?;
+    let foreign = from
+        .map(|tool| ForeignSetup::load(tool, &cwd))
+        .transpose()?;
+    let detected = detect_project(&cwd);
+    let url = match (url, foreign.as_ref().and_then(|f| f.url.as_deref())) {
+        (Some(url), _) | (None, Some(url)) => url.to_string(),
+        (None, None) => format!(
+            "http://localhost:{}",
+            detected.port.unwrap_or(DEFAULT_STORYBOOK_PORT)
+        ),
+    };
+    let viewports = match &foreign {
+        Some(f) => f
+            .viewports
+            .iter()
+            .map(|v| (v.name.clone(), v.viewport.clone()))
+            .collect(),
+        None => detected.viewports,
+    };
+
+    config::write_template(&url, &viewports)?;
     config::write_gitignore(force)?;
 
     let verb = if force { "Regenerated" } else { "Created" };
     println!("{verb} .snapvrt/config.toml");
-    println!("  source.storybook.url = {url}");
+    let url_note = if detected.port.is_some() {
+        format!(
+            "  {}",
+            terminal::dim("(port detected from Storybook scripts)")
+        )
+    } else {
+        String::new()
+    };
+    println!("  source.storybook.url = {url}{url_note}");
+    if !viewports.is_empty() {
+        let names: Vec<&str> = viewports.iter().map(|(n, _)| n.as_str()).collect();
+        let origin = match &foreign {
+            Some(f) => format!("from {} config", f.tool.label()),
+            None => "from .storybook/preview".to_string(),
+        };
+        println!(
+            "  viewports = {}  {}",
+            names.join(", "),
+            terminal::dim(format!("({origin})"))
+        );
+    }
+
+    if let Some(setup) = &foreign {
+        println!();
+        import_baselines(setup).await?;
+    }
+
+    offer_root_gitignore(&cwd, yes)
+}
+
+/// Copy the foreign tool's baselines into the reference store, matching
+/// each file to a story/viewport by the tool's naming scheme.
+async fn import_baselines(setup: &ForeignSetup) -> Result<()> {
+    let config = ResolvedRunConfig::new(CliOverrides {
+        url: None,
+        threshold: None,
+        capture: CaptureConfig::default(),
+    })?;
+    let plan = CapturePlan::plan(&config, &Selection::default())
+        .await
+        .context("Storybook must be running to match baselines to stories")?;
+
+    let mut files = setup.baseline_files();
+    let total_files = files.len();
+
+    let _lock = store::RunLock::acquire(false).await?;
+    let mut references = store::References::open_for_writing()?;
+    references.set_id_template(config.store.id_template());
+
+    let mut imported = 0usize;
+    for job in plan.jobs() {
+        let Some(viewport) = setup.viewports.iter().find(|v| v.name == job.viewport) else {
+            continue;
+        };
+        let Some(path) = files.remove(&setup.baseline_name(viewport, &job.story)) else {
+            continue;
+        };
+        let png =
+            std::fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let image = store::prepare_reference(png, &config.store)?;
+        let id = job.snapshot_id();
+        references.write(&id, &image, Vec::new(), &BTreeMap::new())?;
+        terminal::print_info_line(
+            "Imported",
+            &id,
+            &format!(
+                "<- {}",
+                path.file_name().unwrap_or_default().to_string_lossy()
+            ),
+        );
+        imported += 1;
+    }
+    references.commit()?;
+
+    println!();
+    println!(
+        "{imported} of {total_files} {} baseline(s) imported.",
+        setup.tool.label()
+    );
+    if !files.is_empty() {
+        println!("{} baseline(s) matched no story:", files.len());
+        for name in files.keys().take(UNMATCHED_SHOWN) {
+            println!("    {name}");
+        }
+        if files.len() > UNMATCHED_SHOWN {
+            println!("    … and {} more", files.len() - UNMATCHED_SHOWN);
+        }
+    }
+    Ok(())
+}
+
+/// Offer to append `.snapvrt` artifacts to the repository root `.gitignore`.
+/// Asks interactively unless `yes`; silently skips outside a git repo or when
+/// every entry is already present.
+fn offer_root_gitignore(cwd: &Path, yes: bool) -> Result<()> {
+    let Some(root) = cwd.ancestors().find(|dir| dir.join(".git").exists()) else {
+        return Ok(());
+    };
+    let prefix = cwd
+        .strip_prefix(root)
+        .ok()
+        .filter(|rel| !rel.as_os_str().is_empty())
+        .map(|rel| format!("{}/", rel.to_string_lossy().replace('\\', "/")))
+        .unwrap_or_default();
+
+    let path = root.join(".gitignore");
+    let existing = std::fs::read_to_string(&path).unwrap_or_default();
+    let present: Vec<&str> = existing.lines().map(str::trim).collect();
+    let missing: Vec<String> = config::GITIGNORE_ENTRIES
+        .iter()
+        .map(|e| format!("{prefix}.snapvrt/{e}"))
+        .filter(|line| !present.contains(&line.as_str()))
+        .collect();
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    if !yes {
+        if !std::io::stdin().is_terminal() {
+            println!(
+                "Tip: run `snapvrt init --yes` to add snapvrt artifacts to the root .gitignore."
+            );
+            return Ok(());
+        }
+        print!("Add snapvrt artifacts to {}? [y/N] ", path.display());
+        std::io::stdout().flush()?;
+        let mut input = String::new();
+        std::io::stdin().read_line(&mut input)?;
+        if !input.trim().eq_ignore_ascii_case("y") {
+            return Ok(());
+        }
+    }
+
+    let mut content = String::new();
+    if !existing.is_empty() && !existing.ends_with('\n') {
+        content.push('\n');
+    }
+    content.push_str("# snapvrt\n");
+    for line in &missing {
+        content.push_str(line);
+        content.push('\n');
+    }
+    std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut f| f.write_all(content.as_bytes()))
+        .with_context(|| format!("Failed to update {}", path.display()))?;
+    println!("Updated {} ({} entries)", path.display(), missing.len());
     Ok(())
 }
@@ -0,0 +1,83 @@
+use std::collections::BTreeSet;
+
+use anyhow::{Result, bail};
+
+use crate::capture::job::{
+    args_variant_id, focus_variant_id, render_snapshot_id, validate_id_template,
+};
+use crate::capture::{CapturePlan, Selection};
+use crate::config::ResolvedRunConfig;
+use crate::report::terminal;
+use crate::store;
+
+/// `snapvrt migrate-ids` — rename references from an old `id_template` to
+/// the configured one. Stories are discovered so each old ID can be mapped
+/// to its new ID; references that match no story keep their old ID.
+pub async fn migrate_ids(
+    config: ResolvedRunConfig,
+    from: Option<&str>,
+    dry_run: bool,
+) -> Result<()> {
+    let mut references = store::References::open_for_writing()?;
+    let to_template = config.store.id_template().to_string();
+    let from_template = from.unwrap_or(references.id_template()).to_string();
+    validate_id_template(&from_template).map_err(|e| anyhow::anyhow!("--from: {e}"))?;
+
+    if from_template == to_template {
+        println!("References already use id_template \"{to_template}\".");
+        return Ok(());
+    }
+
+    let run = CapturePlan::plan(&config, &Selection::default()).await?;
+    let reference_ids = references.ids();
+
+    let pairs: Vec<(String, String)> = run
+        .jobs()
+        .iter()
+        .map(|job| {
+            let old = render_snapshot_id(&from_template, &job.source, &job.viewport, &job.story);
+            let old = match &job.args {
+                Some(label) => args_variant_id(&old, label),
+                None => old,
+            };
+            let old = match job.focus {
+                0 => old,
+                n => focus_variant_id(&old, n),
+            };
+            (old, job.snapshot_id())
+        })
+        .filter(|(old, new)| old != new && reference_ids.contains(old))
+        .collect();
+
+    // Refuse to overwrite references that are not themselves being moved.
+    let sources: BTreeSet<&String> = pairs.iter().map(|(old, _)| old).collect();
+    for (_, new) in &pairs {
+        if reference_ids.contains(new) && !sources.contains(new) {
+            bail!("Cannot migrate: reference \"{new}\" already exists under the new template");
+        }
+    }
+
+    println!("Migrating \"{from_template}\" -> \"{to_template}\"");
+    for (old, new) in &pairs {
+        println!("  Renamed  {old}  {}  {new}", terminal::dim("->"));
+    }
+    let unmatched = reference_ids.len() - pairs.len();
+    println!();
+    println!("{} reference(s) renamed.", pairs.len());
+    if unmatched > 0 {
+        println!("{unmatched} reference(s) not renamed (no matching story or ID unchanged).");
+    }
+
+    if dry_run {
+        println!("Dry run — no files changed.");
+        return Ok(());
+    }
+
+    references.rename_all(&pairs);
+    references.set_id_template(&to_template);
+    references.commit()?;
+    // current/ and difference/ are keyed by the old IDs.
+    store::clear_output_dirs();
+
+    Ok(())
+}
@@ -0,0 +1,93 @@
+use anyhow::{Context, Result, bail};
+use image::imageops::FilterType;
+
+use crate::config;
+use crate::report::terminal;
+use crate::store;
+
+/// `snapvrt migrate-scale` — resample every reference from one device scale
+/// factor to another, scaling its ignore masks and the recorded environment
+/// along with it, so a project changing `capture.device_scale` keeps
+/// comparable baselines instead of re-approving everything.
+///
+/// `from` defaults to the scale recorded in the manifest (1 when none is),
+/// `to` to the configured `capture.device_scale`.
+pub fn migrate_scale(from: Option<f64>, to: Option<f64>, dry_run: bool) -> Result<()> {
+    let config = config::load().context("Run `snapvrt init` first")?;
+    let mut references = store::References::open_for_writing()?;
+    let from = from
+        .or(references.environment().map(|env| env.device_scale))
+        .unwrap_or(1.0);
+    let to = to.unwrap_or(config.capture.device_scale());
+    for (flag, scale) in [("--from", from), ("--to", to)] {
+        if !(scale.is_finite() && scale > 0.0 && scale <= 4.0) {
+            bail!("{flag} must be > 0 and <= 4, got {scale}");
+        }
+    }
+    if from == to {
+        println!("References are already at device scale {to}.");
+        return Ok(());
+    }
+    let factor = to / from;
+
+    let ids = references.ids();
+    println!(
+        "Resampling {} reference(s) from scale {from} to {to}",
+        ids.len()
+    );
+    for id in &ids {
+        let bytes = references
+            .read(id)?
+            .with_context(|| format!("Could not read reference {id}"))?;
+        let image = image::load_from_memory(&bytes)
+            .with_context(|| format!("Failed to decode reference {id}"))?;
+        let width = ((image.width() as f64 * factor).round() as u32).max(1);
+        let height = ((image.height() as f64 * factor).round() as u32).max(1);
+        terminal::print_info_line(
+            "Resampled",
+            id,
+            &format!("{}x{} -> {width}x{height}", image.width(), image.height()),
+        );
+        if dry_run {
+            continue;
+        }
+        let resized = image.resize_exact(width, height, FilterType::Lanczos3);
+        let mut png = Vec::new();
+        resized
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .context("Failed to encode resampled reference")?;
+        let masks = references
+            .get(id)
+            .map(|entry| entry.masks.iter().map(|m| m.scaled(factor)).collect())
+            .unwrap_or_default();
+        references.rewrite(id, &store::prepare_reference(png, &config.store)?)?;
+        references.set_masks(id, masks);
+    }
+
+    println!();
+    if dry_run {
+        println!("Dry run — no files changed.");
+        return Ok(());
+    }
+    if let Some(env) = references.environment() {
+        let mut env = env.clone();
+        env.device_scale = to;
+        references.set_environment(&env);
+    }
+    references.commit()?;
+    // current/ and difference/ were captured at the old scale.
+    store::clear_output_dirs();
+    println!(
+        "{} reference(s) resampled. Resampled images are not pixel-identical to \
+         fresh captures: review them with `snapvrt test`, or re-capture with \
+         `snapvrt update` instead.",
+        ids.len()
+    );
+    if config.capture.device_scale() != to {
+        println!(
+            "capture.device_scale is {}; set it to {to} to capture at the new scale.",
+            config.capture.device_scale()
+        );
+    }
+    Ok(())
+}
@@ -1,13 +1,32 @@
 mod approve;
+mod calibrate;
+mod chrome;
+mod clean;
+mod compare_runs;
+mod flaky;
 mod init;
+mod migrate_ids;
+mod migrate_scale;
+mod mv;
+mod optimize;
 mod prune;
+mod repeat;
 mod review;
 mod test;
 mod update;
 
-pub use self::approve::approve;
+pub use self::approve::{ApproveOptions, approve, read_selection};
+pub use self::calibrate::calibrate;
+pub use self::chrome::{chrome_down, chrome_up};
+pub use self::clean::{clean, parse_age};
+pub use self::compare_runs::compare_runs;
+pub use self::flaky::flaky;
 pub use self::init::init;
+pub use self::migrate_ids::migrate_ids;
+pub use self::migrate_scale::migrate_scale;
+pub use self::mv::mv;
+pub use self::optimize::optimize;
 pub use self::prune::prune;
 pub use self::review::review;
-pub use self::test::test;
+pub use self::test::{FailOn, list, test};
 pub use self::update::update;
@@ -0,0 +1,143 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::{Result, bail};
+
+use crate::capture::{runner, stability};
+use crate::report::terminal;
+use crate::store;
+
+/// The parts of `id` matched by each `*` in `pattern`, leftmost-first like
+/// `matches_pattern`. `None` when `id` does not match.
+fn wildcards<'a>(pattern: &str, id: &'a str) -> Option<Vec<&'a str>> {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let mut rest = id.strip_prefix(first)?;
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty().then(Vec::new);
+    };
+    let mut captured = Vec::new();
+    for part in middle {
+        let i = rest.find(part)?;
+        captured.push(&rest[..i]);
+        rest = &rest[i + part.len()..];
+    }
+    let end = rest.len().checked_sub(last.len())?;
+    rest.ends_with(last).then(|| {
+        captured.push(&rest[..end]);
+        captured
+    })
+}
+
+/// New ID of `id` under `mv from to`, if `from` matches it: wildcards
+/// are substituted in order, and a pattern without wildcards also moves
+/// everything under it (`Forms/Button` moves `Forms/Button/Primary`).
+fn rename(from: &str, to: &str, id: &str) -> Option<String> {
+    if !from.contains('*') {
+        return if id == from {
+            Some(to.to_string())
+        } else {
+            let rest = id.strip_prefix(from)?.strip_prefix('/')?;
+            Some(format!("{}/{rest}", to.trim_end_matches('/')))
+        };
+    }
+    let captured = wildcards(from, id)?;
+    let mut out = String::new();
+    for (i, part) in to.split('*').enumerate() {
+        if i > 0 {
+            out.push_str(captured[i - 1]);
+        }
+        out.push_str(part);
+    }
+    Some(out)
+}
+
+/// `snapvrt mv` — rename references when stories are retitled, keeping
+/// their baselines (and capture timing history) instead of reporting a new
+/// snapshot plus an orphaned reference.
+pub fn mv(from: &str, to: &str, dry_run: bool) -> Result<()> {
+    let (from_stars, to_stars) = (from.matches('*').count(), to.matches('*').count());
+    if from_stars != to_stars {
+        bail!(
+            "\"{from}\" has {from_stars} wildcard(s) but \"{to}\" has {to_stars}; \
+             each * in the new pattern takes what the matching * in the old one matched"
+        );
+    }
+    let mut references = store::References::open_for_writing()?;
+    let reference_ids = references.ids();
+    let pairs: Vec<(String, String)> = reference_ids
+        .iter()
+        .filter_map(|id| rename(from, to, id).map(|new| (id.clone(), new)))
+        .filter(|(old, new)| old != new)
+        .collect();
+    if pairs.is_empty() {
+        println!("No reference snapshots match \"{from}\".");
+        return Ok(());
+    }
+
+    let sources: BTreeSet<&String> = pairs.iter().map(|(old, _)| old).collect();
+    let mut targets: BTreeMap<&String, &String> = BTreeMap::new();
+    for (old, new) in &pairs {
+        if let Some(other) = targets.insert(new, old) {
+            bail!("Cannot move: both \"{other}\" and \"{old}\" would become \"{new}\"");
+        }
+        if reference_ids.contains(new) && !sources.contains(new) {
+            bail!("Cannot move \"{old}\": reference \"{new}\" already exists");
+        }
+    }
+
+    for (old, new) in &pairs {
+        println!("  Moved  {old}  {}  {new}", terminal::dim("->"));
+    }
+    println!();
+    println!("{} reference(s) moved.", pairs.len());
+    if dry_run {
+        println!("Dry run — no files changed.");
+        return Ok(());
+    }
+
+    references.rename_all(&pairs);
+    references.commit()?;
+    runner::rename_timings(&pairs);
+    stability::rename(&pairs);
+    // Run outputs of the old IDs no longer belong to any reference.
+    let old: Vec<String> = pairs.into_iter().map(|(old, _)| old).collect();
+    store::clean_output_files(&old);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renames_by_wildcards_or_prefix() {
+        let from = "*/Forms/Button/*";
+        let to = "*/Inputs/Button/*";
+        assert_eq!(
+            rename(from, to, "storybook/laptop/Forms/Button/Primary").as_deref(),
+            Some("storybook/laptop/Inputs/Button/Primary")
+        );
+        assert_eq!(
+            rename(from, to, "storybook/laptop/Forms/Input/Primary"),
+            None
+        );
+        assert_eq!(
+            rename(
+                "sb/laptop/Forms",
+                "sb/laptop/Inputs",
+                "sb/laptop/Forms/Button/Primary"
+            )
+            .as_deref(),
+            Some("sb/laptop/Inputs/Button/Primary")
+        );
+        assert_eq!(
+            rename("sb/laptop/Form", "sb/laptop/X", "sb/laptop/Forms/A"),
+            None
+        );
+        assert_eq!(
+            rename("*/Old Name", "*/New Name", "sb/mobile/Card/Old Name").as_deref(),
+            Some("sb/mobile/Card/New Name")
+        );
+    }
+}
@@ -0,0 +1,74 @@
+use anyhow::{Context, Result, bail};
+
+use crate::report::terminal;
+use crate::store;
+use crate::storybook::Filter;
+
+/// `snapvrt optimize` — losslessly recompress existing reference PNGs.
+pub fn optimize(filter: Option<&Filter>, zopfli: bool, dry_run: bool) -> Result<()> {
+    if filter.is_some_and(Filter::needs_stories) {
+        bail!("optimize matches snapshot IDs only; --filter can't use viewport: or tag:");
+    }
+    let mut references = store::References::open_for_writing()?;
+    let ids: Vec<String> = references
+        .ids()
+        .into_iter()
+        .filter(|id| filter.is_none_or(|f| f.matches_id(id)))
+        .collect();
+
+    if ids.is_empty() {
+        println!("No reference snapshots to optimize.");
+        return Ok(());
+    }
+
+    let mut before_total = 0u64;
+    let mut after_total = 0u64;
+    let mut rewritten = 0usize;
+
+    for id in &ids {
+        let png = references
+            .read(id)?
+            .with_context(|| format!("Could not read reference/{id}.png"))?;
+        if store::extension_of(&png) != "png" {
+            continue;
+        }
+        let optimized =
+            store::optimize_png(&png, zopfli).with_context(|| format!("reference/{id}.png"))?;
+        let before = png.len() as u64;
+        let after = (optimized.len() as u64).min(before);
+        before_total += before;
+        after_total += after;
+
+        if optimized.len() < png.len() {
+            rewritten += 1;
+            if !dry_run {
+                references.rewrite(id, &optimized)?;
+            }
+            terminal::print_info_line(
+                "Optimized",
+                id,
+                &format!(
+                    "{} -> {}",
+                    terminal::format_bytes(before),
+                    terminal::format_bytes(after)
+                ),
+            );
+        }
+    }
+
+    references.commit()?;
+
+    let saved = before_total - after_total;
+    println!();
+    println!(
+        "{rewritten} of {} reference(s) recompressed, {} saved ({} -> {}).",
+        ids.len(),
+        terminal::format_bytes(saved),
+        terminal::format_bytes(before_total),
+        terminal::format_bytes(after_total),
+    );
+    if dry_run {
+        println!("Dry run — no files written.");
+    }
+    Ok(())
+}
@@ -1,50 +1,118 @@
+use std::collections::BTreeSet;
 use std::io::Write;
 
 use anyhow::Result;
 
-use crate::capture::CapturePlan;
+use crate::capture::{CapturePlan, Selection};
 use crate::config::ResolvedRunConfig;
+use crate::report::terminal;
 use crate::store;
 
-/// `snapvrt prune` — find and delete orphaned reference snapshots.
-pub async fn prune(config: ResolvedRunConfig, dry_run: bool, yes: bool) -> Result<()> {
-    let run = CapturePlan::plan(&config, None).await?;
-    let planned_ids: std::collections::BTreeSet<String> = run.job_names().into_iter().collect();
-    let reference_ids = store::list_reference_ids();
+/// `snapvrt prune` — find and delete orphaned reference snapshots, plus
+/// current/difference images and error artifacts of snapshots that no
+/// longer exist.
+/// Orphans still within `store.orphan_grace_*` are kept unless
+/// `ignore_grace`.
+pub async fn prune(
+    config: ResolvedRunConfig,
+    dry_run: bool,
+    yes: bool,
+    ignore_grace: bool,
+) -> Result<()> {
+    let run = CapturePlan::plan(&config, &Selection::default()).await?;
+    let planned_ids: BTreeSet<String> = run.job_names().into_iter().collect();
+    let mut references = store::References::open_for_writing()?;
+    references.check_id_template(config.store.id_template())?;
+    let reference_ids = references.ids();
 
-    let orphans: Vec<&String> = reference_ids.difference(&planned_ids).collect();
+    let all_orphans: BTreeSet<&String> = reference_ids.difference(&planned_ids).collect();
+    let mut tombstones = store::Tombstones::load();
+    let expired = tombstones.track(&all_orphans, &config.store, false);
+    let orphans: BTreeSet<&String> = if ignore_grace {
+        all_orphans.clone()
+    } else {
+        expired.iter().collect()
+    };
+    // Outputs of references kept in their grace period stay too.
+    let mut kept_ids = planned_ids.clone();
+    kept_ids.extend(all_orphans.difference(&orphans).map(|id| (*id).clone()));
+    let outputs = store::orphan_output_files(&kept_ids);
+    let in_grace = all_orphans.len() - orphans.len();
+    if in_grace > 0 {
+        println!(
+            "{in_grace} orphaned reference(s) kept within the grace period \
+             (store.orphan_grace_days / orphan_grace_runs; --ignore-grace deletes them too)."
+        );
+    }
 
-    if orphans.is_empty() {
-        println!("No orphaned references found.");
+    if orphans.is_empty() && outputs.is_empty() {
+        println!("No orphaned references or outputs found.");
+        if !dry_run {
+            tombstones.save();
+        }
         return Ok(());
     }
 
-    println!("Orphaned references ({}):", orphans.len());
-    for id in &orphans {
-        println!("  {id}");
+    let reference_bytes = references.reclaimable(&orphans);
+    if !orphans.is_empty() {
+        println!("Orphaned references ({}):", orphans.len());
+        for id in &orphans {
+            println!("  {id}");
+        }
+        println!();
     }
-    println!();
+    let output_files: Vec<_> = outputs.values().flatten().collect();
+    let output_bytes: u64 = output_files
+        .iter()
+        .filter_map(|path| std::fs::metadata(path).ok())
+        .map(|meta| meta.len())
+        .sum();
+    if !outputs.is_empty() {
+        println!("Orphaned outputs ({}):", outputs.len());
+        for (id, files) in &outputs {
+            println!("  {id} ({} file(s))", files.len());
+        }
+        println!();
+    }
+    let reclaimed = terminal::format_bytes(reference_bytes + output_bytes);
 
     if dry_run {
-        println!("Dry run — no files deleted.");
+        println!("Dry run — no files deleted ({reclaimed} would be reclaimed).");
         return Ok(());
     }
 
     if !yes {
-        print!("Delete {} reference(s)? [y/N] ", orphans.len());
+        print!(
+            "Delete {} reference(s) and {} output file(s)? [y/N] ",
+            orphans.len(),
+            output_files.len()
+        );
         std::io::stdout().flush()?;
         let mut input = String::new();
         std::io::stdin().read_line(&mut input)?;
         if !input.trim().eq_ignore_ascii_case("y") {
             println!("Aborted.");
+            tombstones.save();
             return Ok(());
         }
     }
 
     for id in &orphans {
-        store::remove_reference(id);
+        references.remove(id);
+    }
+    references.commit()?;
+    tombstones.save();
+    for path in &output_files {
+        if let Err(e) = std::fs::remove_file(path) {
+            terminal::print_error_line(&path.display().to_string(), &e.to_string());
+        }
     }
-    println!("Deleted {} orphaned reference(s).", orphans.len());
+    store::remove_empty_output_dirs();
+    println!(
+        "Deleted {} orphaned reference(s) and {} output file(s), reclaimed {reclaimed}.",
+        orphans.len(),
+        output_files.len()
+    );
 
     Ok(())
 }
@@ -0,0 +1,105 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+use std::time::Instant;
+
+use anyhow::{Context, Result};
+use tracing::debug;
+
+use crate::capture::pipeline::CaptureResult;
+use crate::capture::{CaptureOutcome, CapturePlan};
+use crate::report::terminal;
+
+/// Captures of a plan taken several times over, grouped by snapshot, for
+/// `flaky` and `calibrate`.
+pub(super) struct RepeatedCaptures {
+    /// Successful captures by snapshot ID.
+    captures: BTreeMap<String, Vec<CaptureResult>>,
+    /// Snapshots with at least one failed capture.
+    pub errored: BTreeSet<String>,
+    /// Captures finished (an interrupted run stops short of `total`).
+    pub done: usize,
+    pub total: usize,
+    started: Instant,
+}
+
+/// Capture every job of `plan` `runs` times, with progress, printing the
+/// first error of each snapshot.
+pub(super) async fn capture_repeated(
+    mut plan: CapturePlan,
+    runs: usize,
+) -> Result<RepeatedCaptures> {
+    plan.repeat(runs);
+    let started = Instant::now();
+    let total = plan.total();
+    terminal::start_progress(total, plan.activity());
+    let mut rx = plan.execute().await?;
+
+    let mut captures: BTreeMap<String, Vec<CaptureResult>> = BTreeMap::new();
+    let mut errored: BTreeSet<String> = BTreeSet::new();
+    let mut done = 0usize;
+
+    debug!(total, runs, "waiting for capture results");
+    while let Some((job, outcome)) = rx.recv().await {
+        done += 1;
+        let name = job.snapshot_id();
+        match outcome {
+            CaptureOutcome::Ok(result) => captures.entry(name).or_default().push(*result),
+            CaptureOutcome::Err(msg) => {
+                if errored.insert(name.clone()) {
+                    terminal::print_error_line(&name, &msg);
+                }
+            }
+        }
+        terminal::show_progress(done, total, errored.len());
+    }
+    terminal::finish_progress();
+
+    Ok(RepeatedCaptures {
+        captures,
+        errored,
+        done,
+        total,
+        started,
+    })
+}
+
+impl RepeatedCaptures {
+    /// Run `analyze` on the captures of each snapshot, off the async runtime.
+    pub async fn analyze<T, F>(&mut self, analyze: F) -> Result<Vec<T>>
+    where
+        T: Send + 'static,
+        F: Fn(String, Vec<CaptureResult>) -> Result<T> + Send + Sync + 'static,
+    {
+        let analyze = Arc::new(analyze);
+        let mut results = Vec::new();
+        for (name, runs) in std::mem::take(&mut self.captures) {
+            let analyze = Arc::clone(&analyze);
+            let result = tokio::task::spawn_blocking(move || analyze(name, runs))
+                .await
+                .context("Analysis task panicked")??;
+            results.push(result);
+        }
+        Ok(results)
+    }
+
+    /// Print the snapshot counts (`outcomes` being the command's own, already
+    /// colored) followed by the errored count and the elapsed time.
+    pub fn print_summary(&self, snapshots: usize, runs: usize, outcomes: &[String]) {
+        println!();
+        print!(
+            "Snapshots:  {snapshots} total, {runs} runs each, {}",
+            outcomes.join(", ")
+        );
+        if !self.errored.is_empty() {
+            print!(
+                ", {}",
+                terminal::red(format!("{} errored", self.errored.len()))
+            );
+        }
+        println!();
+        println!(
+            "Time:       {}",
+            terminal::format_duration(self.started.elapsed())
+        );
+    }
+}
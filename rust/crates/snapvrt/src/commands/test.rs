@@ -1,26 +1,100 @@
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
 use std::time::Instant;
 
-use anyhow::{Context, Result};
-use tracing::debug;
+use anyhow::{Context, Result, bail};
+use image::RgbaImage;
+use tokio::task::JoinSet;
+use tracing::{debug, warn};
 
-use crate::capture::{CaptureOutcome, CapturePlan, CaptureTimings};
-use crate::compare::SnapshotStatus;
-use crate::compare::diff;
-use crate::config::ResolvedRunConfig;
+use crate::capture::job::CaptureJob;
+use crate::capture::pipeline::CaptureResult;
+use crate::capture::{CaptureOutcome, CapturePlan, CaptureTimings, Environment, Selection};
+use crate::compare::diff::{self, CompareOptions, Mask};
+use crate::compare::{SnapshotStatus, Thresholds, text};
+use crate::config::{DomMode, ResolvedRunConfig, StoreConfig, owner_for};
+use crate::interrupt;
+use crate::report::json::{self, JsonEntry};
+use crate::report::run_info::RunInfo;
 use crate::report::terminal;
+use crate::report::{ReportOptions, Reporters, RunEnd, SnapshotEvent, notify};
 use crate::store;
 
+/// Exit code for visual differences.
+const EXIT_FAILED: i32 = 1;
+/// Exit code for snapshots without a reference.
+const EXIT_NEW: i32 = 2;
+/// Exit code for capture errors.
+const EXIT_ERRORED: i32 = 3;
+
+/// Captures buffered ahead of the comparison workers. Once full, the capture
+/// channel backs up and the capture workers wait.
+const COMPARE_QUEUE: usize = 64;
+
+/// Which outcomes make `snapvrt test` exit non-zero (`--fail-on`).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum FailOn {
+    /// Only visual differences
+    Failed,
+    /// Visual differences and snapshots without a reference
+    #[value(name = "failed+new")]
+    FailedNew,
+    /// Differences, new snapshots and capture errors
+    #[default]
+    Any,
+}
+
+impl FailOn {
+    /// Exit code for a run's counts. When several outcomes apply, capture
+    /// errors win over differences, which win over new snapshots.
+    fn exit_code(self, failed: usize, new: usize, errored: usize) -> i32 {
+        if errored > 0 && self == FailOn::Any {
+            EXIT_ERRORED
+        } else if failed > 0 {
+            EXIT_FAILED
+        } else if new > 0 && self != FailOn::Failed {
+            EXIT_NEW
+        } else {
+            0
+        }
+    }
+}
+
+/// `snapvrt test --list` — discover stories and expand them into snapshots
+/// as a run would, then print each snapshot's ID, viewport and URL instead
+/// of capturing. For checking filters, tags and `[story]` settings.
+pub async fn list(config: ResolvedRunConfig, selection: &Selection) -> Result<()> {
+    let run = CapturePlan::plan(&config, selection).await?;
+    let jobs = run.jobs();
+    let id_width = jobs.iter().map(|j| j.id.len()).max().unwrap_or(0);
+    let vp_width = jobs.iter().map(|j| j.viewport.len()).max().unwrap_or(0);
+    for job in jobs {
+        println!(
+            "  {:id_width$}  {:vp_width$}  {}",
+            job.id, job.viewport, job.url
+        );
+    }
+    if !jobs.is_empty() {
+        println!();
+        println!("{} snapshots would be captured", jobs.len());
+    }
+    Ok(())
+}
+
 /// `snapvrt test` — discover, capture, compare, report.
-/// Returns exit code: 0 = all pass, 1 = any fail or new.
+/// Returns exit code: 0 = pass, 1 = differences, 2 = new snapshots,
+/// 3 = capture errors, filtered by `fail_on`. With `strict_env`, a run whose
+/// environment differs from the baselines' fails before comparing.
 pub async fn test(
     config: ResolvedRunConfig,
-    filter: Option<&str>,
-    timings: bool,
+    selection: &Selection,
     prune: bool,
+    fail_on: FailOn,
+    strict_env: bool,
+    report: &ReportOptions,
 ) -> Result<i32> {
-    let threshold = config.diff_threshold;
-    let run = CapturePlan::plan(&config, filter).await?;
+    let store_config = config.store.clone();
+    let run = CapturePlan::plan(&config, selection).await?;
     if run.total() == 0 {
         return Ok(0);
     }
@@ -28,151 +102,490 @@ pub async fn test(
     // Save planned IDs before execute() consumes the plan.
     let planned_ids: BTreeSet<String> = run.job_names().into_iter().collect();
 
+    let references = store::References::open_for_writing()?;
+    references.check_id_template(store_config.id_template())?;
+    references.verify_signature()?;
+
     // Clear stale current/difference files before capturing.
     // Full run: wipe both dirs (catches removed/renamed stories).
     // Filtered run: only clear files for the snapshots being tested.
-    if filter.is_some() {
+    let previous_run = if selection.is_partial() {
         store::clean_output_files(&run.job_names());
+        store::RunManifest::read()
     } else {
         store::clear_output_dirs();
-    }
+        None
+    };
+    let run_manifest = store::RunManifest::start(previous_run, &run.job_names());
 
     let run_start = Instant::now();
+    let mut info = RunInfo::start(&config);
     let total = run.total();
+    let activity = run.activity();
+    if !config.capture.debug {
+        terminal::start_progress(total, activity.clone());
+    }
     let mut rx = run.execute().await?;
+    let mut env_diffs: Vec<String> = Vec::new();
 
-    let mut done = 0usize;
-    let mut passed = 0usize;
-    let mut failed = 0usize;
-    let mut new = 0usize;
-    let mut errored = 0usize;
-    let mut all_timings: Vec<(String, CaptureTimings)> = Vec::new();
-
-    let mut failed_names: Vec<String> = Vec::new();
-    let mut new_names: Vec<String> = Vec::new();
-    let mut errored_names: Vec<String> = Vec::new();
-
-    debug!(total, "waiting for capture results");
-    while let Some((job, outcome)) = rx.recv().await {
-        done += 1;
-        let name = job.snapshot_id();
-        debug!(done, total, name = %name, "received result");
-        let (current_png, mut timings) = match outcome {
-            CaptureOutcome::Ok(png, timings) => (png, timings),
-            CaptureOutcome::Err(msg) => {
-                errored += 1;
-                errored_names.push(name.clone());
-                terminal::print_error_line(&name, &msg);
-                terminal::show_progress(done, total);
-                continue;
-            }
-        };
+    let mut received_count = 0usize;
+    let mut reporters = Reporters::for_run(report);
+    let owner = |job: &CaptureJob| owner_for(&config.owners, &job.story.title).map(str::to_string);
+    reporters.start(total);
+    let mut entries: Vec<JsonEntry> = Vec::new();
 
-        let reference_png = store::read_reference(&name);
-
-        let status = match reference_png {
-            Some(ref_png) => {
-                let cur_png = current_png.clone();
-                let t_compare = Instant::now();
-                let compare_result =
-                    tokio::task::spawn_blocking(move || diff::compare(&ref_png, &cur_png))
-                        .await
-                        .context("Diff task panicked")
-                        .and_then(|r| r);
-                timings.compare = t_compare.elapsed();
-
-                match compare_result {
-                    Err(e) => {
-                        store::write_current(&name, &current_png)?;
-                        SnapshotStatus::Error(format!("{e:#}"))
+    // Comparisons (reference decode, diff, image encoding, store writes) run on
+    // blocking threads, off the capture receiver: captures are drained into
+    // `queued` as they arrive so slow disks don't stall the capture workers.
+    let context = Arc::new(CompareContext {
+        decoded: DecodedReferences::new(&references, &planned_ids),
+        references,
+        run: Mutex::new(run_manifest),
+        store: store_config.clone(),
+        options: config.compare_options(),
+        dom_precheck: config.capture.dom() == DomMode::Precheck,
+    });
+    let compare_workers = std::thread::available_parallelism().map_or(4, |n| n.get());
+    let mut queued: VecDeque<(CaptureJob, CaptureResult)> = VecDeque::new();
+    let mut comparing = JoinSet::new();
+    let mut captures_done = false;
+    let mut peak_queued = 0usize;
+
+    debug!(total, compare_workers, "waiting for capture results");
+    loop {
+        tokio::select! {
+            received = rx.recv(), if !captures_done && queued.len() < COMPARE_QUEUE => {
+                let Some((job, outcome)) = received else {
+                    captures_done = true;
+                    continue;
+                };
+                received_count += 1;
+                if received_count == 1 {
+                    // The environment is fingerprinted before the first capture.
+                    env_diffs = environment_differences(
+                        context.references.environment(),
+                        activity.environment(),
+                    );
+                    if !env_diffs.is_empty() {
+                        terminal::print_env_warning(&env_diffs);
+                        if strict_env {
+                            terminal::finish_progress();
+                            bail!("Capture environment differs from the baselines (--strict-env)");
+                        }
                     }
-                    Ok(result) if result.is_match || result.score <= threshold => {
-                        store::clean_output(&name);
-                        SnapshotStatus::Pass
+                }
+                match outcome {
+                    CaptureOutcome::Ok(result) => {
+                        queued.push_back((job, *result));
+                        peak_queued = peak_queued.max(queued.len());
+                        debug!(
+                            queued = queued.len(),
+                            comparing = comparing.len(),
+                            "capture queued for comparison"
+                        );
                     }
-                    Ok(result) => {
-                        store::write_current(&name, &current_png)?;
-                        if let Some(diff_img) = &result.diff_image {
-                            let mut diff_png = Vec::new();
-                            diff_img
-                                .write_to(
-                                    &mut std::io::Cursor::new(&mut diff_png),
-                                    image::ImageFormat::Png,
-                                )
-                                .context("Failed to encode diff image")?;
-                            store::write_difference(&name, &diff_png)?;
-                        }
-                        SnapshotStatus::Fail {
-                            diff_pixels: result.diff_pixels,
-                            score: result.score,
-                            dimension_mismatch: result.dimension_mismatch,
-                        }
+                    CaptureOutcome::Err(msg) => {
+                        let status = SnapshotStatus::Error(msg);
+                        let mut entry = JsonEntry::new(&job, &status);
+                        entry.owner = owner(&job);
+                        reporters.snapshot(&SnapshotEvent {
+                            entry: &entry,
+                            status: &status,
+                            timings: None,
+                        })?;
+                        entries.push(entry);
                     }
                 }
             }
-            None => {
-                store::write_current(&name, &current_png)?;
-                SnapshotStatus::New
+            Some(joined) = comparing.join_next(), if !comparing.is_empty() => {
+                let (job, status, timings): (CaptureJob, SnapshotStatus, CaptureTimings) =
+                    joined.context("Comparison task panicked")??;
+                debug!(done = entries.len() + 1, total, name = %job.snapshot_id(), "compared");
+                let mut entry = JsonEntry::new(&job, &status);
+                entry.owner = owner(&job);
+                entry.perf = timings.perf.clone();
+                reporters.snapshot(&SnapshotEvent {
+                    entry: &entry,
+                    status: &status,
+                    timings: Some(&timings),
+                })?;
+                entries.push(entry);
             }
+            else => break,
+        }
+
+        while comparing.len() < compare_workers
+            && let Some((job, result)) = queued.pop_front()
+        {
+            let context = context.clone();
+            let thresholds = config.thresholds_for(&job.viewport, &job.snapshot_id());
+            comparing.spawn_blocking(move || {
+                let name = job.snapshot_id();
+                let mut timings = result.timings;
+                let status = context.compare(
+                    &name,
+                    result.png,
+                    &result.masks,
+                    &result.texts,
+                    thresholds,
+                    &mut timings,
+                )?;
+                Ok::<_, anyhow::Error>((job, status, timings))
+            });
+        }
+    }
+    debug!(peak_queued, compare_workers, "comparisons done");
+    let context = Arc::into_inner(context).context("References still shared")?;
+    let mut references = context.references;
+    terminal::finish_progress();
+    if let Some(env) = activity.environment() {
+        store::write_current_environment(env)?;
+    }
+    context
+        .run
+        .into_inner()
+        .unwrap_or_else(|e| e.into_inner())
+        .write()?;
+
+    // Orphan detection: only on full (unfiltered), uninterrupted runs.
+    // Orphans stay tombstoned, unreported, until their grace period is over.
+    let mut removed_names: Vec<String> = Vec::new();
+    let mut orphans_in_grace = 0;
+    if !selection.is_partial() && !interrupt::requested() {
+        let reference_ids = references.ids();
+        let orphans: BTreeSet<&String> = reference_ids.difference(&planned_ids).collect();
+        let mut tombstones = store::Tombstones::load();
+        removed_names = tombstones.track(&orphans, &config.store, true);
+        tombstones.save();
+        orphans_in_grace = orphans.len() - removed_names.len();
+        if prune {
+            for id in &removed_names {
+                references.remove(id);
+            }
+        }
+    }
+
+    references.commit()?;
+    let breakdown = json::breakdown(&entries);
+    info.finish(activity.environment(), run_start.elapsed());
+    reporters.finish(&RunEnd {
+        total,
+        breakdown: &breakdown,
+        removed: &removed_names,
+        orphans_in_grace,
+        env_diffs: &env_diffs,
+        elapsed: run_start.elapsed(),
+        peak_queued,
+        queue_capacity: COMPARE_QUEUE,
+        compare_workers,
+        info: &info,
+    })?;
+
+    // Removed snapshots do NOT affect exit code.
+    let count = |status: &str| entries.iter().filter(|e| e.status == status).count();
+    let exit_code = fail_on.exit_code(count("fail"), count("new"), count("error"));
+    if exit_code != 0
+        && let Some(url) = &config.notify.webhook_url
+    {
+        let names = |status: &str| -> Vec<String> {
+            entries
+                .iter()
+                .filter(|e| e.status == status)
+                .map(|e| e.id.clone())
+                .collect()
         };
+        let (failed_names, errored_names) = (names("fail"), names("error"));
+        let summary = notify::RunSummary {
+            source: &config.source_name,
+            total,
+            breakdown: &breakdown,
+            failed: &failed_names,
+            errored: &errored_names,
+            report_url: config.notify.report_url.as_deref(),
+        };
+        // A failed notification must not mask the run's own result.
+        if let Err(e) = notify::send(url, &summary).await {
+            warn!("{e:#}");
+        }
+    }
+    Ok(exit_code)
+}
 
-        match &status {
-            SnapshotStatus::Pass => passed += 1,
-            SnapshotStatus::Fail { .. } => {
-                failed += 1;
-                failed_names.push(name.clone());
+/// Differences between the baselines' environment and this run's; empty
+/// when either is unknown.
+fn environment_differences(
+    baseline: Option<&Environment>,
+    current: Option<&Environment>,
+) -> Vec<String> {
+    match (baseline, current) {
+        (Some(baseline), Some(current)) => baseline.differences(current),
+        _ => Vec::new(),
+    }
+}
+
+/// What comparison workers share.
+struct CompareContext {
+    references: store::References,
+    decoded: DecodedReferences,
+    /// Records which images this run wrote to `current/`.
+    run: Mutex<store::RunManifest>,
+    store: StoreConfig,
+    options: CompareOptions,
+    /// `capture.dom = "precheck"`: a DOM matching the reference passes
+    /// without a pixel comparison.
+    dom_precheck: bool,
+}
+
+impl CompareContext {
+    /// Compare a capture against its reference, writing `current/` and
+    /// `difference/` files as needed. Blocking: runs on a comparison worker.
+    fn compare(
+        &self,
+        name: &str,
+        current_png: Vec<u8>,
+        current_masks: &[Mask],
+        current_texts: &BTreeMap<String, String>,
+        thresholds: Thresholds,
+        timings: &mut CaptureTimings,
+    ) -> Result<SnapshotStatus> {
+        let save = |png| self.save_current(name, png, current_masks, current_texts);
+        let Some(entry) = self.references.get(name) else {
+            save(current_png)?;
+            return Ok(SnapshotStatus::New);
+        };
+        let (text_changes, dom_unchanged) = match self.text_changes(name, current_texts) {
+            Ok(compared) => compared,
+            Err(e) => {
+                self.decoded.release(&entry.hash);
+                save(current_png)?;
+                return Ok(SnapshotStatus::Error(format!("{e:#}")));
+            }
+        };
+        if self.references.is_unchanged(name, &current_png) {
+            // Byte-identical: nothing to decode, compare or write.
+            self.decoded.release(&entry.hash);
+            timings.identical = true;
+            if text_changes.is_empty() {
+                return Ok(SnapshotStatus::Pass);
+            }
+            save(current_png)?;
+            return Ok(SnapshotStatus::text_changed(text_changes));
+        }
+        if dom_unchanged && text_changes.is_empty() {
+            // Same DOM as the reference: skip decoding and diffing.
+            self.decoded.release(&entry.hash);
+            store::clean_output(name);
+            return Ok(SnapshotStatus::Pass);
+        }
+
+        let t_compare = Instant::now();
+        let reference = match self
+            .decoded
+            .get(&self.references, name, self.options.max_pixels)
+        {
+            Ok(Some(reference)) => Ok(reference),
+            Ok(None) => {
+                save(current_png)?;
+                return Ok(SnapshotStatus::New);
+            }
+            Err(e) => Err(e),
+        };
+        // Mask wherever an ignored element sat in either capture.
+        let mut masks = entry.masks.clone();
+        masks.extend_from_slice(current_masks);
+        let compare_result = reference.and_then(|reference| {
+            diff::compare_decoded(reference, &current_png, &masks, &self.options)
+        });
+        timings.compare = t_compare.elapsed();
+
+        Ok(match compare_result {
+            Err(e) => {
+                save(current_png)?;
+                SnapshotStatus::Error(format!("{e:#}"))
             }
-            SnapshotStatus::New => {
-                new += 1;
-                new_names.push(name.clone());
+            Ok(result) if thresholds.allow(&result) && text_changes.is_empty() => {
+                store::clean_output(name);
+                SnapshotStatus::Pass
             }
-            SnapshotStatus::Error(_) => {
-                errored += 1;
-                errored_names.push(name.clone());
+            Ok(result) if thresholds.allow(&result) => {
+                save(current_png)?;
+                SnapshotStatus::text_changed(text_changes)
+            }
+            Ok(result) => {
+                save(current_png)?;
+                if let Some(diff_img) = &result.diff_image {
+                    let mut diff_png = Vec::new();
+                    diff_img
+                        .write_to(
+                            &mut std::io::Cursor::new(&mut diff_png),
+                            image::ImageFormat::Png,
+                        )
+                        .context("Failed to encode diff image")?;
+                    store::write_difference(name, &diff_png)?;
+                }
+                SnapshotStatus::Fail {
+                    diff_pixels: result.diff_pixels,
+                    score: result.score,
+                    ssim: result.ssim,
+                    flip: result.flip,
+                    dimension_mismatch: result.dimension_mismatch,
+                    regions: result.regions,
+                    text_changes,
+                }
+            }
+        })
+    }
+
+    /// Kinds of text snapshots that differ from the reference, with their
+    /// diffs written to `difference/`, and whether a pre-check DOM matches
+    /// the reference's. References recorded without a text snapshot of a
+    /// kind (before it was enabled) are not compared.
+    fn text_changes(
+        &self,
+        name: &str,
+        current: &BTreeMap<String, String>,
+    ) -> Result<(Vec<String>, bool)> {
+        let mut changes = Vec::new();
+        let mut dom_unchanged = false;
+        for (kind, text) in current {
+            let Some(reference) = self.references.text(name, kind)? else {
+                continue;
+            };
+            let diff = text::diff_lines(&reference, text);
+            if self.dom_precheck && kind == store::DOM_TEXT {
+                dom_unchanged = diff.is_none();
+            } else if let Some(diff) = diff {
+                store::write_text_difference(name, kind, &diff)?;
+                changes.push(kind.clone());
             }
         }
+        Ok((changes, dom_unchanged))
+    }
 
-        terminal::print_line(&name, &status, timings.total + timings.compare);
-        all_timings.push((name, timings));
-        terminal::show_progress(done, total);
+    fn save_current(
+        &self,
+        name: &str,
+        png: Vec<u8>,
+        masks: &[Mask],
+        texts: &BTreeMap<String, String>,
+    ) -> Result<()> {
+        let hash = save_current(name, png, masks, texts, &self.store)?;
+        self.run
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .record(name, hash);
+        Ok(())
     }
+}
 
-    // Orphan detection: only on full (unfiltered) runs.
-    let mut removed_names: Vec<String> = Vec::new();
-    if filter.is_none() {
-        let reference_ids = store::list_reference_ids();
-        let orphans: BTreeSet<&String> = reference_ids.difference(&planned_ids).collect();
-        for id in &orphans {
-            terminal::print_removed_line(id);
-            removed_names.push((*id).clone());
+/// Object hash -> (snapshots still to compare, decoded image once loaded).
+type SharedImages = HashMap<String, (usize, Option<Arc<RgbaImage>>)>;
+
+/// Decoded references for objects several planned snapshots share (a story
+/// that renders the same at two viewports), kept until the last of them has
+/// been compared so each object is decoded once.
+struct DecodedReferences {
+    shared: Mutex<SharedImages>,
+}
+
+impl DecodedReferences {
+    fn new(references: &store::References, ids: &BTreeSet<String>) -> Self {
+        let mut users = SharedImages::new();
+        for entry in ids.iter().filter_map(|id| references.get(id)) {
+            users.entry(entry.hash.clone()).or_default().0 += 1;
         }
-        if prune {
-            for id in &orphans {
-                store::remove_reference(id);
+        users.retain(|_, (count, _)| *count > 1);
+        Self {
+            shared: Mutex::new(users),
+        }
+    }
+
+    /// Decoded reference for `id`; `None` when it can't be read.
+    fn get(
+        &self,
+        references: &store::References,
+        id: &str,
+        max_pixels: u64,
+    ) -> Result<Option<RgbaImage>> {
+        let Some(hash) = references.get(id).map(|e| e.hash.as_str()) else {
+            return Ok(None);
+        };
+        if let Some(image) = self.take(hash) {
+            return Ok(Some(image.as_ref().clone()));
+        }
+        let Some(png) = references.read(id)? else {
+            return Ok(None);
+        };
+        let image = diff::decode_reference(&png, max_pixels)?;
+        let mut shared = self.lock();
+        if let Some((count, cached)) = shared.get_mut(hash) {
+            *count -= 1;
+            if *count == 0 {
+                shared.remove(hash);
+            } else if cached.is_none() {
+                *cached = Some(Arc::new(image.clone()));
             }
         }
+        Ok(Some(image))
     }
 
-    if timings {
-        terminal::print_timing_table(&all_timings);
-        terminal::print_timing_summary(&all_timings);
+    /// A snapshot of `hash` needed no decoding; drop the image after its
+    /// last user.
+    fn release(&self, hash: &str) {
+        let mut shared = self.lock();
+        if let Some((count, _)) = shared.get_mut(hash) {
+            *count -= 1;
+            if *count == 0 {
+                shared.remove(hash);
+            }
+        }
     }
 
-    terminal::print_actionable_summary(&failed_names, &new_names, &errored_names, &removed_names);
-    terminal::print_summary(
-        total,
-        passed,
-        failed,
-        new,
-        errored,
-        removed_names.len(),
-        run_start.elapsed(),
-    );
+    /// Cached image for `hash`, counting this use.
+    fn take(&self, hash: &str) -> Option<Arc<RgbaImage>> {
+        let image = self.lock().get(hash)?.1.clone()?;
+        self.release(hash);
+        Some(image)
+    }
 
-    // Removed snapshots do NOT affect exit code.
-    if failed > 0 || new > 0 || errored > 0 {
-        Ok(1)
-    } else {
-        Ok(0)
+    fn lock(&self) -> std::sync::MutexGuard<'_, SharedImages> {
+        self.shared.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Write a captured image (with its ignore masks and text snapshots) to
+/// `current/` in the configured store format. Returns the hash of the
+/// written image.
+fn save_current(
+    name: &str,
+    png: Vec<u8>,
+    masks: &[Mask],
+    texts: &BTreeMap<String, String>,
+    config: &StoreConfig,
+) -> Result<String> {
+    let image = store::prepare_current(png, config)?;
+    store::write_current(name, &image)?;
+    store::write_current_masks(name, masks)?;
+    store::write_current_texts(name, texts)?;
+    Ok(store::hash_bytes(&image))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exit_code_prefers_errors_then_diffs_then_new() {
+        assert_eq!(FailOn::Any.exit_code(0, 0, 0), 0);
+        assert_eq!(FailOn::Any.exit_code(1, 1, 1), EXIT_ERRORED);
+        assert_eq!(FailOn::Any.exit_code(1, 1, 0), EXIT_FAILED);
+        assert_eq!(FailOn::Any.exit_code(0, 1, 0), EXIT_NEW);
+    }
+
+    #[test]
+    fn fail_on_ignores_outcomes_outside_policy() {
+        assert_eq!(FailOn::Failed.exit_code(0, 2, 3), 0);
+        assert_eq!(FailOn::Failed.exit_code(1, 2, 3), EXIT_FAILED);
+        assert_eq!(FailOn::FailedNew.exit_code(0, 2, 3), EXIT_NEW);
     }
 }
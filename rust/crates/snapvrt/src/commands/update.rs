@@ -1,26 +1,82 @@
+use std::collections::BTreeMap;
 use std::time::Instant;
 
-use anyhow::Result;
-use tracing::debug;
+use anyhow::{Context, Result};
+use tracing::{debug, warn};
 
-use crate::capture::{CaptureOutcome, CapturePlan, CaptureTimings};
-use crate::config::ResolvedRunConfig;
+use crate::capture::pipeline::CaptureResult;
+use crate::capture::{CaptureOutcome, CapturePlan, CaptureTimings, Selection};
+use crate::compare::diff::{self, CompareOptions, Mask};
+use crate::compare::render::DiffRender;
+use crate::config::{DiffChannels, ResolvedRunConfig};
+use crate::interrupt;
+use crate::report::run_info::{self, GitState};
 use crate::report::terminal;
 use crate::store;
 
-/// `snapvrt update` — discover, capture, save as references.
-pub async fn update(config: ResolvedRunConfig, filter: Option<&str>, timings: bool) -> Result<()> {
-    let run = CapturePlan::plan(&config, filter).await?;
+/// How a re-captured reference compares to the one it replaces.
+enum Change {
+    /// No reference yet.
+    New,
+    /// Same pixels (outside ignore masks) and text snapshots.
+    Unchanged,
+    /// Differs; the diff score, when the images could be compared.
+    Changed(Option<f64>),
+    /// The reference no longer matches its checksum.
+    Tampered,
+}
+
+impl Change {
+    fn describe(&self) -> String {
+        match self {
+            Self::New => "new".to_string(),
+            Self::Unchanged => "unchanged".to_string(),
+            Self::Changed(Some(score)) => format!("changed, score {score:.4}"),
+            Self::Changed(None) => "changed".to_string(),
+            Self::Tampered => "changed outside snapvrt".to_string(),
+        }
+    }
+}
+
+/// `snapvrt update` — discover, capture, save as references. Each capture is
+/// compared with the reference it replaces, so the output shows how much
+/// every baseline changed; `only_changed` keeps unchanged references as
+/// they are.
+///
+/// Like `approve`, refuses to run from a detached HEAD or with uncommitted
+/// changes when `store.require_clean_git` is set.
+pub async fn update(
+    config: ResolvedRunConfig,
+    selection: &Selection,
+    timings: bool,
+    only_changed: bool,
+) -> Result<()> {
+    let store_config = config.store.clone();
+    let max_pixels = config.max_pixels;
+    let git = GitState::read();
+    if store_config.require_clean_git {
+        run_info::check_clean_git(git.as_ref())?;
+    }
+    let run = CapturePlan::plan(&config, selection).await?;
     if run.total() == 0 {
         return Ok(());
     }
 
+    let mut references = store::References::open_for_writing()?;
+    let commit = git.as_ref().map(GitState::label);
+    references.check_id_template(store_config.id_template())?;
+    references.set_id_template(store_config.id_template());
+
     let run_start = Instant::now();
     let total = run.total();
+    let activity = run.activity();
+    terminal::start_progress(total, activity.clone());
     let mut rx = run.execute().await?;
 
     let mut done = 0usize;
     let mut saved = 0usize;
+    let mut changed = 0usize;
+    let mut kept = 0usize;
     let mut errored = 0usize;
     let mut all_timings: Vec<(String, CaptureTimings)> = Vec::new();
     debug!(total, "waiting for capture results");
@@ -29,23 +85,77 @@ pub async fn update(config: ResolvedRunConfig, filter: Option<&str>, timings: bo
         let name = job.snapshot_id();
         debug!(done, total, name = %name, "received result");
         match outcome {
-            CaptureOutcome::Ok(png, timings) => {
-                terminal::clear_line();
-                store::write_reference(&name, &png)?;
-                println!(
-                    "  Updated  {name}  \x1b[2m{}ms\x1b[0m",
-                    timings.total.as_millis()
+            CaptureOutcome::Ok(result) => {
+                let CaptureResult {
+                    png,
+                    timings,
+                    masks,
+                    texts,
+                } = *result;
+                // A reference edited outside snapvrt fails its checksum; it
+                // is replaced and reported as such.
+                let (previous, tampered) = match references.read(&name) {
+                    Ok(previous) => (previous, false),
+                    Err(e) => {
+                        debug!(name = %name, error = %format!("{e:#}"), "reference fails its checksum");
+                        (None, true)
+                    }
+                };
+                let mut compare_masks = masks.clone();
+                if let Some(entry) = references.get(&name) {
+                    compare_masks.extend_from_slice(&entry.masks);
+                }
+                let store_config = store_config.clone();
+                let id = name.clone();
+                let (image, score) = tokio::task::spawn_blocking(move || {
+                    let image = store::prepare_reference(png, &store_config)?;
+                    let score = previous.map(|previous| {
+                        compare(&id, &previous, &image, &compare_masks, max_pixels)
+                    });
+                    anyhow::Ok((image, score))
+                })
+                .await
+                .context("Encode task panicked")??;
+                let change = match score {
+                    None if tampered => Change::Tampered,
+                    None => Change::New,
+                    Some(Some(0.0)) if texts_match(&references, &name, &texts) => Change::Unchanged,
+                    Some(score) => Change::Changed(score),
+                };
+                let detail = format!(
+                    "{} · {}",
+                    change.describe(),
+                    terminal::format_duration(timings.total)
                 );
+                if only_changed && matches!(change, Change::Unchanged) {
+                    terminal::print_info_line("Kept", &name, &detail);
+                    kept += 1;
+                } else {
+                    references.write(&name, &image, masks, &texts)?;
+                    if let Some(commit) = &commit {
+                        references.set_commit(&name, commit);
+                    }
+                    terminal::print_info_line("Updated", &name, &detail);
+                    saved += 1;
+                    if matches!(change, Change::Changed(_) | Change::Tampered) {
+                        changed += 1;
+                    }
+                }
                 all_timings.push((name, timings));
-                saved += 1;
             }
             CaptureOutcome::Err(msg) => {
                 terminal::print_error_line(&name, &msg);
                 errored += 1;
             }
         }
-        terminal::show_progress(done, total);
+        terminal::show_progress(done, total, errored);
+    }
+    terminal::finish_progress();
+
+    if let Some(env) = activity.environment() {
+        references.set_environment(env);
     }
+    references.commit()?;
 
     if timings {
         terminal::print_timing_table(&all_timings);
@@ -53,11 +163,50 @@ pub async fn update(config: ResolvedRunConfig, filter: Option<&str>, timings: bo
     }
 
     println!();
-    println!("{saved} reference snapshot(s) saved.");
+    println!("{saved} reference snapshot(s) saved, {changed} of them changed.");
+    if kept > 0 {
+        println!("{kept} unchanged reference snapshot(s) kept.");
+    }
     if errored > 0 {
         println!("{errored} snapshot(s) failed to capture.");
     }
+    if interrupt::requested() {
+        println!("Interrupted: {} snapshot(s) not captured.", total - done);
+    }
     println!("Time: {}", terminal::format_duration(run_start.elapsed()));
 
     Ok(())
 }
+
+/// Diff score of `image` against the `previous` reference; `None` when they
+/// can't be compared (an undecodable old reference, say).
+fn compare(
+    id: &str,
+    previous: &[u8],
+    image: &[u8],
+    masks: &[Mask],
+    max_pixels: u64,
+) -> Option<f64> {
+    let options = CompareOptions {
+        max_pixels,
+        ssim: false,
+        flip: false,
+        render: DiffRender::DEFAULT,
+        blur_sigma: None,
+        channels: DiffChannels::Rgba,
+    };
+    match diff::compare(previous, image, masks, &options) {
+        Ok(result) => Some(result.score),
+        Err(e) => {
+            warn!(snapshot = %id, error = %format!("{e:#}"), "failed to compare with the old reference");
+            None
+        }
+    }
+}
+
+/// Whether every text snapshot (`a11y`, `dom`) matches the reference's.
+fn texts_match(references: &store::References, id: &str, texts: &BTreeMap<String, String>) -> bool {
+    texts.iter().all(|(kind, text)| {
+        references.text(id, kind).ok().flatten().as_deref() == Some(text.as_str())
+    })
+}
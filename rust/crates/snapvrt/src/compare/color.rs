@@ -0,0 +1,327 @@
+use image::RgbaImage;
+use tracing::warn;
+
+/// Linear sRGB to PCS XYZ (D50, Bradford adapted); the columns are the
+/// `rXYZ`, `gXYZ` and `bXYZ` tags of the standard sRGB profile.
+const SRGB_TO_XYZ_D50: [[f64; 3]; 3] = [
+    [0.436_074_7, 0.385_064_9, 0.143_080_4],
+    [0.222_504_5, 0.716_878_6, 0.060_616_9],
+    [0.013_932_2, 0.097_104_5, 0.714_173_3],
+];
+
+/// Largest matrix or tone curve deviation still treated as sRGB. Profiles
+/// store s15Fixed16 / u16 values, so sRGB copies differ in the last digits.
+const SRGB_TOLERANCE: f64 = 2e-3;
+
+/// One channel's tone curve (`rTRC` ...): encoded value in 0..=1 to linear.
+#[derive(Debug)]
+enum Curve {
+    Gamma(f64),
+    /// Sampled curve, linearly interpolated.
+    Table(Vec<f64>),
+    /// ICC parametric curve type 4 (types 0-3 are special cases):
+    /// `(a x + b)^g + e` for `x >= d`, else `c x + f`.
+    Parametric {
+        g: f64,
+        a: f64,
+        b: f64,
+        c: f64,
+        d: f64,
+        e: f64,
+        f: f64,
+    },
+}
+
+impl Curve {
+    fn eval(&self, x: f64) -> f64 {
+        match self {
+            Self::Gamma(g) => x.powf(*g),
+            Self::Table(t) => {
+                let pos = x * (t.len() - 1) as f64;
+                let i = (pos.floor() as usize).min(t.len() - 2);
+                t[i] + (t[i + 1] - t[i]) * (pos - i as f64)
+            }
+            Self::Parametric {
+                g,
+                a,
+                b,
+                c,
+                d,
+                e,
+                f,
+            } => {
+                if x >= *d {
+                    (a * x + b).max(0.0).powf(*g) + e
+                } else {
+                    c * x + f
+                }
+            }
+        }
+    }
+}
+
+/// An RGB matrix/TRC ("matrix-shaper") ICC profile, the kind displays and
+/// browsers embed (Display P3, Adobe RGB, sRGB).
+#[derive(Debug)]
+struct MatrixShaper {
+    curves: [Curve; 3],
+    /// Linear RGB to PCS XYZ.
+    matrix: [[f64; 3]; 3],
+}
+
+fn be_u32(b: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(b.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn be_u16(b: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(b.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn s15_fixed16(b: &[u8], at: usize) -> Option<f64> {
+    Some(be_u32(b, at)? as i32 as f64 / 65536.0)
+}
+
+impl MatrixShaper {
+    /// `None` for profiles that are not RGB matrix/TRC (LUT-based, CMYK,
+    /// gray) or are malformed.
+    fn parse(icc: &[u8]) -> Option<Self> {
+        if icc.get(16..20)? != b"RGB " {
+            return None;
+        }
+        let count = be_u32(icc, 128)? as usize;
+        let tag = |sig: &[u8; 4]| -> Option<&[u8]> {
+            (0..count.min(256)).find_map(|i| {
+                let entry = 132 + i * 12;
+                (icc.get(entry..entry + 4)? == sig).then_some(())?;
+                let offset = be_u32(icc, entry + 4)? as usize;
+                let size = be_u32(icc, entry + 8)? as usize;
+                icc.get(offset..offset.checked_add(size)?)
+            })
+        };
+        let xyz = |sig| -> Option<[f64; 3]> {
+            let t = tag(sig)?;
+            (t.get(0..4)? == b"XYZ ").then_some(())?;
+            Some([s15_fixed16(t, 8)?, s15_fixed16(t, 12)?, s15_fixed16(t, 16)?])
+        };
+        let [r, g, b] = [xyz(b"rXYZ")?, xyz(b"gXYZ")?, xyz(b"bXYZ")?];
+        let matrix = [0, 1, 2].map(|row| [r[row], g[row], b[row]]);
+        let curves = [
+            parse_curve(tag(b"rTRC")?)?,
+            parse_curve(tag(b"gTRC")?)?,
+            parse_curve(tag(b"bTRC")?)?,
+        ];
+        Some(Self { curves, matrix })
+    }
+
+    /// Close enough to sRGB that converting would only add rounding noise.
+    fn is_srgb(&self) -> bool {
+        let matrix_matches = self
+            .matrix
+            .iter()
+            .flatten()
+            .zip(SRGB_TO_XYZ_D50.iter().flatten())
+            .all(|(a, b)| (a - b).abs() < SRGB_TOLERANCE);
+        matrix_matches
+            && self.curves.iter().all(|curve| {
+                (0..=16).all(|i| {
+                    let x = i as f64 / 16.0;
+                    (curve.eval(x) - srgb_to_linear(x)).abs() < SRGB_TOLERANCE
+                })
+            })
+    }
+}
+
+fn parse_curve(t: &[u8]) -> Option<Curve> {
+    match t.get(0..4)? {
+        b"curv" => match be_u32(t, 8)? {
+            0 => Some(Curve::Gamma(1.0)),
+            1 => Some(Curve::Gamma(be_u16(t, 12)? as f64 / 256.0)),
+            n => {
+                let table = (0..n as usize)
+                    .map(|i| Some(be_u16(t, 12 + i * 2)? as f64 / 65535.0))
+                    .collect::<Option<Vec<_>>>()?;
+                Some(Curve::Table(table))
+            }
+        },
+        b"para" => {
+            let kind = be_u16(t, 8)?;
+            let n = [1, 3, 4, 5, 7].get(kind as usize).copied()?;
+            let p = (0..n)
+                .map(|i| s15_fixed16(t, 12 + i * 4))
+                .collect::<Option<Vec<_>>>()?;
+            let g = p[0];
+            Some(match kind {
+                0 => Curve::Gamma(g),
+                // Below -b/a: 0, or c with type 2.
+                1 | 2 => Curve::Parametric {
+                    g,
+                    a: p[1],
+                    b: p[2],
+                    c: 0.0,
+                    d: -p[2] / p[1],
+                    e: p.get(3).copied().unwrap_or(0.0),
+                    f: p.get(3).copied().unwrap_or(0.0),
+                },
+                3 => Curve::Parametric {
+                    g,
+                    a: p[1],
+                    b: p[2],
+                    c: p[3],
+                    d: p[4],
+                    e: 0.0,
+                    f: 0.0,
+                },
+                _ => Curve::Parametric {
+                    g,
+                    a: p[1],
+                    b: p[2],
+                    c: p[3],
+                    d: p[4],
+                    e: p[5],
+                    f: p[6],
+                },
+            })
+        }
+        _ => None,
+    }
+}
+
+fn srgb_to_linear(x: f64) -> f64 {
+    if x <= 0.04045 {
+        x / 12.92
+    } else {
+        ((x + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(x: f64) -> f64 {
+    if x <= 0.003_130_8 {
+        x * 12.92
+    } else {
+        1.055 * x.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+fn invert(m: [[f64; 3]; 3]) -> [[f64; 3]; 3] {
+    let det = m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+        - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+        + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0]);
+    let cofactor =
+        |r0: usize, r1: usize, c0: usize, c1: usize| m[r0][c0] * m[r1][c1] - m[r0][c1] * m[r1][c0];
+    [
+        [
+            cofactor(1, 2, 1, 2) / det,
+            -cofactor(0, 2, 1, 2) / det,
+            cofactor(0, 1, 1, 2) / det,
+        ],
+        [
+            -cofactor(1, 2, 0, 2) / det,
+            cofactor(0, 2, 0, 2) / det,
+            -cofactor(0, 1, 0, 2) / det,
+        ],
+        [
+            cofactor(1, 2, 0, 1) / det,
+            -cofactor(0, 2, 0, 1) / det,
+            cofactor(0, 1, 0, 1) / det,
+        ],
+    ]
+}
+
+fn multiply(a: [[f64; 3]; 3], b: [[f64; 3]; 3]) -> [[f64; 3]; 3] {
+    [0, 1, 2].map(|r| [0, 1, 2].map(|c| (0..3).map(|k| a[r][k] * b[k][c]).sum()))
+}
+
+/// Convert pixels tagged with the ICC profile `icc` to sRGB in place, so a
+/// wide-gamut capture (a Mac's Display P3) and an untagged sRGB capture
+/// compare on the same colors. Returns whether pixels changed. Unsupported
+/// profiles are left as they are, with a warning.
+pub fn to_srgb(image: &mut RgbaImage, icc: &[u8]) -> bool {
+    let Some(profile) = MatrixShaper::parse(icc) else {
+        warn!("image has an unsupported ICC profile; comparing its pixels as sRGB");
+        return false;
+    };
+    if profile.is_srgb() {
+        return false;
+    }
+    let to_srgb = multiply(invert(SRGB_TO_XYZ_D50), profile.matrix);
+    let decode: [Vec<f64>; 3] = [0, 1, 2].map(|ch| {
+        (0..=255)
+            .map(|v| profile.curves[ch].eval(v as f64 / 255.0))
+            .collect()
+    });
+    for pixel in image.pixels_mut() {
+        let linear = [0, 1, 2].map(|ch| decode[ch][pixel[ch] as usize]);
+        for (ch, row) in to_srgb.iter().enumerate() {
+            let value: f64 = row.iter().zip(linear).map(|(m, v)| m * v).sum();
+            pixel[ch] = (linear_to_srgb(value.clamp(0.0, 1.0)) * 255.0).round() as u8;
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Minimal matrix/TRC profile with one parametric sRGB curve shared by
+    /// all channels.
+    fn profile(columns: [[f64; 3]; 3]) -> Vec<u8> {
+        let fixed = |v: f64| ((v * 65536.0).round() as i32).to_be_bytes();
+        let mut tags: Vec<(&[u8; 4], Vec<u8>)> = Vec::new();
+        for (sig, xyz) in [b"rXYZ", b"gXYZ", b"bXYZ"].into_iter().zip(columns) {
+            let mut data = b"XYZ \0\0\0\0".to_vec();
+            xyz.iter().for_each(|v| data.extend(fixed(*v)));
+            tags.push((sig, data));
+        }
+        let mut curve = b"para\0\0\0\0\0\x03\0\0".to_vec();
+        for v in [2.4, 1.0 / 1.055, 0.055 / 1.055, 1.0 / 12.92, 0.04045] {
+            curve.extend(fixed(v));
+        }
+        for sig in [b"rTRC", b"gTRC", b"bTRC"] {
+            tags.push((sig, curve.clone()));
+        }
+        let mut icc = vec![0u8; 128];
+        icc[16..20].copy_from_slice(b"RGB ");
+        icc.extend((tags.len() as u32).to_be_bytes());
+        let mut offset = 132 + tags.len() * 12;
+        for (sig, data) in &tags {
+            icc.extend(*sig);
+            icc.extend((offset as u32).to_be_bytes());
+            icc.extend((data.len() as u32).to_be_bytes());
+            offset += data.len();
+        }
+        tags.iter().for_each(|(_, data)| icc.extend(data));
+        icc
+    }
+
+    #[test]
+    fn converts_display_p3_and_leaves_srgb_alone() {
+        let srgb = [0, 1, 2].map(|c| [0, 1, 2].map(|r| SRGB_TO_XYZ_D50[r][c]));
+        let mut image = RgbaImage::from_pixel(1, 1, image::Rgba([255, 0, 0, 255]));
+        assert!(!to_srgb(&mut image, &profile(srgb)));
+        assert_eq!(image.get_pixel(0, 0).0, [255, 0, 0, 255]);
+
+        // Display P3 primaries, D50 adapted: pure P3 red is outside sRGB and
+        // clips, a mid gray stays gray.
+        let p3 = [
+            [0.515_102, 0.241_182, -0.001_050],
+            [0.291_965, 0.692_236, 0.041_881],
+            [0.157_153, 0.066_582, 0.784_378],
+        ];
+        let mut image = RgbaImage::from_fn(2, 1, |x, _| {
+            image::Rgba(if x == 0 {
+                [255, 0, 0, 255]
+            } else {
+                [128, 128, 128, 200]
+            })
+        });
+        assert!(to_srgb(&mut image, &profile(p3)));
+        assert_eq!(image.get_pixel(0, 0).0, [255, 0, 0, 255]);
+        assert_eq!(image.get_pixel(1, 0).0, [128, 128, 128, 200]);
+
+        let mut image = RgbaImage::from_pixel(1, 1, image::Rgba([200, 100, 50, 255]));
+        assert!(to_srgb(&mut image, &profile(p3)));
+        let [r, g, b, _] = image.get_pixel(0, 0).0;
+        assert!(r > 200 && g < 100 && b < 50, "{:?}", (r, g, b));
+    }
+}
@@ -1,5 +1,14 @@
+use std::fmt;
+use std::io::Cursor;
+
 use anyhow::{Context, Result};
-use image::RgbaImage;
+use image::{DynamicImage, ImageDecoder, ImageReader, Limits, Rgba, RgbaImage, imageops};
+use serde::{Deserialize, Serialize};
+
+use super::color;
+use super::render::DiffRender;
+use super::ssim;
+use crate::config::DiffChannels;
 
 /// Maximum possible delta in YIQ color space (used by dify internally).
 const MAX_YIQ_POSSIBLE_DELTA: f32 = 35215.0;
@@ -7,23 +16,234 @@ const MAX_YIQ_POSSIBLE_DELTA: f32 = 35215.0;
 /// Pre-computed threshold: MAX_YIQ_POSSIBLE_DELTA * 0.1 * 0.1
 const THRESHOLD: f32 = MAX_YIQ_POSSIBLE_DELTA * 0.1 * 0.1;
 
+/// Largest per-channel RGB delta that can never exceed `THRESHOLD` in YIQ space.
+///
+/// With every channel differing by at most `d`, the weighted YIQ delta is
+/// bounded by roughly `1.144 * d²`, so `d = 17` stays below `THRESHOLD` (~352).
+const PREFILTER_MAX_CHANNEL_DELTA: u8 = 17;
+
+/// Color dify paints changed pixels with. Its faded base image never
+/// reaches pure red, so this identifies them exactly.
+pub(super) const DIFY_CHANGED: Rgba<u8> = Rgba([255, 0, 0, 255]);
+
+/// Opacity of the left image in dify's gray overlay base.
+const DIFY_FADE_ALPHA: f32 = 0.1;
+
+/// Changed pixels closer than this many pixels are grouped into one region.
+const REGION_CELL_SIZE: u32 = 8;
+
+/// Maximum number of regions kept per comparison (largest first).
+const MAX_REGIONS: usize = 10;
+
+/// Bounding box of a connected group of changed pixels.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Region {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// Number of changed pixels inside the box.
+    pub pixels: u64,
+}
+
+/// How a snapshot's dimensions changed. The two images are compared on a
+/// common canvas, aligned on the edges where their content still matches,
+/// so only the strip that was added or removed counts as changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SizeChange {
+    /// Reference width and height.
+    pub reference: (u32, u32),
+    /// Current width and height.
+    pub current: (u32, u32),
+    /// The width changed at the left edge rather than the right.
+    pub at_left: bool,
+    /// The height changed at the top rather than the bottom.
+    pub at_top: bool,
+}
+
+impl SizeChange {
+    /// Find where `reference` and `current` (of different sizes) grew or
+    /// shrank: on each axis, the end whose opposite edge has more matching
+    /// rows (or columns). Ties keep the top-left anchor.
+    fn detect(reference: &RgbaImage, current: &RgbaImage) -> Self {
+        let at_top = reference.height() != current.height()
+            && matching_rows(reference, current, true) > matching_rows(reference, current, false);
+        let at_left = reference.width() != current.width()
+            && matching_columns(reference, current, true)
+                > matching_columns(reference, current, false);
+        Self {
+            reference: reference.dimensions(),
+            current: current.dimensions(),
+            at_left,
+            at_top,
+        }
+    }
+
+    /// `[ref_w, ref_h, cur_w, cur_h]`.
+    pub fn dims(&self) -> [u32; 4] {
+        let ((rw, rh), (cw, ch)) = (self.reference, self.current);
+        [rw, rh, cw, ch]
+    }
+
+    fn canvas(&self) -> (u32, u32) {
+        let ((rw, rh), (cw, ch)) = (self.reference, self.current);
+        (rw.max(cw), rh.max(ch))
+    }
+
+    /// Where the reference and the current image sit on the canvas.
+    pub fn extents(&self) -> [Mask; 2] {
+        let (canvas_w, canvas_h) = self.canvas();
+        let place = |(width, height): (u32, u32)| Mask {
+            x: if self.at_left { canvas_w - width } else { 0 },
+            y: if self.at_top { canvas_h - height } else { 0 },
+            width,
+            height,
+        };
+        [place(self.reference), place(self.current)]
+    }
+
+    /// Both images on the canvas, transparent where one doesn't reach.
+    fn align(&self, reference: &RgbaImage, current: &RgbaImage) -> (RgbaImage, RgbaImage) {
+        let (w, h) = self.canvas();
+        let [ref_at, cur_at] = self.extents();
+        let place = |src: &RgbaImage, at: Mask| {
+            let mut canvas = RgbaImage::new(w, h);
+            imageops::replace(&mut canvas, src, i64::from(at.x), i64::from(at.y));
+            canvas
+        };
+        (place(reference, ref_at), place(current, cur_at))
+    }
+}
+
+/// `+24px height at bottom`, `-10px width at left, +8px height at top`.
+impl fmt::Display for SizeChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ((rw, rh), (cw, ch)) = (self.reference, self.current);
+        let mut parts = Vec::new();
+        if rw != cw {
+            let edge = if self.at_left { "left" } else { "right" };
+            parts.push(format!(
+                "{:+}px width at {edge}",
+                i64::from(cw) - i64::from(rw)
+            ));
+        }
+        if rh != ch {
+            let edge = if self.at_top { "top" } else { "bottom" };
+            parts.push(format!(
+                "{:+}px height at {edge}",
+                i64::from(ch) - i64::from(rh)
+            ));
+        }
+        f.write_str(&parts.join(", "))
+    }
+}
+
+/// Rows of `a` and `b` that are identical over their common width, with
+/// the images aligned at the top or (`bottom`) at the bottom.
+fn matching_rows(a: &RgbaImage, b: &RgbaImage, bottom: bool) -> usize {
+    let (w, h) = (a.width().min(b.width()), a.height().min(b.height()));
+    let (ay, by) = if bottom {
+        (a.height() - h, b.height() - h)
+    } else {
+        (0, 0)
+    };
+    (0..h)
+        .filter(|&y| (0..w).all(|x| a.get_pixel(x, ay + y) == b.get_pixel(x, by + y)))
+        .count()
+}
+
+/// Columns of `a` and `b` that are identical over their common height,
+/// aligned at the left or (`right`) at the right.
+fn matching_columns(a: &RgbaImage, b: &RgbaImage, right: bool) -> usize {
+    let (w, h) = (a.width().min(b.width()), a.height().min(b.height()));
+    let (ax, bx) = if right {
+        (a.width() - w, b.width() - w)
+    } else {
+        (0, 0)
+    };
+    (0..w)
+        .filter(|&x| (0..h).all(|y| a.get_pixel(ax + x, y) == b.get_pixel(bx + x, y)))
+        .count()
+}
+
+/// Rectangle (image pixels) excluded from comparison, e.g. the bounds of an
+/// element matching `ignore_selectors`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Mask {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Mask {
+    /// The mask on an image resampled by `factor`, grown to whole pixels.
+    pub fn scaled(&self, factor: f64) -> Self {
+        let x = (self.x as f64 * factor).floor();
+        let y = (self.y as f64 * factor).floor();
+        let right = ((self.x + self.width) as f64 * factor).ceil();
+        let bottom = ((self.y + self.height) as f64 * factor).ceil();
+        Self {
+            x: x as u32,
+            y: y as u32,
+            width: (right - x) as u32,
+            height: (bottom - y) as u32,
+        }
+    }
+}
+
+/// Knobs for [`compare`], from `[diff]`.
+pub struct CompareOptions {
+    /// Images larger than this are refused before decoding.
+    pub max_pixels: u64,
+    /// Also compute the mean SSIM.
+    pub ssim: bool,
+    /// Also compute the mean FLIP error (`flip` feature).
+    pub flip: bool,
+    /// Diff image style.
+    pub render: DiffRender,
+    /// Gaussian blur applied to both images before diffing
+    /// (`diff.blur_sigma`).
+    pub blur_sigma: Option<f32>,
+    /// Parts of each pixel compared (`diff.channels`).
+    pub channels: DiffChannels,
+}
+
 pub struct CompareResult {
     pub is_match: bool,
     pub diff_pixels: u64,
     #[allow(dead_code)]
     pub total_pixels: u64,
     pub score: f64,
+    /// Mean SSIM (1.0 = identical), when requested.
+    pub ssim: Option<f64>,
+    /// Mean FLIP error (0.0 = identical), when requested.
+    pub flip: Option<f64>,
     pub diff_image: Option<RgbaImage>,
-    /// `Some((ref_w, ref_h, cur_w, cur_h))` when images have different dimensions.
-    pub dimension_mismatch: Option<(u32, u32, u32, u32)>,
+    /// Reference and current sizes, and which edges moved, when they differ.
+    pub dimension_mismatch: Option<SizeChange>,
+    /// Changed regions, largest first (at most `MAX_REGIONS`). Empty on match.
+    pub regions: Vec<Region>,
 }
 
-/// Two-phase comparison:
+/// Three-phase comparison:
 /// 1. Byte-identical check (memcmp)
-/// 2. Perceptual diff via dify
+/// 2. Decoded-pixel prefilter (identical or below-threshold channel deltas),
+///    streamed row by row for PNGs so a passing pair is never held in memory
+/// 3. Perceptual diff via dify
+///
+/// Pixels inside `masks` are blanked in both images after decoding, so
+/// changes there never count as differences.
+///
+/// Images over `options.max_pixels` are refused before decoding.
 ///
 /// Runs synchronously — call via `spawn_blocking`.
-pub fn compare(reference_png: &[u8], current_png: &[u8]) -> Result<CompareResult> {
+pub fn compare(
+    reference_png: &[u8],
+    current_png: &[u8],
+    masks: &[Mask],
+    options: &CompareOptions,
+) -> Result<CompareResult> {
     // Phase 1: byte-identical
     if reference_png == current_png {
         return Ok(CompareResult {
@@ -31,52 +251,170 @@ pub fn compare(reference_png: &[u8], current_png: &[u8]) -> Result<CompareResult
             diff_pixels: 0,
             total_pixels: 0,
             score: 0.0,
+            ssim: options.ssim.then_some(1.0),
+            flip: options.flip.then_some(0.0),
             diff_image: None,
             dimension_mismatch: None,
+            regions: Vec::new(),
         });
     }
 
-    // Phase 2: decode and diff
-    let left = image::load_from_memory(reference_png)
-        .context("Failed to decode reference PNG")?
-        .to_rgba8();
+    // Phase 2, streamed: most pairs pass here without decoding either image whole.
+    if let Some(total_pixels) = streamed_prefilter(reference_png, current_png, masks, options) {
+        return Ok(CompareResult {
+            is_match: true,
+            diff_pixels: 0,
+            total_pixels,
+            score: 0.0,
+            ssim: options.ssim.then_some(1.0),
+            flip: options.flip.then_some(0.0),
+            diff_image: None,
+            dimension_mismatch: None,
+            regions: Vec::new(),
+        });
+    }
 
-    let right = image::load_from_memory(current_png)
-        .context("Failed to decode current PNG")?
-        .to_rgba8();
+    compare_decoded(
+        decode_reference(reference_png, options.max_pixels)?,
+        current_png,
+        masks,
+        options,
+    )
+}
 
-    let dimension_mismatch = if left.dimensions() != right.dimensions() {
-        Some((left.width(), left.height(), right.width(), right.height()))
-    } else {
-        None
-    };
+/// Decode a reference image for [`compare_decoded`].
+pub fn decode_reference(reference_png: &[u8], max_pixels: u64) -> Result<RgbaImage> {
+    decode(reference_png, "reference", max_pixels)
+}
 
-    // Pad both images to the same canvas size if dimensions differ.
-    // Fill colour is magenta (#FF00FF) so the size delta is obvious in the diff overlay.
-    let (left, right) = if dimension_mismatch.is_some() {
-        let max_w = left.width().max(right.width());
-        let max_h = left.height().max(right.height());
-        (pad_to(&left, max_w, max_h), pad_to(&right, max_w, max_h))
-    } else {
-        (left, right)
+/// Decode to RGBA, checking the header dimensions against `max_pixels` first
+/// so an oversized capture fails cleanly instead of allocating gigabytes.
+/// Images with an embedded ICC profile are converted to sRGB.
+///
+/// Plain PNGs are read a row at a time straight into the RGBA buffer, so the
+/// decoder's own full-size copy is never allocated.
+fn decode(bytes: &[u8], what: &str, max_pixels: u64) -> Result<RgbaImage> {
+    #[cfg(test)]
+    tests::FULL_DECODES.with(|n| n.set(n.get() + 1));
+    let reader = || ImageReader::new(Cursor::new(bytes)).with_guessed_format();
+    let (width, height) = reader()?
+        .into_dimensions()
+        .with_context(|| format!("Failed to read {what} image header"))?;
+    let pixels = u64::from(width) * u64::from(height);
+    if pixels > max_pixels {
+        anyhow::bail!(
+            "{what} image is {width}x{height} ({:.1} MP), over the {:.1} MP \
+             diff.max_megapixels limit — clip the story or raise the limit",
+            pixels as f64 / 1e6,
+            max_pixels as f64 / 1e6,
+        );
+    }
+    if let Some(image) = PngRows::open(bytes, max_pixels).and_then(PngRows::into_image) {
+        return Ok(image);
+    }
+    let mut reader = reader()?;
+    let mut limits = Limits::default();
+    limits.max_alloc = Some(max_pixels.saturating_mul(4).saturating_mul(2));
+    reader.limits(limits);
+    let mut decoder = reader
+        .into_decoder()
+        .with_context(|| format!("Failed to decode {what} image"))?;
+    let icc = decoder.icc_profile().ok().flatten();
+    // `into_rgba8` reuses the buffer when the image is already RGBA.
+    let mut image = DynamicImage::from_decoder(decoder)
+        .with_context(|| format!("Failed to decode {what} image"))?
+        .into_rgba8();
+    if let Some(icc) = icc {
+        color::to_srgb(&mut image, &icc);
+    }
+    Ok(image)
+}
+
+/// [`compare`] against an already decoded reference, for references shared
+/// by several snapshots. Skips the byte-identity check.
+pub fn compare_decoded(
+    mut left: RgbaImage,
+    current_png: &[u8],
+    masks: &[Mask],
+    options: &CompareOptions,
+) -> Result<CompareResult> {
+    let mut right = decode(current_png, "current", options.max_pixels)?;
+
+    apply_masks(&mut left, masks);
+    apply_masks(&mut right, masks);
+    select_channels(&mut left, options.channels);
+    select_channels(&mut right, options.channels);
+
+    let dimension_mismatch =
+        (left.dimensions() != right.dimensions()).then(|| SizeChange::detect(&left, &right));
+
+    // Low-pass both sides, so sub-pixel text rendering differences spread
+    // below the per-pixel threshold instead of counting as changed pixels.
+    // The sharp images are kept to draw the diff image from.
+    let mut sharp = None;
+    if let Some(sigma) = options.blur_sigma.filter(|s| *s > 0.0) {
+        let blurred = (imageops::blur(&left, sigma), imageops::blur(&right, sigma));
+        sharp = Some((left, right));
+        (left, right) = blurred;
+    }
+
+    // Phase 2: prefilter — same size and no pixel can exceed the YIQ threshold.
+    // Covers PNG encoder nondeterminism without a full YIQ pass.
+    if dimension_mismatch.is_none() && within_prefilter_tolerance(&left, &right) {
+        return Ok(CompareResult {
+            is_match: true,
+            diff_pixels: 0,
+            total_pixels: (left.width() as u64) * (left.height() as u64),
+            score: 0.0,
+            // Within the prefilter tolerance counts as identical.
+            ssim: options.ssim.then_some(1.0),
+            flip: options.flip.then_some(0.0),
+            diff_image: None,
+            dimension_mismatch: None,
+            regions: Vec::new(),
+        });
+    }
+
+    // Put both images on one canvas, aligned on the edges they share. The
+    // diff image outlines both extents (see `DiffRender::render`).
+    let align = |(left, right): (RgbaImage, RgbaImage)| match &dimension_mismatch {
+        Some(change) => change.align(&left, &right),
+        None => (left, right),
     };
+    let (left, right) = align((left, right));
+    let sharp = sharp.map(align);
 
     let total_pixels = (left.width() as u64) * (left.height() as u64);
+    let ssim = options.ssim.then(|| ssim::mean_ssim(&left, &right));
+    let flip = options.flip.then(|| mean_flip(&left, &right));
 
+    // Styles drawing from the compared images need them after dify consumes them.
+    let originals = options.render.needs_images().then(|| match &sharp {
+        Some(sharp) => sharp.clone(),
+        None => (left.clone(), right.clone()),
+    });
+
+    // Phase 3: perceptual diff
     let output_base = Some(dify::cli::OutputImageBase::LeftImage);
     let block_out: Option<std::collections::HashSet<(u32, u32)>> = None;
 
+    #[cfg(test)]
+    tests::DIFY_RUNS.with(|n| n.set(n.get() + 1));
     match dify::diff::get_results(
         left,
         right,
         THRESHOLD,
         true, // detect anti-aliased
-        Some(0.1),
+        Some(DIFY_FADE_ALPHA),
         &output_base,
         &block_out,
     ) {
-        Some((diff_count, diff_image)) => {
+        Some((diff_count, mut diff_image)) => {
             let diff_pixels = diff_count.max(0) as u64;
+            let regions = changed_regions(&diff_image);
+            if let Some((sharp_left, _)) = &sharp {
+                redraw_base(&mut diff_image, sharp_left);
+            }
             let score = if total_pixels > 0 {
                 diff_pixels as f64 / total_pixels as f64
             } else {
@@ -87,8 +425,16 @@ pub fn compare(reference_png: &[u8], current_png: &[u8]) -> Result<CompareResult
                 diff_pixels,
                 total_pixels,
                 score,
-                diff_image: Some(diff_image),
+                ssim,
+                flip,
+                diff_image: Some(options.render.render(
+                    diff_image,
+                    originals.as_ref().map(|(l, r)| (l, r)),
+                    &regions,
+                    dimension_mismatch.as_ref(),
+                )),
                 dimension_mismatch,
+                regions: if diff_pixels > 0 { regions } else { Vec::new() },
             })
         }
         None => Ok(CompareResult {
@@ -96,23 +442,375 @@ pub fn compare(reference_png: &[u8], current_png: &[u8]) -> Result<CompareResult
             diff_pixels: 0,
             total_pixels,
             score: 0.0,
+            ssim,
+            flip,
             diff_image: None,
             dimension_mismatch,
+            regions: Vec::new(),
         }),
     }
 }
 
-/// Paste `src` onto a magenta canvas of `w x h`, anchored at top-left.
-fn pad_to(src: &RgbaImage, w: u32, h: u32) -> RgbaImage {
-    let mut canvas = RgbaImage::from_pixel(w, h, image::Rgba([255, 0, 255, 255]));
-    image::imageops::overlay(&mut canvas, src, 0, 0);
-    canvas
+#[cfg(feature = "flip")]
+fn mean_flip(left: &RgbaImage, right: &RgbaImage) -> f64 {
+    super::flip::mean_flip(left, right)
+}
+
+/// Unreachable: config validation rejects `diff.metric = "flip"` without
+/// the feature.
+#[cfg(not(feature = "flip"))]
+fn mean_flip(_: &RgbaImage, _: &RgbaImage) -> f64 {
+    0.0
+}
+
+/// The `reference` image with the pixels inside `regions` taken from
+/// `current`, as PNG: approves those regions of a diff and keeps the rest of
+/// the reference. Both images must have the same dimensions.
+pub fn composite(
+    reference: &[u8],
+    current: &[u8],
+    regions: &[Mask],
+    max_pixels: u64,
+) -> Result<Vec<u8>> {
+    let mut out = decode(reference, "reference", max_pixels)?;
+    let current = decode(current, "current", max_pixels)?;
+    if out.dimensions() != current.dimensions() {
+        anyhow::bail!(
+            "reference is {}x{} but current is {}x{}; approve the whole snapshot instead",
+            out.width(),
+            out.height(),
+            current.width(),
+            current.height()
+        );
+    }
+    let (w, h) = out.dimensions();
+    for r in regions {
+        let x1 = r.x.saturating_add(r.width).min(w);
+        let y1 = r.y.saturating_add(r.height).min(h);
+        for y in r.y.min(h)..y1 {
+            for x in r.x.min(w)..x1 {
+                out.put_pixel(x, y, *current.get_pixel(x, y));
+            }
+        }
+    }
+    let mut png = Vec::new();
+    out.write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)
+        .context("Failed to encode composite image")?;
+    Ok(png)
+}
+
+/// Fill each mask rect (clamped to the image) with opaque black.
+fn apply_masks(img: &mut RgbaImage, masks: &[Mask]) {
+    let stride = img.width() as usize * 4;
+    if stride == 0 {
+        return;
+    }
+    for (y, row) in img.chunks_exact_mut(stride).enumerate() {
+        mask_row(row, y as u32, masks);
+    }
+}
+
+/// [`apply_masks`] for row `y` of RGBA samples.
+fn mask_row(row: &mut [u8], y: u32, masks: &[Mask]) {
+    let w = (row.len() / 4) as u32;
+    for m in masks.iter().filter(|m| y >= m.y && y - m.y < m.height) {
+        let x0 = m.x.min(w) as usize;
+        let x1 = m.x.saturating_add(m.width).min(w) as usize;
+        for p in row[x0 * 4..x1 * 4].chunks_exact_mut(4) {
+            p.copy_from_slice(&[0, 0, 0, 255]);
+        }
+    }
+}
+
+/// Drop what `channels` leaves out of the comparison from RGBA samples:
+/// alpha (set opaque), and for `Luminance` also hue (pixels become gray at
+/// their luma).
+fn select_channels(samples: &mut [u8], channels: DiffChannels) {
+    match channels {
+        DiffChannels::Rgba => {}
+        DiffChannels::Rgb => samples.chunks_exact_mut(4).for_each(|p| p[3] = 255),
+        DiffChannels::Luminance => {
+            for p in samples.chunks_exact_mut(4) {
+                let [r, g, b] = [p[0], p[1], p[2]].map(f32::from);
+                let y = (0.299 * r + 0.587 * g + 0.114 * b).round() as u8;
+                p.copy_from_slice(&[y, y, y, 255]);
+            }
+        }
+    }
+}
+
+/// True when both sample runs have equal alpha everywhere and every RGB
+/// channel differs by at most `PREFILTER_MAX_CHANNEL_DELTA`. Such pairs are
+/// guaranteed to produce zero diff pixels in dify, so the YIQ pass is skipped.
+fn within_prefilter_tolerance(left: &[u8], right: &[u8]) -> bool {
+    left == right
+        || left
+            .chunks_exact(4)
+            .zip(right.chunks_exact(4))
+            .all(|(a, b)| !pixel_changed(a, b))
+}
+
+/// The prefilter without holding either image: decode both PNGs a row at a
+/// time, mask and reduce each row like [`compare_decoded`] does, and stop at
+/// the first row outside the tolerance. Returns the pixel count when the
+/// whole pair is within it; `None` sends the pair down the full path, which
+/// also reports any decode error.
+///
+/// Blur needs neighbouring rows, so blurred comparisons always take the full
+/// path.
+fn streamed_prefilter(
+    reference: &[u8],
+    current: &[u8],
+    masks: &[Mask],
+    options: &CompareOptions,
+) -> Option<u64> {
+    if options.blur_sigma.is_some_and(|s| s > 0.0) {
+        return None;
+    }
+    let mut left = PngRows::open(reference, options.max_pixels)?;
+    let mut right = PngRows::open(current, options.max_pixels)?;
+    let (w, h) = left.dimensions;
+    if right.dimensions != (w, h) {
+        return None;
+    }
+    for y in 0..h {
+        let (a, b) = (left.next_row()?, right.next_row()?);
+        for row in [&mut *a, &mut *b] {
+            mask_row(row, y, masks);
+            select_channels(row, options.channels);
+        }
+        if !within_prefilter_tolerance(a, b) {
+            return None;
+        }
+    }
+    Some(u64::from(w) * u64::from(h))
+}
+
+/// A PNG decoded one RGBA row at a time, for [`streamed_prefilter`].
+struct PngRows<'a> {
+    reader: png::Reader<Cursor<&'a [u8]>>,
+    color: png::ColorType,
+    dimensions: (u32, u32),
+    row: Vec<u8>,
+}
+
+impl<'a> PngRows<'a> {
+    /// `None` unless `bytes` is a non-interlaced 8-bit PNG within
+    /// `max_pixels` and without an ICC profile: the images whose rows come
+    /// out exactly as [`decode`] would produce them.
+    fn open(bytes: &'a [u8], max_pixels: u64) -> Option<Self> {
+        let mut decoder = png::Decoder::new(Cursor::new(bytes));
+        decoder.set_transformations(png::Transformations::EXPAND);
+        let reader = decoder.read_info().ok()?;
+        let info = reader.info();
+        let (width, height) = (info.width, info.height);
+        if info.interlaced
+            || info.icc_profile.is_some()
+            || u64::from(width) * u64::from(height) > max_pixels
+        {
+            return None;
+        }
+        let (color, depth) = reader.output_color_type();
+        if depth != png::BitDepth::Eight || color == png::ColorType::Indexed {
+            return None;
+        }
+        Some(Self {
+            reader,
+            color,
+            dimensions: (width, height),
+            row: vec![0; width as usize * 4],
+        })
+    }
+
+    /// All remaining rows as one image; `None` on a decode error.
+    fn into_image(mut self) -> Option<RgbaImage> {
+        let (w, h) = self.dimensions;
+        let len = w as usize * h as usize * 4;
+        let mut samples = Vec::with_capacity(len);
+        while samples.len() < len {
+            samples.extend_from_slice(self.next_row()?);
+        }
+        RgbaImage::from_raw(w, h, samples)
+    }
+
+    /// The next row as RGBA samples; `None` past the end or on a decode error.
+    fn next_row(&mut self) -> Option<&mut [u8]> {
+        let row = self.reader.next_row().ok()??;
+        let pixels = self.row.chunks_exact_mut(4);
+        match self.color {
+            png::ColorType::Rgba => self.row.copy_from_slice(row.data()),
+            png::ColorType::Rgb => {
+                for (p, s) in pixels.zip(row.data().chunks_exact(3)) {
+                    p.copy_from_slice(&[s[0], s[1], s[2], 255]);
+                }
+            }
+            png::ColorType::GrayscaleAlpha => {
+                for (p, s) in pixels.zip(row.data().chunks_exact(2)) {
+                    p.copy_from_slice(&[s[0], s[0], s[0], s[1]]);
+                }
+            }
+            png::ColorType::Grayscale => {
+                for (p, &s) in pixels.zip(row.data()) {
+                    p.copy_from_slice(&[s, s, s, 255]);
+                }
+            }
+            png::ColorType::Indexed => return None,
+        }
+        Some(&mut self.row)
+    }
+}
+
+/// True when a pixel pair falls outside the prefilter tolerance.
+fn pixel_changed(a: &[u8], b: &[u8]) -> bool {
+    a[3] != b[3]
+        || a[..3]
+            .iter()
+            .zip(&b[..3])
+            .any(|(x, y)| x.abs_diff(*y) > PREFILTER_MAX_CHANNEL_DELTA)
+}
+
+/// Tight pixel bounds accumulated while grouping changed pixels.
+#[derive(Clone, Copy)]
+struct Bounds {
+    x0: u32,
+    y0: u32,
+    x1: u32,
+    y1: u32,
+    pixels: u64,
+}
+
+impl Bounds {
+    fn point(x: u32, y: u32) -> Self {
+        Self {
+            x0: x,
+            y0: y,
+            x1: x,
+            y1: y,
+            pixels: 1,
+        }
+    }
+
+    fn merge(&mut self, other: &Bounds) {
+        self.x0 = self.x0.min(other.x0);
+        self.y0 = self.y0.min(other.y0);
+        self.x1 = self.x1.max(other.x1);
+        self.y1 = self.y1.max(other.y1);
+        self.pixels += other.pixels;
+    }
+}
+
+/// Redraw the faded base of dify's `overlay` from `base`, keeping dify's
+/// marks, so a blurred comparison's diff image shows the captured pixels.
+/// The base is the only gray in the overlay; marks are colored.
+fn redraw_base(overlay: &mut RgbaImage, base: &RgbaImage) {
+    for (o, b) in overlay.pixels_mut().zip(base.pixels()) {
+        if o[0] == o[1] && o[1] == o[2] {
+            *o = faded_gray(b);
+        }
+    }
+}
+
+/// A pixel as dify draws it in the overlay base: its luma over white,
+/// faded to `DIFY_FADE_ALPHA`.
+fn faded_gray(p: &Rgba<u8>) -> Rgba<u8> {
+    let alpha = f32::from(p[3]) / 255.0;
+    let [r, g, b] = [p[0], p[1], p[2]].map(|c| 255.0 + (f32::from(c) - 255.0) * alpha);
+    let y = 0.298_895_3 * r + 0.586_622_5 * g + 0.114_482_2 * b;
+    let v = (255.0 + (y - 255.0) * DIFY_FADE_ALPHA).round() as u8;
+    Rgba([v, v, v, 255])
+}
+
+/// Bounding boxes of the pixels dify marked as changed in its `overlay`.
+///
+/// Changed pixels are bucketed into `REGION_CELL_SIZE` cells; 8-connected
+/// groups of dirty cells form one region, whose box is then tightened to the
+/// actual changed pixels.
+fn changed_regions(overlay: &RgbaImage) -> Vec<Region> {
+    let (w, h) = overlay.dimensions();
+    let cols = w.div_ceil(REGION_CELL_SIZE) as usize;
+    let rows = h.div_ceil(REGION_CELL_SIZE) as usize;
+
+    let mut cells: Vec<Option<Bounds>> = vec![None; cols * rows];
+    for (x, y, p) in overlay.enumerate_pixels() {
+        if *p != DIFY_CHANGED {
+            continue;
+        }
+        let idx = (y / REGION_CELL_SIZE) as usize * cols + (x / REGION_CELL_SIZE) as usize;
+        let point = Bounds::point(x, y);
+        match &mut cells[idx] {
+            Some(b) => b.merge(&point),
+            cell => *cell = Some(point),
+        }
+    }
+
+    let mut seen = vec![false; cells.len()];
+    let mut regions = Vec::new();
+    let mut stack = Vec::new();
+    for start in 0..cells.len() {
+        let Some(first) = cells[start] else {
+            continue;
+        };
+        if seen[start] {
+            continue;
+        }
+        seen[start] = true;
+        stack.push(start);
+        let mut bounds = Bounds { pixels: 0, ..first };
+        while let Some(idx) = stack.pop() {
+            if let Some(cell) = &cells[idx] {
+                bounds.merge(cell);
+            }
+
+            let (col, row) = ((idx % cols) as isize, (idx / cols) as isize);
+            for dy in -1..=1 {
+                for dx in -1..=1 {
+                    let (c, r) = (col + dx, row + dy);
+                    if c < 0 || r < 0 || c >= cols as isize || r >= rows as isize {
+                        continue;
+                    }
+                    let n_idx = r as usize * cols + c as usize;
+                    if !seen[n_idx] && cells[n_idx].is_some() {
+                        seen[n_idx] = true;
+                        stack.push(n_idx);
+                    }
+                }
+            }
+        }
+        regions.push(Region {
+            x: bounds.x0,
+            y: bounds.y0,
+            width: bounds.x1 - bounds.x0 + 1,
+            height: bounds.y1 - bounds.y0 + 1,
+            pixels: bounds.pixels,
+        });
+    }
+
+    regions.sort_by_key(|r| std::cmp::Reverse(r.pixels));
+    regions.truncate(MAX_REGIONS);
+    regions
 }
 
 #[cfg(test)]
 mod tests {
+    use std::cell::Cell;
+
     use super::*;
-    use image::Rgba;
+
+    thread_local! {
+        /// Calls of [`decode`] on this test's thread.
+        pub(super) static FULL_DECODES: Cell<usize> = const { Cell::new(0) };
+        /// Calls into dify on this test's thread.
+        pub(super) static DIFY_RUNS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    const OPTIONS: CompareOptions = CompareOptions {
+        max_pixels: u64::MAX,
+        ssim: false,
+        flip: false,
+        render: DiffRender::DEFAULT,
+        blur_sigma: None,
+        channels: DiffChannels::Rgba,
+    };
 
     /// Create a small test PNG with a solid fill.
     fn solid_png(w: u32, h: u32, color: Rgba<u8>) -> Vec<u8> {
@@ -143,7 +841,7 @@ mod tests {
     #[test]
     fn identical_bytes_skip_dify() {
         let png = solid_png(100, 100, Rgba([200, 200, 200, 255]));
-        let r = compare(&png, &png).unwrap();
+        let r = compare(&png, &png, &[], &OPTIONS).unwrap();
         assert!(r.is_match);
         assert_eq!(r.diff_pixels, 0);
         assert_eq!(r.total_pixels, 0); // memcmp path sets 0
@@ -151,13 +849,227 @@ mod tests {
         assert!(r.dimension_mismatch.is_none());
     }
 
+    // -- prefilter --
+
+    #[test]
+    fn pixel_identical_skips_dify() {
+        let a = solid_png(40, 40, Rgba([10, 20, 30, 255]));
+        let img = image::load_from_memory(&a).unwrap().to_rgba8();
+        let mut b = Vec::new();
+        img.write_to(&mut std::io::Cursor::new(&mut b), image::ImageFormat::Bmp)
+            .unwrap();
+        let r = compare(&a, &b, &[], &OPTIONS).unwrap();
+        assert!(r.is_match);
+        assert_eq!(r.total_pixels, 1600);
+        assert!(r.diff_image.is_none());
+        assert!(r.regions.is_empty());
+        // BMP can't be streamed: both sides are decoded, but dify never runs.
+        assert_eq!(FULL_DECODES.get(), 2);
+        assert_eq!(DIFY_RUNS.get(), 0);
+    }
+
+    #[test]
+    fn pngs_within_tolerance_match_without_a_full_decode() {
+        let a = solid_png(40, 40, Rgba([10, 20, 30, 255]));
+        let mut img = RgbaImage::from_pixel(40, 40, Rgba([10, 20, 30, 255]));
+        img.put_pixel(5, 5, Rgba([20, 30, 40, 255]));
+        // Outside the tolerance, but masked.
+        img.put_pixel(30, 30, Rgba([255, 0, 0, 255]));
+        let mut b = Vec::new();
+        image::DynamicImage::ImageRgba8(img)
+            .into_rgb8()
+            .write_to(&mut std::io::Cursor::new(&mut b), image::ImageFormat::Png)
+            .unwrap();
+        let mask = Mask {
+            x: 28,
+            y: 28,
+            width: 4,
+            height: 4,
+        };
+        let r = compare(&a, &b, &[mask], &OPTIONS).unwrap();
+        assert!(r.is_match);
+        assert_eq!(r.total_pixels, 1600);
+        assert!(r.diff_image.is_none());
+        assert!(r.regions.is_empty());
+        assert_eq!((FULL_DECODES.get(), DIFY_RUNS.get()), (0, 0));
+
+        // Without the mask the streamed pass gives up and dify runs.
+        let r = compare(&a, &b, &[], &OPTIONS).unwrap();
+        assert!(!r.is_match);
+        assert_eq!((FULL_DECODES.get(), DIFY_RUNS.get()), (2, 1));
+    }
+
+    #[test]
+    fn streamed_png_rows_decode_like_image() {
+        let mut img = RgbaImage::new(5, 3);
+        for (x, y, p) in img.enumerate_pixels_mut() {
+            *p = Rgba([x as u8 * 50, y as u8 * 80, 7, 100 + x as u8]);
+        }
+        let rgba = image::DynamicImage::ImageRgba8(img);
+        for img in [rgba.clone(), rgba.to_rgb8().into(), rgba.to_luma8().into()] {
+            let mut png = Vec::new();
+            img.write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+                .unwrap();
+            let streamed = PngRows::open(&png, u64::MAX)
+                .and_then(PngRows::into_image)
+                .unwrap();
+            assert_eq!(streamed, img.to_rgba8());
+        }
+    }
+
+    #[test]
+    fn prefilter_rejects_alpha_change() {
+        let a = RgbaImage::from_pixel(4, 4, Rgba([100, 100, 100, 255]));
+        let mut b = a.clone();
+        b.put_pixel(1, 1, Rgba([100, 100, 100, 254]));
+        assert!(!within_prefilter_tolerance(&a, &b));
+    }
+
+    #[test]
+    fn prefilter_rejects_large_channel_delta() {
+        let a = RgbaImage::from_pixel(4, 4, Rgba([100, 100, 100, 255]));
+        let mut b = a.clone();
+        b.put_pixel(
+            2,
+            3,
+            Rgba([100, 100, 100 + PREFILTER_MAX_CHANNEL_DELTA + 1, 255]),
+        );
+        assert!(!within_prefilter_tolerance(&a, &b));
+        b.put_pixel(
+            2,
+            3,
+            Rgba([100, 100, 100 + PREFILTER_MAX_CHANNEL_DELTA, 255]),
+        );
+        assert!(within_prefilter_tolerance(&a, &b));
+    }
+
+    // -- regions --
+
+    #[test]
+    fn regions_group_nearby_pixels() {
+        let mut overlay = RgbaImage::from_pixel(100, 100, Rgba([200, 200, 200, 255]));
+        // Two pixels a few px apart form one region; a distant block forms another.
+        overlay.put_pixel(10, 10, DIFY_CHANGED);
+        overlay.put_pixel(13, 12, DIFY_CHANGED);
+        for y in 60..70 {
+            for x in 70..90 {
+                overlay.put_pixel(x, y, DIFY_CHANGED);
+            }
+        }
+        let regions = changed_regions(&overlay);
+        assert_eq!(
+            regions,
+            vec![
+                Region {
+                    x: 70,
+                    y: 60,
+                    width: 20,
+                    height: 10,
+                    pixels: 200,
+                },
+                Region {
+                    x: 10,
+                    y: 10,
+                    width: 4,
+                    height: 3,
+                    pixels: 2,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn regions_cover_only_what_dify_counts() {
+        let mut img = RgbaImage::from_pixel(64, 64, Rgba([100, 100, 100, 255]));
+        // Outside the prefilter tolerance, but below dify's threshold.
+        img.put_pixel(
+            5,
+            5,
+            Rgba([100, 100, 100 + PREFILTER_MAX_CHANNEL_DELTA + 1, 255]),
+        );
+        for y in 40..44 {
+            for x in 40..44 {
+                img.put_pixel(x, y, Rgba([255, 0, 0, 255]));
+            }
+        }
+        let mut b = Vec::new();
+        img.write_to(&mut std::io::Cursor::new(&mut b), image::ImageFormat::Png)
+            .unwrap();
+        let a = solid_png(64, 64, Rgba([100, 100, 100, 255]));
+        let r = compare(&a, &b, &[], &OPTIONS).unwrap();
+        assert_eq!(r.diff_pixels, 16);
+        assert_eq!(
+            r.regions,
+            vec![Region {
+                x: 40,
+                y: 40,
+                width: 4,
+                height: 4,
+                pixels: 16,
+            }]
+        );
+    }
+
+    // -- masks --
+
+    #[test]
+    fn masked_change_is_match() {
+        let reference = solid_png(40, 40, Rgba([200, 200, 200, 255]));
+        let mut img = image::load_from_memory(&reference).unwrap().to_rgba8();
+        for y in 5..10 {
+            for x in 5..15 {
+                img.put_pixel(x, y, Rgba([0, 0, 255, 255]));
+            }
+        }
+        let mut current = Vec::new();
+        img.write_to(
+            &mut std::io::Cursor::new(&mut current),
+            image::ImageFormat::Png,
+        )
+        .unwrap();
+
+        assert!(
+            !compare(&reference, &current, &[], &OPTIONS)
+                .unwrap()
+                .is_match
+        );
+        let mask = Mask {
+            x: 5,
+            y: 5,
+            width: 10,
+            height: 5,
+        };
+        assert!(
+            compare(&reference, &current, &[mask], &OPTIONS)
+                .unwrap()
+                .is_match
+        );
+    }
+
+    #[test]
+    fn scaled_masks_cover_whole_pixels() {
+        let mask = Mask {
+            x: 3,
+            y: 5,
+            width: 10,
+            height: 1,
+        };
+        let scaled = |factor| {
+            let m = mask.scaled(factor);
+            (m.x, m.y, m.width, m.height)
+        };
+        assert_eq!(scaled(2.0), (6, 10, 20, 2));
+        assert_eq!(scaled(1.5), (4, 7, 16, 2));
+        assert_eq!(scaled(0.5), (1, 2, 6, 1));
+    }
+
     // -- dify phase --
 
     #[test]
     fn pixel_diffs_detected() {
         let reference = solid_png(100, 100, Rgba([200, 200, 200, 255]));
         let current = with_pixel_diffs(&reference, 50);
-        let r = compare(&reference, &current).unwrap();
+        let r = compare(&reference, &current, &[], &OPTIONS).unwrap();
         assert!(!r.is_match);
         assert!(r.diff_pixels > 0);
         assert!(r.score > 0.0);
@@ -176,7 +1088,7 @@ mod tests {
         let mut b = Vec::new();
         img.write_to(&mut std::io::Cursor::new(&mut b), image::ImageFormat::Png)
             .unwrap();
-        let r = compare(&a, &b).unwrap();
+        let r = compare(&a, &b, &[], &OPTIONS).unwrap();
         // dify should detect 0 diff pixels (below threshold).
         assert_eq!(r.diff_pixels, 0);
     }
@@ -187,7 +1099,7 @@ mod tests {
     fn score_is_ratio_of_diff_to_total() {
         let reference = solid_png(100, 100, Rgba([200, 200, 200, 255]));
         let current = with_pixel_diffs(&reference, 20);
-        let r = compare(&reference, &current).unwrap();
+        let r = compare(&reference, &current, &[], &OPTIONS).unwrap();
         let expected = r.diff_pixels as f64 / r.total_pixels as f64;
         assert!((r.score - expected).abs() < 1e-9);
     }
@@ -197,37 +1109,194 @@ mod tests {
         let a = solid_png(50, 50, Rgba([128, 128, 128, 255]));
         let b = solid_png(50, 50, Rgba([128, 128, 128, 255]));
         // Bytes differ (separate encoding) but pixels are identical.
-        let r = compare(&a, &b).unwrap();
+        let r = compare(&a, &b, &[], &OPTIONS).unwrap();
         assert_eq!(r.score, 0.0);
     }
 
     // -- dimension mismatch + padding --
 
+    #[test]
+    fn oversized_image_is_refused() {
+        let a = solid_png(100, 100, Rgba([255, 255, 255, 255]));
+        let b = solid_png(100, 100, Rgba([0, 0, 0, 255]));
+        let Err(err) = compare(
+            &a,
+            &b,
+            &[],
+            &CompareOptions {
+                max_pixels: 9_999,
+                ..OPTIONS
+            },
+        ) else {
+            panic!("expected the size limit to be enforced");
+        };
+        assert!(format!("{err:#}").contains("100x100"));
+        assert!(
+            compare(
+                &a,
+                &b,
+                &[],
+                &CompareOptions {
+                    max_pixels: 10_000,
+                    ..OPTIONS
+                },
+            )
+            .is_ok()
+        );
+    }
+
+    #[test]
+    fn channels_ignore_alpha_or_hue() {
+        let a = solid_png(10, 10, Rgba([40, 80, 120, 255]));
+        let translucent = solid_png(10, 10, Rgba([40, 80, 120, 200]));
+        assert!(!compare(&a, &translucent, &[], &OPTIONS).unwrap().is_match);
+        let rgb = CompareOptions {
+            channels: DiffChannels::Rgb,
+            ..OPTIONS
+        };
+        assert!(compare(&a, &translucent, &[], &rgb).unwrap().is_match);
+
+        // Same luma, different hue.
+        let gray = solid_png(10, 10, Rgba([100, 100, 100, 255]));
+        let tinted = solid_png(10, 10, Rgba([160, 70, 100, 255]));
+        assert!(!compare(&gray, &tinted, &[], &rgb).unwrap().is_match);
+        let luminance = CompareOptions {
+            channels: DiffChannels::Luminance,
+            ..OPTIONS
+        };
+        assert!(compare(&gray, &tinted, &[], &luminance).unwrap().is_match);
+    }
+
+    #[test]
+    fn blur_absorbs_single_pixel_noise() {
+        let a = solid_png(40, 40, Rgba([255, 255, 255, 255]));
+        let mut dot = RgbaImage::from_pixel(40, 40, Rgba([255, 255, 255, 255]));
+        dot.put_pixel(20, 20, Rgba([160, 160, 160, 255]));
+        let mut b = Vec::new();
+        dot.write_to(&mut std::io::Cursor::new(&mut b), image::ImageFormat::Png)
+            .unwrap();
+        assert!(!compare(&a, &b, &[], &OPTIONS).unwrap().is_match);
+        let blurred = CompareOptions {
+            blur_sigma: Some(1.5),
+            ..OPTIONS
+        };
+        assert!(compare(&a, &b, &[], &blurred).unwrap().is_match);
+    }
+
+    #[test]
+    fn blurred_diff_image_is_drawn_from_the_sharp_images() {
+        let mut sharp = RgbaImage::from_pixel(40, 40, Rgba([255, 255, 255, 255]));
+        for y in 0..40 {
+            sharp.put_pixel(10, y, Rgba([0, 0, 0, 255]));
+        }
+        let mut changed = sharp.clone();
+        for y in 30..36 {
+            for x in 30..36 {
+                changed.put_pixel(x, y, Rgba([255, 0, 0, 255]));
+            }
+        }
+        let encode = |img: &RgbaImage| {
+            let mut buf = Vec::new();
+            img.write_to(&mut std::io::Cursor::new(&mut buf), image::ImageFormat::Png)
+                .unwrap();
+            buf
+        };
+        let blurred = CompareOptions {
+            blur_sigma: Some(1.0),
+            ..OPTIONS
+        };
+        let r = compare(&encode(&sharp), &encode(&changed), &[], &blurred).unwrap();
+        let diff = r.diff_image.unwrap();
+        // The line keeps its sharp value instead of its blurred neighbourhood.
+        assert_eq!(*diff.get_pixel(10, 5), faded_gray(&Rgba([0, 0, 0, 255])));
+        assert_eq!(
+            *diff.get_pixel(11, 5),
+            faded_gray(&Rgba([255, 255, 255, 255]))
+        );
+    }
+
     #[test]
     fn dimension_mismatch_detected() {
         let a = solid_png(100, 100, Rgba([200, 200, 200, 255]));
         let b = solid_png(100, 120, Rgba([200, 200, 200, 255]));
-        let r = compare(&a, &b).unwrap();
-        assert_eq!(r.dimension_mismatch, Some((100, 100, 100, 120)));
+        let r = compare(&a, &b, &[], &OPTIONS).unwrap();
+        assert_eq!(
+            r.dimension_mismatch.map(|m| m.dims()),
+            Some([100, 100, 100, 120])
+        );
     }
 
     #[test]
-    fn dimension_mismatch_pads_with_magenta() {
+    fn dimension_mismatch_counts_the_added_strip() {
         let a = solid_png(10, 10, Rgba([200, 200, 200, 255]));
         let b = solid_png(10, 12, Rgba([200, 200, 200, 255]));
-        let r = compare(&a, &b).unwrap();
-        // The 2-row padding area (magenta vs grey) produces diff pixels.
+        let r = compare(&a, &b, &[], &OPTIONS).unwrap();
+        // The 2 added rows (transparent vs grey) produce diff pixels.
         assert!(r.diff_pixels > 0, "padding should cause diff pixels");
         // Total canvas is 10x12 = 120 pixels.
         assert_eq!(r.total_pixels, 120);
+        assert_eq!(
+            r.dimension_mismatch.unwrap().to_string(),
+            "+2px height at bottom"
+        );
+    }
+
+    #[test]
+    fn growth_at_the_top_aligns_on_the_bottom() {
+        let stripes = |h: u32| {
+            RgbaImage::from_fn(10, h, |_, y| {
+                let v = ((h - y) * 20) as u8;
+                Rgba([v, v, v, 255])
+            })
+        };
+        let encode = |img: RgbaImage| {
+            let mut buf = Vec::new();
+            img.write_to(&mut std::io::Cursor::new(&mut buf), image::ImageFormat::Png)
+                .unwrap();
+            buf
+        };
+        // Rows are numbered from the bottom, so `b` is `a` with 3 rows on top.
+        let (a, b) = (encode(stripes(8)), encode(stripes(11)));
+        let r = compare(&a, &b, &[], &OPTIONS).unwrap();
+        let change = r.dimension_mismatch.unwrap();
+        assert_eq!(change.to_string(), "+3px height at top");
+        assert!(r.diff_pixels <= 30, "only the new rows differ");
+        let [reference, current] = change.extents();
+        assert_eq!((reference.y, current.y), (3, 0));
+    }
+
+    #[test]
+    fn composite_takes_only_the_regions_from_current() {
+        let grey = Rgba([200, 200, 200, 255]);
+        let red = Rgba([255, 0, 0, 255]);
+        let reference = solid_png(10, 10, grey);
+        let current = solid_png(10, 10, red);
+        let region = Mask {
+            x: 2,
+            y: 3,
+            width: 4,
+            height: 2,
+        };
+        let png = composite(&reference, &current, &[region], u64::MAX).unwrap();
+        let img = image::load_from_memory(&png).unwrap().to_rgba8();
+        assert_eq!(*img.get_pixel(2, 3), red);
+        assert_eq!(*img.get_pixel(5, 4), red);
+        assert_eq!(*img.get_pixel(6, 4), grey);
+        assert_eq!(*img.get_pixel(2, 5), grey);
+
+        let taller = solid_png(10, 12, red);
+        assert!(composite(&reference, &taller, &[region], u64::MAX).is_err());
     }
 
     #[test]
     fn width_mismatch_reported() {
         let a = solid_png(100, 50, Rgba([200, 200, 200, 255]));
         let b = solid_png(110, 50, Rgba([200, 200, 200, 255]));
-        let r = compare(&a, &b).unwrap();
-        assert_eq!(r.dimension_mismatch, Some((100, 50, 110, 50)));
+        let r = compare(&a, &b, &[], &OPTIONS).unwrap();
+        assert_eq!(
+            r.dimension_mismatch.map(|m| m.to_string()),
+            Some("+10px width at right".to_string())
+        );
         assert!(r.diff_pixels > 0);
     }
 }
@@ -0,0 +1,318 @@
+//! LDR-FLIP (Andersson et al., "FLIP: A Difference Evaluator for Alternating
+//! Images", 2020): a per-pixel perceptual error in [0, 1] that models how
+//! visible a difference is to someone flipping between the two images.
+
+use image::RgbaImage;
+
+/// Observer setup: pixels per degree of visual angle. 67 corresponds to a
+/// 0.7 m viewing distance from a 24" 4K monitor (the paper's default).
+const PIXELS_PER_DEGREE: f32 = 67.0;
+
+/// Color error exponent.
+const QC: f32 = 0.7;
+/// Feature error exponent.
+const QF: f32 = 0.5;
+/// Color error remapping: errors below `PC * cmax` are compressed to `[0, PT]`.
+const PC: f32 = 0.4;
+const PT: f32 = 0.95;
+/// Feature detector width, in degrees.
+const GW: f32 = 0.082;
+
+/// Contrast sensitivity as a sum of two Gaussians per opponent channel:
+/// `(a1, b1, a2, b2)` for achromatic, red-green and blue-yellow.
+const CSF: [(f32, f32, f32, f32); 3] = [
+    (1.0, 0.0047, 0.0, 1e-5),
+    (1.0, 0.0053, 0.0, 1e-5),
+    (34.1, 0.04, 13.5, 0.025),
+];
+
+/// D65 reference white in XYZ.
+const WHITE: [f32; 3] = [0.950_428_5, 1.0, 1.088_9];
+
+/// Mean FLIP error of two same-sized images: 0.0 identical, 1.0 maximal.
+pub fn mean_flip(reference: &RgbaImage, test: &RgbaImage) -> f64 {
+    debug_assert_eq!(reference.dimensions(), test.dimensions());
+    let (width, height) = reference.dimensions();
+    if width == 0 || height == 0 {
+        return 0.0;
+    }
+    let (w, h) = (width as usize, height as usize);
+
+    let reference = to_ycxcz(reference);
+    let test = to_ycxcz(test);
+    let color = color_error(&reference, &test, w, h);
+    let feature = feature_error(&reference, &test, w, h);
+
+    let total: f64 = color
+        .iter()
+        .zip(&feature)
+        .map(|(&c, &f)| f64::from(c.powf(1.0 - f)))
+        .sum();
+    total / (w * h) as f64
+}
+
+/// Per-pixel color error: CSF-filtered images compared in Hunt-adjusted
+/// L*a*b* with the HyAB distance, remapped to [0, 1].
+fn color_error(reference: &Planes, test: &Planes, w: usize, h: usize) -> Vec<f32> {
+    let reference = to_hunt_lab(&csf_filter(reference, w, h));
+    let test = to_hunt_lab(&csf_filter(test, w, h));
+
+    let green = hunt(linear_rgb_to_lab([0.0, 1.0, 0.0]));
+    let blue = hunt(linear_rgb_to_lab([0.0, 0.0, 1.0]));
+    let cmax = hyab(green, blue).powf(QC);
+
+    reference
+        .iter()
+        .zip(&test)
+        .map(|(&r, &t)| {
+            let error = hyab(r, t).powf(QC);
+            if error < PC * cmax {
+                error * PT / (PC * cmax)
+            } else {
+                PT + (error - PC * cmax) / (cmax - PC * cmax) * (1.0 - PT)
+            }
+        })
+        .collect()
+}
+
+/// Per-pixel feature error: the larger change in edge or point response
+/// of the achromatic channel, in [0, 1].
+fn feature_error(reference: &Planes, test: &Planes, w: usize, h: usize) -> Vec<f32> {
+    let sigma = 0.5 * GW * PIXELS_PER_DEGREE;
+    let radius = (3.0 * sigma).ceil() as i32;
+    let offsets: Vec<f32> = (-radius..=radius).map(|x| x as f32).collect();
+    let gauss: Vec<f32> = offsets
+        .iter()
+        .map(|x| (-x * x / (2.0 * sigma * sigma)).exp())
+        .collect();
+    let edge: Vec<f32> =
+        normalize_signed(offsets.iter().zip(&gauss).map(|(x, g)| -x * g).collect());
+    let point: Vec<f32> = normalize_signed(
+        offsets
+            .iter()
+            .zip(&gauss)
+            .map(|(x, g)| (x * x / (sigma * sigma) - 1.0) * g)
+            .collect(),
+    );
+    let smooth = normalize(gauss);
+
+    // Features are detected on the achromatic channel scaled to [0, 1].
+    let lightness = |p: &Planes| -> Vec<f32> { p.y.iter().map(|&y| (y + 16.0) / 116.0).collect() };
+    let responses = |y: &[f32]| {
+        let dx = convolve(&convolve_rows(y, w, h, &edge), w, h, &smooth);
+        let dy = convolve(&convolve_rows(y, w, h, &smooth), w, h, &edge);
+        let dxx = convolve(&convolve_rows(y, w, h, &point), w, h, &smooth);
+        let dyy = convolve(&convolve_rows(y, w, h, &smooth), w, h, &point);
+        let edges: Vec<f32> = dx.iter().zip(&dy).map(|(x, y)| x.hypot(*y)).collect();
+        let points: Vec<f32> = dxx.iter().zip(&dyy).map(|(x, y)| x.hypot(*y)).collect();
+        (edges, points)
+    };
+    let (edges_r, points_r) = responses(&lightness(reference));
+    let (edges_t, points_t) = responses(&lightness(test));
+
+    (0..w * h)
+        .map(|i| {
+            let edge = (edges_r[i] - edges_t[i]).abs();
+            let point = (points_r[i] - points_t[i]).abs();
+            (edge.max(point) / std::f32::consts::SQRT_2)
+                .powf(QF)
+                .min(1.0)
+        })
+        .collect()
+}
+
+/// Opponent color planes (Y, Cx, Cz) of linear RGB.
+struct Planes {
+    y: Vec<f32>,
+    cx: Vec<f32>,
+    cz: Vec<f32>,
+}
+
+fn to_ycxcz(img: &RgbaImage) -> Planes {
+    let mut planes = Planes {
+        y: Vec::with_capacity(img.len() / 4),
+        cx: Vec::with_capacity(img.len() / 4),
+        cz: Vec::with_capacity(img.len() / 4),
+    };
+    for p in img.pixels() {
+        // Composite over white, like the page background.
+        let alpha = f32::from(p.0[3]) / 255.0;
+        let rgb =
+            [0, 1, 2].map(|c| srgb_to_linear(f32::from(p.0[c]) / 255.0) * alpha + 1.0 - alpha);
+        let [x, y, z] = linear_rgb_to_xyz(rgb);
+        let (fx, fy, fz) = (x / WHITE[0], y / WHITE[1], z / WHITE[2]);
+        planes.y.push(116.0 * fy - 16.0);
+        planes.cx.push(500.0 * (fx - fy));
+        planes.cz.push(200.0 * (fy - fz));
+    }
+    planes
+}
+
+/// Spatially filter each opponent channel with its contrast sensitivity
+/// function, then convert back to clamped linear RGB.
+fn csf_filter(planes: &Planes, w: usize, h: usize) -> Vec<[f32; 3]> {
+    let filtered: Vec<Vec<f32>> = [&planes.y, &planes.cx, &planes.cz]
+        .iter()
+        .zip(CSF)
+        .map(|(plane, (a1, b1, a2, b2))| {
+            let max_b = b1.max(b2);
+            let radius =
+                (3.0 * (max_b / (2.0 * std::f32::consts::PI.powi(2))).sqrt() * PIXELS_PER_DEGREE)
+                    .ceil() as i32;
+            let kernel = normalize(
+                (-radius..=radius)
+                    .map(|px| {
+                        let x = px as f32 / PIXELS_PER_DEGREE;
+                        let pi = std::f32::consts::PI;
+                        let g =
+                            |a: f32, b: f32| a * (pi / b).sqrt() * (-(pi * pi) * x * x / b).exp();
+                        g(a1, b1) + g(a2, b2)
+                    })
+                    .collect(),
+            );
+            convolve(&convolve_rows(plane, w, h, &kernel), w, h, &kernel)
+        })
+        .collect();
+
+    (0..w * h)
+        .map(|i| {
+            let (l, cx, cz) = (filtered[0][i], filtered[1][i], filtered[2][i]);
+            let fy = (l + 16.0) / 116.0;
+            let xyz = [
+                WHITE[0] * (fy + cx / 500.0),
+                WHITE[1] * fy,
+                WHITE[2] * (fy - cz / 200.0),
+            ];
+            xyz_to_linear_rgb(xyz).map(|c| c.clamp(0.0, 1.0))
+        })
+        .collect()
+}
+
+fn to_hunt_lab(rgb: &[[f32; 3]]) -> Vec<[f32; 3]> {
+    rgb.iter().map(|&c| hunt(linear_rgb_to_lab(c))).collect()
+}
+
+/// Hunt effect: chroma perceived lower at low lightness.
+fn hunt([l, a, b]: [f32; 3]) -> [f32; 3] {
+    [l, 0.01 * l * a, 0.01 * l * b]
+}
+
+/// HyAB distance: lightness difference plus chroma distance.
+fn hyab(x: [f32; 3], y: [f32; 3]) -> f32 {
+    (x[0] - y[0]).abs() + ((x[1] - y[1]).powi(2) + (x[2] - y[2]).powi(2)).sqrt()
+}
+
+fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_rgb_to_xyz([r, g, b]: [f32; 3]) -> [f32; 3] {
+    [
+        0.412_390_8 * r + 0.357_584_3 * g + 0.180_480_8 * b,
+        0.212_639 * r + 0.715_168_7 * g + 0.072_192_3 * b,
+        0.019_330_8 * r + 0.119_194_8 * g + 0.950_532_2 * b,
+    ]
+}
+
+fn xyz_to_linear_rgb([x, y, z]: [f32; 3]) -> [f32; 3] {
+    [
+        3.240_97 * x - 1.537_383 * y - 0.498_611 * z,
+        -0.969_243_6 * x + 1.875_967_5 * y + 0.041_555_1 * z,
+        0.055_63 * x - 0.203_977 * y + 1.056_971_5 * z,
+    ]
+}
+
+fn linear_rgb_to_lab(rgb: [f32; 3]) -> [f32; 3] {
+    let [x, y, z] = linear_rgb_to_xyz(rgb);
+    let (fx, fy, fz) = (
+        lab_f(x / WHITE[0]),
+        lab_f(y / WHITE[1]),
+        lab_f(z / WHITE[2]),
+    );
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
+
+fn lab_f(t: f32) -> f32 {
+    const DELTA: f32 = 6.0 / 29.0;
+    if t > DELTA.powi(3) {
+        t.cbrt()
+    } else {
+        t / (3.0 * DELTA * DELTA) + 4.0 / 29.0
+    }
+}
+
+fn normalize(kernel: Vec<f32>) -> Vec<f32> {
+    let sum: f32 = kernel.iter().sum();
+    kernel.into_iter().map(|k| k / sum).collect()
+}
+
+/// Scale positive and negative weights separately to sum to 1 and -1, so
+/// derivative filters respond the same at every `sigma`.
+fn normalize_signed(kernel: Vec<f32>) -> Vec<f32> {
+    let pos: f32 = kernel.iter().filter(|k| **k > 0.0).sum();
+    let neg: f32 = -kernel.iter().filter(|k| **k < 0.0).sum::<f32>();
+    kernel
+        .into_iter()
+        .map(|k| if k > 0.0 { k / pos } else { k / neg })
+        .collect()
+}
+
+/// Horizontal 1-D convolution with clamped edges.
+fn convolve_rows(src: &[f32], w: usize, h: usize, kernel: &[f32]) -> Vec<f32> {
+    let radius = (kernel.len() / 2) as isize;
+    let mut out = vec![0.0; w * h];
+    for y in 0..h {
+        let row = &src[y * w..(y + 1) * w];
+        for x in 0..w {
+            out[y * w + x] = kernel
+                .iter()
+                .enumerate()
+                .map(|(k, weight)| {
+                    let sx = (x as isize + k as isize - radius).clamp(0, w as isize - 1);
+                    weight * row[sx as usize]
+                })
+                .sum();
+        }
+    }
+    out
+}
+
+/// Vertical 1-D convolution with clamped edges.
+fn convolve(src: &[f32], w: usize, h: usize, kernel: &[f32]) -> Vec<f32> {
+    let radius = (kernel.len() / 2) as isize;
+    let mut out = vec![0.0; w * h];
+    for y in 0..h {
+        for (k, weight) in kernel.iter().enumerate() {
+            let sy = (y as isize + k as isize - radius).clamp(0, h as isize - 1) as usize;
+            for x in 0..w {
+                out[y * w + x] += weight * src[sy * w + x];
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    #[test]
+    fn identical_is_zero_and_visible_changes_score_higher() {
+        let white = RgbaImage::from_pixel(24, 24, Rgba([255, 255, 255, 255]));
+        assert!(mean_flip(&white, &white) < 1e-6);
+
+        let mut faint = white.clone();
+        let mut bold = white.clone();
+        for x in 4..20 {
+            faint.put_pixel(x, 12, Rgba([250, 250, 250, 255]));
+            bold.put_pixel(x, 12, Rgba([0, 0, 0, 255]));
+        }
+        let (faint, bold) = (mean_flip(&white, &faint), mean_flip(&white, &bold));
+        assert!(faint < bold, "{faint} >= {bold}");
+    }
+}
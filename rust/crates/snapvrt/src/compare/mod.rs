@@ -1,4 +1,52 @@
+pub mod color;
 pub mod diff;
+#[cfg(feature = "flip")]
+pub mod flip;
+pub mod render;
+pub mod ssim;
+pub mod text;
+
+use self::diff::{CompareResult, Region, SizeChange};
+use crate::config::DiffMetric;
+
+/// Pass criteria for one snapshot: `diff.metric` with the snapshot's
+/// threshold.
+#[derive(Clone, Copy)]
+pub struct Thresholds {
+    pub metric: DiffMetric,
+    pub threshold: f64,
+    pub ssim_threshold: Option<f64>,
+}
+
+impl Thresholds {
+    /// Whether `result` passes: a match, or differences within the
+    /// threshold for the configured metric.
+    pub fn allow(&self, result: &CompareResult) -> bool {
+        result.is_match
+            || match self.metric {
+                DiffMetric::Hybrid => {
+                    result.score <= self.threshold
+                        && self
+                            .ssim_threshold
+                            .is_none_or(|t| 1.0 - result.ssim.unwrap_or(1.0) <= t)
+                }
+                metric => metric_score(metric, result) <= self.threshold,
+            }
+    }
+}
+
+/// The value `diff.threshold` is checked against under `metric`: the pixel
+/// score (also for `hybrid`), `1 - SSIM` or the FLIP error. 0 on a match.
+pub fn metric_score(metric: DiffMetric, result: &CompareResult) -> f64 {
+    if result.is_match {
+        return 0.0;
+    }
+    match metric {
+        DiffMetric::Pixel | DiffMetric::Hybrid => result.score,
+        DiffMetric::Ssim => 1.0 - result.ssim.unwrap_or(1.0),
+        DiffMetric::Flip => result.flip.unwrap_or(0.0),
+    }
+}
 
 /// Status of a single snapshot comparison.
 pub enum SnapshotStatus {
@@ -6,8 +54,30 @@ pub enum SnapshotStatus {
     Fail {
         diff_pixels: u64,
         score: f64,
-        dimension_mismatch: Option<(u32, u32, u32, u32)>,
+        /// Mean SSIM (1.0 = identical), with `diff.metric` `ssim` or `hybrid`.
+        ssim: Option<f64>,
+        /// Mean FLIP error (0.0 = identical), with `diff.metric = "flip"`.
+        flip: Option<f64>,
+        dimension_mismatch: Option<SizeChange>,
+        regions: Vec<Region>,
+        /// Text snapshots (`a11y`, ...) that differ from the reference.
+        text_changes: Vec<String>,
     },
     New,
     Error(String),
 }
+
+impl SnapshotStatus {
+    /// A failure where only text snapshots changed; the pixels pass.
+    pub fn text_changed(text_changes: Vec<String>) -> Self {
+        Self::Fail {
+            diff_pixels: 0,
+            score: 0.0,
+            ssim: None,
+            flip: None,
+            dimension_mismatch: None,
+            regions: Vec::new(),
+            text_changes,
+        }
+    }
+}
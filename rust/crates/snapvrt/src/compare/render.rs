@@ -0,0 +1,178 @@
+use image::{Rgba, RgbaImage};
+
+use super::diff::{DIFY_CHANGED, Region, SizeChange};
+use crate::config::DiffStyle;
+
+/// Gap between the panels of a side-by-side composite.
+const PANEL_GAP: u32 = 8;
+
+/// Outline width of region boxes, in pixels.
+const BOX_WIDTH: u32 = 2;
+
+/// Outline of the reference's extent when the dimensions changed.
+const REFERENCE_EXTENT: Rgba<u8> = Rgba([0, 120, 255, 255]);
+
+/// Outline of the current image's extent when the dimensions changed.
+const CURRENT_EXTENT: Rgba<u8> = Rgba([0, 200, 80, 255]);
+
+/// How `difference/` images are drawn, from `[diff]`.
+#[derive(Clone, Copy, Debug)]
+pub struct DiffRender {
+    pub style: DiffStyle,
+    /// Changed-pixel (and box) color.
+    pub color: [u8; 3],
+    /// Outline changed regions.
+    pub boxes: bool,
+}
+
+impl DiffRender {
+    pub const DEFAULT: Self = Self {
+        style: DiffStyle::Overlay,
+        color: [255, 0, 0],
+        boxes: false,
+    };
+
+    /// Whether rendering needs the compared images, not just dify's output.
+    pub fn needs_images(&self) -> bool {
+        matches!(self.style, DiffStyle::Heatmap | DiffStyle::SideBySide)
+    }
+
+    /// Final diff image from dify's overlay and, for styles that need them,
+    /// the (masked, padded) reference and current images. When the
+    /// dimensions changed, both extents are outlined on every panel.
+    pub fn render(
+        &self,
+        overlay: RgbaImage,
+        images: Option<(&RgbaImage, &RgbaImage)>,
+        regions: &[Region],
+        size_change: Option<&SizeChange>,
+    ) -> RgbaImage {
+        let color = Rgba([self.color[0], self.color[1], self.color[2], 255]);
+        let mut out = match (self.style, images) {
+            (DiffStyle::Heatmap, Some((left, right))) => heatmap(left, right, color),
+            (DiffStyle::SideBySide, Some((left, right))) => {
+                side_by_side(left, right, &recolor(overlay, color))
+            }
+            _ => recolor(overlay, color),
+        };
+        let panel_width = images.map_or(out.width(), |(left, _)| left.width());
+        let panels = if self.style == DiffStyle::SideBySide && images.is_some() {
+            3
+        } else {
+            1
+        };
+        for panel in 0..panels {
+            let dx = panel * (panel_width + PANEL_GAP);
+            if self.boxes {
+                for region in regions {
+                    draw_box(&mut out, region, dx, color);
+                }
+            }
+            if let Some(change) = size_change {
+                let [reference, current] = change.extents().map(|m| Region {
+                    x: m.x,
+                    y: m.y,
+                    width: m.width,
+                    height: m.height,
+                    pixels: 0,
+                });
+                draw_box(&mut out, &reference, dx, REFERENCE_EXTENT);
+                draw_box(&mut out, &current, dx, CURRENT_EXTENT);
+            }
+        }
+        out
+    }
+}
+
+/// dify's overlay with changed pixels in `color`.
+fn recolor(mut overlay: RgbaImage, color: Rgba<u8>) -> RgbaImage {
+    if color != DIFY_CHANGED {
+        for p in overlay.pixels_mut() {
+            if *p == DIFY_CHANGED {
+                *p = color;
+            }
+        }
+    }
+    overlay
+}
+
+/// Dimmed grayscale of the current image with `color` blended in by how
+/// much each pixel changed — readable on dark and light UIs alike.
+fn heatmap(left: &RgbaImage, right: &RgbaImage, color: Rgba<u8>) -> RgbaImage {
+    RgbaImage::from_fn(right.width(), right.height(), |x, y| {
+        let (l, r) = (left.get_pixel(x, y), right.get_pixel(x, y));
+        let gray = 0.299 * f32::from(r[0]) + 0.587 * f32::from(r[1]) + 0.114 * f32::from(r[2]);
+        let base = 64.0 + gray * 0.25;
+        let delta =
+            l.0.iter()
+                .zip(r.0)
+                .map(|(a, b)| a.abs_diff(b))
+                .max()
+                .unwrap_or(0);
+        // Square root so small changes stay visible.
+        let alpha = (f32::from(delta) / 255.0).sqrt();
+        let blend = |c: u8| (base * (1.0 - alpha) + f32::from(c) * alpha).round() as u8;
+        Rgba([blend(color[0]), blend(color[1]), blend(color[2]), 255])
+    })
+}
+
+/// Reference | current | diff, separated by gray gaps.
+fn side_by_side(left: &RgbaImage, right: &RgbaImage, diff: &RgbaImage) -> RgbaImage {
+    let (w, h) = left.dimensions();
+    let mut out = RgbaImage::from_pixel(w * 3 + PANEL_GAP * 2, h, Rgba([128, 128, 128, 255]));
+    for (i, panel) in [left, right, diff].into_iter().enumerate() {
+        image::imageops::replace(&mut out, panel, i64::from((w + PANEL_GAP) * i as u32), 0);
+    }
+    out
+}
+
+/// Outline `region`, shifted right by `dx`, clamped to the image.
+fn draw_box(img: &mut RgbaImage, region: &Region, dx: u32, color: Rgba<u8>) {
+    let (w, h) = img.dimensions();
+    let x0 = region.x + dx;
+    let x1 = (x0 + region.width).min(w);
+    let y1 = (region.y + region.height).min(h);
+    for y in region.y..y1 {
+        for x in x0..x1 {
+            let edge = x < x0 + BOX_WIDTH
+                || x + BOX_WIDTH >= x1
+                || y < region.y + BOX_WIDTH
+                || y + BOX_WIDTH >= y1;
+            if edge {
+                img.put_pixel(x, y, color);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn side_by_side_boxes_every_panel() {
+        let left = RgbaImage::from_pixel(10, 6, Rgba([255, 255, 255, 255]));
+        let mut overlay = left.clone();
+        overlay.put_pixel(1, 1, DIFY_CHANGED);
+        let render = DiffRender {
+            style: DiffStyle::SideBySide,
+            color: [0, 0, 255],
+            boxes: true,
+        };
+        let region = Region {
+            x: 0,
+            y: 0,
+            width: 4,
+            height: 4,
+            pixels: 1,
+        };
+        let out = render.render(overlay, Some((&left, &left)), &[region], None);
+        assert_eq!(out.dimensions(), (10 * 3 + PANEL_GAP * 2, 6));
+        let blue = Rgba([0, 0, 255, 255]);
+        for panel in 0..3 {
+            assert_eq!(*out.get_pixel(panel * (10 + PANEL_GAP), 0), blue);
+        }
+        // Changed pixel recolored in the overlay panel.
+        assert_eq!(*out.get_pixel(2 * (10 + PANEL_GAP) + 1, 1), blue);
+    }
+}
@@ -0,0 +1,96 @@
+use image::RgbaImage;
+
+/// Side of the square window SSIM statistics are computed over.
+const WINDOW: u32 = 8;
+
+/// Step between windows; overlapping windows smooth out block edges.
+const STRIDE: u32 = 4;
+
+/// Stabilizing constants for 8-bit luma: (0.01 * 255)², (0.03 * 255)².
+const C1: f64 = 6.5025;
+const C2: f64 = 58.5225;
+
+/// Mean structural similarity of the luma channels, 1.0 for identical
+/// images. Both images must have the same dimensions.
+pub fn mean_ssim(left: &RgbaImage, right: &RgbaImage) -> f64 {
+    debug_assert_eq!(left.dimensions(), right.dimensions());
+    let (width, height) = left.dimensions();
+    if width == 0 || height == 0 {
+        return 1.0;
+    }
+    let left = luma(left);
+    let right = luma(right);
+
+    // Images smaller than a window are compared as one window.
+    let win_w = WINDOW.min(width);
+    let win_h = WINDOW.min(height);
+    let mut total = 0.0;
+    let mut windows = 0u64;
+    for y in (0..=height - win_h).step_by(STRIDE as usize) {
+        for x in (0..=width - win_w).step_by(STRIDE as usize) {
+            total += window_ssim(&left, &right, width, x, y, win_w, win_h);
+            windows += 1;
+        }
+    }
+    total / windows as f64
+}
+
+/// SSIM of one `w`×`h` window at (`x0`, `y0`).
+fn window_ssim(left: &[f32], right: &[f32], stride: u32, x0: u32, y0: u32, w: u32, h: u32) -> f64 {
+    let (mut sum_l, mut sum_r, mut sum_ll, mut sum_rr, mut sum_lr) = (0.0, 0.0, 0.0, 0.0, 0.0);
+    for y in y0..y0 + h {
+        let row = (y * stride) as usize;
+        for x in x0..x0 + w {
+            let l = f64::from(left[row + x as usize]);
+            let r = f64::from(right[row + x as usize]);
+            sum_l += l;
+            sum_r += r;
+            sum_ll += l * l;
+            sum_rr += r * r;
+            sum_lr += l * r;
+        }
+    }
+    let n = f64::from(w * h);
+    let (mean_l, mean_r) = (sum_l / n, sum_r / n);
+    let var_l = sum_ll / n - mean_l * mean_l;
+    let var_r = sum_rr / n - mean_r * mean_r;
+    let covar = sum_lr / n - mean_l * mean_r;
+    ((2.0 * mean_l * mean_r + C1) * (2.0 * covar + C2))
+        / ((mean_l * mean_l + mean_r * mean_r + C1) * (var_l + var_r + C2))
+}
+
+/// Rec. 601 luma, composited over white so transparent pixels compare as
+/// the page background.
+fn luma(img: &RgbaImage) -> Vec<f32> {
+    img.pixels()
+        .map(|p| {
+            let [r, g, b, a] = p.0.map(f32::from);
+            let y = 0.299 * r + 0.587 * g + 0.114 * b;
+            let alpha = a / 255.0;
+            y * alpha + 255.0 * (1.0 - alpha)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    #[test]
+    fn identical_is_one_and_changes_lower_it() {
+        let white = RgbaImage::from_pixel(32, 32, Rgba([255, 255, 255, 255]));
+        assert!((mean_ssim(&white, &white) - 1.0).abs() < 1e-9);
+
+        let mut stripe = white.clone();
+        for x in 0..32 {
+            stripe.put_pixel(x, 16, Rgba([0, 0, 0, 255]));
+        }
+        let score = mean_ssim(&white, &stripe);
+        assert!(score < 0.95, "{score}");
+
+        // Tiny images fall back to a single window.
+        let dot = RgbaImage::from_pixel(3, 2, Rgba([10, 20, 30, 255]));
+        assert!((mean_ssim(&dot, &dot) - 1.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,137 @@
+/// Unchanged lines kept around each change in a text diff.
+const CONTEXT_LINES: usize = 2;
+
+/// Largest changed block (reference lines x current lines) diffed line by
+/// line; bigger blocks are shown as all removed, then all added.
+const MAX_LCS_CELLS: usize = 4_000_000;
+
+/// Line diff of two text snapshots: changed lines prefixed with `-`/`+`,
+/// a little unchanged context with two spaces, and `@@ line N @@` before
+/// each hunk. `None` when the texts are equal.
+pub fn diff_lines(reference: &str, current: &str) -> Option<String> {
+    if reference == current {
+        return None;
+    }
+    let old: Vec<&str> = reference.lines().collect();
+    let new: Vec<&str> = current.lines().collect();
+    let ops = edit_script(&old, &new);
+
+    let changed: Vec<bool> = ops.iter().map(|op| !matches!(op, Op::Same(..))).collect();
+    let near_change = |i: usize| {
+        let lo = i.saturating_sub(CONTEXT_LINES);
+        let hi = (i + CONTEXT_LINES + 1).min(ops.len());
+        changed[lo..hi].iter().any(|c| *c)
+    };
+
+    let mut out = String::new();
+    let mut in_hunk = false;
+    for (i, op) in ops.iter().enumerate() {
+        if !near_change(i) {
+            in_hunk = false;
+            continue;
+        }
+        if !in_hunk {
+            let line = match op {
+                Op::Same(at, _) | Op::Removed(at, _) | Op::Added(at, _) => at + 1,
+            };
+            out.push_str(&format!("@@ line {line} @@\n"));
+            in_hunk = true;
+        }
+        let (prefix, text) = match op {
+            Op::Same(_, text) => (' ', text),
+            Op::Removed(_, text) => ('-', text),
+            Op::Added(_, text) => ('+', text),
+        };
+        out.push(prefix);
+        out.push(' ');
+        out.push_str(text);
+        out.push('\n');
+    }
+    if out.is_empty() {
+        // Only line endings differ.
+        out.push_str("@@ line endings differ @@\n");
+    }
+    Some(out)
+}
+
+/// One line of an edit script, with its (0-based) line number in the
+/// reference (`Same`, `Removed`) or current (`Added`) text.
+enum Op<'a> {
+    Same(usize, &'a str),
+    Removed(usize, &'a str),
+    Added(usize, &'a str),
+}
+
+/// Shortest edit script via LCS, after trimming the common prefix and
+/// suffix (most changes are small).
+fn edit_script<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<Op<'a>> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (a, b) = (
+        &old[prefix..old.len() - suffix],
+        &new[prefix..new.len() - suffix],
+    );
+
+    let mut ops: Vec<Op<'a>> = (0..prefix).map(|i| Op::Same(i, old[i])).collect();
+    if a.len() * b.len() > MAX_LCS_CELLS {
+        ops.extend(
+            a.iter()
+                .enumerate()
+                .map(|(i, l)| Op::Removed(prefix + i, l)),
+        );
+        ops.extend(b.iter().enumerate().map(|(j, l)| Op::Added(prefix + j, l)));
+    } else {
+        // lcs[i][j]: LCS length of a[i..] and b[j..].
+        let width = b.len() + 1;
+        let mut lcs = vec![0u32; (a.len() + 1) * width];
+        for i in (0..a.len()).rev() {
+            for j in (0..b.len()).rev() {
+                lcs[i * width + j] = if a[i] == b[j] {
+                    lcs[(i + 1) * width + j + 1] + 1
+                } else {
+                    lcs[(i + 1) * width + j].max(lcs[i * width + j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < a.len() || j < b.len() {
+            if i < a.len() && j < b.len() && a[i] == b[j] {
+                ops.push(Op::Same(prefix + i, a[i]));
+                i += 1;
+                j += 1;
+            } else if j == b.len()
+                || (i < a.len() && lcs[(i + 1) * width + j] >= lcs[i * width + j + 1])
+            {
+                ops.push(Op::Removed(prefix + i, a[i]));
+                i += 1;
+            } else {
+                ops.push(Op::Added(prefix + j, b[j]));
+                j += 1;
+            }
+        }
+    }
+    let old_tail = old.len() - suffix;
+    ops.extend((old_tail..old.len()).map(|i| Op::Same(i, old[i])));
+    ops
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_shows_changes_with_context() {
+        let reference = "a\nb\nc\nd\ne\nf\ng\n";
+        let current = "a\nb\nc\nD\ne\nf\ng\nh\n";
+        assert_eq!(diff_lines(reference, reference), None);
+        assert_eq!(
+            diff_lines(reference, current).unwrap(),
+            "@@ line 2 @@\n  b\n  c\n- d\n+ D\n  e\n  f\n  g\n+ h\n"
+        );
+    }
+}
@@ -1,11 +1,131 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
 use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy, Debug, Default, clap::ValueEnum, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub enum ScreenshotKind {
     #[default]
     Stable,
     Single,
+    /// `single` for snapshots whose recent stable captures settled at once,
+    /// `stable` for new and noisy ones.
+    Adaptive,
+}
+
+/// Built-in capture pipeline hooks (see `capture::hooks`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BuiltinHook {
+    /// Hide scrollbars without disabling scrolling.
+    HideScrollbars,
+    /// Blur an autofocused input.
+    BlurInputs,
+    /// Stop videos on their first frame.
+    StopVideos,
+}
+
+/// How the stable screenshot strategy decides two consecutive screenshots
+/// show the same frame.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum StabilityMetric {
+    /// Byte-identical encoded images.
+    #[default]
+    Bytes,
+    /// Decoded pixels, allowing `stability_tolerance` of them to differ.
+    Pixels,
+}
+
+/// Encoding Chrome uses for captured screenshots. JPEG and WebP are lossy
+/// but faster to transfer; stored images are converted to `store.format`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ScreenshotFormat {
+    #[default]
+    Png,
+    Jpeg,
+    Webp,
+}
+
+/// Order the capture queue is worked through.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CaptureOrder {
+    /// Discovery order.
+    #[default]
+    Discovery,
+    /// Slowest captures of the previous run first (new stories before
+    /// known ones), so long stories don't leave workers idle at the end.
+    SlowestFirst,
+    /// Grouped by story title, so stories sharing a component bundle run
+    /// back to back.
+    Title,
+}
+
+/// What each capture shares with the others.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Isolation {
+    /// A fresh tab per capture; cookies, storage and caches are shared.
+    #[default]
+    Tab,
+    /// A fresh browser context (incognito-like profile) per capture, so
+    /// cookies and storage don't leak between stories.
+    Context,
+    /// A separate Chrome process per worker, each with its own profile.
+    Browser,
+}
+
+/// What the serialized DOM (`--dom`) is used for.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DomMode {
+    /// No DOM snapshots.
+    #[default]
+    Off,
+    /// Diff the DOM as its own regression signal: a changed DOM fails the
+    /// snapshot, even when the pixels match.
+    Diff,
+    /// Pre-check: when the DOM matches the reference, pass without
+    /// decoding and diffing the images. A changed DOM just falls through
+    /// to the pixel comparison.
+    Precheck,
+}
+
+/// How JavaScript dialogs (`alert`, `confirm`, `prompt`, `beforeunload`)
+/// opened by a story are answered.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DialogAction {
+    /// Close with Cancel: `confirm` returns false, `prompt` null.
+    #[default]
+    Dismiss,
+    /// Close with OK: `confirm` returns true, `prompt` its default text.
+    Accept,
+}
+
+/// What happens to tabs that stories open (`window.open`, `target="_blank"`).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Popups {
+    /// Close them as soon as Chrome creates them.
+    #[default]
+    Close,
+    /// Leave them open until Chrome exits.
+    Keep,
+}
+
+/// Where `chrome_token` is sent to a managed Chrome service.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TokenPlacement {
+    /// `?token=...` on every URL (browserless, browsercloud).
+    #[default]
+    Query,
+    /// `Authorization: Bearer ...` on every request and WebSocket upgrade.
+    Header,
 }
 
 /// Configuration for the capture pipeline.
@@ -18,6 +138,26 @@ pub struct CaptureConfig {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub screenshot: Option<ScreenshotKind>,
 
+    #[arg(long, value_enum)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub screenshot_format: Option<ScreenshotFormat>,
+
+    /// JPEG/WebP quality (0–100)
+    #[arg(long, value_parser = clap::value_parser!(u8).range(0..=100))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub screenshot_quality: Option<u8>,
+
+    /// Capture content taller than the viewport directly instead of
+    /// enlarging the viewport first (needs a recent Chrome)
+    #[arg(long, num_args = 0..=1, default_missing_value = "true", value_name = "BOOL")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub capture_beyond_viewport: Option<bool>,
+
+    /// Capture from the compositor surface (Chrome default: true)
+    #[arg(long, num_args = 0..=1, default_missing_value = "true", value_name = "BOOL")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from_surface: Option<bool>,
+
     #[arg(long)]
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub stability_attempts: Option<u32>,
@@ -26,16 +166,250 @@ pub struct CaptureConfig {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub stability_delay_ms: Option<u64>,
 
+    /// How consecutive screenshots are compared for stability
+    #[arg(long, value_enum)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stability_metric: Option<StabilityMetric>,
+
+    /// Fraction of pixels (0-1) allowed to differ between consecutive
+    /// screenshots that count as stable, with `stability_metric = "pixels"`
+    #[arg(long, value_name = "FRACTION")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stability_tolerance: Option<f64>,
+
     /// Number of parallel browser tabs for capturing
     #[arg(long, short = 'p')]
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub parallel: Option<usize>,
 
+    /// Longest a single CDP command may wait for Chrome's response (ms);
+    /// 0 waits as long as the capture timeout allows
+    #[arg(long, value_name = "MS")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cdp_timeout_ms: Option<u64>,
+
+    /// Memory budget per tab (MB) used to lower `parallel` inside a
+    /// container with a memory limit (cgroup); 0 disables the cap
+    #[arg(long, value_name = "MB")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tab_memory_mb: Option<u32>,
+
+    /// V8 heap limit per renderer (MB), passed to locally launched Chrome
+    /// as `--js-flags=--max-old-space-size`
+    #[arg(long, value_name = "MB")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_heap_mb: Option<u32>,
+
+    /// Most renderer processes locally launched Chrome may start
+    /// (`--renderer-process-limit`); tabs beyond it share processes
+    #[arg(long, value_name = "N")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub renderer_process_limit: Option<u32>,
+
+    /// Persistent Chrome profile directory for locally launched Chrome, kept
+    /// between runs (HTTP cache, fonts, service workers) instead of a fresh
+    /// temp profile per run
+    #[arg(long, value_name = "DIR")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile_dir: Option<PathBuf>,
+
+    /// Order captures are scheduled in
+    #[arg(long, value_enum)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub order: Option<CaptureOrder>,
+
+    /// How captures are isolated from each other
+    #[arg(long, value_enum)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub isolation: Option<Isolation>,
+
     /// Connect to a remote Chrome instead of launching a local one.
     /// Value is `http://host:port` (e.g. `http://localhost:9222`).
     #[arg(long)]
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub chrome_url: Option<String>,
+
+    /// API token for a managed Chrome service at `chrome_url`. Prefer the
+    /// SNAPVRT_CHROME_TOKEN environment variable to committing it
+    #[arg(long, value_name = "TOKEN")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chrome_token: Option<String>,
+
+    /// How `chrome_token` is sent
+    #[arg(long, value_enum)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chrome_token_in: Option<TokenPlacement>,
+
+    /// Capture through a W3C WebDriver server (e.g. a Selenium Grid at
+    /// `http://grid:4444`) instead of Chrome DevTools, for environments
+    /// without CDP access. Features that need DevTools are unavailable
+    #[arg(long, value_name = "URL", conflicts_with = "chrome_url")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webdriver_url: Option<String>,
+
+    /// `browserName` requested from the WebDriver server (`chrome`,
+    /// `firefox`, `MicrosoftEdge`, ...)
+    #[arg(long, value_name = "NAME")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webdriver_browser: Option<String>,
+
+    /// CSS selector whose elements are masked out of comparisons (repeatable).
+    /// Bounds are measured at capture time, so masks follow layout changes.
+    #[arg(long = "ignore-selector", value_name = "SELECTOR")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ignore_selectors: Option<Vec<String>>,
+
+    /// Accept invalid TLS certificates (self-signed or private-CA Storybooks),
+    /// both in Chrome and for index.json discovery.
+    #[arg(long, num_args = 0..=1, default_missing_value = "true", value_name = "BOOL")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ignore_https_errors: Option<bool>,
+
+    /// How JavaScript dialogs opened by stories are answered, so they don't
+    /// block the page
+    #[arg(long, value_enum)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dialogs: Option<DialogAction>,
+
+    /// What happens to tabs stories open with `window.open` or
+    /// `target="_blank"` links
+    #[arg(long, value_enum)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub popups: Option<Popups>,
+
+    /// Render in sRGB regardless of the display's color profile
+    /// (`--force-color-profile=srgb`), so wide-gamut machines capture the
+    /// same colors as CI. On by default; applies to locally launched Chrome
+    #[arg(long, num_args = 0..=1, default_missing_value = "true", value_name = "BOOL")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub force_srgb: Option<bool>,
+
+    /// Keep retrying story discovery for up to this many seconds while the
+    /// dev server starts (e.g. when CI launches Storybook concurrently)
+    #[arg(long, value_name = "SECS")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wait_for_storybook: Option<u64>,
+
+    /// Reuse the last discovery result from `.snapvrt/cache/` instead of
+    /// contacting the server
+    #[arg(long)]
+    #[serde(skip)]
+    pub offline: bool,
+
+    /// Pause `<video>`/`<audio>` elements and seek them to this time (in
+    /// seconds) before the readiness checks
+    #[arg(long, value_name = "SECONDS")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub media_time: Option<f64>,
+
+    /// Built-in pipeline hook to run on every capture (repeatable)
+    #[arg(long = "hook", value_enum, value_name = "HOOK")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hooks: Option<Vec<BuiltinHook>>,
+
+    /// JS file defining `beforeCapture(story)`, run in the page once the
+    /// story is ready
+    #[arg(long, value_name = "FILE")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hook_script: Option<PathBuf>,
+
+    /// Device scale factor (`devicePixelRatio`) to render at; 2 captures
+    /// retina-resolution images. After changing it, run `snapvrt
+    /// migrate-scale` or re-capture the references
+    #[arg(long, value_name = "FACTOR")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device_scale: Option<f64>,
+
+    /// Canvas/WebGL helpers: seed `Math.random`, force `preserveDrawingBuffer`,
+    /// and wait an extra composited frame before the screenshot
+    #[arg(long, num_args = 0..=1, default_missing_value = "true", value_name = "BOOL")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deterministic_canvas: Option<bool>,
+
+    /// Hide scrollbars that take up layout space (classic, non-overlay
+    /// scrollbars) before measuring the story, so every platform lays
+    /// content out at the same width. Off by default: turning it on can
+    /// change the captured layout, so re-approve references afterwards
+    #[arg(long, num_args = 0..=1, default_missing_value = "true", value_name = "BOOL")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hide_scrollbar_gutters: Option<bool>,
+
+    /// Load every request from the network, bypassing service workers, so
+    /// one registered by an earlier story can't serve cached assets
+    #[arg(long, num_args = 0..=1, default_missing_value = "true", value_name = "BOOL")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bypass_service_workers: Option<bool>,
+
+    /// Disable Chrome's HTTP cache for every capture
+    #[arg(long, num_args = 0..=1, default_missing_value = "true", value_name = "BOOL")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disable_cache: Option<bool>,
+
+    /// Block requests to web font services (Google Fonts, Adobe Fonts, ...)
+    /// so text renders with local or bundled fonts only.
+    #[arg(long, num_args = 0..=1, default_missing_value = "true", value_name = "BOOL")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_remote_fonts: Option<bool>,
+
+    /// Font stack forced onto every element (e.g. `Arial, sans-serif`).
+    #[arg(long, value_name = "STACK")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub font_override: Option<String>,
+
+    /// Font family that must be loaded before the screenshot (repeatable).
+    /// The capture fails if it is not available within the ready timeout.
+    #[arg(long = "wait-for-font", value_name = "FAMILY")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wait_for_fonts: Option<Vec<String>>,
+
+    /// Directory of font files (`Family-Bold.woff2`, `Family/Italic.ttf`)
+    /// declared with `@font-face` on every page, overriding system fonts of
+    /// the same family.
+    #[arg(long, value_name = "DIR")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub font_dir: Option<PathBuf>,
+
+    /// Also capture each story after 1..=N Tab presses, as `<id>__focus-<n>`
+    /// snapshots, to cover focus rings and skip links
+    #[arg(long, value_name = "N")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub focus_steps: Option<u32>,
+
+    /// Record each capture's network activity as a HAR file (`.snapvrt/har/`,
+    /// or next to the error artifacts when the capture fails)
+    #[arg(long, num_args = 0..=1, default_missing_value = "true", value_name = "BOOL")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub har: Option<bool>,
+
+    /// Also snapshot each story's accessibility tree and fail when it
+    /// changes, even if the pixels don't
+    #[arg(long, num_args = 0..=1, default_missing_value = "true", value_name = "BOOL")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub a11y: Option<bool>,
+
+    /// Also snapshot each story's normalized DOM (`diff` when given
+    /// without a value)
+    #[arg(long, value_enum, num_args = 0..=1, default_missing_value = "diff", value_name = "MODE")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dom: Option<DomMode>,
+
+    /// Track which CSS rules the captured stories use and write the unused
+    /// ones per stylesheet to `.snapvrt/css-coverage.json`
+    #[arg(long, num_args = 0..=1, default_missing_value = "true", value_name = "BOOL")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub css_coverage: Option<bool>,
+
+    /// Record page metrics (layouts, script time, JS heap) at screenshot
+    /// time, in report.json and the `--timings` table
+    #[arg(long, num_args = 0..=1, default_missing_value = "true", value_name = "BOOL")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub perf_metrics: Option<bool>,
+
+    /// Debug mode (`test --debug`): headful Chrome, one tab, no capture
+    /// timeout, and a pause before each screenshot.
+    #[arg(skip)]
+    #[serde(skip)]
+    pub debug: bool,
 }
 
 impl CaptureConfig {
@@ -44,21 +418,281 @@ impl CaptureConfig {
         if other.screenshot.is_some() {
             self.screenshot = other.screenshot;
         }
+        if other.screenshot_format.is_some() {
+            self.screenshot_format = other.screenshot_format;
+        }
+        if other.screenshot_quality.is_some() {
+            self.screenshot_quality = other.screenshot_quality;
+        }
+        if other.capture_beyond_viewport.is_some() {
+            self.capture_beyond_viewport = other.capture_beyond_viewport;
+        }
+        if other.from_surface.is_some() {
+            self.from_surface = other.from_surface;
+        }
         if other.stability_attempts.is_some() {
             self.stability_attempts = other.stability_attempts;
         }
         if other.stability_delay_ms.is_some() {
             self.stability_delay_ms = other.stability_delay_ms;
         }
+        if other.stability_metric.is_some() {
+            self.stability_metric = other.stability_metric;
+        }
+        if other.stability_tolerance.is_some() {
+            self.stability_tolerance = other.stability_tolerance;
+        }
         if other.parallel.is_some() {
             self.parallel = other.parallel;
         }
+        if other.cdp_timeout_ms.is_some() {
+            self.cdp_timeout_ms = other.cdp_timeout_ms;
+        }
+        if other.tab_memory_mb.is_some() {
+            self.tab_memory_mb = other.tab_memory_mb;
+        }
+        if other.max_heap_mb.is_some() {
+            self.max_heap_mb = other.max_heap_mb;
+        }
+        if other.renderer_process_limit.is_some() {
+            self.renderer_process_limit = other.renderer_process_limit;
+        }
+        if other.profile_dir.is_some() {
+            self.profile_dir = other.profile_dir.clone();
+        }
+        if other.order.is_some() {
+            self.order = other.order;
+        }
+        if other.isolation.is_some() {
+            self.isolation = other.isolation;
+        }
         if other.chrome_url.is_some() {
             self.chrome_url = other.chrome_url.clone();
         }
+        if other.chrome_token.is_some() {
+            self.chrome_token = other.chrome_token.clone();
+        }
+        if other.chrome_token_in.is_some() {
+            self.chrome_token_in = other.chrome_token_in;
+        }
+        if other.webdriver_url.is_some() {
+            self.webdriver_url = other.webdriver_url.clone();
+        }
+        if other.webdriver_browser.is_some() {
+            self.webdriver_browser = other.webdriver_browser.clone();
+        }
+        if other.ignore_selectors.is_some() {
+            self.ignore_selectors = other.ignore_selectors.clone();
+        }
+        if other.ignore_https_errors.is_some() {
+            self.ignore_https_errors = other.ignore_https_errors;
+        }
+        if other.dialogs.is_some() {
+            self.dialogs = other.dialogs;
+        }
+        if other.popups.is_some() {
+            self.popups = other.popups;
+        }
+        if other.force_srgb.is_some() {
+            self.force_srgb = other.force_srgb;
+        }
+        if other.wait_for_storybook.is_some() {
+            self.wait_for_storybook = other.wait_for_storybook;
+        }
+        if other.media_time.is_some() {
+            self.media_time = other.media_time;
+        }
+        if other.hooks.is_some() {
+            self.hooks = other.hooks.clone();
+        }
+        if other.hook_script.is_some() {
+            self.hook_script = other.hook_script.clone();
+        }
+        if other.device_scale.is_some() {
+            self.device_scale = other.device_scale;
+        }
+        if other.deterministic_canvas.is_some() {
+            self.deterministic_canvas = other.deterministic_canvas;
+        }
+        if other.hide_scrollbar_gutters.is_some() {
+            self.hide_scrollbar_gutters = other.hide_scrollbar_gutters;
+        }
+        if other.bypass_service_workers.is_some() {
+            self.bypass_service_workers = other.bypass_service_workers;
+        }
+        if other.disable_cache.is_some() {
+            self.disable_cache = other.disable_cache;
+        }
+        if other.block_remote_fonts.is_some() {
+            self.block_remote_fonts = other.block_remote_fonts;
+        }
+        if other.font_override.is_some() {
+            self.font_override = other.font_override.clone();
+        }
+        if other.wait_for_fonts.is_some() {
+            self.wait_for_fonts = other.wait_for_fonts.clone();
+        }
+        if other.font_dir.is_some() {
+            self.font_dir = other.font_dir.clone();
+        }
+        if other.focus_steps.is_some() {
+            self.focus_steps = other.focus_steps;
+        }
+        if other.har.is_some() {
+            self.har = other.har;
+        }
+        if other.a11y.is_some() {
+            self.a11y = other.a11y;
+        }
+        if other.dom.is_some() {
+            self.dom = other.dom;
+        }
+        if other.css_coverage.is_some() {
+            self.css_coverage = other.css_coverage;
+        }
+        if other.perf_metrics.is_some() {
+            self.perf_metrics = other.perf_metrics;
+        }
+        self.debug |= other.debug;
+        self.offline |= other.offline;
     }
 
     pub fn parallel(&self) -> usize {
+        if self.debug {
+            return 1;
+        }
         self.parallel.unwrap_or(4)
     }
+
+    /// `None` in debug mode, where the page may sit at a breakpoint.
+    pub fn cdp_timeout(&self) -> Option<Duration> {
+        if self.debug {
+            return None;
+        }
+        match self.cdp_timeout_ms.unwrap_or(15_000) {
+            0 => None,
+            ms => Some(Duration::from_millis(ms)),
+        }
+    }
+
+    pub fn tab_memory_mb(&self) -> u32 {
+        self.tab_memory_mb.unwrap_or(300)
+    }
+
+    /// Extra flags for locally launched Chrome.
+    pub fn chrome_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if self.force_srgb() {
+            args.push("--force-color-profile=srgb".to_string());
+        }
+        if let Some(mb) = self.max_heap_mb {
+            args.push(format!("--js-flags=--max-old-space-size={mb}"));
+        }
+        if let Some(n) = self.renderer_process_limit {
+            args.push(format!("--renderer-process-limit={n}"));
+        }
+        args
+    }
+
+    pub fn order(&self) -> CaptureOrder {
+        self.order.unwrap_or_default()
+    }
+
+    pub fn isolation(&self) -> Isolation {
+        self.isolation.unwrap_or_default()
+    }
+
+    pub fn capture_beyond_viewport(&self) -> bool {
+        self.capture_beyond_viewport.unwrap_or(false)
+    }
+
+    pub fn ignore_https_errors(&self) -> bool {
+        self.ignore_https_errors.unwrap_or(false)
+    }
+
+    pub fn dialogs(&self) -> DialogAction {
+        self.dialogs.unwrap_or_default()
+    }
+
+    pub fn popups(&self) -> Popups {
+        self.popups.unwrap_or_default()
+    }
+
+    pub fn chrome_token_in(&self) -> TokenPlacement {
+        self.chrome_token_in.unwrap_or_default()
+    }
+
+    pub fn webdriver_browser(&self) -> &str {
+        self.webdriver_browser.as_deref().unwrap_or("chrome")
+    }
+
+    /// Whether the browser runs on this machine (not `chrome_url` or
+    /// `webdriver_url`), sharing its network and memory.
+    pub fn local_browser(&self) -> bool {
+        self.chrome_url.is_none() && self.webdriver_url.is_none()
+    }
+
+    pub fn device_scale(&self) -> f64 {
+        self.device_scale.unwrap_or(1.0)
+    }
+
+    pub fn force_srgb(&self) -> bool {
+        self.force_srgb.unwrap_or(true)
+    }
+
+    pub fn wait_for_storybook(&self) -> Duration {
+        Duration::from_secs(self.wait_for_storybook.unwrap_or(0))
+    }
+
+    pub fn ignore_selectors(&self) -> &[String] {
+        self.ignore_selectors.as_deref().unwrap_or_default()
+    }
+
+    pub fn hooks(&self) -> &[BuiltinHook] {
+        self.hooks.as_deref().unwrap_or_default()
+    }
+
+    pub fn deterministic_canvas(&self) -> bool {
+        self.deterministic_canvas.unwrap_or(false)
+    }
+
+    pub fn hide_scrollbar_gutters(&self) -> bool {
+        self.hide_scrollbar_gutters.unwrap_or(false)
+    }
+
+    pub fn bypass_service_workers(&self) -> bool {
+        self.bypass_service_workers.unwrap_or(false)
+    }
+
+    pub fn disable_cache(&self) -> bool {
+        self.disable_cache.unwrap_or(false)
+    }
+
+    pub fn har(&self) -> bool {
+        self.har.unwrap_or(false)
+    }
+
+    pub fn a11y(&self) -> bool {
+        self.a11y.unwrap_or(false)
+    }
+
+    pub fn dom(&self) -> DomMode {
+        self.dom.unwrap_or_default()
+    }
+
+    pub fn css_coverage(&self) -> bool {
+        self.css_coverage.unwrap_or(false)
+    }
+
+    pub fn perf_metrics(&self) -> bool {
+        self.perf_metrics.unwrap_or(false)
+    }
+
+    pub fn block_remote_fonts(&self) -> bool {
+        self.block_remote_fonts.unwrap_or(false)
+    }
+
+    pub fn wait_for_fonts(&self) -> &[String] {
+        self.wait_for_fonts.as_deref().unwrap_or_default()
+    }
 }
@@ -0,0 +1,251 @@
+use std::path::Path;
+
+use super::Viewport;
+
+/// Storybook dev server port used when nothing is detected.
+pub const DEFAULT_STORYBOOK_PORT: u16 = 6006;
+
+const STORYBOOK_CONFIG_EXTENSIONS: [&str; 6] = ["ts", "js", "mjs", "cjs", "tsx", "jsx"];
+
+/// Project settings inferred by `snapvrt init`.
+#[derive(Debug, Default)]
+pub struct Detected {
+    /// Port from a `storybook dev -p N` script or `.storybook/main.*`.
+    pub port: Option<u16>,
+    /// Custom viewports from the viewport addon config in `.storybook/preview.*`.
+    pub viewports: Vec<(String, Viewport)>,
+}
+
+/// Inspect `package.json` and `.storybook/` under `dir`. Best-effort: any
+/// unreadable or unrecognised file is ignored.
+pub fn detect_project(dir: &Path) -> Detected {
+    let port = std::fs::read_to_string(dir.join("package.json"))
+        .ok()
+        .and_then(|s| port_from_package_json(&s))
+        .or_else(|| read_storybook_file(dir, "main").and_then(|s| port_from_main(&s)));
+    let viewports = read_storybook_file(dir, "preview")
+        .map(|s| parse_viewports(&s))
+        .unwrap_or_default();
+    Detected { port, viewports }
+}
+
+fn read_storybook_file(dir: &Path, stem: &str) -> Option<String> {
+    STORYBOOK_CONFIG_EXTENSIONS.iter().find_map(|ext| {
+        std::fs::read_to_string(dir.join(".storybook").join(format!("{stem}.{ext}"))).ok()
+    })
+}
+
+/// Port passed to Storybook in a `package.json` script (`-p`, `--port`, `--port=`).
+/// The `storybook` script wins over other scripts that start Storybook.
+fn port_from_package_json(content: &str) -> Option<u16> {
+    let json: serde_json::Value = serde_json::from_str(content).ok()?;
+    let scripts = json.get("scripts")?.as_object()?;
+    let mut candidates: Vec<(&String, &str)> = scripts
+        .iter()
+        .filter_map(|(name, cmd)| cmd.as_str().map(|c| (name, c)))
+        .filter(|(_, cmd)| cmd.contains("storybook"))
+        .collect();
+    candidates.sort_by_key(|(name, _)| name.as_str() != "storybook");
+    candidates
+        .into_iter()
+        .find_map(|(_, cmd)| port_from_args(cmd))
+}
+
+fn port_from_args(cmd: &str) -> Option<u16> {
+    let mut args = cmd.split_whitespace();
+    while let Some(arg) = args.next() {
+        if let Some(v) = arg.strip_prefix("--port=") {
+            return v.parse().ok();
+        }
+        if arg == "-p" || arg == "--port" {
+            return args.next()?.parse().ok();
+        }
+    }
+    None
+}
+
+/// `port: N` in `.storybook/main.*` (e.g. a custom dev server option).
+fn port_from_main(content: &str) -> Option<u16> {
+    let pos = content.find("port")?;
+    let rest = content[pos + 4..]
+        .trim_start()
+        .strip_prefix(':')?
+        .trim_start();
+    let digits: String = rest.chars().take_while(|c| c.is_ascii_digit()).collect();
+    digits.parse().ok()
+}
+
+/// Extract `{ name, styles: { width, height } }` entries from a viewport
+/// addon config. Names are slugified (`Kindle Fire 2` -> `kindle-fire-2`),
+/// falling back to the object key. Non-pixel sizes are skipped.
+fn parse_viewports(src: &str) -> Vec<(String, Viewport)> {
+    let mut out: Vec<(String, Viewport)> = Vec::new();
+    let mut cursor = 0;
+    let mut scope_start = 0;
+    while let Some(rel) = src[cursor..].find("styles") {
+        let pos = cursor + rel;
+        cursor = pos + "styles".len();
+        let after = &src[cursor..];
+        let Some(open) = after.find('{') else {
+            break;
+        };
+        if after[..open].trim() != ":" {
+            continue;
+        }
+        let Some(close) = after[open..].find('}') else {
+            break;
+        };
+        let body = &after[open + 1..open + close];
+        let scope = &src[scope_start..pos];
+        scope_start = cursor + open + close;
+
+        let (Some(width), Some(height)) = (css_px(body, "width"), css_px(body, "height")) else {
+            continue;
+        };
+        let Some(name) = quoted_value(scope, "name")
+            .map(|n| slugify(&n))
+            .or_else(|| object_key(scope))
+            .filter(|n| !n.is_empty())
+        else {
+            continue;
+        };
+        if out.iter().any(|(n, _)| *n == name) {
+            continue;
+        }
+        out.push((name, Viewport::new(width, height)));
+    }
+    out
+}
+
+/// Numeric value of `key: '600px'` / `key: 600` inside an object body.
+fn css_px(body: &str, key: &str) -> Option<u32> {
+    let pos = find_key(body, key)?;
+    let rest = body[pos + key.len()..].trim_start().strip_prefix(':')?;
+    let rest = rest.trim_start().trim_start_matches(['\'', '"', '`']);
+    let digits: String = rest.chars().take_while(|c| c.is_ascii_digit()).collect();
+    let unit = &rest[digits.len()..];
+    if !(unit.starts_with("px")
+        || unit.starts_with(['\'', '"', '`', ',', ' ', '\n'])
+        || unit.is_empty())
+    {
+        return None;
+    }
+    digits.parse().ok().filter(|v| *v > 0)
+}
+
+/// String value of the last `key: '...'` in `scope`.
+pub(super) fn quoted_value(scope: &str, key: &str) -> Option<String> {
+    let pos = rfind_key(scope, key)?;
+    let rest = scope[pos + key.len()..]
+        .trim_start()
+        .strip_prefix(':')?
+        .trim_start();
+    let quote = rest
+        .chars()
+        .next()
+        .filter(|c| matches!(c, '\'' | '"' | '`'))?;
+    let value = &rest[1..];
+    let end = value.find(quote)?;
+    Some(value[..end].to_string())
+}
+
+/// Key of the innermost object opened in `scope` (`kindleFire2: {` -> `kindleFire2`).
+fn object_key(scope: &str) -> Option<String> {
+    let open = scope.rfind('{')?;
+    let before = scope[..open].trim_end().strip_suffix(':')?.trim_end();
+    let key: String = before
+        .chars()
+        .rev()
+        .take_while(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '$' | '\'' | '"'))
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .filter(|c| !matches!(c, '\'' | '"'))
+        .collect();
+    Some(slugify(&key))
+}
+
+/// Position of `key` as a whole identifier (not part of e.g. `displayName`).
+pub(super) fn find_key(s: &str, key: &str) -> Option<usize> {
+    s.match_indices(key)
+        .map(|(i, _)| i)
+        .find(|&i| is_key_boundary(s, i, key))
+}
+
+fn rfind_key(s: &str, key: &str) -> Option<usize> {
+    s.rmatch_indices(key)
+        .map(|(i, _)| i)
+        .find(|&i| is_key_boundary(s, i, key))
+}
+
+fn is_key_boundary(s: &str, i: usize, key: &str) -> bool {
+    let before = s[..i].chars().next_back();
+    let after = s[i + key.len()..].chars().next();
+    !before.is_some_and(|c| c.is_alphanumeric() || c == '_')
+        && !after.is_some_and(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// Lowercase, with runs of non-alphanumerics collapsed to `-`.
+pub(super) fn slugify(s: &str) -> String {
+    let mut out = String::new();
+    for c in s.chars() {
+        if c.is_ascii_alphanumeric() {
+            out.push(c.to_ascii_lowercase());
+        } else if !out.is_empty() && !out.ends_with('-') {
+            out.push('-');
+        }
+    }
+    out.trim_end_matches('-').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn port_from_scripts() {
+        let pkg = r#"{"scripts": {
+            "build-storybook": "storybook build",
+            "storybook:ci": "storybook dev --port=7007 --ci",
+            "storybook": "storybook dev -p 6008"
+        }}"#;
+        assert_eq!(port_from_package_json(pkg), Some(6008));
+        assert_eq!(
+            port_from_package_json(r#"{"scripts": {"sb": "start-storybook --port 9009"}}"#),
+            Some(9009)
+        );
+        assert_eq!(
+            port_from_package_json(r#"{"scripts": {"dev": "vite"}}"#),
+            None
+        );
+    }
+
+    #[test]
+    fn viewports_from_preview() {
+        let preview = r#"
+            const customViewports = {
+              kindleFire2: {
+                name: 'Kindle Fire 2',
+                styles: { width: '600px', height: '963px' },
+              },
+              tablet: {
+                styles: { width: "800px", height: "1280px" },
+              },
+              fluid: {
+                name: 'Fluid',
+                styles: { width: '100%', height: '100%' },
+              },
+            };
+            export default { parameters: { viewport: { viewports: customViewports } } };
+        "#;
+        let vps = parse_viewports(preview);
+        let summary: Vec<(&str, u32, u32)> = vps
+            .iter()
+            .map(|(n, v)| (n.as_str(), v.width, v.height))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![("kindle-fire-2", 600, 963), ("tablet", 800, 1280)]
+        );
+    }
+}
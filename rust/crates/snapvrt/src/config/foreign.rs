@@ -0,0 +1,240 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Result, bail};
+
+use super::Viewport;
+use super::detect::{find_key, quoted_value, slugify};
+use crate::storybook::Story;
+
+/// Viewport Lost Pixel captures at when no breakpoints are configured.
+const LOST_PIXEL_DEFAULT_VIEWPORT: Viewport = Viewport::new(1920, 1080);
+
+/// Visual testing tool whose setup `snapvrt init --from` can import.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum ForeignTool {
+    /// Lost Pixel (`lostpixel.config.*`, `.lostpixel/baseline/`)
+    LostPixel,
+    /// Loki (`"loki"` in package.json, `.loki/reference/`)
+    Loki,
+}
+
+/// A viewport from the foreign config, keyed by snapvrt name.
+#[derive(Debug)]
+pub struct ForeignViewport {
+    /// snapvrt viewport name (TOML-safe slug).
+    pub name: String,
+    /// Name segment the foreign tool uses in baseline file names.
+    pub key: String,
+    pub viewport: Viewport,
+}
+
+/// Config and baseline location read from a foreign project.
+#[derive(Debug)]
+pub struct ForeignSetup {
+    pub tool: ForeignTool,
+    /// Storybook URL, when the foreign config points at a server.
+    pub url: Option<String>,
+    pub viewports: Vec<ForeignViewport>,
+    pub baseline_dir: PathBuf,
+}
+
+impl ForeignSetup {
+    /// Read the foreign tool's config from `dir`.
+    pub fn load(tool: ForeignTool, dir: &Path) -> Result<Self> {
+        let setup = match tool {
+            ForeignTool::LostPixel => {
+                let config = ["ts", "js", "mjs", "cjs"].iter().find_map(|ext| {
+                    std::fs::read_to_string(dir.join(format!("lostpixel.config.{ext}"))).ok()
+                });
+                let (url, viewports) = config
+                    .as_deref()
+                    .map(parse_lost_pixel)
+                    .unwrap_or_else(|| (None, lost_pixel_viewports(&[])));
+                Self {
+                    tool,
+                    url,
+                    viewports,
+                    baseline_dir: dir.join(".lostpixel").join("baseline"),
+                }
+            }
+            ForeignTool::Loki => {
+                let viewports = std::fs::read_to_string(dir.join("package.json"))
+                    .ok()
+                    .map(|s| parse_loki_configurations(&s))
+                    .unwrap_or_default();
+                Self {
+                    tool,
+                    url: None,
+                    viewports,
+                    baseline_dir: dir.join(".loki").join("reference"),
+                }
+            }
+        };
+        if !setup.baseline_dir.is_dir() {
+            bail!(
+                "No baselines found at {} — is this a {} project?",
+                setup.baseline_dir.display(),
+                setup.tool.label()
+            );
+        }
+        Ok(setup)
+    }
+
+    /// Baseline file stem the foreign tool would use for `story` at `viewport`.
+    pub fn baseline_name(&self, viewport: &ForeignViewport, story: &Story) -> String {
+        match self.tool {
+            // `{story id}`, or `{story id}__w{width}px` per breakpoint.
+            ForeignTool::LostPixel if viewport.key.is_empty() => story.id.clone(),
+            ForeignTool::LostPixel => format!("{}__{}", story.id, viewport.key),
+            // `{configuration}_{kind}_{story}`, non-identifier chars as `_`.
+            ForeignTool::Loki => format!("{}_{}_{}", viewport.key, story.title, story.name)
+                .chars()
+                .map(|c| {
+                    if c.is_ascii_alphanumeric() || c == '-' {
+                        c
+                    } else {
+                        '_'
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    /// PNG baselines in `baseline_dir`, keyed by file stem.
+    pub fn baseline_files(&self) -> BTreeMap<String, PathBuf> {
+        let Ok(entries) = std::fs::read_dir(&self.baseline_dir) else {
+            return BTreeMap::new();
+        };
+        entries
+            .flatten()
+            .map(|e| e.path())
+            .filter(|p| p.extension().is_some_and(|e| e.eq_ignore_ascii_case("png")))
+            .filter_map(|p| Some((p.file_stem()?.to_string_lossy().into_owned(), p)))
+            .collect()
+    }
+}
+
+impl ForeignTool {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::LostPixel => "Lost Pixel",
+            Self::Loki => "Loki",
+        }
+    }
+}
+
+/// `storybookUrl` (only when it is an http URL) and `breakpoints` from a
+/// Lost Pixel config.
+fn parse_lost_pixel(src: &str) -> (Option<String>, Vec<ForeignViewport>) {
+    let url = quoted_value(src, "storybookUrl").filter(|u| u.starts_with("http"));
+    let breakpoints: Vec<u32> = find_key(src, "breakpoints")
+        .and_then(|pos| {
+            let rest = &src[pos..];
+            let open = rest.find('[')?;
+            let close = rest[open..].find(']')?;
+            Some(
+                rest[open + 1..open + close]
+                    .split(',')
+                    .filter_map(|v| v.trim().parse().ok())
+                    .collect(),
+            )
+        })
+        .unwrap_or_default();
+    (url, lost_pixel_viewports(&breakpoints))
+}
+
+fn lost_pixel_viewports(breakpoints: &[u32]) -> Vec<ForeignViewport> {
+    if breakpoints.is_empty() {
+        return vec![ForeignViewport {
+            name: "desktop".to_string(),
+            key: String::new(),
+            viewport: LOST_PIXEL_DEFAULT_VIEWPORT,
+        }];
+    }
+    breakpoints
+        .iter()
+        .map(|&width| ForeignViewport {
+            name: format!("w{width}"),
+            key: format!("w{width}px"),
+            viewport: Viewport::new(width, LOST_PIXEL_DEFAULT_VIEWPORT.height),
+        })
+        .collect()
+}
+
+/// `loki.configurations` from package.json (entries with width and height).
+fn parse_loki_configurations(package_json: &str) -> Vec<ForeignViewport> {
+    let Ok(json) = serde_json::from_str::<serde_json::Value>(package_json) else {
+        return Vec::new();
+    };
+    let Some(configs) = json
+        .get("loki")
+        .and_then(|l| l.get("configurations"))
+        .and_then(|c| c.as_object())
+    else {
+        return Vec::new();
+    };
+    configs
+        .iter()
+        .filter_map(|(key, cfg)| {
+            let width = cfg.get("width")?.as_u64()? as u32;
+            let height = cfg.get("height")?.as_u64()? as u32;
+            Some(ForeignViewport {
+                name: slugify(key),
+                key: key.clone(),
+                viewport: Viewport::new(width, height),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn story() -> Story {
+        Story {
+            id: "forms-button--primary".into(),
+            name: "Primary".into(),
+            title: "Forms/Button".into(),
+            tags: Vec::new(),
+            url: None,
+        }
+    }
+
+    #[test]
+    fn lost_pixel_breakpoint_names() {
+        let (url, viewports) = parse_lost_pixel(
+            "export const config = { storybookShots: { storybookUrl: 'http://localhost:6006' }, breakpoints: [640, 1024] };",
+        );
+        assert_eq!(url.as_deref(), Some("http://localhost:6006"));
+        let setup = ForeignSetup {
+            tool: ForeignTool::LostPixel,
+            url,
+            viewports,
+            baseline_dir: PathBuf::new(),
+        };
+        assert_eq!(
+            setup.baseline_name(&setup.viewports[0], &story()),
+            "forms-button--primary__w640px"
+        );
+    }
+
+    #[test]
+    fn loki_names() {
+        let viewports = parse_loki_configurations(
+            r#"{"loki": {"configurations": {"chrome.laptop": {"target": "chrome.docker", "width": 1366, "height": 768}}}}"#,
+        );
+        assert_eq!(viewports[0].name, "chrome-laptop");
+        let setup = ForeignSetup {
+            tool: ForeignTool::Loki,
+            url: None,
+            viewports,
+            baseline_dir: PathBuf::new(),
+        };
+        assert_eq!(
+            setup.baseline_name(&setup.viewports[0], &story()),
+            "chrome_laptop_Forms_Button_Primary"
+        );
+    }
+}
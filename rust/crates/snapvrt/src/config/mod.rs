@@ -1,25 +1,261 @@
 pub mod capture;
+pub mod detect;
+pub mod foreign;
 pub mod resolve;
+pub mod story;
 pub mod template;
 
 use std::collections::BTreeMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use anyhow::{Context, Result, bail};
 use serde::{Deserialize, Serialize};
 
-pub use self::capture::CaptureConfig;
+pub use self::capture::{
+    CaptureConfig, CaptureOrder, DialogAction, DomMode, Isolation, Popups, TokenPlacement,
+};
 pub use self::resolve::{CliOverrides, ResolvedRunConfig};
-pub use self::template::{config_file_exists, write_gitignore, write_template};
+pub use self::story::{ScrollTo, StoryConfig, matches_pattern, owner_for, settings_for};
+pub use self::template::{GITIGNORE_ENTRIES, config_file_exists, write_gitignore, write_template};
+use crate::capture::job::{DEFAULT_ID_TEMPLATE, validate_id_template};
+use crate::compare::render::DiffRender;
+use crate::storybook::{NextjsOptions, PageOptions, SourceKind};
 
 pub(crate) const CONFIG_DIR: &str = ".snapvrt";
 const CONFIG_FILE: &str = "config.toml";
+const DEFAULT_MAX_MEGAPIXELS: f64 = 50.0;
+
+/// Metric `diff.threshold` is checked against.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffMetric {
+    /// Share of perceptually changed pixels.
+    #[default]
+    Pixel,
+    /// Structural dissimilarity, `1 - SSIM`. Tolerates gradient and
+    /// anti-aliasing noise better than pixel counts.
+    Ssim,
+    /// Both: `threshold` applies to the pixel score, `ssim_threshold` to
+    /// `1 - SSIM`, and both are reported.
+    Hybrid,
+    /// Mean LDR-FLIP error, calibrated to how visible differences are when
+    /// flipping between images. Needs the `flip` build feature.
+    Flip,
+}
+
+/// Which parts of each pixel `diff` compares.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffChannels {
+    /// Color and transparency.
+    #[default]
+    Rgba,
+    /// Color only, so differences in transparency alone (Chrome compositing
+    /// transparent backgrounds) don't count.
+    Rgb,
+    /// Brightness only (Rec. 601 luma), ignoring hue and transparency.
+    Luminance,
+}
+
+/// How `difference/` images are drawn.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DiffStyle {
+    /// Changed pixels over a faded reference.
+    #[default]
+    Overlay,
+    /// Change intensity blended over a dimmed current image.
+    Heatmap,
+    /// Reference, current and overlay side by side in one image.
+    SideBySide,
+}
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct DiffConfig {
     /// Maximum allowed diff score (0.0-1.0). Snapshots with score <= threshold pass.
     #[serde(default)]
     pub threshold: f64,
+    /// Largest image decoded for comparison, in megapixels. Bigger captures
+    /// fail with an error instead of exhausting memory.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_megapixels: Option<f64>,
+    #[serde(default)]
+    pub metric: DiffMetric,
+    /// Max `1 - SSIM` with `metric = "hybrid"`. Unset: SSIM is only reported.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssim_threshold: Option<f64>,
+    #[serde(default)]
+    pub style: DiffStyle,
+    /// Changed-pixel color, `#rrggbb` (default red).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub highlight_color: Option<String>,
+    /// Outline changed regions in the diff image.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub boxes: Option<bool>,
+    #[serde(default)]
+    pub channels: DiffChannels,
+    /// Gaussian blur sigma (pixels) applied to both images before diffing,
+    /// to tolerate sub-pixel text rendering differences.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blur_sigma: Option<f32>,
+    /// `[[diff.override]]`: thresholds for particular snapshots.
+    #[serde(default, rename = "override", skip_serializing_if = "Vec::is_empty")]
+    pub overrides: Vec<DiffOverride>,
+}
+
+/// Threshold for the snapshots matching `snapshot`, over the viewport's and
+/// `diff.threshold`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiffOverride {
+    /// Snapshot ID, or a pattern with `*` wildcards.
+    pub snapshot: String,
+    pub threshold: f64,
+}
+
+/// The override for snapshot `id`: an exact match if there is one,
+/// otherwise the first matching `*` pattern.
+pub fn override_for<'a>(overrides: &'a [DiffOverride], id: &str) -> Option<&'a DiffOverride> {
+    overrides.iter().find(|o| o.snapshot == id).or_else(|| {
+        overrides
+            .iter()
+            .find(|o| o.snapshot.contains('*') && matches_pattern(&o.snapshot, id))
+    })
+}
+
+/// Write `overrides` to the config file as `[[diff.override]]` tables,
+/// leaving the rest of it (comments included) as it is. An existing entry
+/// for the same snapshot gets the new threshold: a second one would never
+/// be reached by [`override_for`].
+pub fn write_overrides(overrides: &[DiffOverride]) -> Result<PathBuf> {
+    let path = Path::new(CONFIG_DIR).join(CONFIG_FILE);
+    let content = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let content = merge_overrides(&content, overrides)
+        .with_context(|| format!("Failed to update {}", path.display()))?;
+    std::fs::write(&path, content)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+fn merge_overrides(content: &str, overrides: &[DiffOverride]) -> Result<String> {
+    use toml_edit::{ArrayOfTables, DocumentMut, Item, Table, value};
+
+    let mut doc: DocumentMut = content.parse()?;
+    let diff = doc.entry("diff").or_insert_with(|| {
+        let mut table = Table::new();
+        table.set_implicit(true);
+        Item::Table(table)
+    });
+    let tables = diff
+        .as_table_mut()
+        .context("diff must be a table")?
+        .entry("override")
+        .or_insert_with(|| Item::ArrayOfTables(ArrayOfTables::new()))
+        .as_array_of_tables_mut()
+        .context("diff.override must be an array of tables")?;
+    for o in overrides {
+        let existing = tables
+            .iter_mut()
+            .find(|t| t.get("snapshot").and_then(Item::as_str) == Some(&o.snapshot));
+        match existing {
+            Some(table) => table["threshold"] = value(o.threshold),
+            None => {
+                let mut table = Table::new();
+                table["snapshot"] = value(&o.snapshot);
+                table["threshold"] = value(o.threshold);
+                tables.push(table);
+            }
+        }
+    }
+    Ok(doc.to_string())
+}
+
+impl DiffConfig {
+    pub fn max_pixels(&self) -> u64 {
+        (self.max_megapixels.unwrap_or(DEFAULT_MAX_MEGAPIXELS) * 1e6) as u64
+    }
+
+    pub fn render(&self) -> Result<DiffRender> {
+        let mut render = DiffRender::DEFAULT;
+        render.style = self.style;
+        render.boxes = self.boxes.unwrap_or(false);
+        if let Some(color) = &self.highlight_color {
+            render.color = parse_hex_color(color)
+                .with_context(|| format!("diff.highlight_color must be #rrggbb, got {color:?}"))?;
+        }
+        Ok(render)
+    }
+}
+
+fn parse_hex_color(s: &str) -> Option<[u8; 3]> {
+    let hex = s.strip_prefix('#')?;
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
+
+/// On-disk encoding for reference and current snapshots.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StoreFormat {
+    #[default]
+    Png,
+    /// Lossless WebP — typically much smaller than PNG for UI screenshots.
+    Webp,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StoreConfig {
+    /// Losslessly recompress reference PNGs (oxipng) when writing them.
+    #[serde(default)]
+    pub optimize: bool,
+    /// Encoding for reference and current images. Diff images stay PNG.
+    #[serde(default)]
+    pub format: StoreFormat,
+    /// Snapshot ID / file layout, e.g. `{source}/{title}/{name}@{viewport}`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id_template: Option<String>,
+    /// Days an orphaned reference is kept quiet before it is reported as
+    /// removed (and `--prune`/`prune` delete it).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub orphan_grace_days: Option<u32>,
+    /// Full `test` runs an orphaned reference is kept quiet for, likewise.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub orphan_grace_runs: Option<u32>,
+    /// Refuse to `approve` or `update` from a detached HEAD or a working tree with
+    /// uncommitted changes, so every reference traces to a commit.
+    #[serde(default)]
+    pub require_clean_git: bool,
+}
+
+impl StoreConfig {
+    pub fn id_template(&self) -> &str {
+        self.id_template.as_deref().unwrap_or(DEFAULT_ID_TEMPLATE)
+    }
+}
+
+/// Where to announce failed `test` runs.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NotifyConfig {
+    /// Slack-compatible incoming webhook. Also `SNAPVRT_NOTIFY_WEBHOOK_URL`,
+    /// which keeps the secret out of the committed config.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook_url: Option<String>,
+    /// Link to the published report (e.g. a CI artifact page), included in
+    /// the message. Also `SNAPVRT_NOTIFY_REPORT_URL`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub report_url: Option<String>,
+}
+
+/// Blur radius in pixels: past 10 the blur hides real changes, and it gets
+/// slow on large screenshots.
+fn validate_blur_sigma(v: f32) -> Result<f32, String> {
+    if !(v.is_finite() && (0.0..=10.0).contains(&v)) {
+        return Err(format!("blur_sigma must be between 0 and 10, got {v}"));
+    }
+    Ok(v)
 }
 
 pub fn validate_threshold(v: f64) -> Result<f64, String> {
@@ -39,9 +275,68 @@ pub struct Config {
     pub capture: CaptureConfig,
     #[serde(default)]
     pub diff: DiffConfig,
+    #[serde(default)]
+    pub store: StoreConfig,
+    #[serde(default)]
+    pub notify: NotifyConfig,
+    /// Owning team per story title pattern, e.g. `"Forms/*" = "@forms"`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub owners: BTreeMap<String, String>,
+    /// Per-story settings, keyed by Storybook story ID.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub story: BTreeMap<String, StoryConfig>,
 }
 
 impl Config {
+    /// Replace each sweep viewport (`widths = [...]`) with one viewport per
+    /// width, named `<name>-<width>`, including in source `viewports` lists.
+    fn expand_sweeps(&mut self) -> Result<()> {
+        let sweeps: Vec<String> = self
+            .viewport
+            .iter()
+            .filter(|(_, vp)| vp.widths.is_some())
+            .map(|(name, _)| name.clone())
+            .collect();
+
+        for name in sweeps {
+            let Some(sweep) = self.viewport.remove(&name) else {
+                continue;
+            };
+            let widths = sweep.widths.unwrap_or_default();
+            if sweep.width != 0 {
+                bail!("Viewport '{name}' sets both width and widths. Use one or the other");
+            }
+            if widths.is_empty() {
+                bail!("Viewport '{name}' has an empty widths list");
+            }
+
+            let mut expanded = Vec::with_capacity(widths.len());
+            for width in widths {
+                let vp_name = format!("{name}-{width}");
+                let vp = Viewport {
+                    threshold: sweep.threshold,
+                    ..Viewport::new(width, sweep.height)
+                };
+                if self.viewport.insert(vp_name.clone(), vp).is_some() {
+                    bail!(
+                        "Viewport sweep '{name}' expands to '{vp_name}', which is already defined"
+                    );
+                }
+                expanded.push(vp_name);
+            }
+
+            for source in self.source.values_mut() {
+                let Some(refs) = source.viewports_mut() else {
+                    continue;
+                };
+                if let Some(pos) = refs.iter().position(|r| *r == name) {
+                    refs.splice(pos..=pos, expanded.iter().cloned());
+                }
+            }
+        }
+        Ok(())
+    }
+
     /// Validate semantic constraints that serde cannot express.
     fn validate(&self) -> Result<()> {
         if self.source.is_empty() {
@@ -73,6 +368,10 @@ impl Config {
             }
         }
 
+        for (id, story) in &self.story {
+            story.validate(id)?;
+        }
+
         for (source_name, source) in &self.source {
             if let Some(refs) = source.viewports() {
                 for vp_ref in refs {
@@ -100,38 +399,187 @@ pub enum SourceConfig {
         url: String,
         #[serde(default)]
         viewports: Option<Vec<String>>,
+        /// Extra HTTP headers sent with index.json discovery and page loads.
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        headers: BTreeMap<String, String>,
+        /// HTTP basic auth credentials, sent as an `Authorization` header.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        basic_auth: Option<BasicAuth>,
+    },
+    /// A Ladle dev server or build, discovered through `meta.json`.
+    #[serde(rename = "ladle")]
+    Ladle {
+        url: String,
+        #[serde(default)]
+        viewports: Option<Vec<String>>,
+        /// Extra HTTP headers sent with meta.json discovery and page loads.
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        headers: BTreeMap<String, String>,
+        /// HTTP basic auth credentials, sent as an `Authorization` header.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        basic_auth: Option<BasicAuth>,
+    },
+    /// Pages listed in a sitemap (`url` is the site or the sitemap itself).
+    #[serde(rename = "sitemap")]
+    Sitemap {
+        url: String,
+        #[serde(default)]
+        viewports: Option<Vec<String>>,
+        /// Extra HTTP headers sent with sitemap requests and page loads.
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        headers: BTreeMap<String, String>,
+        /// HTTP basic auth credentials, sent as an `Authorization` header.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        basic_auth: Option<BasicAuth>,
+        #[serde(flatten)]
+        pages: PageOptions,
+    },
+    /// Next.js routes from the filesystem, captured against `url`.
+    #[serde(rename = "nextjs")]
+    Nextjs {
+        url: String,
+        #[serde(default)]
+        viewports: Option<Vec<String>>,
+        /// Extra HTTP headers sent with page loads.
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        headers: BTreeMap<String, String>,
+        /// HTTP basic auth credentials, sent as an `Authorization` header.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        basic_auth: Option<BasicAuth>,
+        #[serde(flatten)]
+        routes: NextjsOptions,
+        #[serde(flatten)]
+        pages: PageOptions,
     },
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BasicAuth {
+    pub username: String,
+    pub password: String,
+}
+
+impl BasicAuth {
+    /// Parse `user:password` (as in `SNAPVRT_STORYBOOK_BASIC_AUTH`).
+    pub fn parse(s: &str) -> Option<Self> {
+        let (username, password) = s.split_once(':')?;
+        Some(Self {
+            username: username.to_string(),
+            password: password.to_string(),
+        })
+    }
+
+    /// `Authorization` header value.
+    pub fn header_value(&self) -> String {
+        use base64::Engine;
+        let credentials = format!("{}:{}", self.username, self.password);
+        format!(
+            "Basic {}",
+            base64::engine::general_purpose::STANDARD.encode(credentials)
+        )
+    }
+}
+
 impl SourceConfig {
+    pub fn kind(&self) -> SourceKind {
+        match self {
+            Self::Storybook { .. } => SourceKind::Storybook,
+            Self::Ladle { .. } => SourceKind::Ladle,
+            Self::Sitemap { .. } => SourceKind::Sitemap,
+            Self::Nextjs { .. } => SourceKind::Nextjs,
+        }
+    }
+
+    /// Page selection, for sitemap and Next.js sources.
+    pub fn page_options(&self) -> Option<&PageOptions> {
+        match self {
+            Self::Sitemap { pages, .. } | Self::Nextjs { pages, .. } => Some(pages),
+            _ => None,
+        }
+    }
+
+    /// Route discovery, for Next.js sources.
+    pub fn nextjs_options(&self) -> Option<&NextjsOptions> {
+        match self {
+            Self::Nextjs { routes, .. } => Some(routes),
+            _ => None,
+        }
+    }
+
     pub fn url(&self) -> &str {
         match self {
-            Self::Storybook { url, .. } => url,
+            Self::Storybook { url, .. }
+            | Self::Ladle { url, .. }
+            | Self::Sitemap { url, .. }
+            | Self::Nextjs { url, .. } => url,
         }
     }
 
     pub fn viewports(&self) -> Option<&[String]> {
         match self {
-            Self::Storybook { viewports, .. } => viewports.as_deref(),
+            Self::Storybook { viewports, .. }
+            | Self::Ladle { viewports, .. }
+            | Self::Sitemap { viewports, .. }
+            | Self::Nextjs { viewports, .. } => viewports.as_deref(),
+        }
+    }
+
+    fn viewports_mut(&mut self) -> Option<&mut Vec<String>> {
+        match self {
+            Self::Storybook { viewports, .. }
+            | Self::Ladle { viewports, .. }
+            | Self::Sitemap { viewports, .. }
+            | Self::Nextjs { viewports, .. } => viewports.as_mut(),
+        }
+    }
+
+    pub fn headers(&self) -> &BTreeMap<String, String> {
+        match self {
+            Self::Storybook { headers, .. }
+            | Self::Ladle { headers, .. }
+            | Self::Sitemap { headers, .. }
+            | Self::Nextjs { headers, .. } => headers,
+        }
+    }
+
+    pub fn basic_auth(&self) -> Option<&BasicAuth> {
+        match self {
+            Self::Storybook { basic_auth, .. }
+            | Self::Ladle { basic_auth, .. }
+            | Self::Sitemap { basic_auth, .. }
+            | Self::Nextjs { basic_auth, .. } => basic_auth.as_ref(),
         }
     }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Viewport {
+    #[serde(default)]
     pub width: u32,
     pub height: u32,
+    /// Overrides `diff.threshold` for snapshots at this viewport.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub threshold: Option<f64>,
+    /// Sweep: instead of `width`, capture at each of these widths (expanded
+    /// into `<name>-<width>` viewports when the config is loaded).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub widths: Option<Vec<u32>>,
+}
+
+impl Viewport {
+    pub const fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            threshold: None,
+            widths: None,
+        }
+    }
 }
 
 fn default_viewports() -> BTreeMap<String, Viewport> {
     let mut m = BTreeMap::new();
-    m.insert(
-        "laptop".to_string(),
-        Viewport {
-            width: 1366,
-            height: 768,
-        },
-    );
+    m.insert("laptop".to_string(), Viewport::new(1366, 768));
     m
 }
 
@@ -139,9 +587,123 @@ pub fn load() -> Result<Config> {
     let path = Path::new(CONFIG_DIR).join(CONFIG_FILE);
     let content = std::fs::read_to_string(&path)
         .with_context(|| format!("Failed to read {}", path.display()))?;
-    let config: Config =
+    let mut config: Config =
         toml::from_str(&content).with_context(|| format!("Failed to parse {}", path.display()))?;
+    config.expand_sweeps()?;
     validate_threshold(config.diff.threshold).map_err(|e| anyhow::anyhow!("diff.{e}"))?;
+    if config.diff.metric == DiffMetric::Flip && !cfg!(feature = "flip") {
+        anyhow::bail!(
+            "diff.metric = \"flip\" needs snapvrt built with the `flip` feature \
+             (cargo install snapvrt --features flip)"
+        );
+    }
+    config.diff.render()?;
+    if let Some(t) = config.diff.ssim_threshold {
+        validate_threshold(t).map_err(|e| anyhow::anyhow!("diff.ssim_{e}"))?;
+    }
+    if let Some(sigma) = config.diff.blur_sigma {
+        validate_blur_sigma(sigma).map_err(|e| anyhow::anyhow!("diff.{e}"))?;
+    }
+    for o in &config.diff.overrides {
+        validate_threshold(o.threshold)
+            .map_err(|e| anyhow::anyhow!("diff.override for '{}': {e}", o.snapshot))?;
+    }
+    if let Some(mp) = config.diff.max_megapixels
+        && !(mp.is_finite() && mp > 0.0)
+    {
+        anyhow::bail!("diff.max_megapixels must be > 0, got {mp}");
+    }
+    for (name, vp) in &config.viewport {
+        if let Some(t) = vp.threshold {
+            validate_threshold(t).map_err(|e| anyhow::anyhow!("viewport.{name}.{e}"))?;
+        }
+    }
+    validate_id_template(config.store.id_template()).map_err(|e| anyhow::anyhow!("store.{e}"))?;
     config.validate()?;
     Ok(config)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sweep_expands_into_viewport_per_width() {
+        let mut config: Config = toml::from_str(
+            r#"
+            [source.storybook]
+            type = "storybook"
+            url = "http://localhost:6006"
+            viewports = ["mobile", "sweep"]
+
+            [viewport.mobile]
+            width = 375
+            height = 667
+
+            [viewport.sweep]
+            widths = [320, 1024]
+            height = 900
+            "#,
+        )
+        .unwrap();
+        config.expand_sweeps().unwrap();
+
+        let names: Vec<&str> = config.viewport.keys().map(String::as_str).collect();
+        assert_eq!(names, ["mobile", "sweep-1024", "sweep-320"]);
+        assert_eq!(config.viewport["sweep-320"].height, 900);
+        assert_eq!(
+            config.source["storybook"].viewports().unwrap(),
+            ["mobile", "sweep-320", "sweep-1024"]
+        );
+        config.validate().unwrap();
+    }
+
+    #[test]
+    fn blur_sigma_is_bounded() {
+        assert_eq!(validate_blur_sigma(0.0), Ok(0.0));
+        assert_eq!(validate_blur_sigma(10.0), Ok(10.0));
+        for bad in [-0.5, 10.5, f32::NAN, f32::INFINITY] {
+            assert!(validate_blur_sigma(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn written_overrides_replace_existing_entries() {
+        let content = r#"
+            [diff]
+            threshold = 0.01
+
+            # Charts never render the same twice.
+            [[diff.override]]
+            snapshot = "Charts/Line@desktop"
+            threshold = 0.02
+            "#;
+        let merged = merge_overrides(
+            content,
+            &[
+                DiffOverride {
+                    snapshot: "Charts/Line@desktop".to_string(),
+                    threshold: 0.05,
+                },
+                DiffOverride {
+                    snapshot: "Hero@mobile".to_string(),
+                    threshold: 0.03,
+                },
+            ],
+        )
+        .unwrap();
+        assert!(merged.contains("# Charts never render the same twice."));
+
+        let diff = toml::from_str::<Config>(&merged).unwrap().diff;
+        assert_eq!(diff.overrides.len(), 2);
+        let threshold = |id| override_for(&diff.overrides, id).unwrap().threshold;
+        assert_eq!(threshold("Charts/Line@desktop"), 0.05);
+        assert_eq!(threshold("Hero@mobile"), 0.03);
+
+        // No [diff] table yet.
+        let merged = merge_overrides("", &diff.overrides).unwrap();
+        assert!(!merged.contains("[diff]\n"));
+        let diff = toml::from_str::<Config>(&merged).unwrap().diff;
+        assert_eq!(diff.overrides.len(), 2);
+    }
+}
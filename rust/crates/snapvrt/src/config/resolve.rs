@@ -3,7 +3,15 @@ use std::collections::BTreeMap;
 use anyhow::{Context, Result};
 
 use super::capture::CaptureConfig;
-use super::{Viewport, load, validate_threshold};
+use crate::storybook::{NextjsOptions, PageOptions, SourceKind};
+
+use super::{
+    BasicAuth, DiffChannels, DiffMetric, DiffOverride, NotifyConfig, StoreConfig, StoryConfig,
+    Viewport, load, override_for, validate_threshold,
+};
+use crate::compare::Thresholds;
+use crate::compare::diff::CompareOptions;
+use crate::compare::render::DiffRender;
 
 /// Values extracted from the CLI that participate in the merge.
 pub struct CliOverrides {
@@ -15,9 +23,37 @@ pub struct CliOverrides {
 /// Fully resolved config after CLI > env > file > defaults merge.
 pub struct ResolvedRunConfig {
     pub storybook_url: String,
+    /// Extra HTTP headers (including basic auth) for discovery and page loads.
+    pub headers: BTreeMap<String, String>,
     pub capture: CaptureConfig,
+    /// Global threshold; see `threshold_for` for per-viewport and
+    /// per-snapshot overrides.
     pub diff_threshold: f64,
+    /// `[[diff.override]]` entries.
+    pub diff_overrides: Vec<DiffOverride>,
+    /// Largest image decoded for comparison (`diff.max_megapixels`).
+    pub max_pixels: u64,
+    pub diff_metric: DiffMetric,
+    /// `diff.ssim_threshold`, for the hybrid metric.
+    pub ssim_threshold: Option<f64>,
+    pub diff_render: DiffRender,
+    /// `diff.blur_sigma`.
+    pub blur_sigma: Option<f32>,
+    pub diff_channels: DiffChannels,
     pub viewports: BTreeMap<String, Viewport>,
+    pub store: StoreConfig,
+    /// `[notify]`, with `SNAPVRT_NOTIFY_*` overrides applied.
+    pub notify: NotifyConfig,
+    /// Per-story settings, keyed by story ID.
+    pub stories: BTreeMap<String, StoryConfig>,
+    /// `[owners]`: team per story title pattern; see `owner_for`.
+    pub owners: BTreeMap<String, String>,
+    /// Story explorer the source is served by.
+    pub source_kind: SourceKind,
+    /// Page selection, for sitemap and Next.js sources.
+    pub pages: PageOptions,
+    /// Route discovery, for Next.js sources.
+    pub nextjs: NextjsOptions,
     /// Source name (from `[source.<name>]` map key), used as top-level
     /// directory in the snapshot hierarchy.
     pub source_name: String,
@@ -35,6 +71,21 @@ impl ResolvedRunConfig {
             .map(|v| v.parse::<f64>())
             .transpose()
             .context("SNAPVRT_DIFF_THRESHOLD must be a valid float")?;
+        let env_basic_auth = std::env::var("SNAPVRT_STORYBOOK_BASIC_AUTH")
+            .ok()
+            .map(|v| BasicAuth::parse(&v))
+            .map(|auth| auth.context("SNAPVRT_STORYBOOK_BASIC_AUTH must be user:password"))
+            .transpose()?;
+
+        let env_chrome_token = std::env::var("SNAPVRT_CHROME_TOKEN").ok();
+
+        let mut notify = file_config.notify;
+        if let Ok(url) = std::env::var("SNAPVRT_NOTIFY_WEBHOOK_URL") {
+            notify.webhook_url = Some(url);
+        }
+        if let Ok(url) = std::env::var("SNAPVRT_NOTIFY_REPORT_URL") {
+            notify.report_url = Some(url);
+        }
 
         // 3. Extract the single source (multi-source is future work)
         let (source_name, source) = file_config
@@ -50,18 +101,64 @@ impl ResolvedRunConfig {
             .or(env_url)
             .unwrap_or_else(|| source.url().to_owned());
 
-        let diff_threshold = cli
-            .threshold
-            .or(env_threshold)
-            .unwrap_or(file_config.diff.threshold);
+        let mut headers = source.headers().clone();
+        if let Some(auth) = env_basic_auth.as_ref().or(source.basic_auth()) {
+            headers.insert("Authorization".to_string(), auth.header_value());
+        }
+
+        let threshold_override = cli.threshold.or(env_threshold);
+        let diff_threshold = threshold_override.unwrap_or(file_config.diff.threshold);
         validate_threshold(diff_threshold).map_err(|e| anyhow::anyhow!("{e}"))?;
 
         // 5. Merge capture: file base, then CLI overlay
         let mut capture = file_config.capture;
+        if env_chrome_token.is_some() {
+            capture.chrome_token = env_chrome_token;
+        }
         capture.merge(&cli.capture);
+        if let Some(t) = capture.media_time
+            && !(t.is_finite() && t >= 0.0)
+        {
+            anyhow::bail!("capture.media_time must be >= 0 seconds, got {t}");
+        }
+        if let Some(t) = capture.stability_tolerance
+            && !(0.0..=1.0).contains(&t)
+        {
+            anyhow::bail!("capture.stability_tolerance must be between 0 and 1, got {t}");
+        }
+        let scale = capture.device_scale();
+        if !(scale.is_finite() && scale > 0.0 && scale <= 4.0) {
+            anyhow::bail!("capture.device_scale must be > 0 and <= 4, got {scale}");
+        }
+        if let Some(url) = &capture.webdriver_url {
+            if capture.chrome_url.is_some() {
+                anyhow::bail!("capture.webdriver_url and capture.chrome_url are exclusive");
+            }
+            if capture.screenshot_format.unwrap_or_default()
+                != super::capture::ScreenshotFormat::Png
+            {
+                anyhow::bail!(
+                    "WebDriver ({url}) only takes PNG screenshots; unset capture.screenshot_format"
+                );
+            }
+            if scale != 1.0 {
+                anyhow::bail!("WebDriver ({url}) can't emulate capture.device_scale = {scale}");
+            }
+        } else if capture.chrome_url.is_none() {
+            // Container from `snapvrt chrome up`, when one is running.
+            capture.chrome_url = crate::cdp::docker::managed_url();
+        }
+        if capture.isolation() == super::Isolation::Browser
+            && let Some(url) = &capture.chrome_url
+        {
+            anyhow::bail!(
+                "capture.isolation = \"browser\" launches a Chrome per worker, \
+                 which a remote Chrome ({url}) can't do; use \"context\" instead"
+            );
+        }
 
         // 6. Resolve viewports: if source specifies a subset, filter; otherwise use all
-        let viewports = match source.viewports() {
+        let mut viewports = match source.viewports() {
             Some(selected) => {
                 let mut filtered = BTreeMap::new();
                 for name in selected {
@@ -74,13 +171,68 @@ impl ResolvedRunConfig {
             }
             None => file_config.viewport,
         };
+        // An explicit --threshold / SNAPVRT_DIFF_THRESHOLD applies everywhere.
+        let mut diff_overrides = file_config.diff.overrides.clone();
+        if threshold_override.is_some() {
+            for vp in viewports.values_mut() {
+                vp.threshold = None;
+            }
+            diff_overrides.clear();
+        }
 
         Ok(Self {
             storybook_url,
+            headers,
             capture,
             diff_threshold,
+            diff_overrides,
+            max_pixels: file_config.diff.max_pixels(),
+            diff_metric: file_config.diff.metric,
+            ssim_threshold: file_config.diff.ssim_threshold,
+            diff_render: file_config.diff.render()?,
+            blur_sigma: file_config.diff.blur_sigma,
+            diff_channels: file_config.diff.channels,
             viewports,
+            store: file_config.store,
+            notify,
+            stories: file_config.story,
+            owners: file_config.owners,
+            source_kind: source.kind(),
+            pages: source.page_options().cloned().unwrap_or_default(),
+            nextjs: source.nextjs_options().cloned().unwrap_or_default(),
             source_name,
         })
     }
+
+    pub fn compare_options(&self) -> CompareOptions {
+        CompareOptions {
+            max_pixels: self.max_pixels,
+            ssim: matches!(self.diff_metric, DiffMetric::Ssim | DiffMetric::Hybrid),
+            flip: self.diff_metric == DiffMetric::Flip,
+            render: self.diff_render,
+            blur_sigma: self.blur_sigma,
+            channels: self.diff_channels,
+        }
+    }
+
+    /// Pass criteria for snapshot `id`, captured at `viewport`.
+    pub fn thresholds_for(&self, viewport: &str, id: &str) -> Thresholds {
+        Thresholds {
+            metric: self.diff_metric,
+            threshold: self.threshold_for(viewport, id),
+            ssim_threshold: self.ssim_threshold,
+        }
+    }
+
+    /// Diff threshold for snapshot `id`: its `[[diff.override]]`, else the
+    /// threshold of `viewport`, else `diff.threshold`.
+    pub fn threshold_for(&self, viewport: &str, id: &str) -> f64 {
+        if let Some(o) = override_for(&self.diff_overrides, id) {
+            return o.threshold;
+        }
+        self.viewports
+            .get(viewport)
+            .and_then(|vp| vp.threshold)
+            .unwrap_or(self.diff_threshold)
+    }
 }
@@ -0,0 +1,368 @@
+use std::collections::BTreeMap;
+
+use anyhow::{Result, bail};
+use serde::{Deserialize, Serialize};
+
+/// Per-story capture settings (`[story."<story-id>"]`, or a `*` pattern such
+/// as `[story."forms-button--*"]`).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StoryConfig {
+    /// Where to scroll before capture: a y-offset in CSS pixels, or a CSS
+    /// selector scrolled into view (scrolling overflow containers as needed).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scroll_to: Option<ScrollTo>,
+    /// Overflow container a numeric `scroll_to` applies to, instead of the window.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scroll_container: Option<String>,
+    /// Overrides `capture.focus_steps` for this story.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub focus_steps: Option<u32>,
+    /// Simulated connection for this story: `"Fast3G"`, `"Slow3G"`, or
+    /// `{ latency_ms, download_kbps, upload_kbps }`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub network: Option<NetworkProfile>,
+    /// Storybook args to permute: one snapshot per combination of values,
+    /// e.g. `{ size = ["sm", "md", "lg"] }`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub args: Option<BTreeMap<String, Vec<ArgValue>>>,
+}
+
+/// A Storybook arg value that can be set from the URL.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ArgValue {
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+}
+
+impl ArgValue {
+    /// Value in Storybook's `args` URL syntax (`!true` for booleans).
+    fn url_value(&self) -> String {
+        match self {
+            Self::Bool(b) => format!("!{b}"),
+            Self::Integer(n) => n.to_string(),
+            Self::Float(n) => n.to_string(),
+            Self::String(s) => s.replace(' ', "%20"),
+        }
+    }
+
+    /// Value as it appears in snapshot IDs.
+    fn label(&self) -> String {
+        match self {
+            Self::Bool(b) => b.to_string(),
+            Self::Integer(n) => n.to_string(),
+            Self::Float(n) => n.to_string(),
+            Self::String(s) => s.replace(' ', "_"),
+        }
+    }
+}
+
+/// One combination of `args` values.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArgsCombination(Vec<(String, ArgValue)>);
+
+impl ArgsCombination {
+    /// `args` query parameter value, e.g. `size:sm;disabled:!true`.
+    pub fn url_param(&self) -> String {
+        self.0
+            .iter()
+            .map(|(k, v)| format!("{k}:{}", v.url_value()))
+            .collect::<Vec<_>>()
+            .join(";")
+    }
+
+    /// Snapshot ID suffix, e.g. `size=sm,disabled=true`.
+    pub fn label(&self) -> String {
+        self.0
+            .iter()
+            .map(|(k, v)| format!("{k}={}", v.label()))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ScrollTo {
+    Offset(f64),
+    Selector(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum NetworkProfile {
+    Preset(NetworkPreset),
+    Custom(NetworkConditions),
+}
+
+/// Chrome DevTools' throttling presets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NetworkPreset {
+    Fast3G,
+    Slow3G,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NetworkConditions {
+    /// Added round-trip latency in milliseconds.
+    #[serde(default)]
+    pub latency_ms: f64,
+    /// Download throughput in kilobits per second.
+    pub download_kbps: f64,
+    /// Upload throughput in kilobits per second.
+    pub upload_kbps: f64,
+}
+
+impl NetworkProfile {
+    pub fn conditions(&self) -> NetworkConditions {
+        match self {
+            // Same numbers as the DevTools Network panel.
+            Self::Preset(NetworkPreset::Fast3G) => NetworkConditions {
+                latency_ms: 562.5,
+                download_kbps: 1440.0,
+                upload_kbps: 675.0,
+            },
+            Self::Preset(NetworkPreset::Slow3G) => NetworkConditions {
+                latency_ms: 2000.0,
+                download_kbps: 400.0,
+                upload_kbps: 400.0,
+            },
+            Self::Custom(conditions) => *conditions,
+        }
+    }
+}
+
+impl NetworkConditions {
+    /// Download throughput in bytes per second, as CDP expects.
+    pub fn download_bytes_per_sec(&self) -> f64 {
+        self.download_kbps * 1000.0 / 8.0
+    }
+
+    /// Upload throughput in bytes per second, as CDP expects.
+    pub fn upload_bytes_per_sec(&self) -> f64 {
+        self.upload_kbps * 1000.0 / 8.0
+    }
+}
+
+impl StoryConfig {
+    /// Every combination of `args` values, in key order. Empty when no args
+    /// are configured.
+    pub fn arg_combinations(&self) -> Vec<ArgsCombination> {
+        let Some(args) = &self.args else {
+            return Vec::new();
+        };
+        let mut combinations = vec![Vec::new()];
+        for (key, values) in args {
+            combinations = combinations
+                .iter()
+                .flat_map(|prefix: &Vec<(String, ArgValue)>| {
+                    values.iter().map(move |value| {
+                        let mut next = prefix.clone();
+                        next.push((key.clone(), value.clone()));
+                        next
+                    })
+                })
+                .collect();
+        }
+        combinations.into_iter().map(ArgsCombination).collect()
+    }
+
+    pub(super) fn validate(&self, id: &str) -> Result<()> {
+        let url_safe = |s: &str, extra: &[char]| {
+            s.chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || extra.contains(&c))
+        };
+        for (key, values) in self.args.iter().flatten() {
+            if key.is_empty() || !url_safe(key, &[]) {
+                bail!(
+                    "story.\"{id}\": arg name \"{key}\" may only contain letters, digits, '_' and '-'"
+                );
+            }
+            if values.is_empty() {
+                bail!("story.\"{id}\": args.{key} needs at least one value");
+            }
+            for value in values {
+                match value {
+                    ArgValue::String(s) if !url_safe(s, &[' ']) => bail!(
+                        "story.\"{id}\": args.{key} value \"{s}\" may only contain letters, \
+                         digits, spaces, '_' and '-' (Storybook ignores other URL args)"
+                    ),
+                    ArgValue::Float(n) if !n.is_finite() => {
+                        bail!("story.\"{id}\": args.{key} value {n} is not a finite number")
+                    }
+                    _ => {}
+                }
+            }
+        }
+        if let Some(NetworkProfile::Custom(c)) = &self.network {
+            for (name, value) in [
+                ("latency_ms", c.latency_ms),
+                ("download_kbps", c.download_kbps),
+                ("upload_kbps", c.upload_kbps),
+            ] {
+                if !value.is_finite() || value < 0.0 {
+                    bail!("story.\"{id}\": network.{name} must be >= 0, got {value}");
+                }
+            }
+            if c.download_kbps == 0.0 || c.upload_kbps == 0.0 {
+                bail!(
+                    "story.\"{id}\": network throughput must be > 0 \
+                     (a stalled connection would never finish loading)"
+                );
+            }
+        }
+        match (&self.scroll_to, &self.scroll_container) {
+            (Some(ScrollTo::Selector(_)), Some(_)) => bail!(
+                "story.\"{id}\": scroll_container only applies to a numeric scroll_to \
+                 (a selector is scrolled into view wherever it is)"
+            ),
+            (None, Some(_)) => bail!("story.\"{id}\": scroll_container requires scroll_to"),
+            (Some(ScrollTo::Offset(y)), _) if !y.is_finite() || *y < 0.0 => {
+                bail!("story.\"{id}\": scroll_to offset must be >= 0, got {y}")
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Settings for story `id`: the exact key if present, otherwise the first
+/// `*` pattern (in key order) that matches.
+pub fn settings_for<'a>(
+    stories: &'a BTreeMap<String, StoryConfig>,
+    id: &str,
+) -> Option<&'a StoryConfig> {
+    stories.get(id).or_else(|| {
+        stories
+            .iter()
+            .find(|(pattern, _)| pattern.contains('*') && matches_pattern(pattern, id))
+            .map(|(_, settings)| settings)
+    })
+}
+
+/// Owner of the story titled `title` under `[owners]`: the exact key if
+/// present, otherwise the longest matching `*` pattern, so
+/// `"Forms/Select/*"` beats `"Forms/*"`.
+pub fn owner_for<'a>(owners: &'a BTreeMap<String, String>, title: &str) -> Option<&'a str> {
+    owners
+        .get(title)
+        .or_else(|| {
+            owners
+                .iter()
+                .filter(|(pattern, _)| pattern.contains('*') && matches_pattern(pattern, title))
+                .max_by_key(|(pattern, _)| pattern.len())
+                .map(|(_, owner)| owner)
+        })
+        .map(String::as_str)
+}
+
+/// Match `id` against `pattern`, where `*` matches any run of characters.
+pub fn matches_pattern(pattern: &str, id: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = id.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scroll_to_accepts_offset_or_selector() {
+        let offset: StoryConfig = toml::from_str("scroll_to = 400").unwrap();
+        assert_eq!(offset.scroll_to, Some(ScrollTo::Offset(400.0)));
+        let selector: StoryConfig = toml::from_str("scroll_to = \"#row-50\"").unwrap();
+        assert_eq!(
+            selector.scroll_to,
+            Some(ScrollTo::Selector("#row-50".into()))
+        );
+        assert!(selector.validate("a--b").is_ok());
+    }
+
+    #[test]
+    fn scroll_container_needs_numeric_scroll_to() {
+        let config: StoryConfig =
+            toml::from_str("scroll_to = \"#row\"\nscroll_container = \".list\"").unwrap();
+        assert!(config.validate("a--b").is_err());
+        let config: StoryConfig = toml::from_str("scroll_container = \".list\"").unwrap();
+        assert!(config.validate("a--b").is_err());
+    }
+
+    #[test]
+    fn network_accepts_preset_or_custom() {
+        let preset: StoryConfig = toml::from_str("network = \"Slow3G\"").unwrap();
+        assert_eq!(
+            preset.network,
+            Some(NetworkProfile::Preset(NetworkPreset::Slow3G))
+        );
+        let custom: StoryConfig = toml::from_str(
+            "network = { latency_ms = 300, download_kbps = 800, upload_kbps = 200 }",
+        )
+        .unwrap();
+        let conditions = custom.network.unwrap().conditions();
+        assert_eq!(conditions.download_bytes_per_sec(), 100_000.0);
+        assert!(toml::from_str::<StoryConfig>("network = \"4G\"").is_err());
+    }
+
+    #[test]
+    fn args_expand_to_every_combination() {
+        let config: StoryConfig =
+            toml::from_str("args = { size = [\"sm\", \"lg\"], disabled = [false, true] }").unwrap();
+        assert!(config.validate("a--b").is_ok());
+        let combinations = config.arg_combinations();
+        assert_eq!(combinations.len(), 4);
+        assert_eq!(combinations[0].url_param(), "disabled:!false;size:sm");
+        assert_eq!(combinations[3].label(), "disabled=true,size=lg");
+
+        let config: StoryConfig = toml::from_str("args = { label = [\"a&b\"] }").unwrap();
+        assert!(config.validate("a--b").is_err());
+    }
+
+    #[test]
+    fn settings_lookup_prefers_exact_id() {
+        let mut stories = BTreeMap::new();
+        stories.insert("forms-*".to_string(), StoryConfig::default());
+        let exact = StoryConfig {
+            focus_steps: Some(2),
+            ..Default::default()
+        };
+        stories.insert("forms-button--primary".to_string(), exact.clone());
+        assert_eq!(
+            settings_for(&stories, "forms-button--primary"),
+            Some(&exact)
+        );
+        assert!(settings_for(&stories, "forms-input--basic").is_some());
+        assert!(settings_for(&stories, "layout-header--sticky").is_none());
+    }
+
+    #[test]
+    fn owner_is_exact_title_or_longest_pattern() {
+        let owners: BTreeMap<String, String> = [
+            ("Forms/*", "@forms"),
+            ("Forms/Select/*", "@select"),
+            ("Forms/Input", "@inputs"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        assert_eq!(owner_for(&owners, "Forms/Select/Multi"), Some("@select"));
+        assert_eq!(owner_for(&owners, "Forms/Checkbox"), Some("@forms"));
+        assert_eq!(owner_for(&owners, "Forms/Input"), Some("@inputs"));
+        assert_eq!(owner_for(&owners, "Layout/Header"), None);
+    }
+}
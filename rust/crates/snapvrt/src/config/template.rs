@@ -2,36 +2,126 @@ use std::path::Path;
 
 use anyhow::{Context, Result};
 
-use super::{CONFIG_DIR, CONFIG_FILE};
+use super::{CONFIG_DIR, CONFIG_FILE, Viewport};
 
 /// Hand-crafted config template with commented-out keys.
 /// Used by `snapvrt init` instead of `toml::to_string_pretty()` so that
 /// users can see the available knobs without uncommenting section headers.
-const CONFIG_TEMPLATE: &str = r#"[source.storybook]
+const CONFIG_TEMPLATE: &str = r##"[source.storybook]
 type = "storybook"
 url = "{url}"
 # viewports = ["laptop"]           # optional: omit = use all defined viewports
 
-[viewport.laptop]
-width = 1366
-height = 768
-
+{viewports}
 # ─────────────────────────────────────────────────────────
 # Capture pipeline — all fields optional.
 # ─────────────────────────────────────────────────────────
 [capture]
-# screenshot = "stable"             # "stable" | "single" (single is faster)
+# screenshot = "stable"             # "stable" | "single" | "adaptive" (single is faster)
+# screenshot_format = "png"         # "png" | "jpeg" | "webp" (lossy, faster)
+# screenshot_quality = 90           # jpeg/webp quality (0-100)
+# capture_beyond_viewport = false   # capture tall stories without resizing the viewport
+# from_surface = true               # capture from the compositor surface
 # stability_attempts = 3
 # stability_delay_ms = 100
+# stability_metric = "bytes"       # "bytes" | "pixels" (decoded pixels, with a tolerance)
+# stability_tolerance = 0.0         # pixels only: fraction of pixels allowed to differ
+# cdp_timeout_ms = 15000            # fail a CDP command Chrome doesn't answer in time
 # parallel = 4                      # concurrent browser tabs
+# tab_memory_mb = 300               # lowers parallel under a container memory limit (0 = off)
+# max_heap_mb = 512                 # V8 heap limit per renderer (local Chrome)
+# renderer_process_limit = 4        # renderer processes local Chrome may start
+# profile_dir = ".snapvrt/profile"  # reuse a Chrome profile between runs (local Chrome)
+# order = "discovery"               # "discovery" | "slowest-first" | "title"
+# isolation = "tab"                 # "tab" | "context" | "browser" (what captures share)
 # chrome_url = "http://localhost:9222"  # remote Chrome (e.g. Docker)
+# chrome_token = "..."              # managed Chrome service; or SNAPVRT_CHROME_TOKEN
+# chrome_token_in = "query"         # "query" | "header" (Authorization: Bearer)
+# webdriver_url = "http://grid:4444"  # WebDriver / Selenium Grid instead of DevTools
+# webdriver_browser = "chrome"      # browserName requested from webdriver_url
+# ignore_https_errors = false       # accept self-signed / private-CA certs
+# dialogs = "dismiss"               # "dismiss" | "accept" (alert/confirm/prompt)
+# popups = "close"                  # "close" | "keep" (tabs opened by stories)
+# force_srgb = true                 # render in sRGB, whatever the display profile
+# wait_for_storybook = 0            # retry discovery this many seconds while the server starts
+# ignore_selectors = ["[data-testid=timestamp]"]  # masked out of comparisons
+# block_remote_fonts = false        # block Google Fonts, Adobe Fonts, ...
+# font_override = "Arial, sans-serif"  # force one font stack everywhere
+# wait_for_fonts = ["Inter"]        # families that must load before capture
+# font_dir = "fonts"                # @font-face every font in this directory
+# media_time = 0.0                 # pause videos at this time (seconds)
+# hooks = ["hide-scrollbars"]      # "hide-scrollbars" | "blur-inputs" | "stop-videos"
+# hook_script = "snapvrt.hooks.js" # beforeCapture(story), run in the page when ready
+# device_scale = 1.0               # devicePixelRatio; 2 = retina-resolution images
+# hide_scrollbar_gutters = false   # hide scrollbars that take up layout space
+# deterministic_canvas = false     # seed Math.random, keep WebGL buffers
+# bypass_service_workers = false   # load from the network, not service workers
+# disable_cache = false            # no HTTP cache between captures
+# har = false                      # record network activity as HAR files
+# a11y = false                     # also diff the accessibility tree
+# dom = "off"                      # "off" | "diff" | "precheck" (normalized DOM snapshots)
+# css_coverage = false             # report unused CSS rules in css-coverage.json
+# perf_metrics = false             # layouts, script time and JS heap per snapshot
+# focus_steps = 0                  # also capture after 1..N Tab presses (__focus-N)
 
 # ─────────────────────────────────────────────────────────
 # Comparison — all fields optional.
 # ─────────────────────────────────────────────────────────
 [diff]
 # threshold = 0.0                   # max allowed diff score (0.0 = exact, 0.01 = 1%)
-"#;
+# metric = "pixel"                 # "pixel" | "ssim" | "hybrid" | "flip" (what threshold measures)
+# ssim_threshold = 0.01             # hybrid only: max 1 - SSIM (unset: just reported)
+# max_megapixels = 50.0             # refuse to compare larger images (memory cap)
+# style = "overlay"                 # "overlay" | "heatmap" | "side-by-side" (difference images)
+# highlight_color = "#ff0000"       # changed-pixel color
+# boxes = false                     # outline changed regions
+# channels = "rgba"                 # "rgba" | "rgb" (ignore alpha) | "luminance" (brightness only)
+# blur_sigma = 0.5                  # blur both images before diffing (sub-pixel text noise)
+# [[diff.override]]                 # per-snapshot threshold, e.g. from `snapvrt calibrate --write`
+# snapshot = "storybook/laptop/Charts/*"
+# threshold = 0.002
+
+# ─────────────────────────────────────────────────────────
+# Snapshot store — all fields optional.
+# ─────────────────────────────────────────────────────────
+[store]
+# optimize = false                  # losslessly recompress reference PNGs (oxipng)
+# format = "png"                    # "png" | "webp" (lossless, smaller)
+# id_template = "{source}/{viewport}/{title}/{name}"  # snapshot ID / file layout
+# orphan_grace_days = 7             # keep orphaned references quiet this long
+# orphan_grace_runs = 5             # ... or for this many full test runs
+# require_clean_git = false         # approve/update only from a committed branch
+
+# ─────────────────────────────────────────────────────────
+# Notifications for failed runs — all fields optional.
+# ─────────────────────────────────────────────────────────
+# [notify]
+# webhook_url = "https://hooks.slack.com/services/..."  # or SNAPVRT_NOTIFY_WEBHOOK_URL
+# report_url = "https://ci.example.com/artifacts/report.html"  # linked in the message
+
+# ─────────────────────────────────────────────────────────
+# Owning team per story title — `*` patterns allowed.
+# ─────────────────────────────────────────────────────────
+# [owners]
+# "Forms/*" = "@acme/forms"
+# "Forms/Select/*" = "@acme/select"   # the longest matching pattern wins
+"##;
+
+/// Generated artifacts under `.snapvrt/` that should not be committed.
+pub const GITIGNORE_ENTRIES: [&str; 12] = [
+    "current/",
+    "difference/",
+    "errors/",
+    "har/",
+    "cache/",
+    "thumbs/",
+    "report.html",
+    "report.json",
+    "css-coverage.json",
+    "run.log",
+    ".lock",
+    "chrome-url",
+];
 
 pub fn config_file_exists() -> bool {
     Path::new(CONFIG_DIR).join(CONFIG_FILE).exists()
@@ -42,7 +132,8 @@ pub fn write_gitignore(force: bool) -> Result<()> {
     if !force && path.exists() {
         return Ok(());
     }
-    std::fs::write(&path, "current/\ndifference/\nreport.html\n")
+    let content: String = GITIGNORE_ENTRIES.iter().map(|e| format!("{e}\n")).collect();
+    std::fs::write(&path, content)
         .with_context(|| format!("Failed to write {}", path.display()))?;
     Ok(())
 }
@@ -59,12 +150,29 @@ pub fn save(config: &super::Config) -> Result<()> {
 }
 
 /// Write the hand-crafted config template (with commented-out sections).
-/// Used by `snapvrt init` instead of `save()`.
-pub fn write_template(url: &str) -> Result<()> {
+/// Used by `snapvrt init` instead of `save()`. An empty `viewports` list
+/// writes the default `laptop` viewport.
+pub fn write_template(url: &str, viewports: &[(String, Viewport)]) -> Result<()> {
     let dir = Path::new(CONFIG_DIR);
     std::fs::create_dir_all(dir).context("Failed to create .snapvrt directory")?;
     let path = dir.join(CONFIG_FILE);
-    let content = CONFIG_TEMPLATE.replace("{url}", url);
+    let viewports_toml = if viewports.is_empty() {
+        "[viewport.laptop]\nwidth = 1366\nheight = 768\n".to_string()
+    } else {
+        viewports
+            .iter()
+            .map(|(name, vp)| {
+                format!(
+                    "[viewport.{name}]\nwidth = {}\nheight = {}\n",
+                    vp.width, vp.height
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    let content = CONFIG_TEMPLATE
+        .replace("{url}", url)
+        .replace("{viewports}", &viewports_toml);
     std::fs::write(&path, content)
         .with_context(|| format!("Failed to write {}", path.display()))?;
     Ok(())
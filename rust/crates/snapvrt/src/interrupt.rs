@@ -0,0 +1,55 @@
+use std::sync::Once;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use tokio::sync::Notify;
+
+use crate::report::terminal;
+
+/// Exit code of a run stopped with Ctrl-C (128 + SIGINT).
+pub const EXIT_CODE: i32 = 130;
+
+static INSTALL: Once = Once::new();
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+static NOTIFY: Notify = Notify::const_new();
+
+/// Trap Ctrl-C for a capturing command. The first one stops capturing: new
+/// captures don't start, in-flight ones are abandoned, and the command
+/// finishes with the results it has. A second one exits immediately.
+pub fn install() {
+    INSTALL.call_once(|| {
+        tokio::spawn(async {
+            if tokio::signal::ctrl_c().await.is_err() {
+                return;
+            }
+            REQUESTED.store(true, Ordering::SeqCst);
+            NOTIFY.notify_waiters();
+            terminal::finish_progress();
+            eprintln!(
+                "\nInterrupted: stopping captures and writing partial results \
+                 (Ctrl-C again to quit now)"
+            );
+            if tokio::signal::ctrl_c().await.is_ok() {
+                std::process::exit(EXIT_CODE);
+            }
+        });
+    });
+}
+
+/// Whether Ctrl-C was pressed.
+pub fn requested() -> bool {
+    REQUESTED.load(Ordering::SeqCst)
+}
+
+/// Resolves once Ctrl-C is pressed.
+pub async fn wait() {
+    let notified = NOTIFY.notified();
+    if requested() {
+        return;
+    }
+    notified.await;
+}
+
+/// `code`, or [`EXIT_CODE`] when the run was interrupted.
+pub fn exit_code(code: i32) -> i32 {
+    if requested() { EXIT_CODE } else { code }
+}
@@ -0,0 +1,153 @@
+use std::fs::File;
+use std::io::{LineWriter, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+use anyhow::{Context, Result};
+use serde_json::{Map, Value};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::filter::{EnvFilter, Targets};
+use tracing_subscriber::layer::{Context as LayerContext, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{Layer, fmt};
+
+use crate::report::terminal;
+
+/// Install the tracing subscriber: human-readable output on stderr, filtered
+/// by `RUST_LOG` (default `snapvrt=info`), and with `log_file`, every
+/// debug-level event as JSON lines regardless of that filter.
+pub fn init(log_file: Option<&Path>) -> Result<()> {
+    let stderr = fmt::layer()
+        .with_target(false)
+        .with_ansi(terminal::color_enabled())
+        .with_writer(std::io::stderr)
+        .with_filter(
+            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("snapvrt=info")),
+        );
+    let file = log_file
+        .map(|path| {
+            if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                std::fs::create_dir_all(parent)
+                    .with_context(|| format!("Failed to create {}", parent.display()))?;
+            }
+            let file = File::create(path)
+                .with_context(|| format!("Failed to create {}", path.display()))?;
+            let filter = Targets::new()
+                .with_target("snapvrt", Level::DEBUG)
+                .with_default(Level::WARN);
+            Ok::<_, anyhow::Error>(JsonLines::new(file).with_filter(filter))
+        })
+        .transpose()?;
+    tracing_subscriber::registry()
+        .with(stderr)
+        .with(file)
+        .init();
+    tracing::debug!(
+        version = env!("CARGO_PKG_VERSION"),
+        args = ?std::env::args().skip(1).collect::<Vec<_>>(),
+        "snapvrt started"
+    );
+    Ok(())
+}
+
+/// Writes each event as one JSON object: `time` (Unix seconds), `level`,
+/// `target`, the event's fields and the fields of its enclosing `spans`.
+struct JsonLines {
+    out: Mutex<LineWriter<File>>,
+}
+
+/// Fields of a span, stored in its extensions when it is created.
+struct SpanFields(Map<String, Value>);
+
+impl JsonLines {
+    fn new(file: File) -> Self {
+        Self {
+            out: Mutex::new(LineWriter::new(file)),
+        }
+    }
+}
+
+impl<S> Layer<S> for JsonLines
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: LayerContext<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut fields = Map::new();
+        fields.insert("name".into(), span.name().into());
+        attrs.record(&mut JsonVisitor(&mut fields));
+        span.extensions_mut().insert(SpanFields(fields));
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: LayerContext<'_, S>) {
+        if let Some(span) = ctx.span(id)
+            && let Some(SpanFields(fields)) = span.extensions_mut().get_mut::<SpanFields>()
+        {
+            values.record(&mut JsonVisitor(fields));
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: LayerContext<'_, S>) {
+        let time = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs_f64();
+        let meta = event.metadata();
+        let mut line = Map::new();
+        line.insert("time".into(), time.into());
+        line.insert("level".into(), meta.level().as_str().into());
+        line.insert("target".into(), meta.target().into());
+        event.record(&mut JsonVisitor(&mut line));
+        if let Some(scope) = ctx.event_scope(event) {
+            let spans: Vec<Value> = scope
+                .from_root()
+                .filter_map(|span| {
+                    let ext = span.extensions();
+                    let SpanFields(fields) = ext.get::<SpanFields>()?;
+                    Some(Value::Object(fields.clone()))
+                })
+                .collect();
+            line.insert("spans".into(), spans.into());
+        }
+        let Ok(mut json) = serde_json::to_string(&line) else {
+            return;
+        };
+        json.push('\n');
+        let mut out = self.out.lock().unwrap_or_else(|e| e.into_inner());
+        let _ = out.write_all(json.as_bytes());
+    }
+}
+
+struct JsonVisitor<'a>(&'a mut Map<String, Value>);
+
+impl Visit for JsonVisitor<'_> {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0
+            .insert(field.name().into(), format!("{value:?}").into());
+    }
+}
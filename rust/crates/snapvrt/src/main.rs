@@ -4,29 +4,33 @@ mod cli;
 mod commands;
 mod compare;
 mod config;
+mod interrupt;
+mod logging;
 mod report;
 mod store;
 mod storybook;
+mod webdriver;
 
+use capture::Selection;
 use clap::Parser;
 use config::{CliOverrides, ResolvedRunConfig};
-use tracing_subscriber::EnvFilter;
+use report::{ReportOptions, terminal};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("snapvrt=info")),
-        )
-        .with_target(false)
-        .with_writer(std::io::stderr)
-        .init();
-
     let cli = cli::Cli::parse();
+    terminal::configure(cli.quiet, cli.no_color);
+
+    logging::init(cli.log_file.as_deref())?;
 
     match cli.command {
-        cli::Command::Init { url, force } => {
-            commands::init(&url, force)?;
+        cli::Command::Init {
+            url,
+            force,
+            yes,
+            from,
+        } => {
+            commands::init(url.as_deref(), force, yes, from).await?;
         }
         cli::Command::Review { open } => {
             commands::review(open)?;
@@ -34,24 +38,85 @@ async fn main() -> anyhow::Result<()> {
         cli::Command::Test {
             url,
             filter,
+            ids_from,
             threshold,
             timings,
             prune,
-            capture,
+            fail_on,
+            debug,
+            strict_env,
+            list,
+            reports,
+            group_by_owner,
+            mut capture,
         } => {
+            capture.debug = debug;
             let overrides = CliOverrides {
                 url,
                 threshold,
                 capture,
             };
             let config = ResolvedRunConfig::new(overrides)?;
-            let code = commands::test(config, filter.as_deref(), timings, prune).await?;
-            std::process::exit(code);
+            let selection = Selection {
+                filter,
+                ids: ids_from
+                    .as_deref()
+                    .map(commands::read_selection)
+                    .transpose()?,
+            };
+            if list {
+                return commands::list(config, &selection).await;
+            }
+            let _lock = store::RunLock::acquire(cli.wait).await?;
+            interrupt::install();
+            let report = ReportOptions {
+                targets: reports,
+                timings,
+                group_by_owner,
+            };
+            let code =
+                commands::test(config, &selection, prune, fail_on, strict_env, &report).await?;
+            std::process::exit(interrupt::exit_code(code));
+        }
+        cli::Command::Flaky {
+            url,
+            filter,
+            runs,
+            capture,
+        } => {
+            let overrides = CliOverrides {
+                url,
+                threshold: None,
+                capture,
+            };
+            let config = ResolvedRunConfig::new(overrides)?;
+            interrupt::install();
+            let selection = Selection { filter, ids: None };
+            let code = commands::flaky(config, &selection, runs).await?;
+            std::process::exit(interrupt::exit_code(code));
+        }
+        cli::Command::Calibrate {
+            url,
+            filter,
+            runs,
+            write,
+            capture,
+        } => {
+            let overrides = CliOverrides {
+                url,
+                threshold: None,
+                capture,
+            };
+            let config = ResolvedRunConfig::new(overrides)?;
+            interrupt::install();
+            let selection = Selection { filter, ids: None };
+            commands::calibrate(config, &selection, runs, write).await?;
         }
         cli::Command::Prune {
             url,
             dry_run,
             yes,
+            ignore_grace,
             capture,
         } => {
             let overrides = CliOverrides {
@@ -60,20 +125,86 @@ async fn main() -> anyhow::Result<()> {
                 capture,
             };
             let config = ResolvedRunConfig::new(overrides)?;
-            commands::prune(config, dry_run, yes).await?;
+            let _lock = store::RunLock::acquire(cli.wait).await?;
+            commands::prune(config, dry_run, yes, ignore_grace).await?;
         }
         cli::Command::Approve {
             filter,
             new,
             failed,
             all,
+            from_file,
+            force,
+            require_clean_git,
+            regions,
+        } => {
+            let _lock = store::RunLock::acquire(cli.wait).await?;
+            let selection = from_file
+                .as_deref()
+                .map(commands::read_selection)
+                .transpose()?;
+            let options = commands::ApproveOptions {
+                new_only: new,
+                failed_only: failed,
+                all,
+                force,
+                require_clean_git,
+            };
+            commands::approve(filter.as_ref(), selection.as_ref(), &options, &regions)?;
+        }
+        cli::Command::MigrateIds {
+            url,
+            from,
+            dry_run,
+            capture,
+        } => {
+            let overrides = CliOverrides {
+                url,
+                threshold: None,
+                capture,
+            };
+            let config = ResolvedRunConfig::new(overrides)?;
+            let _lock = store::RunLock::acquire(cli.wait).await?;
+            commands::migrate_ids(config, from.as_deref(), dry_run).await?;
+        }
+        cli::Command::CompareRuns { base, head, output } => {
+            let code = commands::compare_runs(&base, &head, output.as_deref())?;
+            std::process::exit(code);
+        }
+        cli::Command::Mv { from, to, dry_run } => {
+            let _lock = store::RunLock::acquire(cli.wait).await?;
+            commands::mv(&from, &to, dry_run)?;
+        }
+        cli::Command::MigrateScale { from, to, dry_run } => {
+            let _lock = store::RunLock::acquire(cli.wait).await?;
+            commands::migrate_scale(from, to, dry_run)?;
+        }
+        cli::Command::Chrome { action } => match action {
+            cli::ChromeAction::Up { port } => commands::chrome_up(port).await?,
+            cli::ChromeAction::Down => commands::chrome_down().await?,
+        },
+        cli::Command::Clean {
+            older_than,
+            all,
+            dry_run,
         } => {
-            commands::approve(filter.as_deref(), new, failed, all)?;
+            let _lock = store::RunLock::acquire(cli.wait).await?;
+            commands::clean(older_than, all, dry_run)?;
+        }
+        cli::Command::Optimize {
+            filter,
+            zopfli,
+            dry_run,
+        } => {
+            let _lock = store::RunLock::acquire(cli.wait).await?;
+            commands::optimize(filter.as_ref(), zopfli, dry_run)?;
         }
         cli::Command::Update {
             url,
             filter,
+            ids_from,
             timings,
+            only_changed,
             capture,
         } => {
             let overrides = CliOverrides {
@@ -82,7 +213,19 @@ async fn main() -> anyhow::Result<()> {
                 capture,
             };
             let config = ResolvedRunConfig::new(overrides)?;
-            commands::update(config, filter.as_deref(), timings).await?;
+            let _lock = store::RunLock::acquire(cli.wait).await?;
+            interrupt::install();
+            let selection = Selection {
+                filter,
+                ids: ids_from
+                    .as_deref()
+                    .map(commands::read_selection)
+                    .transpose()?,
+            };
+            commands::update(config, &selection, timings, only_changed).await?;
+            if interrupt::requested() {
+                std::process::exit(interrupt::EXIT_CODE);
+            }
         }
     }
 
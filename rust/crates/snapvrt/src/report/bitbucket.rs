@@ -0,0 +1,109 @@
+use anyhow::Result;
+use serde::Serialize;
+
+use super::json::{GroupCounts, JsonEntry};
+
+/// Annotations Bitbucket accepts per report.
+const MAX_ANNOTATIONS: usize = 1000;
+
+/// Bitbucket Code Insights report, with its annotations inlined, for a
+/// pipe or `curl` step to upload to the reports API.
+#[derive(Serialize)]
+struct Report {
+    title: &'static str,
+    details: String,
+    report_type: &'static str,
+    reporter: &'static str,
+    /// `PASSED` or `FAILED`.
+    result: &'static str,
+    data: Vec<DataPoint>,
+    annotations: Vec<Annotation>,
+}
+
+#[derive(Serialize)]
+struct DataPoint {
+    title: &'static str,
+    r#type: &'static str,
+    value: usize,
+}
+
+#[derive(Serialize)]
+struct Annotation {
+    external_id: String,
+    annotation_type: &'static str,
+    summary: String,
+    /// `LOW`, `MEDIUM`, `HIGH` or `CRITICAL`.
+    severity: &'static str,
+    result: &'static str,
+}
+
+pub fn render(entries: &[JsonEntry], breakdown: &[GroupCounts]) -> Result<String> {
+    let sum = |count: fn(&GroupCounts) -> usize| breakdown.iter().map(count).sum::<usize>();
+    let (passed, failed, new, errored) = (
+        sum(|g| g.passed),
+        sum(|g| g.failed),
+        sum(|g| g.new),
+        sum(|g| g.errored),
+    );
+    let annotations = entries
+        .iter()
+        .filter_map(|entry| {
+            let (annotation_type, severity, summary) = match entry.status {
+                "fail" => (
+                    "BUG",
+                    "MEDIUM",
+                    format!("Visual difference in {}", entry.id),
+                ),
+                "new" => ("CODE_SMELL", "LOW", format!("New snapshot {}", entry.id)),
+                "error" => ("BUG", "HIGH", format!("Capture of {} failed", entry.id)),
+                _ => return None,
+            };
+            Some(Annotation {
+                external_id: format!("snapvrt-{}", entry.id),
+                annotation_type,
+                summary,
+                severity,
+                result: "FAILED",
+            })
+        })
+        .take(MAX_ANNOTATIONS)
+        .collect();
+    let report = Report {
+        title: "Visual regression (snapvrt)",
+        details: format!(
+            "{} snapshots: {passed} passed, {failed} failed, {new} new, {errored} errored",
+            entries.len()
+        ),
+        report_type: "TEST",
+        reporter: "snapvrt",
+        result: if failed + errored > 0 {
+            "FAILED"
+        } else {
+            "PASSED"
+        },
+        data: vec![
+            DataPoint {
+                title: "Passed",
+                r#type: "NUMBER",
+                value: passed,
+            },
+            DataPoint {
+                title: "Failed",
+                r#type: "NUMBER",
+                value: failed,
+            },
+            DataPoint {
+                title: "New",
+                r#type: "NUMBER",
+                value: new,
+            },
+            DataPoint {
+                title: "Errored",
+                r#type: "NUMBER",
+                value: errored,
+            },
+        ],
+        annotations,
+    };
+    Ok(serde_json::to_string_pretty(&report)?)
+}
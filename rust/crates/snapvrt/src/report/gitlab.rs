@@ -0,0 +1,81 @@
+use std::path::Path;
+
+use anyhow::Result;
+use serde::Serialize;
+
+use super::json::{GroupCounts, JsonEntry};
+use crate::store;
+
+/// One issue in GitLab's Code Quality report format, shown in the merge
+/// request widget. Stable fingerprints let GitLab tell new failures from
+/// ones already on the target branch.
+#[derive(Serialize)]
+struct Issue {
+    description: String,
+    check_name: &'static str,
+    fingerprint: String,
+    /// `info`, `minor`, `major`, `critical` or `blocker`.
+    severity: &'static str,
+    location: Location,
+}
+
+#[derive(Serialize)]
+struct Location {
+    path: String,
+    lines: Lines,
+}
+
+#[derive(Serialize)]
+struct Lines {
+    begin: u32,
+}
+
+/// Code Quality JSON (`artifacts:reports:codequality`) for failed, new and
+/// errored snapshots. Passing snapshots are left out.
+pub fn render(entries: &[JsonEntry], _breakdown: &[GroupCounts]) -> Result<String> {
+    let issues: Vec<Issue> = entries.iter().filter_map(issue).collect();
+    Ok(serde_json::to_string_pretty(&issues)?)
+}
+
+fn issue(entry: &JsonEntry) -> Option<Issue> {
+    let id = &entry.id;
+    let base = Path::new(store::BASE_DIR);
+    let (check_name, severity, description, path) = match entry.status {
+        "fail" => {
+            let score = entry.score.unwrap_or(0.0);
+            (
+                "snapvrt-diff",
+                "major",
+                format!("Visual difference in {id} (score {score:.4})"),
+                base.join(store::DIFFERENCE_DIR).join(format!("{id}.png")),
+            )
+        }
+        "new" => (
+            "snapvrt-new",
+            "minor",
+            format!("New snapshot {id} has no reference"),
+            store::current_path(id)
+                .unwrap_or_else(|| base.join(store::CURRENT_DIR).join(format!("{id}.png"))),
+        ),
+        "error" => (
+            "snapvrt-error",
+            "critical",
+            format!(
+                "Capture of {id} failed: {}",
+                entry.error.as_deref().unwrap_or("unknown error")
+            ),
+            base.join(store::ERRORS_DIR).join(id).join("error.txt"),
+        ),
+        _ => return None,
+    };
+    Some(Issue {
+        description,
+        check_name,
+        fingerprint: store::hash_bytes(format!("{check_name}:{id}").as_bytes())[..32].to_string(),
+        severity,
+        location: Location {
+            path: path.to_string_lossy().into_owned(),
+            lines: Lines { begin: 1 },
+        },
+    })
+}
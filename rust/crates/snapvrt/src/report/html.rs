@@ -1,115 +1,368 @@
-use std::collections::BTreeSet;
-use std::path::Path;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
 
 use anyhow::{Context, Result};
+use serde::Deserialize;
 
+use super::json::JsonEntry;
+use super::run_info::RunInfo;
+use super::{Reporter, RunEnd, SnapshotEvent};
+use crate::capture::job::snapshot_title;
 use crate::store;
 
-const OUTPUT_FILE: &str = "report.html";
+pub const OUTPUT_FILE: &str = "report.html";
+
+/// Name of the file the report's "Export selection" button downloads, for
+/// `snapvrt approve --from-file`.
+const SELECTION_FILE: &str = "selection.json";
+
+/// Selection toolbar behavior: export checked snapshot IDs as a JSON array
+/// (the `approve --from-file` format) or copy them one per line.
+const SELECTION_SCRIPT: &str = r#"
+    const boxes = () => [...document.querySelectorAll("input.select")];
+    const selected = () => boxes().filter((b) => b.checked).map((b) => b.value);
+    const update = () => {
+      document.getElementById("count").textContent = selected().length + " selected";
+    };
+    document.getElementById("select-all").addEventListener("change", (e) => {
+      boxes().forEach((b) => { b.checked = e.target.checked; });
+      update();
+    });
+    boxes().forEach((b) => b.addEventListener("change", update));
+    document.getElementById("export").addEventListener("click", () => {
+      const blob = new Blob([JSON.stringify(selected(), null, 2) + "\n"], { type: "application/json" });
+      const a = document.createElement("a");
+      a.href = URL.createObjectURL(blob);
+      a.download = document.getElementById("export").dataset.file;
+      a.click();
+      URL.revokeObjectURL(a.href);
+    });
+    document.getElementById("copy").addEventListener("click", () => {
+      navigator.clipboard.writeText(selected().join("\n") + "\n");
+    });
+"#;
 
 struct SnapshotRow {
+    /// Snapshot ID (relative path without extension).
     name: String,
-    has_reference: bool,
-    has_current: bool,
-    has_difference: bool,
+    /// Component title the row is grouped under.
+    title: String,
+    /// Relative file names (with extension) under each store directory.
+    reference: Option<String>,
+    current: Option<String>,
+    difference: Option<String>,
+    /// Diffs of changed text snapshots (`a11y`, ...), by kind.
+    text_diffs: BTreeMap<String, String>,
+}
+
+/// Run metadata and per-snapshot scores shown alongside the images.
+#[derive(Default)]
+struct RunDetails {
+    info: Option<RunInfo>,
+    /// Differing pixels and score, by snapshot ID.
+    scores: HashMap<String, (u64, f64)>,
+}
+
+/// The parts of `report.json` the review page shows.
+#[derive(Deserialize)]
+struct SavedReport {
+    #[serde(default)]
+    run: Option<RunInfo>,
+    #[serde(default)]
+    snapshots: Vec<SavedEntry>,
+}
+
+#[derive(Deserialize)]
+struct SavedEntry {
+    id: String,
+    diff_pixels: Option<u64>,
+    score: Option<f64>,
+}
+
+impl RunDetails {
+    fn new(
+        info: Option<RunInfo>,
+        scores: impl Iterator<Item = (String, Option<u64>, Option<f64>)>,
+    ) -> Self {
+        Self {
+            info,
+            scores: scores
+                .filter_map(|(id, pixels, score)| Some((id, (pixels?, score?))))
+                .collect(),
+        }
+    }
+
+    /// What the last `test` run wrote to `report.json`; empty when it is
+    /// missing or unreadable.
+    fn read_saved() -> Self {
+        let path = Path::new(store::BASE_DIR).join(super::json::OUTPUT_FILE);
+        let Some(saved) = std::fs::read(&path)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice::<SavedReport>(&bytes).ok())
+        else {
+            return Self::default();
+        };
+        Self::new(
+            saved.run,
+            saved
+                .snapshots
+                .into_iter()
+                .map(|e| (e.id, e.diff_pixels, e.score)),
+        )
+    }
 }
 
-/// Recursively collect `.png` files as relative paths (including the `.png` extension).
-fn list_png_relative(dir: &Path) -> BTreeSet<String> {
-    let mut result = BTreeSet::new();
-    collect_pngs(dir, dir, &mut result);
+/// Recursively collect image files, keyed by ID, valued by relative path
+/// (including the extension).
+fn list_images_relative(dir: &Path) -> BTreeMap<String, String> {
+    let mut result = BTreeMap::new();
+    collect_images(dir, dir, &mut result);
     result
 }
 
-fn collect_pngs(base: &Path, dir: &Path, out: &mut BTreeSet<String>) {
+fn collect_images(base: &Path, dir: &Path, out: &mut BTreeMap<String, String>) {
     let Ok(entries) = std::fs::read_dir(dir) else {
         return;
     };
     for entry in entries.flatten() {
         let path = entry.path();
         if path.is_dir() {
-            collect_pngs(base, &path, out);
-        } else if path
-            .extension()
-            .is_some_and(|e| e.eq_ignore_ascii_case("png"))
-            && let Ok(rel) = path.strip_prefix(base)
+            collect_images(base, &path, out);
+        } else if path.extension().is_some_and(|e| {
+            store::IMAGE_EXTENSIONS
+                .iter()
+                .any(|ext| e.eq_ignore_ascii_case(ext))
+        }) && let Ok(rel) = path.strip_prefix(base)
         {
-            out.insert(rel.to_string_lossy().into_owned());
+            out.insert(
+                rel.with_extension("").to_string_lossy().into_owned(),
+                rel.to_string_lossy().into_owned(),
+            );
         }
     }
 }
 
-fn collect_rows() -> Vec<SnapshotRow> {
+fn collect_rows() -> Result<Vec<SnapshotRow>> {
     let base = Path::new(store::BASE_DIR);
-    let reference = list_png_relative(&base.join(store::REFERENCE_DIR));
-    let current = list_png_relative(&base.join(store::CURRENT_DIR));
-    let difference = list_png_relative(&base.join(store::DIFFERENCE_DIR));
+    let references = store::References::open()?;
+    let reference: BTreeMap<String, String> = references
+        .ids()
+        .into_iter()
+        .filter_map(|id| references.relative_path(&id).map(|p| (id, p)))
+        .collect();
+    let current = list_images_relative(&base.join(store::CURRENT_DIR));
+    let difference = list_images_relative(&base.join(store::DIFFERENCE_DIR));
 
     let mut all_names = BTreeSet::new();
-    all_names.extend(reference.iter().cloned());
-    all_names.extend(current.iter().cloned());
-    all_names.extend(difference.iter().cloned());
+    all_names.extend(reference.keys().cloned());
+    all_names.extend(current.keys().cloned());
+    all_names.extend(difference.keys().cloned());
 
-    all_names
+    let template = references.id_template();
+    Ok(all_names
         .into_iter()
         .map(|name| SnapshotRow {
-            has_reference: reference.contains(&name),
-            has_current: current.contains(&name),
-            has_difference: difference.contains(&name),
+            title: snapshot_title(template, &name),
+            reference: reference.get(&name).cloned(),
+            current: current.get(&name).cloned(),
+            difference: difference.get(&name).cloned(),
+            text_diffs: store::read_text_differences(&name),
             name,
         })
-        .collect()
+        .collect())
 }
 
-fn build_html(rows: &[SnapshotRow]) -> (String, usize, usize) {
-    let created_at = {
-        let d = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap_or_default();
-        let secs = d.as_secs();
-        // Simple UTC timestamp (no chrono dependency)
-        let (s, m, h) = (secs % 60, (secs / 60) % 60, (secs / 3600) % 24);
-        let days = secs / 86400;
-        // Days since epoch -> year/month/day (good enough for display)
-        let (y, mo, d) = epoch_days_to_ymd(days);
-        format!("{y:04}-{mo:02}-{d:02}T{h:02}:{m:02}:{s:02}Z")
-    };
-    let diff_rows: Vec<&SnapshotRow> = rows.iter().filter(|r| r.has_difference).collect();
-    let new_rows: Vec<&SnapshotRow> = rows
+/// Report rows: snapshots with a diff, then new ones.
+fn review_rows(rows: &[SnapshotRow]) -> (Vec<&SnapshotRow>, Vec<&SnapshotRow>) {
+    let diff_rows = rows
+        .iter()
+        .filter(|r| r.difference.is_some() || !r.text_diffs.is_empty())
+        .collect();
+    let new_rows = rows
         .iter()
-        .filter(|r| r.has_current && !r.has_reference && !r.has_difference)
+        .filter(|r| r.current.is_some() && r.reference.is_none() && r.difference.is_none())
         .collect();
+    (diff_rows, new_rows)
+}
 
-    let mut body_rows = String::new();
+/// Report rows of one component title.
+struct Group<'a> {
+    title: &'a str,
+    /// Snapshots with a diff, highest score first.
+    diff: Vec<&'a SnapshotRow>,
+    new: Vec<&'a SnapshotRow>,
+    /// Highest score in the group, when any is known.
+    top_score: Option<f64>,
+}
 
-    for row in &diff_rows {
-        body_rows.push_str(&format!(
-            r#"        <tr>
-          <td class="name">{name}</td>
+/// Rows grouped by component title; groups with the most significant
+/// regressions first, then groups with only new snapshots, by title.
+fn group_rows<'a>(
+    diff_rows: &[&'a SnapshotRow],
+    new_rows: &[&'a SnapshotRow],
+    details: &RunDetails,
+) -> Vec<Group<'a>> {
+    let score = |row: &SnapshotRow| details.scores.get(&row.name).map(|(_, score)| *score);
+    let mut groups: BTreeMap<&str, Group<'a>> = BTreeMap::new();
+    let rows = diff_rows.iter().map(|row| (row, true));
+    for (row, has_diff) in rows.chain(new_rows.iter().map(|row| (row, false))) {
+        let group = groups.entry(&row.title).or_insert_with(|| Group {
+            title: &row.title,
+            diff: Vec::new(),
+            new: Vec::new(),
+            top_score: None,
+        });
+        if has_diff {
+            group.diff.push(row);
+        } else {
+            group.new.push(row);
+        }
+    }
+    let mut groups: Vec<Group<'a>> = groups.into_values().collect();
+    for group in &mut groups {
+        // Unscored rows (no report.json) keep name order, after scored ones.
+        group.diff.sort_by(|a, b| {
+            score(b)
+                .unwrap_or(-1.0)
+                .total_cmp(&score(a).unwrap_or(-1.0))
+        });
+        group.top_score = group
+            .diff
+            .iter()
+            .filter_map(|row| score(row))
+            .reduce(f64::max);
+    }
+    groups.sort_by(|a, b| {
+        a.diff.is_empty().cmp(&b.diff.is_empty()).then(
+            b.top_score
+                .unwrap_or(-1.0)
+                .total_cmp(&a.top_score.unwrap_or(-1.0)),
+        )
+    });
+    groups
+}
+
+/// Thumbnails for every image shown in the report, keyed by
+/// `<subdir>/<file>`. Generated in parallel; cached between reports.
+fn thumbnails(rows: &[&SnapshotRow]) -> HashMap<String, String> {
+    let images: Vec<(&str, &str)> = rows
+        .iter()
+        .flat_map(|row| {
+            [
+                (store::REFERENCE_DIR, &row.reference),
+                (store::CURRENT_DIR, &row.current),
+                (store::DIFFERENCE_DIR, &row.difference),
+            ]
+        })
+        .filter_map(|(subdir, file)| Some((subdir, file.as_deref()?)))
+        .collect();
+    if images.is_empty() {
+        return HashMap::new();
+    }
+    let workers = std::thread::available_parallelism().map_or(4, |n| n.get());
+    let chunk = images.len().div_ceil(workers);
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = images
+            .chunks(chunk)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .filter_map(|(subdir, file)| {
+                            Some((format!("{subdir}/{file}"), store::thumbnail(subdir, file)?))
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|h| h.join().unwrap_or_default())
+            .collect()
+    })
+}
+
+/// `prefix` leads from the report's directory to `.snapvrt/` (empty when
+/// the report is written there).
+fn build_html(
+    rows: &[SnapshotRow],
+    thumbs: &HashMap<String, String>,
+    prefix: &str,
+    details: &RunDetails,
+) -> (String, usize, usize) {
+    let created_at = format_utc(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs(),
+    );
+    let (diff_rows, new_rows) = review_rows(rows);
+    let image_cell = |subdir: &str, name: &str, file: Option<&str>| {
+        image_cell(subdir, name, file, thumbs, prefix)
+    };
+
+    let mut sections = String::new();
+    for group in group_rows(&diff_rows, &new_rows, details) {
+        let mut body_rows = String::new();
+        for row in &group.diff {
+            body_rows.push_str(&format!(
+                r#"        <tr>
+          <td class="name">{select}{name}{score}</td>
           <td>{reference}</td>
           <td>{current}</td>
           <td>{difference}</td>
         </tr>
 "#,
-            name = html_escape(&row.name),
-            reference = image_cell("reference", &row.name, row.has_reference),
-            current = image_cell("current", &row.name, row.has_current),
-            difference = image_cell("difference", &row.name, row.has_difference),
-        ));
-    }
-
-    for row in &new_rows {
-        body_rows.push_str(&format!(
-            r#"        <tr>
-          <td class="name">{name} <span class="badge new">NEW</span></td>
+                name = html_escape(&row.name),
+                select = select_box(&row.name),
+                score = score_line(details, &row.name),
+                reference = image_cell("reference", &row.name, row.reference.as_deref()),
+                current = image_cell("current", &row.name, row.current.as_deref()),
+                difference = match &row.difference {
+                    Some(file) => image_cell("difference", &row.name, Some(file)),
+                    None => String::new(),
+                } + &text_diff_cells(&row.text_diffs),
+            ));
+        }
+        for row in &group.new {
+            body_rows.push_str(&format!(
+                r#"        <tr>
+          <td class="name">{select}{name} <span class="badge new">NEW</span></td>
           <td>{reference}</td>
           <td>{current}</td>
           <td class="missing">—</td>
         </tr>
 "#,
-            name = html_escape(&row.name),
-            reference = image_cell("reference", &row.name, row.has_reference),
-            current = image_cell("current", &row.name, row.has_current),
+                name = html_escape(&row.name),
+                select = select_box(&row.name),
+                reference = image_cell("reference", &row.name, row.reference.as_deref()),
+                current = image_cell("current", &row.name, row.current.as_deref()),
+            ));
+        }
+        sections.push_str(&format!(
+            r#"  <details class="group" open>
+    <summary>{title} <span class="counts">{diff} with diff, {new} new</span></summary>
+    <table>
+      <thead>
+        <tr>
+          <th>Name</th>
+          <th>Reference</th>
+          <th>Current</th>
+          <th>Difference</th>
+        </tr>
+      </thead>
+      <tbody>
+{body_rows}      </tbody>
+    </table>
+  </details>
+"#,
+            title = html_escape(if group.title.is_empty() {
+                "(no title)"
+            } else {
+                group.title
+            }),
+            diff = group.diff.len(),
+            new = group.new.len(),
         ));
     }
 
@@ -132,6 +385,17 @@ fn build_html(rows: &[SnapshotRow]) -> (String, usize, usize) {
     }}
     h1 {{ margin: 0 0 8px; font-size: 22px; }}
     .meta {{ margin-bottom: 16px; color: #52606d; font-size: 14px; }}
+    .run {{ margin: 0 0 16px; font-size: 13px; color: #52606d; }}
+    .run summary {{ cursor: pointer; }}
+    .run dl {{ display: grid; grid-template-columns: max-content 1fr; gap: 2px 12px; margin: 8px 0 0; }}
+    .run dt {{ font-weight: 600; }}
+    .run dd {{ margin: 0; }}
+    .group {{ margin-bottom: 16px; }}
+    .group summary {{ cursor: pointer; font-weight: 600; font-size: 15px; margin-bottom: 8px; }}
+    .group .counts {{ font-weight: 400; color: #52606d; font-size: 13px; margin-left: 8px; }}
+    .text-diff {{ margin-top: 8px; font-size: 12px; }}
+    .text-diff pre {{ max-height: 400px; overflow: auto; background: #f0f4f8; padding: 6px; margin: 4px 0 0; }}
+    .score {{ margin-top: 4px; color: #52606d; font-size: 12px; }}
     table {{ width: 100%; border-collapse: collapse; background: #fff; box-shadow: 0 2px 6px rgba(0,0,0,0.05); }}
     th, td {{ border: 1px solid #e4e7eb; padding: 8px; vertical-align: top; text-align: left; }}
     th {{ background: #f0f4f8; font-weight: 600; font-size: 14px; }}
@@ -142,33 +406,32 @@ fn build_html(rows: &[SnapshotRow]) -> (String, usize, usize) {
     .badge {{ font-size: 11px; padding: 1px 6px; border-radius: 3px; font-weight: 600; }}
     .badge.new {{ background: #fef3c7; color: #92400e; }}
     .empty {{ text-align: center; padding: 48px; color: #52606d; font-size: 16px; }}
+    .toolbar {{ display: flex; gap: 12px; align-items: center; margin-bottom: 12px; font-size: 14px; }}
+    .toolbar code {{ background: #e4e7eb; padding: 1px 4px; border-radius: 3px; }}
+    input.select {{ margin-right: 6px; }}
   </style>
 </head>
 <body>
   <h1>snapvrt review</h1>
   <div class="meta">Generated at {created_at} &middot; {summary}</div>
-  {content}
+  {run}{content}
 </body>
 </html>"##,
         created_at = created_at,
         summary = summary,
-        content = if body_rows.is_empty() {
+        run = details.info.as_ref().map(run_header).unwrap_or_default(),
+        content = if sections.is_empty() {
             r#"<div class="empty">All snapshots pass — nothing to review.</div>"#.to_string()
         } else {
             format!(
-                r#"<table>
-    <thead>
-      <tr>
-        <th>Name</th>
-        <th>Reference</th>
-        <th>Current</th>
-        <th>Difference</th>
-      </tr>
-    </thead>
-    <tbody>
-{body_rows}    </tbody>
-  </table>"#,
-                body_rows = body_rows
+                r#"<div class="toolbar">
+    <label><input type="checkbox" id="select-all" /> Select all</label>
+    <span id="count">0 selected</span>
+    <button id="export" data-file="{SELECTION_FILE}">Export selection</button>
+    <button id="copy">Copy IDs</button>
+    <span>then <code>snapvrt approve --from-file {SELECTION_FILE}</code></span>
+  </div>
+{sections}  <script>{SELECTION_SCRIPT}  </script>"#
             )
         }
     );
@@ -176,13 +439,93 @@ fn build_html(rows: &[SnapshotRow]) -> (String, usize, usize) {
     (html, diff_count, new_count)
 }
 
-fn image_cell(subdir: &str, filename: &str, exists: bool) -> String {
-    if !exists {
-        return format!(r#"<div class="missing">no {subdir}</div>"#);
+/// Collapsible summary of the run the report comes from, so a report
+/// attached to a ticket says what it was made from.
+fn run_header(info: &RunInfo) -> String {
+    let mut items = vec![
+        ("Started", format_utc(info.started_at)),
+        ("Duration", format!("{:.1}s", info.duration_secs)),
+    ];
+    if let Some(branch) = &info.git_branch {
+        items.push(("Branch", branch.clone()));
+    }
+    if let Some(commit) = &info.git_commit {
+        items.push(("Commit", commit.clone()));
+    }
+    if let Some(chrome) = &info.chrome {
+        items.push(("Chrome", chrome.clone()));
     }
-    let safe = url_encode(filename);
-    let escaped = html_escape(filename);
-    format!(r#"<img src="{subdir}/{safe}" alt="{subdir} {escaped}" loading="lazy" />"#)
+    items.push(("Viewports", info.viewports.join(", ")));
+    let list: String = items
+        .iter()
+        .map(|(k, v)| (*k, v.as_str()))
+        .chain(info.config.iter().map(|(k, v)| (k.as_str(), v.as_str())))
+        .map(|(k, v)| format!("<dt>{}</dt><dd>{}</dd>", html_escape(k), html_escape(v)))
+        .collect();
+    let revision = info
+        .git_commit
+        .as_deref()
+        .map(|c| format!(" at {}", &c[..c.len().min(12)]))
+        .unwrap_or_default();
+    format!(
+        r#"<details class="run"><summary>Run{revision}</summary><dl>{list}</dl></details>
+  "#,
+        revision = html_escape(&revision),
+    )
+}
+
+/// Collapsible diff of each changed text snapshot.
+fn text_diff_cells(diffs: &BTreeMap<String, String>) -> String {
+    diffs
+        .iter()
+        .map(|(kind, diff)| {
+            format!(
+                r#"<details class="text-diff" open><summary>{} changed</summary><pre>{}</pre></details>"#,
+                html_escape(kind),
+                html_escape(diff)
+            )
+        })
+        .collect()
+}
+
+/// Differing pixels and score of a failed snapshot, when known.
+fn score_line(details: &RunDetails, name: &str) -> String {
+    details
+        .scores
+        .get(name)
+        .map(|(pixels, score)| {
+            format!(r#"<div class="score">{pixels} px differ &middot; score {score:.4}</div>"#)
+        })
+        .unwrap_or_default()
+}
+
+/// Thumbnail (or the image itself, if it has none) linking to the
+/// full-size image.
+fn image_cell(
+    subdir: &str,
+    name: &str,
+    file: Option<&str>,
+    thumbs: &HashMap<String, String>,
+    prefix: &str,
+) -> String {
+    let Some(file) = file else {
+        return format!(r#"<div class="missing">no {subdir}</div>"#);
+    };
+    let full = format!("{prefix}{subdir}/{}", url_encode(file));
+    let src = thumbs.get(&format!("{subdir}/{file}")).map_or_else(
+        || full.clone(),
+        |thumb| format!("{prefix}{}", url_encode(thumb)),
+    );
+    let escaped = html_escape(name);
+    format!(r#"<a href="{full}"><img src="{src}" alt="{subdir} {escaped}" loading="lazy" /></a>"#)
+}
+
+/// Checkbox selecting a snapshot for export.
+fn select_box(name: &str) -> String {
+    format!(
+        r#"<input type="checkbox" class="select" value="{}" />"#,
+        html_escape(name)
+    )
 }
 
 fn html_escape(s: &str) -> String {
@@ -203,8 +546,15 @@ fn url_encode(s: &str) -> String {
         .collect()
 }
 
+/// Unix seconds as an ISO 8601 UTC timestamp (no chrono dependency).
+fn format_utc(secs: u64) -> String {
+    let (s, m, h) = (secs % 60, (secs / 60) % 60, (secs / 3600) % 24);
+    let (y, mo, d) = epoch_days_to_ymd(secs / 86400);
+    format!("{y:04}-{mo:02}-{d:02}T{h:02}:{m:02}:{s:02}Z")
+}
+
 /// Convert days since Unix epoch to (year, month, day).
-fn epoch_days_to_ymd(mut days: u64) -> (u64, u64, u64) {
+pub(crate) fn epoch_days_to_ymd(mut days: u64) -> (u64, u64, u64) {
     // Civil calendar algorithm (Howard Hinnant)
     days += 719468;
     let era = days / 146097;
@@ -221,15 +571,85 @@ fn epoch_days_to_ymd(mut days: u64) -> (u64, u64, u64) {
 
 /// Generate `.snapvrt/report.html` and return the path.
 pub fn generate() -> Result<String> {
-    let rows = collect_rows();
-    let (html, diff_count, new_count) = build_html(&rows);
-
     let out_path = Path::new(store::BASE_DIR).join(OUTPUT_FILE);
-    std::fs::write(&out_path, html)
-        .with_context(|| format!("Failed to write {}", out_path.display()))?;
-
+    let (diff_count, new_count) = generate_to(&out_path, &RunDetails::read_saved())?;
     Ok(format!(
         "{} ({diff_count} with diff, {new_count} new)",
         out_path.display(),
     ))
 }
+
+/// Write the report to `out_path`, linking images in `.snapvrt/` relative
+/// to it. Returns the number of snapshots with a diff and new ones.
+fn generate_to(out_path: &Path, details: &RunDetails) -> Result<(usize, usize)> {
+    let rows = collect_rows()?;
+    let (diff_rows, new_rows) = review_rows(&rows);
+    let thumbs = thumbnails(&[diff_rows, new_rows].concat());
+
+    let dir = out_path
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let (html, diff_count, new_count) = build_html(&rows, &thumbs, &store_prefix(dir), details);
+    std::fs::write(out_path, html)
+        .with_context(|| format!("Failed to write {}", out_path.display()))?;
+    Ok((diff_count, new_count))
+}
+
+/// Relative URL prefix from `dir` to `.snapvrt/`, with a trailing slash
+/// (empty when `dir` is `.snapvrt/`).
+fn store_prefix(dir: &Path) -> String {
+    let (Ok(from), Ok(to)) = (
+        dir.canonicalize(),
+        Path::new(store::BASE_DIR).canonicalize(),
+    ) else {
+        return format!("{}/", store::BASE_DIR);
+    };
+    let common = from
+        .components()
+        .zip(to.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let ups = from.components().count() - common;
+    let downs = to
+        .components()
+        .skip(common)
+        .map(|c| c.as_os_str().to_string_lossy().into_owned());
+    std::iter::repeat_n("..".to_string(), ups)
+        .chain(downs)
+        .map(|part| format!("{}/", url_encode(&part)))
+        .collect()
+}
+
+/// Writes the review page when a `test` run finishes (`--report html=PATH`).
+pub struct HtmlReporter {
+    path: PathBuf,
+    entries: Vec<JsonEntry>,
+}
+
+impl HtmlReporter {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            entries: Vec::new(),
+        }
+    }
+}
+
+impl Reporter for HtmlReporter {
+    fn snapshot(&mut self, event: &SnapshotEvent<'_>) -> Result<()> {
+        self.entries.push(event.entry.clone());
+        Ok(())
+    }
+
+    fn finish(&mut self, run: &RunEnd<'_>) -> Result<()> {
+        let details = RunDetails::new(
+            Some(run.info.clone()),
+            self.entries
+                .iter()
+                .map(|e| (e.id.clone(), e.diff_pixels, e.score)),
+        );
+        generate_to(&self.path, &details).map(|_| ())
+    }
+}
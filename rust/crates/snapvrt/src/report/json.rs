@@ -0,0 +1,154 @@
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use serde::Serialize;
+
+use super::RunEnd;
+use super::run_info::RunInfo;
+use crate::capture::job::CaptureJob;
+use crate::capture::timing::PerfMetrics;
+use crate::compare::SnapshotStatus;
+use crate::compare::diff::Region;
+
+pub const OUTPUT_FILE: &str = "report.json";
+
+/// One snapshot result in `report.json`.
+#[derive(Clone, Serialize)]
+pub struct JsonEntry {
+    pub id: String,
+    pub source: String,
+    pub viewport: String,
+    /// Team owning the story, from `[owners]`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    /// `pass`, `fail`, `new` or `error`.
+    pub status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub diff_pixels: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score: Option<f64>,
+    /// Mean SSIM (1.0 = identical), with `diff.metric` `ssim` or `hybrid`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ssim: Option<f64>,
+    /// Mean FLIP error (0.0 = identical), with `diff.metric = "flip"`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub flip: Option<f64>,
+    /// `[ref_w, ref_h, cur_w, cur_h]` when dimensions differ.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dimension_mismatch: Option<[u32; 4]>,
+    /// Where the size changed, e.g. `+24px height at bottom`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size_change: Option<String>,
+    /// Changed regions, largest first.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub regions: Vec<Region>,
+    /// Text snapshots (`a11y`, ...) that differ; their diffs are in
+    /// `difference/<id>.<kind>.diff`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub text_changes: Vec<String>,
+    /// Page metrics at screenshot time, with `capture.perf_metrics`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub perf: Option<PerfMetrics>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl JsonEntry {
+    pub fn new(job: &CaptureJob, status: &SnapshotStatus) -> Self {
+        let mut entry = Self {
+            id: job.snapshot_id(),
+            source: job.source.clone(),
+            viewport: job.viewport.clone(),
+            owner: None,
+            status: "pass",
+            diff_pixels: None,
+            score: None,
+            ssim: None,
+            flip: None,
+            dimension_mismatch: None,
+            size_change: None,
+            regions: Vec::new(),
+            text_changes: Vec::new(),
+            perf: None,
+            error: None,
+        };
+        match status {
+            SnapshotStatus::Pass => {}
+            SnapshotStatus::Fail {
+                diff_pixels,
+                score,
+                ssim,
+                flip,
+                dimension_mismatch,
+                regions,
+                text_changes,
+            } => {
+                entry.status = "fail";
+                entry.diff_pixels = Some(*diff_pixels);
+                entry.score = Some(*score);
+                entry.ssim = *ssim;
+                entry.flip = *flip;
+                entry.dimension_mismatch = dimension_mismatch.map(|m| m.dims());
+                entry.size_change = dimension_mismatch.map(|m| m.to_string());
+                entry.regions = regions.clone();
+                entry.text_changes = text_changes.clone();
+            }
+            SnapshotStatus::New => entry.status = "new",
+            SnapshotStatus::Error(msg) => {
+                entry.status = "error";
+                entry.error = Some(msg.clone());
+            }
+        }
+        entry
+    }
+}
+
+/// Result counts for one source and viewport.
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct GroupCounts {
+    pub source: String,
+    pub viewport: String,
+    pub passed: usize,
+    pub failed: usize,
+    pub new: usize,
+    pub errored: usize,
+}
+
+/// Counts per source and viewport, in name order.
+pub fn breakdown(entries: &[JsonEntry]) -> Vec<GroupCounts> {
+    let mut groups: BTreeMap<(&str, &str), GroupCounts> = BTreeMap::new();
+    for entry in entries {
+        let group = groups
+            .entry((&entry.source, &entry.viewport))
+            .or_insert_with(|| GroupCounts {
+                source: entry.source.clone(),
+                viewport: entry.viewport.clone(),
+                ..GroupCounts::default()
+            });
+        match entry.status {
+            "pass" => group.passed += 1,
+            "fail" => group.failed += 1,
+            "new" => group.new += 1,
+            _ => group.errored += 1,
+        }
+    }
+    groups.into_values().collect()
+}
+
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    run: &'a RunInfo,
+    breakdown: &'a [GroupCounts],
+    snapshots: &'a [JsonEntry],
+}
+
+/// `report.json`: the last run's metadata, per-snapshot results and their
+/// counts per source and viewport.
+pub fn render(entries: &[JsonEntry], run: &RunEnd<'_>) -> Result<String> {
+    let report = JsonReport {
+        run: run.info,
+        breakdown: run.breakdown,
+        snapshots: entries,
+    };
+    Ok(serde_json::to_string_pretty(&report)?)
+}
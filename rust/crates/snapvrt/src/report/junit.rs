@@ -0,0 +1,123 @@
+use anyhow::Result;
+
+use super::json::{GroupCounts, JsonEntry};
+
+/// JUnit XML with one `<testsuite>` per source and viewport. Differences
+/// and new snapshots are failures, capture errors are errors.
+pub fn render(entries: &[JsonEntry], breakdown: &[GroupCounts]) -> Result<String> {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let failures: usize = breakdown.iter().map(|g| g.failed + g.new).sum();
+    let errors: usize = breakdown.iter().map(|g| g.errored).sum();
+    xml.push_str(&format!(
+        "<testsuites name=\"snapvrt\" tests=\"{}\" failures=\"{failures}\" errors=\"{errors}\">\n",
+        entries.len()
+    ));
+    for group in breakdown {
+        let suite = format!("{}.{}", group.source, group.viewport);
+        let tests = group.passed + group.failed + group.new + group.errored;
+        xml.push_str(&format!(
+            "  <testsuite name=\"{}\" tests=\"{tests}\" failures=\"{}\" errors=\"{}\">\n",
+            escape(&suite),
+            group.failed + group.new,
+            group.errored
+        ));
+        for entry in entries
+            .iter()
+            .filter(|e| e.source == group.source && e.viewport == group.viewport)
+        {
+            xml.push_str(&format!(
+                "    <testcase name=\"{}\" classname=\"{}\"",
+                escape(&entry.id),
+                escape(&suite)
+            ));
+            match testcase_body(entry) {
+                Some(body) => xml.push_str(&format!(">\n      {body}\n    </testcase>\n")),
+                None => xml.push_str(" />\n"),
+            }
+        }
+        xml.push_str("  </testsuite>\n");
+    }
+    xml.push_str("</testsuites>\n");
+    Ok(xml)
+}
+
+fn testcase_body(entry: &JsonEntry) -> Option<String> {
+    match entry.status {
+        "fail" => {
+            let mut message = match entry.dimension_mismatch {
+                Some([rw, rh, cw, ch]) => match &entry.size_change {
+                    Some(change) => format!("dimensions changed: {rw}x{rh} -> {cw}x{ch}, {change}"),
+                    None => format!("dimensions changed: {rw}x{rh} -> {cw}x{ch}"),
+                },
+                None => format!(
+                    "{} pixels differ (score {:.4})",
+                    entry.diff_pixels.unwrap_or(0),
+                    entry.score.unwrap_or(0.0)
+                ),
+            };
+            for kind in &entry.text_changes {
+                message.push_str(&format!(", {kind} snapshot changed"));
+            }
+            Some(format!(
+                "<failure type=\"difference\" message=\"{}\" />",
+                escape(&message)
+            ))
+        }
+        "new" => Some("<failure type=\"new\" message=\"no reference snapshot\" />".into()),
+        "error" => Some(format!(
+            "<error message=\"{}\" />",
+            escape(entry.error.as_deref().unwrap_or("capture failed"))
+        )),
+        _ => None,
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: &str, status: &'static str) -> JsonEntry {
+        JsonEntry {
+            id: id.into(),
+            source: "storybook".into(),
+            viewport: "laptop".into(),
+            owner: None,
+            status,
+            diff_pixels: Some(42),
+            score: Some(0.5),
+            ssim: None,
+            flip: None,
+            dimension_mismatch: None,
+            size_change: None,
+            regions: Vec::new(),
+            text_changes: Vec::new(),
+            perf: None,
+            error: (status == "error").then(|| "timed out <30s>".into()),
+        }
+    }
+
+    #[test]
+    fn junit_marks_failures_and_errors() {
+        let entries = [entry("a", "pass"), entry("b", "fail"), entry("c", "error")];
+        let breakdown = [GroupCounts {
+            source: "storybook".into(),
+            viewport: "laptop".into(),
+            passed: 1,
+            failed: 1,
+            new: 0,
+            errored: 1,
+        }];
+        let xml = render(&entries, &breakdown).unwrap();
+        assert!(xml.contains(r#"<testsuites name="snapvrt" tests="3" failures="1" errors="1">"#));
+        assert!(xml.contains(r#"<testcase name="a" classname="storybook.laptop" />"#));
+        assert!(xml.contains(r#"message="42 pixels differ (score 0.5000)""#));
+        assert!(xml.contains(r#"<error message="timed out &lt;30s&gt;" />"#));
+    }
+}
@@ -1,2 +1,247 @@
+pub mod bitbucket;
+pub mod gitlab;
 pub mod html;
+pub mod json;
+pub mod junit;
+pub mod notify;
+pub mod run_info;
 pub mod terminal;
+
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+
+use self::json::{GroupCounts, JsonEntry};
+use self::run_info::RunInfo;
+use crate::capture::CaptureTimings;
+use crate::compare::SnapshotStatus;
+use crate::store;
+
+/// One finished snapshot of a `test` run.
+pub struct SnapshotEvent<'a> {
+    pub entry: &'a JsonEntry,
+    pub status: &'a SnapshotStatus,
+    /// Stage timings; `None` when the capture itself failed.
+    pub timings: Option<&'a CaptureTimings>,
+}
+
+/// The end of a `test` run.
+pub struct RunEnd<'a> {
+    pub total: usize,
+    pub breakdown: &'a [GroupCounts],
+    /// References no planned snapshot matches any more (full runs only).
+    pub removed: &'a [String],
+    /// Orphaned references still within `store.orphan_grace_*`.
+    pub orphans_in_grace: usize,
+    /// How the capture environment differs from the baselines'.
+    pub env_diffs: &'a [String],
+    pub elapsed: Duration,
+    /// Longest the compare queue got, its capacity, and the compare workers.
+    pub peak_queued: usize,
+    pub queue_capacity: usize,
+    pub compare_workers: usize,
+    /// Git revision, browser, viewports and settings of the run.
+    pub info: &'a RunInfo,
+}
+
+/// Receives a run's results as they arrive. Every enabled reporter sees the
+/// same events, in the same order.
+pub trait Reporter {
+    /// A run of `total` snapshots starts.
+    fn start(&mut self, _total: usize) {}
+
+    fn snapshot(&mut self, event: &SnapshotEvent<'_>) -> Result<()>;
+
+    fn finish(&mut self, run: &RunEnd<'_>) -> Result<()>;
+}
+
+/// How `test` reports, from the command line.
+#[derive(Debug, Default)]
+pub struct ReportOptions {
+    /// Extra reports (`--report`).
+    pub targets: Vec<ReportTarget>,
+    /// Print the per-snapshot timing tables (`--timings`).
+    pub timings: bool,
+    /// List failures under their `[owners]` team (`--group-by-owner`).
+    pub group_by_owner: bool,
+}
+
+/// The reporters enabled for a run.
+#[derive(Default)]
+pub struct Reporters(Vec<Box<dyn Reporter>>);
+
+impl Reporters {
+    /// Terminal output and `.snapvrt/report.json`, plus one reporter per
+    /// `--report` target.
+    pub fn for_run(options: &ReportOptions) -> Self {
+        let mut reporters = Self::default();
+        reporters.register(terminal::TerminalReporter::new(
+            options.timings,
+            options.group_by_owner,
+        ));
+        reporters.register(FileReporter::new(
+            Path::new(store::BASE_DIR).join(json::OUTPUT_FILE),
+            json::render,
+        ));
+        for target in &options.targets {
+            reporters.0.push(target.reporter());
+        }
+        reporters
+    }
+
+    pub fn register(&mut self, reporter: impl Reporter + 'static) {
+        self.0.push(Box::new(reporter));
+    }
+
+    pub fn start(&mut self, total: usize) {
+        for reporter in &mut self.0 {
+            reporter.start(total);
+        }
+    }
+
+    pub fn snapshot(&mut self, event: &SnapshotEvent<'_>) -> Result<()> {
+        for reporter in &mut self.0 {
+            reporter.snapshot(event)?;
+        }
+        Ok(())
+    }
+
+    /// Finish every reporter, even after one fails; returns the first error.
+    pub fn finish(&mut self, run: &RunEnd<'_>) -> Result<()> {
+        let mut result = Ok(());
+        for reporter in &mut self.0 {
+            let finished = reporter.finish(run);
+            if result.is_ok() {
+                result = finished;
+            }
+        }
+        result
+    }
+}
+
+/// Renders a whole report from every snapshot's entry.
+type Render = fn(&[JsonEntry], &RunEnd<'_>) -> Result<String>;
+
+/// Collects entries and writes one rendered file when the run finishes.
+struct FileReporter {
+    path: PathBuf,
+    render: Render,
+    entries: Vec<JsonEntry>,
+}
+
+impl FileReporter {
+    fn new(path: PathBuf, render: Render) -> Self {
+        Self {
+            path,
+            render,
+            entries: Vec::new(),
+        }
+    }
+}
+
+impl Reporter for FileReporter {
+    fn snapshot(&mut self, event: &SnapshotEvent<'_>) -> Result<()> {
+        self.entries.push(event.entry.clone());
+        Ok(())
+    }
+
+    fn finish(&mut self, run: &RunEnd<'_>) -> Result<()> {
+        let content = (self.render)(&self.entries, run)
+            .with_context(|| format!("Failed to render {}", self.path.display()))?;
+        write_file(&self.path, &content)
+    }
+}
+
+/// Write a report file, creating its directory.
+fn write_file(path: &Path, content: &str) -> Result<()> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    std::fs::write(path, content).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Report format for `test --report <kind>=<path>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportKind {
+    /// Same content as `.snapvrt/report.json`.
+    Json,
+    /// The review page `snapvrt review` opens.
+    Html,
+    /// JUnit XML, one test suite per source and viewport.
+    Junit,
+    /// GitLab Code Quality (`artifacts:reports:codequality`).
+    Gitlab,
+    /// Bitbucket Code Insights report with annotations.
+    Bitbucket,
+}
+
+/// An extra report to write during `test`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReportTarget {
+    pub kind: ReportKind,
+    pub path: PathBuf,
+}
+
+impl FromStr for ReportTarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let (kind, path) = s
+            .split_once('=')
+            .filter(|(_, path)| !path.is_empty())
+            .ok_or_else(|| format!("expected KIND=PATH, got '{s}'"))?;
+        let kind = match kind {
+            "json" => ReportKind::Json,
+            "html" => ReportKind::Html,
+            "junit" => ReportKind::Junit,
+            "gitlab" => ReportKind::Gitlab,
+            "bitbucket" => ReportKind::Bitbucket,
+            _ => {
+                return Err(format!(
+                    "unknown report kind '{kind}' (json, html, junit, gitlab, bitbucket)"
+                ));
+            }
+        };
+        Ok(Self {
+            kind,
+            path: PathBuf::from(path),
+        })
+    }
+}
+
+impl ReportTarget {
+    fn reporter(&self) -> Box<dyn Reporter> {
+        let path = self.path.clone();
+        match self.kind {
+            ReportKind::Json => Box::new(FileReporter::new(path, json::render)),
+            ReportKind::Html => Box::new(html::HtmlReporter::new(path)),
+            ReportKind::Junit => Box::new(FileReporter::new(path, |entries, run| {
+                junit::render(entries, run.breakdown)
+            })),
+            ReportKind::Gitlab => Box::new(FileReporter::new(path, |entries, run| {
+                gitlab::render(entries, run.breakdown)
+            })),
+            ReportKind::Bitbucket => Box::new(FileReporter::new(path, |entries, run| {
+                bitbucket::render(entries, run.breakdown)
+            })),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_target_parses_kind_and_path() {
+        let target: ReportTarget = "gitlab=gl-code-quality.json".parse().unwrap();
+        assert_eq!(target.kind, ReportKind::Gitlab);
+        assert_eq!(target.path, PathBuf::from("gl-code-quality.json"));
+        assert!("tap=out.tap".parse::<ReportTarget>().is_err());
+        assert!("gitlab".parse::<ReportTarget>().is_err());
+        assert!("gitlab=".parse::<ReportTarget>().is_err());
+    }
+}
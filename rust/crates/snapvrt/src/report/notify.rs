@@ -0,0 +1,99 @@
+use anyhow::{Context, Result};
+
+use super::json::GroupCounts;
+
+/// Failures listed by name; the rest are only counted.
+const TOP_FAILURES: usize = 10;
+
+/// What a failed run reports to `notify.webhook_url`.
+pub struct RunSummary<'a> {
+    pub source: &'a str,
+    pub total: usize,
+    pub breakdown: &'a [GroupCounts],
+    pub failed: &'a [String],
+    pub errored: &'a [String],
+    pub report_url: Option<&'a str>,
+}
+
+impl RunSummary<'_> {
+    /// Slack mrkdwn message: counts, the first failures, the report link.
+    pub fn message(&self) -> String {
+        let sum =
+            |count: fn(&GroupCounts) -> usize| self.breakdown.iter().map(count).sum::<usize>();
+        let mut text = format!(
+            "*snapvrt* ({}): {} failed, {} new, {} errored of {} snapshots",
+            self.source,
+            sum(|g| g.failed),
+            sum(|g| g.new),
+            sum(|g| g.errored),
+            self.total
+        );
+        if self.breakdown.len() > 1 {
+            for g in self.breakdown.iter().filter(|g| g.failed + g.errored > 0) {
+                text.push_str(&format!(
+                    "\n• {}: {} failed, {} errored",
+                    g.viewport, g.failed, g.errored
+                ));
+            }
+        }
+        let failures: Vec<&String> = self.failed.iter().chain(self.errored).collect();
+        if !failures.is_empty() {
+            text.push_str("\n\n*Failures:*");
+            for name in failures.iter().take(TOP_FAILURES) {
+                text.push_str(&format!("\n• `{name}`"));
+            }
+            if failures.len() > TOP_FAILURES {
+                text.push_str(&format!("\n…and {} more", failures.len() - TOP_FAILURES));
+            }
+        }
+        if let Some(url) = self.report_url {
+            text.push_str(&format!("\n\n<{url}|View report>"));
+        }
+        text
+    }
+}
+
+/// Post `summary` to a Slack-compatible incoming webhook (`{"text": ...}`).
+pub async fn send(webhook_url: &str, summary: &RunSummary<'_>) -> Result<()> {
+    let payload = serde_json::json!({ "text": summary.message() });
+    reqwest::Client::new()
+        .post(webhook_url)
+        .json(&payload)
+        .send()
+        .await
+        .and_then(|resp| resp.error_for_status())
+        .context("Failed to post run summary to notify.webhook_url")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn message_lists_top_failures_and_report() {
+        let breakdown = [GroupCounts {
+            source: "storybook".into(),
+            viewport: "laptop".into(),
+            passed: 5,
+            failed: 12,
+            new: 1,
+            errored: 0,
+        }];
+        let failed: Vec<String> = (0..12).map(|i| format!("button--{i}")).collect();
+        let summary = RunSummary {
+            source: "storybook",
+            total: 18,
+            breakdown: &breakdown,
+            failed: &failed,
+            errored: &[],
+            report_url: Some("https://ci.example/report.html"),
+        };
+        let text = summary.message();
+        assert!(text.starts_with("*snapvrt* (storybook): 12 failed, 1 new, 0 errored of 18"));
+        assert!(text.contains("`button--9`"));
+        assert!(!text.contains("`button--10`"));
+        assert!(text.contains("…and 2 more"));
+        assert!(text.ends_with("<https://ci.example/report.html|View report>"));
+    }
+}
@@ -0,0 +1,196 @@
+use std::collections::BTreeMap;
+use std::process::Command;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Result, bail};
+use serde::{Deserialize, Serialize};
+
+use crate::capture::Environment;
+use crate::config::ResolvedRunConfig;
+
+/// CI variables naming the branch when the checkout is a detached HEAD.
+const CI_BRANCH_VARS: [&str; 4] = [
+    "GITHUB_HEAD_REF",
+    "GITHUB_REF_NAME",
+    "CI_COMMIT_REF_NAME",
+    "BITBUCKET_BRANCH",
+];
+
+/// Where and how a `test` run happened, so exported reports describe
+/// themselves.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct RunInfo {
+    /// Unix seconds.
+    pub started_at: u64,
+    pub duration_secs: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git_branch: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git_commit: Option<String>,
+    /// Browser product, e.g. `HeadlessChrome/139.0.7258.138`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chrome: Option<String>,
+    /// `name (WxH)` for each viewport.
+    #[serde(default)]
+    pub viewports: Vec<String>,
+    /// Settings that affect results. Headers and credentials are left out.
+    #[serde(default)]
+    pub config: BTreeMap<String, String>,
+    /// Stopped with Ctrl-C: snapshots not captured yet are missing.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub interrupted: bool,
+}
+
+impl RunInfo {
+    /// Everything known before capturing starts.
+    pub fn start(config: &ResolvedRunConfig) -> Self {
+        let started_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let branch = git(&["rev-parse", "--abbrev-ref", "HEAD"])
+            .filter(|branch| branch != "HEAD")
+            .or_else(|| {
+                CI_BRANCH_VARS
+                    .iter()
+                    .find_map(|var| std::env::var(var).ok().filter(|v| !v.is_empty()))
+            });
+        Self {
+            started_at,
+            duration_secs: 0.0,
+            git_branch: branch,
+            git_commit: git(&["rev-parse", "HEAD"]),
+            chrome: None,
+            viewports: config
+                .viewports
+                .iter()
+                .map(|(name, vp)| format!("{name} ({}x{})", vp.width, vp.height))
+                .collect(),
+            config: config_summary(config),
+            interrupted: false,
+        }
+    }
+
+    pub fn finish(&mut self, environment: Option<&Environment>, elapsed: Duration) {
+        self.chrome = environment.map(|env| env.chrome.clone());
+        self.duration_secs = elapsed.as_secs_f64();
+        self.interrupted = crate::interrupt::requested();
+    }
+}
+
+fn config_summary(config: &ResolvedRunConfig) -> BTreeMap<String, String> {
+    let mut out = BTreeMap::from([
+        ("source".to_string(), config.source_name.clone()),
+        ("url".to_string(), config.storybook_url.clone()),
+        ("diff.metric".to_string(), serde_name(config.diff_metric)),
+        (
+            "diff.threshold".to_string(),
+            config.diff_threshold.to_string(),
+        ),
+        (
+            "diff.style".to_string(),
+            serde_name(config.diff_render.style),
+        ),
+        (
+            "capture.parallel".to_string(),
+            config.capture.parallel().to_string(),
+        ),
+        (
+            "capture.capture_beyond_viewport".to_string(),
+            config.capture.capture_beyond_viewport().to_string(),
+        ),
+    ]);
+    if let Some(ssim) = config.ssim_threshold {
+        out.insert("diff.ssim_threshold".to_string(), ssim.to_string());
+    }
+    out
+}
+
+/// A unit enum's name as it is spelled in `snapvrt.toml`.
+fn serde_name(value: impl Serialize) -> String {
+    serde_json::to_value(value)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_owned))
+        .unwrap_or_default()
+}
+
+/// The git checkout references are approved from.
+pub struct GitState {
+    pub commit: String,
+    /// HEAD is not on a branch.
+    pub detached: bool,
+    /// Paths with uncommitted changes anywhere in the repository, outside
+    /// `.snapvrt/`: references approved earlier and not committed yet
+    /// don't count.
+    pub changes: Vec<String>,
+}
+
+impl GitState {
+    /// None outside a git checkout.
+    pub fn read() -> Option<Self> {
+        let commit = git(&["rev-parse", "HEAD"])?;
+        let detached = git(&["symbolic-ref", "-q", "HEAD"]).is_none();
+        // The whole repository (`:/`), not just this directory: in a monorepo
+        // a change to a sibling package can alter what the stories render.
+        let exclude = format!(":(exclude){}", crate::store::BASE_DIR);
+        let changes = git(&["status", "--porcelain", "--", ":/", &exclude])
+            .map(|out| out.lines().map(|l| l.trim().to_string()).collect())
+            .unwrap_or_default();
+        Some(Self {
+            commit,
+            detached,
+            changes,
+        })
+    }
+
+    /// The commit recorded in reference metadata, `-dirty` when the tree
+    /// has uncommitted changes.
+    pub fn label(&self) -> String {
+        if self.changes.is_empty() {
+            self.commit.clone()
+        } else {
+            format!("{}-dirty", self.commit)
+        }
+    }
+}
+
+/// Fail unless references would trace to a commit: a git checkout on a
+/// branch, with nothing uncommitted outside `.snapvrt/`.
+pub fn check_clean_git(git: Option<&GitState>) -> Result<()> {
+    let Some(git) = git else {
+        bail!("Not in a git checkout, so approved references can't record a commit");
+    };
+    if git.detached {
+        bail!(
+            "HEAD is detached ({}); check out a branch to approve",
+            git.commit
+        );
+    }
+    if !git.changes.is_empty() {
+        let shown: Vec<String> = git
+            .changes
+            .iter()
+            .take(10)
+            .map(|c| format!("  {c}"))
+            .collect();
+        let more = git.changes.len().saturating_sub(shown.len());
+        bail!(
+            "The working tree has uncommitted changes; commit or stash them to approve:\n{}{}",
+            shown.join("\n"),
+            if more > 0 {
+                format!("\n  ... and {more} more")
+            } else {
+                String::new()
+            }
+        );
+    }
+    Ok(())
+}
+
+/// Trimmed stdout of a successful git command.
+fn git(args: &[&str]) -> Option<String> {
+    let output = Command::new("git").args(args).output().ok()?;
+    let out = String::from_utf8(output.stdout).ok()?;
+    let out = out.trim();
+    (output.status.success() && !out.is_empty()).then(|| out.to_string())
+}
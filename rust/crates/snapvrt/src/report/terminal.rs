@@ -1,8 +1,98 @@
-use std::io::Write;
-use std::time::Duration;
-
-use crate::capture::CaptureTimings;
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::io::{IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use crate::capture::{Activity, CaptureTimings};
 use crate::compare::SnapshotStatus;
+use crate::compare::diff::Region;
+use crate::report::json::GroupCounts;
+use crate::report::{Reporter, RunEnd, SnapshotEvent};
+use crate::store;
+
+/// Number of changed regions listed under a FAIL line.
+const REGIONS_SHOWN: usize = 3;
+
+/// Redraw interval of the live progress display.
+const PROGRESS_TICK: Duration = Duration::from_millis(100);
+
+/// Longest story name shown on a worker line of the live progress display.
+const PROGRESS_NAME_WIDTH: usize = 60;
+
+/// Multi-line live progress display (see `start_progress`).
+struct LiveProgress {
+    activity: Arc<Activity>,
+    started: Instant,
+    done: usize,
+    total: usize,
+    failures: usize,
+    /// Number of lines currently drawn; 0 = nothing on screen.
+    drawn: usize,
+    /// Set while result lines are printed, so the ticker doesn't draw over them.
+    hidden: bool,
+}
+
+static LIVE: Mutex<Option<LiveProgress>> = Mutex::new(None);
+
+static COLOR: AtomicBool = AtomicBool::new(true);
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Apply `--quiet` and `--no-color` to all terminal output. A non-empty
+/// `NO_COLOR` environment variable also disables color.
+pub fn configure(quiet: bool, no_color: bool) {
+    let no_color = no_color || std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+    COLOR.store(!no_color, Ordering::Relaxed);
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+pub fn color_enabled() -> bool {
+    COLOR.load(Ordering::Relaxed)
+}
+
+/// Quiet mode: only failures and summaries are printed.
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// Wrap `text` in the SGR escape `code`, unless color is disabled.
+fn paint(code: &str, text: impl Display) -> String {
+    if color_enabled() {
+        format!("\x1b[{code}m{text}\x1b[0m")
+    } else {
+        text.to_string()
+    }
+}
+
+pub fn red(text: impl Display) -> String {
+    paint("31", text)
+}
+
+pub fn green(text: impl Display) -> String {
+    paint("32", text)
+}
+
+pub fn yellow(text: impl Display) -> String {
+    paint("33", text)
+}
+
+pub fn dim(text: impl Display) -> String {
+    paint("2", text)
+}
+
+pub fn bold(text: impl Display) -> String {
+    paint("1", text)
+}
+
+fn live() -> MutexGuard<'static, Option<LiveProgress>> {
+    LIVE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Whether progress may be drawn: stdout is a terminal and not `--quiet`.
+fn is_tty() -> bool {
+    std::io::stdout().is_terminal() && !is_quiet()
+}
 
 const STAGE_NAMES: [&str; 10] = [
     "viewport",
@@ -32,9 +122,19 @@ fn stage_durations(t: &CaptureTimings) -> [Duration; 10] {
     ]
 }
 
-/// Clear the current terminal line (wipes progress indicator).
+/// Clear the progress indicator so a result line can be printed.
+/// No-op when stdout is not a terminal.
 pub fn clear_line() {
-    print!("\r\x1b[2K");
+    if !is_tty() {
+        return;
+    }
+    match live().as_mut() {
+        Some(p) => {
+            p.hidden = true;
+            erase_progress(p);
+        }
+        None => print!("\r\x1b[2K"),
+    }
 }
 
 pub fn format_duration(d: Duration) -> String {
@@ -46,63 +146,264 @@ pub fn format_duration(d: Duration) -> String {
     }
 }
 
+pub fn format_bytes(n: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut v = n as f64;
+    let mut unit = 0;
+    while v >= 1024.0 && unit < UNITS.len() - 1 {
+        v /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{n}B")
+    } else {
+        format!("{v:.1}{}", UNITS[unit])
+    }
+}
+
 /// Print a single snapshot result line.
 pub fn print_line(name: &str, status: &SnapshotStatus, elapsed: Duration) {
     clear_line();
-    let time_suffix = format!("  \x1b[2m{}\x1b[0m", format_duration(elapsed));
+    if is_quiet() && matches!(status, SnapshotStatus::Pass | SnapshotStatus::New) {
+        return;
+    }
+    let time_suffix = format!("  {}", dim(format_duration(elapsed)));
 
     match status {
         SnapshotStatus::Pass => {
-            println!("  \x1b[32mPASS\x1b[0m  {name}{time_suffix}");
+            println!("  {}  {name}{time_suffix}", green("PASS"));
         }
         SnapshotStatus::Fail {
             diff_pixels,
             score,
+            ssim,
+            flip,
             dimension_mismatch,
+            regions,
+            text_changes,
         } => {
-            if let Some((rw, rh, cw, ch)) = dimension_mismatch {
+            let ssim = ssim.map(|s| format!(", SSIM {s:.4}")).unwrap_or_default()
+                + &flip.map(|f| format!(", FLIP {f:.4}")).unwrap_or_default();
+            if let Some(change) = dimension_mismatch {
+                let [rw, rh, cw, ch] = change.dims();
+                println!(
+                    "  {}  {name}  (dimensions changed: {rw}x{rh} -> {cw}x{ch}, {change}){time_suffix}",
+                    red("FAIL")
+                );
+            } else if *diff_pixels == 0 && !text_changes.is_empty() {
                 println!(
-                    "  \x1b[31mFAIL\x1b[0m  {name}  (dimensions changed: {rw}x{rh} -> {cw}x{ch}){time_suffix}"
+                    "  {}  {name}  ({} changed){time_suffix}",
+                    red("FAIL"),
+                    text_changes.join(", ")
                 );
             } else {
                 println!(
-                    "  \x1b[31mFAIL\x1b[0m  {name}  ({diff_pixels} pixels, {score:.4}){time_suffix}"
+                    "  {}  {name}  ({diff_pixels} pixels, {score:.4}{ssim}){time_suffix}",
+                    red("FAIL")
                 );
             }
+            print_regions(regions);
+            print_text_changes(name, text_changes);
         }
         SnapshotStatus::New => {
-            println!("  \x1b[33m NEW\x1b[0m  {name}  (no reference){time_suffix}");
+            println!("  {}  {name}  (no reference){time_suffix}", yellow(" NEW"));
         }
         SnapshotStatus::Error(msg) => {
-            println!("  \x1b[31m ERR\x1b[0m  {name}  ({msg}){time_suffix}");
+            println!("  {}  {name}  ({msg}){time_suffix}", red(" ERR"));
         }
     }
 }
 
+/// Print the largest changed regions below a FAIL line.
+fn print_regions(regions: &[Region]) {
+    for r in regions.iter().take(REGIONS_SHOWN) {
+        println!(
+            "        {}",
+            dim(format!(
+                "change near ({},{}) {}x{}",
+                r.x, r.y, r.width, r.height
+            ))
+        );
+    }
+    if regions.len() > REGIONS_SHOWN {
+        println!(
+            "        {}",
+            dim(format!("+{} more region(s)", regions.len() - REGIONS_SHOWN))
+        );
+    }
+}
+
+/// Point to the diff of each changed text snapshot below a FAIL line.
+fn print_text_changes(name: &str, kinds: &[String]) {
+    for kind in kinds {
+        println!(
+            "        {}",
+            dim(format!(
+                "{kind} changed: {}/{}/{name}.{kind}.diff",
+                store::BASE_DIR,
+                store::DIFFERENCE_DIR
+            ))
+        );
+    }
+}
+
 /// Print an error line (no timing available).
 pub fn print_error_line(name: &str, msg: &str) {
     clear_line();
-    println!("  \x1b[31m ERR\x1b[0m  {name}  ({msg})");
+    println!("  {}  {name}  ({msg})", red(" ERR"));
 }
 
-/// Print a removed/orphaned reference line.
+/// Print a removed/orphaned reference line (skipped in quiet mode).
 pub fn print_removed_line(name: &str) {
+    if is_quiet() {
+        return;
+    }
+    clear_line();
+    println!(
+        "  {}  {}",
+        dim("GONE"),
+        dim(format!("{name}  (no matching story)"))
+    );
+}
+
+/// Print a progress line for a snapshot that needs no attention, such as
+/// `Updated <id>` (skipped in quiet mode).
+pub fn print_info_line(label: &str, name: &str, detail: &str) {
+    if is_quiet() {
+        return;
+    }
+    clear_line();
+    println!("  {label}  {name}  {}", dim(detail));
+}
+
+/// Warn that the capture environment differs from the one the baselines
+/// were recorded in, so diffs may not come from code changes.
+pub fn print_env_warning(differences: &[String]) {
     clear_line();
-    println!("  \x1b[2mGONE\x1b[0m  \x1b[2m{name}  (no matching story)\x1b[0m");
+    println!();
+    println!(
+        "{}",
+        bold(yellow(
+            "WARNING: capture environment differs from the baselines"
+        ))
+    );
+    for line in differences {
+        println!("  {}", yellow(line));
+    }
+    println!(
+        "  Diffs may come from the environment rather than code. Re-run `snapvrt update` \
+         in the baseline environment, or use `snapvrt chrome up` for a pinned browser."
+    );
+    println!();
 }
 
-/// Show capture progress indicator.
-pub fn show_progress(done: usize, total: usize) {
-    if done < total {
-        print!("  Capturing  [{done}/{total}]");
+/// Switch `show_progress` to a multi-line display listing each worker's
+/// current story and stage, redrawn in the background until
+/// `finish_progress`. Without a terminal, progress is not shown at all.
+pub fn start_progress(total: usize, activity: Arc<Activity>) {
+    if !is_tty() {
+        return;
+    }
+    *live() = Some(LiveProgress {
+        activity,
+        started: Instant::now(),
+        done: 0,
+        total,
+        failures: 0,
+        drawn: 0,
+        hidden: false,
+    });
+    std::thread::spawn(|| {
+        loop {
+            std::thread::sleep(PROGRESS_TICK);
+            let mut guard = live();
+            let Some(p) = guard.as_mut() else {
+                break;
+            };
+            if !p.hidden {
+                draw_progress(p);
+            }
+        }
+    });
+}
+
+/// Remove the live progress display and stop redrawing it.
+pub fn finish_progress() {
+    if let Some(mut p) = live().take() {
+        erase_progress(&mut p);
+    }
+}
+
+/// Show capture progress: `done` of `total` finished, `failures` of them
+/// failed or errored.
+pub fn show_progress(done: usize, total: usize, failures: usize) {
+    if !is_tty() {
+        return;
+    }
+    match live().as_mut() {
+        Some(p) => {
+            p.done = done;
+            p.total = total;
+            p.failures = failures;
+            p.hidden = false;
+            draw_progress(p);
+        }
+        None if done < total => {
+            print!("  Capturing  [{done}/{total}]");
+            let _ = std::io::stdout().flush();
+        }
+        None => {}
+    }
+}
+
+fn erase_progress(p: &mut LiveProgress) {
+    if p.drawn > 0 {
+        // Move to the start of the first drawn line, clear to end of screen.
+        print!("\x1b[{}F\x1b[J", p.drawn);
         let _ = std::io::stdout().flush();
+        p.drawn = 0;
     }
 }
 
+fn draw_progress(p: &mut LiveProgress) {
+    erase_progress(p);
+    if p.done >= p.total {
+        return;
+    }
+
+    let mut out = String::new();
+    out.push_str(&format!("  Capturing  [{}/{}]", p.done, p.total));
+    if p.done > 0 {
+        let per_job = p.started.elapsed() / p.done as u32;
+        let eta = per_job * (p.total - p.done) as u32;
+        out.push_str(&format!("  ETA {}", format_duration(eta)));
+    }
+    if p.failures > 0 {
+        out.push_str(&format!("  {}", red(format!("{} failed", p.failures))));
+    }
+    out.push('\n');
+    let mut lines = 1;
+
+    for a in p.activity.snapshot().into_iter().flatten() {
+        out.push_str(&format!(
+            "    {} {}\n",
+            dim(format!("{:<10}", a.stage)),
+            truncate_name(&a.story, PROGRESS_NAME_WIDTH)
+        ));
+        lines += 1;
+    }
+
+    print!("{out}");
+    let _ = std::io::stdout().flush();
+    p.drawn = lines;
+}
+
 /// Print an actionable summary listing snapshot names grouped by status.
-/// Only prints sections with at least one entry.
+/// Only prints sections with at least one entry. With `by_owner`, failed
+/// snapshots are listed under their owner.
 pub fn print_actionable_summary(
-    failed: &[String],
+    failed: &[(String, Option<String>)],
+    by_owner: bool,
     new: &[String],
     errored: &[String],
     removed: &[String],
@@ -115,8 +416,16 @@ pub fn print_actionable_summary(
     println!();
     println!("Actionable snapshots:");
 
+    if by_owner && !failed.is_empty() {
+        print_failed_by_owner(failed);
+    }
+    let failed_names: Vec<String> = if by_owner {
+        Vec::new()
+    } else {
+        failed.iter().map(|(name, _)| name.clone()).collect()
+    };
     for (label, names) in [
-        ("Failed", failed),
+        ("Failed", failed_names.as_slice()),
         ("New", new),
         ("Errored", errored),
         ("Removed", removed),
@@ -131,28 +440,54 @@ pub fn print_actionable_summary(
     }
 }
 
-/// Print the final summary.
-pub fn print_summary(
-    total: usize,
-    passed: usize,
-    failed: usize,
-    new: usize,
-    errored: usize,
-    removed: usize,
-    elapsed: Duration,
-) {
+/// The failed section with a sub-list per owner, unowned snapshots last.
+fn print_failed_by_owner(failed: &[(String, Option<String>)]) {
+    let mut owned: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    let mut unowned: Vec<&str> = Vec::new();
+    for (name, owner) in failed {
+        match owner {
+            Some(owner) => owned.entry(owner).or_default().push(name),
+            None => unowned.push(name),
+        }
+    }
+    println!();
+    println!("  Failed ({}):", failed.len());
+    let groups = owned
+        .into_iter()
+        .chain((!unowned.is_empty()).then_some(("(no owner)", unowned)));
+    for (owner, names) in groups {
+        println!("    {owner} ({}):", names.len());
+        for name in names {
+            println!("      {name}");
+        }
+    }
+}
+
+/// Print the final summary; counts are the totals of `breakdown`.
+pub fn print_summary(total: usize, breakdown: &[GroupCounts], removed: usize, elapsed: Duration) {
+    let sum = |count: fn(&GroupCounts) -> usize| breakdown.iter().map(count).sum::<usize>();
+    let (passed, failed, new, errored) = (
+        sum(|g| g.passed),
+        sum(|g| g.failed),
+        sum(|g| g.new),
+        sum(|g| g.errored),
+    );
     clear_line();
     println!();
     print!(
-        "Snapshots:  {total} total, \x1b[32m{passed} passed\x1b[0m, \x1b[31m{failed} failed\x1b[0m, \x1b[33m{new} new\x1b[0m"
+        "Snapshots:  {total} total, {}, {}, {}",
+        green(format!("{passed} passed")),
+        red(format!("{failed} failed")),
+        yellow(format!("{new} new"))
     );
     if errored > 0 {
-        print!(", \x1b[31m{errored} errored\x1b[0m");
+        print!(", {}", red(format!("{errored} errored")));
     }
     if removed > 0 {
-        print!(", \x1b[2m{removed} removed\x1b[0m");
+        print!(", {}", dim(format!("{removed} removed")));
     }
     println!();
+    print_breakdown(breakdown);
     println!("Time:       {}", format_duration(elapsed));
 
     if failed > 0 || new > 0 || errored > 0 || removed > 0 {
@@ -175,6 +510,45 @@ pub fn print_summary(
     }
 }
 
+/// Per source/viewport counts, when the run covered more than one — shows
+/// at a glance which viewport regressed. The source is left out when there
+/// is only one.
+fn print_breakdown(breakdown: &[GroupCounts]) {
+    if breakdown.len() < 2 {
+        return;
+    }
+    let one_source = breakdown.iter().all(|g| g.source == breakdown[0].source);
+    let labels: Vec<String> = breakdown
+        .iter()
+        .map(|g| {
+            if one_source {
+                g.viewport.clone()
+            } else {
+                format!("{}/{}", g.source, g.viewport)
+            }
+        })
+        .collect();
+    let width = labels.iter().map(String::len).max().unwrap_or(0);
+    for (group, label) in breakdown.iter().zip(&labels) {
+        let failed = format!("{} failed", group.failed);
+        let new = format!("{} new", group.new);
+        print!(
+            "  {label:<width$}  {}, {}, {}",
+            green(format!("{} passed", group.passed)),
+            if group.failed > 0 {
+                red(failed)
+            } else {
+                dim(failed)
+            },
+            if group.new > 0 { yellow(new) } else { dim(new) },
+        );
+        if group.errored > 0 {
+            print!(", {}", red(format!("{} errored", group.errored)));
+        }
+        println!();
+    }
+}
+
 /// Print a per-snapshot timing table with all stage breakdowns.
 ///
 /// Sorted by total descending (slowest first). Right-aligned numeric columns.
@@ -202,20 +576,27 @@ pub fn print_timing_table(entries: &[(String, CaptureTimings)]) {
         "total", "viewpt", "navig", "load", "network", "anim", "ready", "select", "clip", "screen",
         "compare",
     ];
+    // Page metrics, when the run recorded them.
+    let perf = sorted.iter().any(|(_, t)| t.perf.is_some());
+    let perf_headers: &[&str] = if perf {
+        &["layouts", "script", "heap"]
+    } else {
+        &[]
+    };
 
     println!();
-    println!("\x1b[1mCapture timings (all snapshots):\x1b[0m");
+    println!("{}", bold("Capture timings (all snapshots):"));
     println!();
 
     // Header line.
     print!("  {:<width$}", "Snapshot", width = name_width);
-    for h in &headers {
+    for h in headers.iter().chain(perf_headers) {
         print!("  {:>7}", h);
     }
     println!();
 
     // Separator line.
-    let sep_len = name_width + 2 + headers.len() * 9;
+    let sep_len = name_width + 2 + (headers.len() + perf_headers.len()) * 9;
     print!("  ");
     for _ in 0..sep_len {
         print!("\u{2500}");
@@ -230,6 +611,16 @@ pub fn print_timing_table(entries: &[(String, CaptureTimings)]) {
         for d in stage_durations(t) {
             print!("  {:>5}ms", d.as_millis());
         }
+        match &t.perf {
+            Some(p) => print!(
+                "  {:>7}  {:>5}ms  {:>5}MB",
+                p.layout_count,
+                p.script_ms.round() as u64,
+                p.js_heap_used.div_ceil(1024 * 1024)
+            ),
+            None if perf => print!("  {:>7}  {:>7}  {:>7}", "-", "-", "-"),
+            None => {}
+        }
         println!();
     }
 }
@@ -267,7 +658,7 @@ pub fn print_timing_summary(entries: &[(String, CaptureTimings)]) {
     let max_avg = indexed.first().map_or(1, |&(_, v)| v.max(1));
 
     println!();
-    println!("\x1b[1mTiming breakdown\x1b[0m (avg per snapshot):");
+    println!("{} (avg per snapshot):", bold("Timing breakdown"));
 
     for &(i, avg) in &indexed {
         let pct = if total_avg > 0 {
@@ -292,6 +683,29 @@ pub fn print_timing_summary(entries: &[(String, CaptureTimings)]) {
         );
     }
 
+    // Identical snapshots skip comparison entirely; averaging them in would
+    // hide what a real comparison costs.
+    let identical = entries.iter().filter(|(_, t)| t.identical).count();
+    let compared: Vec<u128> = entries
+        .iter()
+        .filter(|(_, t)| !t.identical && !t.compare.is_zero())
+        .map(|(_, t)| t.compare.as_millis())
+        .collect();
+    println!();
+    println!(
+        "{} {identical} identical (skipped), {} compared{}",
+        bold("Comparison:"),
+        compared.len(),
+        if compared.is_empty() {
+            String::new()
+        } else {
+            format!(
+                ", avg {}ms",
+                compared.iter().sum::<u128>() / compared.len() as u128
+            )
+        },
+    );
+
     // Top 5 slowest snapshots.
     let mut by_total: Vec<(usize, u128)> = entries
         .iter()
@@ -302,7 +716,7 @@ pub fn print_timing_summary(entries: &[(String, CaptureTimings)]) {
 
     let top_n = by_total.len().min(5);
     println!();
-    println!("\x1b[1mSlowest snapshots:\x1b[0m");
+    println!("{}", bold("Slowest snapshots:"));
     for &(i, total_ms) in &by_total[..top_n] {
         let (name, t) = &entries[i];
         let (dom_name, dom_ms) = dominant_stage(t);
@@ -314,6 +728,21 @@ pub fn print_timing_summary(entries: &[(String, CaptureTimings)]) {
     }
 }
 
+/// Peak depth of the queue between capture and comparison. A queue that fills
+/// up means comparisons (or disk writes) are holding back the capture workers.
+pub fn print_compare_queue(peak: usize, capacity: usize, workers: usize) {
+    println!();
+    println!(
+        "{} peak {peak}/{capacity} queued, {workers} workers{}",
+        bold("Compare queue:"),
+        if peak >= capacity {
+            dim(" (full: captures waited on comparisons)")
+        } else {
+            String::new()
+        },
+    );
+}
+
 /// Return the name and duration (ms) of the dominant (longest) stage.
 fn dominant_stage(t: &CaptureTimings) -> (&'static str, u128) {
     STAGE_NAMES
@@ -335,3 +764,107 @@ fn truncate_name(name: &str, max: usize) -> String {
         format!("…{truncated}")
     }
 }
+
+/// `test` output: a line per snapshot, live progress, then the timing
+/// tables (with `--timings`), actionable lists and summary.
+#[derive(Default)]
+pub struct TerminalReporter {
+    timings: bool,
+    group_by_owner: bool,
+    total: usize,
+    /// Failed snapshot IDs and their owners.
+    failed: Vec<(String, Option<String>)>,
+    new: Vec<String>,
+    errored: Vec<String>,
+    all_timings: Vec<(String, CaptureTimings)>,
+    done: usize,
+}
+
+impl TerminalReporter {
+    pub fn new(timings: bool, group_by_owner: bool) -> Self {
+        Self {
+            timings,
+            group_by_owner,
+            ..Self::default()
+        }
+    }
+}
+
+impl Reporter for TerminalReporter {
+    fn start(&mut self, total: usize) {
+        self.total = total;
+    }
+
+    fn snapshot(&mut self, event: &SnapshotEvent<'_>) -> anyhow::Result<()> {
+        let name = &event.entry.id;
+        self.done += 1;
+        match event.status {
+            SnapshotStatus::Pass => {}
+            SnapshotStatus::Fail { .. } => {
+                self.failed.push((name.clone(), event.entry.owner.clone()));
+            }
+            SnapshotStatus::New => self.new.push(name.clone()),
+            SnapshotStatus::Error(_) => self.errored.push(name.clone()),
+        }
+        match (event.timings, event.status) {
+            (Some(timings), status) => {
+                print_line(name, status, timings.total + timings.compare);
+                self.all_timings.push((name.clone(), timings.clone()));
+            }
+            (None, SnapshotStatus::Error(msg)) => print_error_line(name, msg),
+            (None, status) => print_line(name, status, Duration::ZERO),
+        }
+        show_progress(
+            self.done,
+            self.total,
+            self.failed.len() + self.errored.len(),
+        );
+        Ok(())
+    }
+
+    fn finish(&mut self, run: &RunEnd<'_>) -> anyhow::Result<()> {
+        for id in run.removed {
+            print_removed_line(id);
+        }
+        if self.timings {
+            print_timing_table(&self.all_timings);
+            print_timing_summary(&self.all_timings);
+            print_compare_queue(run.peak_queued, run.queue_capacity, run.compare_workers);
+        }
+        print_actionable_summary(
+            &self.failed,
+            self.group_by_owner,
+            &self.new,
+            &self.errored,
+            run.removed,
+        );
+        if !run.env_diffs.is_empty() {
+            print_env_warning(run.env_diffs);
+        }
+        print_summary(run.total, run.breakdown, run.removed.len(), run.elapsed);
+        if run.info.interrupted {
+            let reported: usize = run
+                .breakdown
+                .iter()
+                .map(|g| g.passed + g.failed + g.new + g.errored)
+                .sum();
+            println!(
+                "{}",
+                yellow(format!(
+                    "Interrupted: {reported} of {} snapshot(s) have results.",
+                    run.total
+                ))
+            );
+        }
+        if run.orphans_in_grace > 0 {
+            println!(
+                "{}",
+                dim(format!(
+                    "{} orphaned reference(s) kept within the grace period.",
+                    run.orphans_in_grace
+                ))
+            );
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,91 @@
+use std::fs::{File, OpenOptions, TryLockError};
+use std::io::Write;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+
+use super::{BASE_DIR, ensure_parent};
+
+const LOCK_FILE: &str = ".lock";
+
+/// Whether this process holds the lock.
+static HELD: AtomicBool = AtomicBool::new(false);
+
+/// Poll interval while `--wait`ing for another process to release the lock.
+const WAIT_POLL: Duration = Duration::from_millis(200);
+
+/// Exclusive lock on `.snapvrt/.lock`, held for the lifetime of a command
+/// that writes to the store. Two overlapping runs would otherwise interleave
+/// writes to `current/` and the reference manifest.
+///
+/// Released on drop (or by the OS when the process exits).
+pub struct RunLock {
+    _file: File,
+}
+
+impl RunLock {
+    /// Acquire the lock. Fails immediately when another snapvrt process holds
+    /// it, unless `wait` is set, in which case this blocks until it is free.
+    pub async fn acquire(wait: bool) -> Result<Self> {
+        let path = Path::new(BASE_DIR).join(LOCK_FILE);
+        ensure_parent(&path)?;
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+
+        let mut announced = false;
+        loop {
+            match file.try_lock() {
+                Ok(()) => break,
+                Err(TryLockError::WouldBlock) => {
+                    let holder = holder_pid(&path)
+                        .map(|pid| format!(" (pid {pid})"))
+                        .unwrap_or_default();
+                    if !wait {
+                        bail!(
+                            "Another snapvrt process{holder} is using {BASE_DIR}/. \
+                             Wait for it to finish, or pass --wait to queue behind it."
+                        );
+                    }
+                    if !announced {
+                        eprintln!("Waiting for another snapvrt process{holder} to finish...");
+                        announced = true;
+                    }
+                    tokio::time::sleep(WAIT_POLL).await;
+                }
+                Err(TryLockError::Error(e)) => {
+                    return Err(e).with_context(|| format!("Failed to lock {}", path.display()));
+                }
+            }
+        }
+
+        // Record our PID so a blocked process can say who holds the lock.
+        file.set_len(0)
+            .and_then(|()| write!(file, "{}", std::process::id()))
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+
+        HELD.store(true, Ordering::SeqCst);
+        Ok(Self { _file: file })
+    }
+
+    /// Whether this process currently holds the lock.
+    pub fn held() -> bool {
+        HELD.load(Ordering::SeqCst)
+    }
+}
+
+impl Drop for RunLock {
+    fn drop(&mut self) {
+        HELD.store(false, Ordering::SeqCst);
+    }
+}
+
+fn holder_pid(path: &Path) -> Option<u32> {
+    std::fs::read_to_string(path).ok()?.trim().parse().ok()
+}
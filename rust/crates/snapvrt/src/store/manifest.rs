@@ -0,0 +1,665 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::{debug, info};
+
+use crate::capture::Environment;
+use crate::capture::job::DEFAULT_ID_TEMPLATE;
+use crate::compare::diff::Mask;
+
+use super::signing;
+use super::{
+    BASE_DIR, IMAGE_EXTENSIONS, REFERENCE_DIR, RunLock, clean_output, ensure_parent, extension_of,
+    remove_empty_dirs,
+};
+
+const MANIFEST_FILE: &str = "manifest.json";
+const OBJECTS_DIR: &str = "objects";
+const MANIFEST_VERSION: u32 = 1;
+
+/// Object file extension of text snapshots.
+const TEXT_FORMAT: &str = "txt";
+
+/// Metadata recorded for each reference snapshot.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReferenceEntry {
+    /// SHA-256 of the stored image bytes (hex). Also the object file stem.
+    pub hash: String,
+    /// Object file extension (`png` or `webp`).
+    pub format: String,
+    pub width: u32,
+    pub height: u32,
+    /// Encoded size in bytes.
+    pub size: u64,
+    /// Unix timestamp (seconds) of the last write.
+    pub updated_at: u64,
+    /// `ignore_selectors` bounds measured when the reference was captured.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub masks: Vec<Mask>,
+    /// Text snapshots (`a11y`, ...) by kind: SHA-256 of the text, stored
+    /// as `objects/<sha256>.txt`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub texts: BTreeMap<String, String>,
+    /// Git commit checked out when the reference was approved, with a
+    /// `-dirty` suffix if the working tree had uncommitted changes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Manifest {
+    version: u32,
+    /// `store.id_template` the snapshot IDs were rendered with.
+    /// Absent in manifests written before templates existed (= default).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id_template: Option<String>,
+    /// Environment the references were last captured in (by `update`, or
+    /// the `test` run whose images `approve` accepted).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    environment: Option<Environment>,
+    /// HMAC-SHA256 over every field of every snapshot entry and the ID
+    /// template, when committed with a signing key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    signature: Option<String>,
+    snapshots: BTreeMap<String, ReferenceEntry>,
+}
+
+/// Content-addressed reference store.
+///
+/// Images live in `reference/objects/<sha256>.<ext>`; `reference/manifest.json`
+/// maps snapshot IDs to object hashes. Identical images (e.g. the same story
+/// at two viewports that render the same) share one object.
+///
+/// Objects are written immediately, but the manifest is only replaced on
+/// `commit()` (write-to-temp + rename), so an interrupted run leaves the
+/// previous baselines intact. Unreferenced objects are collected on commit.
+pub struct References {
+    /// `.snapvrt/reference`.
+    root: PathBuf,
+    manifest: Manifest,
+    dirty: bool,
+}
+
+fn object_relative(hash: &str, format: &str) -> String {
+    format!("{OBJECTS_DIR}/{hash}.{format}")
+}
+
+pub fn hash_bytes(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+pub(super) fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+impl References {
+    /// Load the manifest to read references. A store still in the
+    /// pre-manifest layout is left as it is: it fails, pointing at the
+    /// commands that migrate it (see [`Self::open_for_writing`]).
+    pub fn open() -> Result<Self> {
+        Self::open_in(Path::new(BASE_DIR).join(REFERENCE_DIR), false)
+    }
+
+    /// Load the manifest to change references, first migrating a legacy
+    /// per-file layout. Needs the [`RunLock`], so no other snapvrt process
+    /// reads the store while its layout changes.
+    pub fn open_for_writing() -> Result<Self> {
+        if !RunLock::held() {
+            bail!("Internal error: reference store opened for writing without the run lock");
+        }
+        Self::open_in(Path::new(BASE_DIR).join(REFERENCE_DIR), true)
+    }
+
+    fn open_in(root: PathBuf, migrate: bool) -> Result<Self> {
+        let path = root.join(MANIFEST_FILE);
+        if path.exists() {
+            let content = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            let manifest: Manifest = serde_json::from_str(&content)
+                .with_context(|| format!("Failed to parse {}", path.display()))?;
+            return Ok(Self {
+                root,
+                manifest,
+                dirty: false,
+            });
+        }
+
+        let mut refs = Self {
+            root,
+            manifest: Manifest {
+                version: MANIFEST_VERSION,
+                id_template: None,
+                environment: None,
+                signature: None,
+                snapshots: BTreeMap::new(),
+            },
+            dirty: false,
+        };
+        let mut legacy = Vec::new();
+        collect_legacy(&refs.root, &refs.root, &mut legacy);
+        if !legacy.is_empty() {
+            if !migrate {
+                bail!(
+                    "{} uses the pre-manifest layout. Run `snapvrt test` or `snapvrt update` \
+                     once to migrate it",
+                    refs.root.display()
+                );
+            }
+            refs.migrate_legacy(&legacy)?;
+        }
+        Ok(refs)
+    }
+
+    fn manifest_path(&self) -> PathBuf {
+        self.root.join(MANIFEST_FILE)
+    }
+
+    fn object_path(&self, hash: &str, format: &str) -> PathBuf {
+        self.root.join(object_relative(hash, format))
+    }
+
+    /// Import `legacy` `(id, path)` files (`reference/<id>.<ext>`), commit
+    /// the manifest, then delete them.
+    fn migrate_legacy(&mut self, legacy: &[(String, PathBuf)]) -> Result<()> {
+        info!(
+            count = legacy.len(),
+            "migrating references to content-addressed store"
+        );
+        for (id, path) in legacy {
+            let bytes = std::fs::read(path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            self.insert(id, &bytes, Vec::new(), BTreeMap::new())?;
+        }
+        self.commit_manifest()?;
+        for (_, path) in legacy {
+            let _ = std::fs::remove_file(path);
+        }
+        remove_empty_dirs(&self.root, &self.root);
+        Ok(())
+    }
+
+    pub fn ids(&self) -> BTreeSet<String> {
+        self.manifest.snapshots.keys().cloned().collect()
+    }
+
+    /// Template the stored IDs were rendered with.
+    pub fn id_template(&self) -> &str {
+        self.manifest
+            .id_template
+            .as_deref()
+            .unwrap_or(DEFAULT_ID_TEMPLATE)
+    }
+
+    /// Fail when references exist under a different ID template than
+    /// `template`, which would turn every snapshot into new + orphaned.
+    pub fn check_id_template(&self, template: &str) -> Result<()> {
+        if self.manifest.snapshots.is_empty() || self.id_template() == template {
+            return Ok(());
+        }
+        bail!(
+            "References were stored with id_template \"{}\", but store.id_template is \"{template}\". \
+             Run `snapvrt migrate-ids` to rename them.",
+            self.id_template()
+        );
+    }
+
+    /// Record the template used for IDs (written on the next `commit`).
+    pub fn set_id_template(&mut self, template: &str) {
+        let value = (template != DEFAULT_ID_TEMPLATE).then(|| template.to_string());
+        if self.manifest.id_template != value {
+            self.manifest.id_template = value;
+            self.dirty = true;
+        }
+    }
+
+    pub fn environment(&self) -> Option<&Environment> {
+        self.manifest.environment.as_ref()
+    }
+
+    /// Record the environment references were captured in (written on the next `commit`).
+    pub fn set_environment(&mut self, env: &Environment) {
+        if self.manifest.environment.as_ref() != Some(env) {
+            self.manifest.environment = Some(env.clone());
+            self.dirty = true;
+        }
+    }
+
+    /// Re-key references (`(from, to)` pairs). All sources are detached
+    /// before any target is inserted, so chains and swaps are safe. Objects
+    /// are shared, so no image data is copied.
+    pub fn rename_all(&mut self, pairs: &[(String, String)]) {
+        let moved: Vec<(&String, ReferenceEntry)> = pairs
+            .iter()
+            .filter_map(|(from, to)| self.manifest.snapshots.remove(from).map(|e| (to, e)))
+            .collect();
+        for (to, entry) in moved {
+            self.manifest.snapshots.insert(to.clone(), entry);
+            self.dirty = true;
+        }
+    }
+
+    pub fn get(&self, id: &str) -> Option<&ReferenceEntry> {
+        self.manifest.snapshots.get(id)
+    }
+
+    /// Object path relative to `reference/` (for report links).
+    pub fn relative_path(&self, id: &str) -> Option<String> {
+        self.get(id).map(|e| object_relative(&e.hash, &e.format))
+    }
+
+    /// The reference image for `id`, checked against its recorded SHA-256
+    /// so baselines edited outside snapvrt fail instead of silently
+    /// changing results. `None` when there is no reference (or its object
+    /// is missing).
+    pub fn read(&self, id: &str) -> Result<Option<Vec<u8>>> {
+        let Some(entry) = self.get(id) else {
+            return Ok(None);
+        };
+        let path = self.object_path(&entry.hash, &entry.format);
+        let Ok(bytes) = std::fs::read(&path) else {
+            return Ok(None);
+        };
+        if hash_bytes(&bytes) != entry.hash {
+            bail!(
+                "Reference {id} was modified outside snapvrt: {} no longer matches its \
+                 checksum. Restore it (e.g. from git) or re-capture it with `snapvrt update`",
+                path.display()
+            );
+        }
+        Ok(Some(bytes))
+    }
+
+    /// With `SNAPVRT_SIGNING_KEY` set, fail unless the manifest carries a
+    /// valid signature from a run with the same key.
+    pub fn verify_signature(&self) -> Result<()> {
+        let Some(key) = signing::signing_key() else {
+            return Ok(());
+        };
+        let (template, snapshots) = (
+            self.manifest.id_template.as_deref(),
+            &self.manifest.snapshots,
+        );
+        match &self.manifest.signature {
+            Some(signature) if signing::verify(&key, template, snapshots, signature) => Ok(()),
+            Some(_) => bail!(
+                "{} signature does not match: references were changed outside snapvrt, \
+                 or signed with another {}",
+                self.manifest_path().display(),
+                signing::SIGNING_KEY_ENV
+            ),
+            None if self.manifest.snapshots.is_empty() => Ok(()),
+            None => bail!(
+                "{} is not signed, but {} is set. Re-approve the references with the key set",
+                self.manifest_path().display(),
+                signing::SIGNING_KEY_ENV
+            ),
+        }
+    }
+
+    /// Cheap change check: true when `image` hashes to the stored reference.
+    pub fn is_unchanged(&self, id: &str, image: &[u8]) -> bool {
+        self.get(id).is_some_and(|e| e.hash == hash_bytes(image))
+    }
+
+    /// Store `image` (with its ignore masks and text snapshots) as the
+    /// reference for `id` and clean its stale current/difference.
+    pub fn write(
+        &mut self,
+        id: &str,
+        image: &[u8],
+        masks: Vec<Mask>,
+        texts: &BTreeMap<String, String>,
+    ) -> Result<()> {
+        let mut hashes = BTreeMap::new();
+        for (kind, text) in texts {
+            hashes.insert(
+                kind.clone(),
+                self.write_object(text.as_bytes(), TEXT_FORMAT)?,
+            );
+        }
+        self.insert(id, image, masks, hashes)?;
+        clean_output(id);
+        Ok(())
+    }
+
+    /// Replace the reference image for `id`, keeping its masks, text
+    /// snapshots and commit and leaving current/difference alone.
+    pub fn rewrite(&mut self, id: &str, image: &[u8]) -> Result<()> {
+        let (masks, texts, commit) = self
+            .get(id)
+            .map(|e| (e.masks.clone(), e.texts.clone(), e.commit.clone()))
+            .unwrap_or_default();
+        self.insert(id, image, masks, texts)?;
+        if let Some(entry) = self.manifest.snapshots.get_mut(id) {
+            entry.commit = commit;
+        }
+        Ok(())
+    }
+
+    /// Replace the ignore masks recorded for `id`.
+    pub fn set_masks(&mut self, id: &str, masks: Vec<Mask>) {
+        if let Some(entry) = self.manifest.snapshots.get_mut(id) {
+            entry.masks = masks;
+            self.dirty = true;
+        }
+    }
+
+    /// Record the git commit `id` was approved at.
+    pub fn set_commit(&mut self, id: &str, commit: &str) {
+        if let Some(entry) = self.manifest.snapshots.get_mut(id) {
+            entry.commit = Some(commit.to_string());
+            self.dirty = true;
+        }
+    }
+
+    /// The reference text snapshot of `kind` for `id`, checked against its
+    /// checksum like images. `None` when the reference has none.
+    pub fn text(&self, id: &str, kind: &str) -> Result<Option<String>> {
+        let Some(hash) = self.get(id).and_then(|e| e.texts.get(kind)) else {
+            return Ok(None);
+        };
+        let path = self.object_path(hash, TEXT_FORMAT);
+        let Ok(bytes) = std::fs::read(&path) else {
+            return Ok(None);
+        };
+        if hash_bytes(&bytes) != *hash {
+            bail!(
+                "Reference {kind} snapshot of {id} was modified outside snapvrt: {} no longer \
+                 matches its checksum. Restore it (e.g. from git) or re-capture it with `snapvrt update`",
+                path.display()
+            );
+        }
+        String::from_utf8(bytes)
+            .map(Some)
+            .with_context(|| format!("{} is not UTF-8", path.display()))
+    }
+
+    /// Bytes freed by removing `ids`: the objects no other snapshot shares.
+    pub fn reclaimable(&self, ids: &BTreeSet<&String>) -> u64 {
+        let (removed, kept): (Vec<_>, Vec<_>) = self
+            .manifest
+            .snapshots
+            .iter()
+            .partition(|(id, _)| ids.contains(id));
+        let live: HashSet<&str> = kept.iter().map(|(_, e)| e.hash.as_str()).collect();
+        let freed: BTreeMap<&str, u64> = removed
+            .iter()
+            .filter(|(_, e)| !live.contains(e.hash.as_str()))
+            .map(|(_, e)| (e.hash.as_str(), e.size))
+            .collect();
+        freed.values().sum()
+    }
+
+    pub fn remove(&mut self, id: &str) {
+        if self.manifest.snapshots.remove(id).is_some() {
+            self.dirty = true;
+        }
+    }
+
+    fn insert(
+        &mut self,
+        id: &str,
+        image: &[u8],
+        masks: Vec<Mask>,
+        texts: BTreeMap<String, String>,
+    ) -> Result<()> {
+        let format = extension_of(image).to_string();
+        let hash = self.write_object(image, &format)?;
+        let (width, height) = image_dimensions(image).unwrap_or((0, 0));
+        self.manifest.snapshots.insert(
+            id.to_string(),
+            ReferenceEntry {
+                hash,
+                format,
+                width,
+                height,
+                size: image.len() as u64,
+                updated_at: now_secs(),
+                masks,
+                texts,
+                commit: None,
+            },
+        );
+        self.dirty = true;
+        Ok(())
+    }
+
+    /// Atomically persist the manifest and garbage-collect unreferenced objects.
+    /// No-op when nothing changed.
+    pub fn commit(mut self) -> Result<()> {
+        if !self.dirty {
+            return Ok(());
+        }
+        self.commit_manifest()?;
+        self.collect_garbage();
+        Ok(())
+    }
+
+    fn commit_manifest(&mut self) -> Result<()> {
+        let path = self.manifest_path();
+        ensure_parent(&path)?;
+        self.manifest.version = MANIFEST_VERSION;
+        // Without the key a stale signature would only fail the next
+        // verification, so drop it.
+        self.manifest.signature = signing::signing_key().map(|key| {
+            signing::sign(
+                &key,
+                self.manifest.id_template.as_deref(),
+                &self.manifest.snapshots,
+            )
+        });
+        let content =
+            serde_json::to_string_pretty(&self.manifest).context("Failed to serialize manifest")?;
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, content)
+            .with_context(|| format!("Failed to write {}", tmp.display()))?;
+        std::fs::rename(&tmp, &path)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        self.dirty = false;
+        Ok(())
+    }
+
+    fn collect_garbage(&self) {
+        let live: HashSet<String> = self
+            .manifest
+            .snapshots
+            .values()
+            .flat_map(|e| {
+                let texts = e.texts.values().map(|hash| format!("{hash}.{TEXT_FORMAT}"));
+                std::iter::once(format!("{}.{}", e.hash, e.format)).chain(texts)
+            })
+            .collect();
+        let Ok(entries) = std::fs::read_dir(self.root.join(OBJECTS_DIR)) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if !live.contains(&name) {
+                debug!(object = %name, "removing unreferenced object");
+                let _ = std::fs::remove_file(entry.path());
+            }
+        }
+    }
+
+    /// Store `bytes` as `objects/<sha256>.<format>` (unless already there)
+    /// and return the hash.
+    fn write_object(&self, bytes: &[u8], format: &str) -> Result<String> {
+        let hash = hash_bytes(bytes);
+        let path = self.object_path(&hash, format);
+        if !path.exists() {
+            ensure_parent(&path)?;
+            let tmp = path.with_extension("tmp");
+            std::fs::write(&tmp, bytes)
+                .with_context(|| format!("Failed to write {}", tmp.display()))?;
+            std::fs::rename(&tmp, &path)
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+        }
+        Ok(hash)
+    }
+}
+
+/// Read image dimensions from the header without decoding pixels.
+fn image_dimensions(image: &[u8]) -> Option<(u32, u32)> {
+    image::ImageReader::new(std::io::Cursor::new(image))
+        .with_guessed_format()
+        .ok()?
+        .into_dimensions()
+        .ok()
+}
+
+/// Collect `(id, path)` for legacy reference images, skipping `objects/`.
+fn collect_legacy(base: &Path, dir: &Path, out: &mut Vec<(String, PathBuf)>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            if dir == base && entry.file_name() == OBJECTS_DIR {
+                continue;
+            }
+            collect_legacy(base, &path, out);
+        } else if path
+            .extension()
+            .is_some_and(|e| IMAGE_EXTENSIONS.iter().any(|ext| e == *ext))
+            && let Ok(rel) = path.strip_prefix(base)
+        {
+            let id = rel.with_extension("").to_string_lossy().into_owned();
+            out.push((id, path));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::StoreConfig;
+    use crate::store::orphans::Tombstones;
+
+    /// An empty directory to root a store in.
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("snapvrt-{}-{name}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn png(width: u32, value: u8) -> Vec<u8> {
+        let img = image::RgbaImage::from_pixel(width, 2, image::Rgba([value, value, value, 255]));
+        let mut buf = Vec::new();
+        img.write_to(&mut std::io::Cursor::new(&mut buf), image::ImageFormat::Png)
+            .unwrap();
+        buf
+    }
+
+    fn files(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = std::fs::read_dir(dir)
+            .unwrap()
+            .flatten()
+            .map(|e| e.file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn stored_images_read_back_and_share_objects() {
+        let root = scratch("round-trip");
+        let image = png(3, 10);
+        let mut refs = References::open_in(root.clone(), false).unwrap();
+        refs.insert("a", &image, Vec::new(), BTreeMap::new())
+            .unwrap();
+        refs.insert("b", &image, Vec::new(), BTreeMap::new())
+            .unwrap();
+        refs.commit().unwrap();
+
+        let refs = References::open_in(root.clone(), false).unwrap();
+        assert_eq!(refs.read("b").unwrap(), Some(image.clone()));
+        let entry = refs.get("a").unwrap();
+        assert_eq!(
+            (entry.width, entry.height, entry.format.as_str()),
+            (3, 2, "png")
+        );
+        assert_eq!(files(&root.join(OBJECTS_DIR)).len(), 1);
+        assert_eq!(refs.read("missing").unwrap(), None);
+
+        // An object edited outside snapvrt no longer matches its checksum.
+        std::fs::write(refs.object_path(&entry.hash, "png"), png(3, 11)).unwrap();
+        assert!(refs.read("a").is_err());
+    }
+
+    #[test]
+    fn legacy_layout_is_migrated_only_for_writing() {
+        let root = scratch("legacy");
+        let legacy = root.join("Forms/Button.png");
+        std::fs::create_dir_all(legacy.parent().unwrap()).unwrap();
+        std::fs::write(&legacy, png(4, 20)).unwrap();
+
+        assert!(References::open_in(root.clone(), false).is_err());
+        assert!(legacy.exists(), "reading must not touch the legacy layout");
+
+        let refs = References::open_in(root.clone(), true).unwrap();
+        assert_eq!(refs.ids(), BTreeSet::from(["Forms/Button".to_string()]));
+        assert_eq!(refs.read("Forms/Button").unwrap(), Some(png(4, 20)));
+        assert!(!root.join("Forms").exists());
+        assert!(References::open_in(root, false).is_ok());
+    }
+
+    #[test]
+    fn commit_replaces_the_manifest_and_collects_garbage() {
+        let root = scratch("commit");
+        let mut refs = References::open_in(root.clone(), false).unwrap();
+        refs.insert("keep", &png(2, 30), Vec::new(), BTreeMap::new())
+            .unwrap();
+        refs.insert("drop", &png(2, 40), Vec::new(), BTreeMap::new())
+            .unwrap();
+        refs.commit().unwrap();
+        assert_eq!(files(&root.join(OBJECTS_DIR)).len(), 2);
+
+        let mut refs = References::open_in(root.clone(), false).unwrap();
+        refs.remove("drop");
+        // Uncommitted changes leave the manifest on disk as it was.
+        let before = std::fs::read_to_string(root.join(MANIFEST_FILE)).unwrap();
+        assert!(before.contains("\"drop\""));
+        refs.commit().unwrap();
+
+        let keep = hash_bytes(&png(2, 30));
+        assert_eq!(files(&root.join(OBJECTS_DIR)), [format!("{keep}.png")]);
+        assert_eq!(
+            files(&root),
+            [MANIFEST_FILE, OBJECTS_DIR],
+            "no temp files left"
+        );
+        let refs = References::open_in(root, false).unwrap();
+        assert_eq!(refs.ids(), BTreeSet::from(["keep".to_string()]));
+    }
+
+    #[test]
+    fn tracking_orphans_leaves_the_manifest_untouched() {
+        let root = scratch("orphans");
+        let mut refs = References::open_in(root.clone(), false).unwrap();
+        refs.insert("gone", &png(2, 50), Vec::new(), BTreeMap::new())
+            .unwrap();
+        refs.commit().unwrap();
+        let before = std::fs::read(root.join(MANIFEST_FILE)).unwrap();
+
+        // What a full `test` run does with an orphan in its grace period.
+        let refs = References::open_in(root.clone(), false).unwrap();
+        let ids = refs.ids();
+        let config = StoreConfig {
+            orphan_grace_runs: Some(5),
+            ..StoreConfig::default()
+        };
+        let expired = Tombstones::default().track(&ids.iter().collect(), &config, true);
+        assert!(expired.is_empty());
+        refs.commit().unwrap();
+
+        assert_eq!(std::fs::read(root.join(MANIFEST_FILE)).unwrap(), before);
+    }
+}
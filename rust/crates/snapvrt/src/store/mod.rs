@@ -1,11 +1,60 @@
-use std::path::Path;
+mod lock;
+mod manifest;
+mod orphans;
+mod run;
+mod signing;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
 
 use anyhow::{Context, Result};
 
+use crate::capture::Environment;
+use crate::compare::color;
+use crate::compare::diff::Mask;
+use crate::config::{StoreConfig, StoreFormat};
+
+pub use self::lock::RunLock;
+pub use self::manifest::{References, hash_bytes};
+pub use self::orphans::Tombstones;
+pub use self::run::RunManifest;
+
 pub const BASE_DIR: &str = ".snapvrt";
 pub const REFERENCE_DIR: &str = "reference";
 pub const CURRENT_DIR: &str = "current";
 pub const DIFFERENCE_DIR: &str = "difference";
+pub const ERRORS_DIR: &str = "errors";
+pub const HAR_DIR: &str = "har";
+pub const CACHE_DIR: &str = "cache";
+pub const THUMBS_DIR: &str = "thumbs";
+
+/// Per-run output directories, cleared before each run.
+const OUTPUT_DIRS: [&str; 5] = [CURRENT_DIR, DIFFERENCE_DIR, ERRORS_DIR, HAR_DIR, THUMBS_DIR];
+
+/// File extensions the store reads. The extension of a written file is
+/// derived from the image bytes, so PNG and WebP snapshots can coexist
+/// while a project migrates between `store.format` values.
+pub const IMAGE_EXTENSIONS: [&str; 2] = ["png", "webp"];
+
+/// Suffix of the sidecar holding a current image's ignore masks.
+const MASKS_SUFFIX: &str = "masks.json";
+
+/// Text snapshot of the accessibility tree (`capture.a11y`).
+pub const A11Y_TEXT: &str = "a11y";
+
+/// Text snapshot of the normalized DOM (`capture.dom`).
+pub const DOM_TEXT: &str = "dom";
+
+/// Kinds of text snapshots taken alongside screenshots. Current ones are
+/// `current/<id>.<kind>.txt`, their diffs `difference/<id>.<kind>.diff`.
+pub const TEXT_KINDS: [&str; 2] = [A11Y_TEXT, DOM_TEXT];
+
+/// Environment of the run that produced `current/` (read by `approve`).
+const CURRENT_ENVIRONMENT_FILE: &str = "environment.json";
+
+/// Bounding box of report thumbnails. Narrower images are linked as-is.
+const THUMB_WIDTH: u32 = 480;
+const THUMB_HEIGHT: u32 = 2400;
 
 fn ensure_parent(path: &Path) -> Result<()> {
     if let Some(parent) = path.parent() {
@@ -15,47 +64,324 @@ fn ensure_parent(path: &Path) -> Result<()> {
     Ok(())
 }
 
-fn file_path(subdir: &str, id: &str) -> std::path::PathBuf {
-    Path::new(BASE_DIR).join(subdir).join(format!("{id}.png"))
+fn file_path(subdir: &str, id: &str, ext: &str) -> std::path::PathBuf {
+    Path::new(BASE_DIR).join(subdir).join(format!("{id}.{ext}"))
 }
 
-pub fn write_reference(id: &str, png: &[u8]) -> Result<()> {
-    let path = file_path(REFERENCE_DIR, id);
+/// Path of the stored file for `id`, whichever supported extension it has.
+fn existing_path(subdir: &str, id: &str) -> Option<std::path::PathBuf> {
+    IMAGE_EXTENSIONS
+        .iter()
+        .map(|ext| file_path(subdir, id, ext))
+        .find(|p| p.exists())
+}
+
+/// File extension matching the encoded image bytes (`webp`, `jpg` or `png`).
+pub fn extension_of(image: &[u8]) -> &'static str {
+    if image.len() >= 12 && &image[0..4] == b"RIFF" && &image[8..12] == b"WEBP" {
+        "webp"
+    } else if image.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        "jpg"
+    } else {
+        "png"
+    }
+}
+
+/// Write `image` under `subdir`, removing any copy stored with another extension.
+fn write_image(subdir: &str, id: &str, image: &[u8]) -> Result<()> {
+    let ext = extension_of(image);
+    let path = file_path(subdir, id, ext);
     ensure_parent(&path)?;
-    std::fs::write(&path, png).with_context(|| format!("Failed to write {}", path.display()))?;
-    // Clean stale current/difference for this id
-    let _ = std::fs::remove_file(file_path(CURRENT_DIR, id));
-    let _ = std::fs::remove_file(file_path(DIFFERENCE_DIR, id));
+    std::fs::write(&path, image).with_context(|| format!("Failed to write {}", path.display()))?;
+    for other in IMAGE_EXTENSIONS.iter().filter(|e| **e != ext) {
+        let _ = std::fs::remove_file(file_path(subdir, id, other));
+    }
     Ok(())
 }
 
-pub fn write_current(id: &str, png: &[u8]) -> Result<()> {
-    let path = file_path(CURRENT_DIR, id);
+fn remove_image(subdir: &str, id: &str) {
+    for ext in IMAGE_EXTENSIONS {
+        let _ = std::fs::remove_file(file_path(subdir, id, ext));
+    }
+}
+
+fn read_image(subdir: &str, id: &str) -> Option<Vec<u8>> {
+    let path = existing_path(subdir, id)?;
+    std::fs::read(&path).ok()
+}
+
+/// Losslessly recompress a PNG with oxipng.
+///
+/// `zopfli` trades a lot of CPU time for a few extra percent of savings.
+/// Pixels are unchanged, but bytes differ from a fresh capture, so compare
+/// falls through to the decode path instead of the memcmp fast path.
+pub fn optimize_png(png: &[u8], zopfli: bool) -> Result<Vec<u8>> {
+    let mut opts = oxipng::Options::from_preset(2);
+    if zopfli {
+        opts.deflater = oxipng::Deflater::Zopfli(oxipng::ZopfliOptions::default());
+    }
+    oxipng::optimize_from_memory(png, &opts).context("Failed to optimize PNG")
+}
+
+/// Decode `image` and re-encode it losslessly in `format`.
+fn reencode(image: &[u8], format: image::ImageFormat) -> Result<Vec<u8>> {
+    let decoded = image::load_from_memory(image).context("Failed to decode snapshot")?;
+    encode(decoded, format)
+}
+
+fn encode(decoded: image::DynamicImage, format: image::ImageFormat) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    decoded
+        .write_to(&mut std::io::Cursor::new(&mut out), format)
+        .with_context(|| format!("Failed to encode snapshot as {format:?}"))?;
+    Ok(out)
+}
+
+/// Decoded sRGB pixels of an image with an embedded ICC profile; `None` for
+/// untagged images, which are sRGB already.
+fn tagged_to_srgb(image: &[u8]) -> Result<Option<image::DynamicImage>> {
+    use image::ImageDecoder;
+
+    let mut decoder = image::ImageReader::new(std::io::Cursor::new(image))
+        .with_guessed_format()?
+        .into_decoder()
+        .context("Failed to decode snapshot")?;
+    let Some(icc) = decoder.icc_profile().ok().flatten() else {
+        return Ok(None);
+    };
+    let mut rgba = image::DynamicImage::from_decoder(decoder)
+        .context("Failed to decode snapshot")?
+        .into_rgba8();
+    color::to_srgb(&mut rgba, &icc);
+    Ok(Some(rgba.into()))
+}
+
+/// Convert a captured (or previously stored) image to the configured store format.
+///
+/// WebP output is lossless, so encodes are byte-stable and compare still
+/// sees the exact captured pixels. JPEG captures are always converted, so
+/// only PNG and WebP are ever stored. Images with an ICC profile are
+/// converted to sRGB and stored untagged.
+pub fn prepare_current(image: Vec<u8>, config: &StoreConfig) -> Result<Vec<u8>> {
+    if let Some(srgb) = tagged_to_srgb(&image)? {
+        let format = match config.format {
+            StoreFormat::Png => image::ImageFormat::Png,
+            StoreFormat::Webp => image::ImageFormat::WebP,
+        };
+        return encode(srgb, format);
+    }
+    match (config.format, extension_of(&image)) {
+        (StoreFormat::Png, "webp" | "jpg") => reencode(&image, image::ImageFormat::Png),
+        (StoreFormat::Webp, "png" | "jpg") => reencode(&image, image::ImageFormat::WebP),
+        _ => Ok(image),
+    }
+}
+
+/// Like `prepare_current`, plus oxipng recompression when `store.optimize` is set.
+pub fn prepare_reference(image: Vec<u8>, config: &StoreConfig) -> Result<Vec<u8>> {
+    let image = prepare_current(image, config)?;
+    if config.optimize && extension_of(&image) == "png" {
+        optimize_png(&image, false)
+    } else {
+        Ok(image)
+    }
+}
+
+pub fn write_current(id: &str, image: &[u8]) -> Result<()> {
+    write_image(CURRENT_DIR, id, image)
+}
+
+/// Record the ignore masks of a current image (removes a stale sidecar when empty).
+pub fn write_current_masks(id: &str, masks: &[Mask]) -> Result<()> {
+    let path = file_path(CURRENT_DIR, id, MASKS_SUFFIX);
+    if masks.is_empty() {
+        let _ = std::fs::remove_file(&path);
+        return Ok(());
+    }
     ensure_parent(&path)?;
-    std::fs::write(&path, png).with_context(|| format!("Failed to write {}", path.display()))?;
+    let content = serde_json::to_string(masks).context("Failed to serialize masks")?;
+    std::fs::write(&path, content).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Record the text snapshots of a current image, replacing earlier ones.
+pub fn write_current_texts(id: &str, texts: &BTreeMap<String, String>) -> Result<()> {
+    for kind in TEXT_KINDS {
+        let path = file_path(CURRENT_DIR, id, &format!("{kind}.txt"));
+        match texts.get(kind) {
+            Some(text) => {
+                ensure_parent(&path)?;
+                std::fs::write(&path, text)
+                    .with_context(|| format!("Failed to write {}", path.display()))?;
+            }
+            None => {
+                let _ = std::fs::remove_file(&path);
+            }
+        }
+    }
     Ok(())
 }
 
-pub fn write_difference(id: &str, png: &[u8]) -> Result<()> {
-    let path = file_path(DIFFERENCE_DIR, id);
+/// Text snapshots recorded for a current image, by kind.
+pub fn read_current_texts(id: &str) -> BTreeMap<String, String> {
+    TEXT_KINDS
+        .iter()
+        .filter_map(|kind| {
+            let text =
+                std::fs::read_to_string(file_path(CURRENT_DIR, id, &format!("{kind}.txt"))).ok()?;
+            Some((kind.to_string(), text))
+        })
+        .collect()
+}
+
+/// Write the diff of a text snapshot against its reference.
+pub fn write_text_difference(id: &str, kind: &str, diff: &str) -> Result<()> {
+    let path = file_path(DIFFERENCE_DIR, id, &format!("{kind}.diff"));
     ensure_parent(&path)?;
-    std::fs::write(&path, png).with_context(|| format!("Failed to write {}", path.display()))?;
-    Ok(())
+    std::fs::write(&path, diff).with_context(|| format!("Failed to write {}", path.display()))
 }
 
-pub fn read_reference(id: &str) -> Option<Vec<u8>> {
-    let path = file_path(REFERENCE_DIR, id);
-    std::fs::read(&path).ok()
+/// Text snapshot diffs written for `id`, by kind.
+pub fn read_text_differences(id: &str) -> BTreeMap<String, String> {
+    TEXT_KINDS
+        .iter()
+        .filter_map(|kind| {
+            let diff =
+                std::fs::read_to_string(file_path(DIFFERENCE_DIR, id, &format!("{kind}.diff")))
+                    .ok()?;
+            Some((kind.to_string(), diff))
+        })
+        .collect()
+}
+
+/// Ignore masks recorded for a current image (empty if none).
+pub fn read_current_masks(id: &str) -> Vec<Mask> {
+    std::fs::read_to_string(file_path(CURRENT_DIR, id, MASKS_SUFFIX))
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+/// Record the environment of the run that wrote `current/`.
+pub fn write_current_environment(env: &Environment) -> Result<()> {
+    let path = Path::new(BASE_DIR)
+        .join(CURRENT_DIR)
+        .join(CURRENT_ENVIRONMENT_FILE);
+    ensure_parent(&path)?;
+    let content = serde_json::to_string_pretty(env).context("Failed to serialize environment")?;
+    std::fs::write(&path, content).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+pub fn read_current_environment() -> Option<Environment> {
+    let path = Path::new(BASE_DIR)
+        .join(CURRENT_DIR)
+        .join(CURRENT_ENVIRONMENT_FILE);
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+}
+
+/// Read `.snapvrt/cache/<name>`.
+pub fn read_cache(name: &str) -> Option<String> {
+    std::fs::read_to_string(Path::new(BASE_DIR).join(CACHE_DIR).join(name)).ok()
+}
+
+pub fn write_cache(name: &str, content: &str) -> Result<()> {
+    let path = Path::new(BASE_DIR).join(CACHE_DIR).join(name);
+    ensure_parent(&path)?;
+    std::fs::write(&path, content).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+pub fn write_difference(id: &str, png: &[u8]) -> Result<()> {
+    write_image(DIFFERENCE_DIR, id, png)
 }
 
 pub fn clean_output(id: &str) {
-    let _ = std::fs::remove_file(file_path(CURRENT_DIR, id));
-    let _ = std::fs::remove_file(file_path(DIFFERENCE_DIR, id));
+    remove_image(CURRENT_DIR, id);
+    let _ = std::fs::remove_file(file_path(CURRENT_DIR, id, MASKS_SUFFIX));
+    remove_image(DIFFERENCE_DIR, id);
+    let _ = std::fs::remove_dir_all(Path::new(BASE_DIR).join(ERRORS_DIR).join(id));
+    let _ = std::fs::remove_file(file_path(HAR_DIR, id, "har"));
+    for kind in TEXT_KINDS {
+        let _ = std::fs::remove_file(file_path(CURRENT_DIR, id, &format!("{kind}.txt")));
+        let _ = std::fs::remove_file(file_path(DIFFERENCE_DIR, id, &format!("{kind}.diff")));
+    }
+}
+
+/// Save debugging artifacts for a failed capture into `errors/<id>/`
+/// (replacing earlier ones). Returns the directory for display.
+pub fn write_error_artifacts(
+    id: &str,
+    message: &str,
+    screenshot: Option<&[u8]>,
+    html: Option<&str>,
+    console: &[String],
+    har: Option<&str>,
+) -> Result<String> {
+    let dir = Path::new(BASE_DIR).join(ERRORS_DIR).join(id);
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+
+    let mut files: Vec<(&str, &[u8])> = vec![("error.txt", message.as_bytes())];
+    if let Some(png) = screenshot {
+        files.push(("screenshot.png", png));
+    }
+    if let Some(html) = html {
+        files.push(("page.html", html.as_bytes()));
+    }
+    let console_log = console.join("\n");
+    if !console.is_empty() {
+        files.push(("console.log", console_log.as_bytes()));
+    }
+    if let Some(har) = har {
+        files.push(("network.har", har.as_bytes()));
+    }
+    for (name, bytes) in files {
+        let path = dir.join(name);
+        std::fs::write(&path, bytes)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+    }
+    Ok(format!("{}/", dir.display()))
+}
+
+/// Write the network log (HAR) of a successful capture to `har/<id>.har`.
+pub fn write_har(id: &str, har: &str) -> Result<()> {
+    let path = file_path(HAR_DIR, id, "har");
+    ensure_parent(&path)?;
+    std::fs::write(&path, har).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Downscaled copy of `<subdir>/<file>` for the HTML report, written to
+/// `thumbs/<subdir>/<file>` as PNG and reused while newer than the source.
+/// Returns the thumbnail path relative to `.snapvrt/`, or `None` when the
+/// source is already small enough (or unreadable) and should be shown as-is.
+pub fn thumbnail(subdir: &str, file: &str) -> Option<String> {
+    let source = Path::new(BASE_DIR).join(subdir).join(file);
+    let rel = Path::new(THUMBS_DIR)
+        .join(subdir)
+        .join(file)
+        .with_extension("png");
+    let path = Path::new(BASE_DIR).join(&rel);
+    let modified = |p: &Path| std::fs::metadata(p).and_then(|m| m.modified()).ok();
+    let fresh = matches!(
+        (modified(&path), modified(&source)),
+        (Some(thumb), Some(src)) if thumb >= src
+    );
+    if !fresh {
+        let (width, height) = image::image_dimensions(&source).ok()?;
+        if width <= THUMB_WIDTH && height <= THUMB_HEIGHT {
+            return None;
+        }
+        let img = image::open(&source).ok()?;
+        ensure_parent(&path).ok()?;
+        img.thumbnail(THUMB_WIDTH, THUMB_HEIGHT).save(&path).ok()?;
+    }
+    Some(rel.to_string_lossy().into_owned())
 }
 
-/// Remove all files from `current/` and `difference/` directories.
+/// Remove all files from the output directories (`current/`, `difference/`,
+/// `errors/`, `har/`, `thumbs/`).
 pub fn clear_output_dirs() {
-    for subdir in [CURRENT_DIR, DIFFERENCE_DIR] {
+    for subdir in OUTPUT_DIRS {
         let dir = Path::new(BASE_DIR).join(subdir);
         if dir.exists() {
             let _ = std::fs::remove_dir_all(&dir);
@@ -64,73 +390,213 @@ pub fn clear_output_dirs() {
     }
 }
 
+/// Every file under the output directories.
+pub fn output_files() -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for subdir in OUTPUT_DIRS {
+        collect_files(&Path::new(BASE_DIR).join(subdir), &mut files);
+    }
+    files
+}
+
+fn collect_files(dir: &Path, out: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_files(&path, out);
+        } else {
+            out.push(path);
+        }
+    }
+}
+
+/// Remove directories left empty below the output directories.
+pub fn remove_empty_output_dirs() {
+    for subdir in OUTPUT_DIRS {
+        let dir = Path::new(BASE_DIR).join(subdir);
+        remove_empty_dirs(&dir, &dir);
+    }
+}
+
+/// Remove empty directories below `dir` (never `base` itself).
+fn remove_empty_dirs(base: &Path, dir: &Path) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            remove_empty_dirs(base, &path);
+        }
+    }
+    if dir != base && std::fs::read_dir(dir).is_ok_and(|mut e| e.next().is_none()) {
+        let _ = std::fs::remove_dir(dir);
+    }
+}
+
+/// Output files (`current/`, `difference/`, `errors/`, `har/`) of snapshot
+/// IDs not in `keep`, grouped by ID.
+pub fn orphan_output_files(keep: &BTreeSet<String>) -> BTreeMap<String, Vec<PathBuf>> {
+    let mut orphans: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+    for subdir in [CURRENT_DIR, DIFFERENCE_DIR, ERRORS_DIR, HAR_DIR] {
+        let dir = Path::new(BASE_DIR).join(subdir);
+        let mut files = Vec::new();
+        collect_files(&dir, &mut files);
+        for path in files {
+            let Some(id) = path
+                .strip_prefix(&dir)
+                .ok()
+                .and_then(|rel| output_id(subdir, rel))
+            else {
+                continue;
+            };
+            if !keep.contains(&id) {
+                orphans.entry(id).or_default().push(path);
+            }
+        }
+    }
+    orphans
+}
+
+/// Snapshot ID an output file belongs to, from its path relative to its
+/// output directory. `None` for run-wide files (`current/run.json`, ...).
+fn output_id(subdir: &str, rel: &Path) -> Option<String> {
+    if subdir == ERRORS_DIR {
+        // errors/<id>/<artifact>
+        let dir = rel.parent()?;
+        return (!dir.as_os_str().is_empty()).then(|| dir.to_string_lossy().into_owned());
+    }
+    let rel = rel.to_string_lossy();
+    let texts = TEXT_KINDS
+        .iter()
+        .flat_map(|kind| [format!("{kind}.txt"), format!("{kind}.diff")]);
+    let suffixes = IMAGE_EXTENSIONS
+        .iter()
+        .copied()
+        .chain([MASKS_SUFFIX, "har"])
+        .map(String::from)
+        .chain(texts)
+        .map(|ext| format!(".{ext}"));
+    suffixes
+        .filter_map(|suffix| rel.strip_suffix(&suffix).map(String::from))
+        .next()
+}
+
 /// Remove `current/` and `difference/` files for the given snapshot IDs only.
 pub fn clean_output_files(ids: &[String]) {
     for id in ids {
-        let _ = std::fs::remove_file(file_path(CURRENT_DIR, id));
-        let _ = std::fs::remove_file(file_path(DIFFERENCE_DIR, id));
+        clean_output(id);
     }
 }
 
-/// Recursively walk a directory, collecting all `.png` files as IDs
-/// (relative path without the `.png` extension).
-fn collect_png_ids(base: &Path, dir: &Path, ids: &mut std::collections::BTreeSet<String>) {
+/// Recursively walk a directory, collecting all image files as IDs
+/// (relative path without the extension).
+fn collect_image_ids(base: &Path, dir: &Path, ids: &mut BTreeSet<String>) {
     let Ok(entries) = std::fs::read_dir(dir) else {
         return;
     };
     for entry in entries.flatten() {
         let path = entry.path();
         if path.is_dir() {
-            collect_png_ids(base, &path, ids);
-        } else if path.extension().is_some_and(|e| e == "png")
+            collect_image_ids(base, &path, ids);
+        } else if path
+            .extension()
+            .is_some_and(|e| IMAGE_EXTENSIONS.iter().any(|ext| e == *ext))
             && let Ok(rel) = path.strip_prefix(base)
         {
-            // Strip the .png extension to get the ID
+            // Strip the extension to get the ID
             let id = rel.with_extension("");
             ids.insert(id.to_string_lossy().into_owned());
         }
     }
 }
 
-pub fn list_current_ids() -> std::collections::BTreeSet<String> {
+pub fn list_current_ids() -> BTreeSet<String> {
     let dir = Path::new(BASE_DIR).join(CURRENT_DIR);
-    let mut ids = std::collections::BTreeSet::new();
-    collect_png_ids(&dir, &dir, &mut ids);
+    let mut ids = BTreeSet::new();
+    collect_image_ids(&dir, &dir, &mut ids);
     ids
 }
 
-pub fn list_reference_ids() -> std::collections::BTreeSet<String> {
-    let dir = Path::new(BASE_DIR).join(REFERENCE_DIR);
-    let mut ids = std::collections::BTreeSet::new();
-    collect_png_ids(&dir, &dir, &mut ids);
-    ids
+/// Whether `id` has a difference image or a changed text snapshot.
+pub fn has_difference(id: &str) -> bool {
+    existing_path(DIFFERENCE_DIR, id).is_some()
+        || TEXT_KINDS
+            .iter()
+            .any(|kind| file_path(DIFFERENCE_DIR, id, &format!("{kind}.diff")).exists())
 }
 
-/// Delete a reference PNG and clean up empty parent directories.
-pub fn remove_reference(id: &str) {
-    let path = file_path(REFERENCE_DIR, id);
-    let _ = std::fs::remove_file(&path);
-    // Walk up and remove empty parent dirs up to the reference root.
-    let root = Path::new(BASE_DIR).join(REFERENCE_DIR);
-    let mut dir = path.parent();
-    while let Some(d) = dir {
-        if d == root {
-            break;
-        }
-        if std::fs::read_dir(d).map_or(true, |mut e| e.next().is_none()) {
-            let _ = std::fs::remove_dir(d);
-            dir = d.parent();
-        } else {
-            break;
-        }
-    }
+/// Path of the current image for `id`, if there is one.
+pub fn current_path(id: &str) -> Option<PathBuf> {
+    existing_path(CURRENT_DIR, id)
 }
 
-pub fn has_difference(id: &str) -> bool {
-    file_path(DIFFERENCE_DIR, id).exists()
+pub fn read_current(id: &str) -> Option<Vec<u8>> {
+    read_image(CURRENT_DIR, id)
 }
 
-pub fn read_current(id: &str) -> Option<Vec<u8>> {
-    let path = file_path(CURRENT_DIR, id);
-    std::fs::read(&path).ok()
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn output_ids_strip_artifact_suffixes() {
+        let id = |subdir, rel: &str| output_id(subdir, Path::new(rel));
+        assert_eq!(
+            id(CURRENT_DIR, "a/b--default.png").as_deref(),
+            Some("a/b--default")
+        );
+        assert_eq!(
+            id(CURRENT_DIR, "a/b--default.masks.json").as_deref(),
+            Some("a/b--default")
+        );
+        assert_eq!(
+            id(HAR_DIR, "a/b--default.har").as_deref(),
+            Some("a/b--default")
+        );
+        assert_eq!(
+            id(ERRORS_DIR, "a/b--default/page.html").as_deref(),
+            Some("a/b--default")
+        );
+        assert_eq!(id(CURRENT_DIR, "run.json"), None);
+        assert_eq!(id(ERRORS_DIR, "stray.txt"), None);
+    }
+
+    #[test]
+    fn extension_is_detected_from_bytes() {
+        assert_eq!(extension_of(b"RIFF\x10\0\0\0WEBPVP8L"), "webp");
+        assert_eq!(extension_of(&[0xFF, 0xD8, 0xFF, 0xE0]), "jpg");
+        assert_eq!(extension_of(b"\x89PNG\r\n\x1a\n"), "png");
+        // Other RIFF files and short input fall back to PNG.
+        assert_eq!(extension_of(b"RIFF\x10\0\0\0WAVEfmt "), "png");
+        assert_eq!(extension_of(b"RIFF"), "png");
+    }
+
+    #[test]
+    fn webp_store_round_trips_pixels() {
+        let mut pixels = image::RgbaImage::new(16, 8);
+        for (x, y, p) in pixels.enumerate_pixels_mut() {
+            *p = image::Rgba([x as u8 * 16, y as u8 * 32, 200, 255 - x as u8]);
+        }
+        let png = encode(pixels.clone().into(), image::ImageFormat::Png).unwrap();
+        let webp_config = StoreConfig {
+            format: StoreFormat::Webp,
+            optimize: true,
+            ..StoreConfig::default()
+        };
+        let decode = |bytes: &[u8]| image::load_from_memory(bytes).unwrap().to_rgba8();
+
+        let webp = prepare_reference(png.clone(), &webp_config).unwrap();
+        assert_eq!(extension_of(&webp), "webp");
+        assert_eq!(decode(&webp), pixels);
+        // Already in the store format: kept byte for byte.
+        assert_eq!(prepare_current(webp.clone(), &webp_config).unwrap(), webp);
+
+        let back = prepare_current(webp, &StoreConfig::default()).unwrap();
+        assert_eq!(extension_of(&back), "png");
+        assert_eq!(decode(&back), pixels);
+    }
 }
@@ -0,0 +1,142 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::config::StoreConfig;
+
+use super::manifest::now_secs;
+
+/// Cache file holding the tombstones of orphaned references. Kept out of
+/// the committed (and signed) reference manifest, so counting a `test` run
+/// never rewrites references.
+const ORPHANS_CACHE: &str = "orphans.json";
+
+/// Tombstone of a reference no planned snapshot matches.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Orphaned {
+    /// Unix timestamp (seconds) it was first found orphaned.
+    since: u64,
+    /// Full `test` runs it has been orphaned in.
+    runs: u32,
+}
+
+impl Orphaned {
+    /// Whether the grace period of `config` is over: either configured limit
+    /// reached, or no limit configured at all.
+    fn grace_over(&self, config: &StoreConfig, now: u64) -> bool {
+        let days = config.orphan_grace_days;
+        let runs = config.orphan_grace_runs;
+        (days.is_none() && runs.is_none())
+            || days.is_some_and(|d| now.saturating_sub(self.since) >= u64::from(d) * 86_400)
+            || runs.is_some_and(|r| self.runs >= r)
+    }
+}
+
+/// Orphaned references by snapshot ID, for `store.orphan_grace_*`.
+#[derive(Debug, Default)]
+pub struct Tombstones {
+    entries: BTreeMap<String, Orphaned>,
+}
+
+impl Tombstones {
+    pub fn load() -> Self {
+        let entries = super::read_cache(ORPHANS_CACHE)
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+        Self { entries }
+    }
+
+    /// Tombstone `orphans` (keeping the time each was first seen orphaned,
+    /// counting this run when `count_run`) and drop the tombstones of
+    /// references that match a story again. Returns the orphans whose grace
+    /// period is over, which are the ones to report or delete.
+    pub fn track(
+        &mut self,
+        orphans: &BTreeSet<&String>,
+        config: &StoreConfig,
+        count_run: bool,
+    ) -> Vec<String> {
+        let now = now_secs();
+        self.entries.retain(|id, _| orphans.contains(id));
+        let mut expired = Vec::new();
+        for id in orphans {
+            let orphaned = self.entries.entry((*id).clone()).or_insert(Orphaned {
+                since: now,
+                runs: 0,
+            });
+            if count_run {
+                orphaned.runs += 1;
+            }
+            if orphaned.grace_over(config, now) {
+                expired.push((*id).clone());
+            }
+        }
+        expired
+    }
+
+    pub fn save(&self) {
+        let result = serde_json::to_string(&self.entries)
+            .map_err(anyhow::Error::from)
+            .and_then(|json| super::write_cache(ORPHANS_CACHE, &json));
+        if let Err(e) = result {
+            warn!(error = %format!("{e:#}"), "failed to save orphan tombstones");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(days: Option<u32>, runs: Option<u32>) -> StoreConfig {
+        StoreConfig {
+            orphan_grace_days: days,
+            orphan_grace_runs: runs,
+            ..StoreConfig::default()
+        }
+    }
+
+    #[test]
+    fn orphan_grace_ends_at_either_limit() {
+        let orphaned = Orphaned {
+            since: 1_000_000,
+            runs: 3,
+        };
+        let day = 86_400;
+        assert!(orphaned.grace_over(&config(None, None), orphaned.since));
+        assert!(!orphaned.grace_over(&config(Some(7), None), orphaned.since + 6 * day));
+        assert!(orphaned.grace_over(&config(Some(7), None), orphaned.since + 7 * day));
+        assert!(!orphaned.grace_over(&config(None, Some(5)), orphaned.since));
+        assert!(orphaned.grace_over(&config(Some(30), Some(3)), orphaned.since));
+    }
+
+    #[test]
+    fn runs_are_counted_until_the_story_comes_back() {
+        let (button, card) = ("button".to_string(), "card".to_string());
+        let config = config(None, Some(2));
+        let mut tombstones = Tombstones::default();
+
+        assert!(
+            tombstones
+                .track(&BTreeSet::from([&button]), &config, true)
+                .is_empty()
+        );
+        // `prune` reads the tombstones without counting a run.
+        assert!(
+            tombstones
+                .track(&BTreeSet::from([&button]), &config, false)
+                .is_empty()
+        );
+        let both = BTreeSet::from([&button, &card]);
+        assert_eq!(tombstones.track(&both, &config, true), ["button"]);
+
+        // Back in the plan: the tombstone is dropped and the count restarts.
+        assert_eq!(
+            tombstones.track(&BTreeSet::from([&card]), &config, true),
+            ["card"]
+        );
+        assert_eq!(tombstones.track(&both, &config, true), ["card"]);
+        assert_eq!(tombstones.entries["button"].runs, 1);
+    }
+}
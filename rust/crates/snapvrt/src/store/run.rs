@@ -0,0 +1,114 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use super::manifest::now_secs;
+use super::{BASE_DIR, CURRENT_DIR, ensure_parent, hash_bytes};
+
+/// Manifest of the run that last wrote `current/`.
+const RUN_FILE: &str = "run.json";
+
+/// Which run wrote each image in `current/`, so `approve` only promotes
+/// captures from the latest run. A filtered run keeps the entries of
+/// images it did not retake, still attributed to their older run.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RunManifest {
+    /// ID of the latest run.
+    pub run_id: String,
+    /// Unix timestamp (seconds) the latest run started.
+    pub started_at: u64,
+    images: BTreeMap<String, RunImage>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RunImage {
+    run_id: String,
+    /// SHA-256 of the image as written to `current/`.
+    hash: String,
+}
+
+fn path() -> PathBuf {
+    Path::new(BASE_DIR).join(CURRENT_DIR).join(RUN_FILE)
+}
+
+impl RunManifest {
+    pub fn read() -> Option<Self> {
+        std::fs::read_to_string(path())
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+    }
+
+    /// Start a new run. `retaken` IDs are about to be recaptured; entries of
+    /// the others are carried over from `previous` (a filtered run).
+    pub fn start(previous: Option<Self>, retaken: &[String]) -> Self {
+        let mut images = previous.map(|p| p.images).unwrap_or_default();
+        for id in retaken {
+            images.remove(id);
+        }
+        let started_at = now_secs();
+        let nonce = format!(
+            "{started_at}:{}:{:?}",
+            std::process::id(),
+            std::time::Instant::now()
+        );
+        Self {
+            run_id: hash_bytes(nonce.as_bytes())[..12].to_string(),
+            started_at,
+            images,
+        }
+    }
+
+    /// Record an image this run wrote to `current/`.
+    pub fn record(&mut self, id: &str, hash: String) {
+        let run_id = self.run_id.clone();
+        self.images
+            .insert(id.to_string(), RunImage { run_id, hash });
+    }
+
+    /// Why `image` (the bytes in `current/` for `id`) is not a capture of the
+    /// latest run, if it isn't.
+    pub fn stale_reason(&self, id: &str, image: &[u8]) -> Option<&'static str> {
+        match self.images.get(id) {
+            None => Some("not recorded by any run"),
+            Some(entry) if entry.run_id != self.run_id => Some("from an older run"),
+            Some(entry) if entry.hash != hash_bytes(image) => Some("modified since the run"),
+            Some(_) => None,
+        }
+    }
+
+    pub fn write(&self) -> Result<()> {
+        let path = path();
+        ensure_parent(&path)?;
+        let content =
+            serde_json::to_string_pretty(self).context("Failed to serialize run manifest")?;
+        std::fs::write(&path, content)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filtered_run_keeps_older_entries_as_stale() {
+        let mut first = RunManifest::start(None, &[]);
+        first.record("a", hash_bytes(b"a1"));
+        first.record("b", hash_bytes(b"b1"));
+        let mut second = RunManifest::start(Some(first), &["a".to_string()]);
+        second.record("a", hash_bytes(b"a2"));
+
+        assert_eq!(second.stale_reason("a", b"a2"), None);
+        assert_eq!(
+            second.stale_reason("a", b"edited"),
+            Some("modified since the run")
+        );
+        assert_eq!(second.stale_reason("b", b"b1"), Some("from an older run"));
+        assert_eq!(
+            second.stale_reason("c", b"c"),
+            Some("not recorded by any run")
+        );
+    }
+}
@@ -0,0 +1,107 @@
+use std::collections::BTreeMap;
+
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+
+use super::manifest::ReferenceEntry;
+
+/// Secret that signs the reference manifest. When set, `update`/`approve`
+/// sign what they commit and `test` refuses unsigned or altered manifests.
+pub const SIGNING_KEY_ENV: &str = "SNAPVRT_SIGNING_KEY";
+
+pub fn signing_key() -> Option<String> {
+    std::env::var(SIGNING_KEY_ENV)
+        .ok()
+        .filter(|key| !key.is_empty())
+}
+
+/// What the signature covers: every field of every entry (so masks, text
+/// snapshots and formats can't be edited either) and the ID template.
+#[derive(Serialize)]
+struct Signed<'a> {
+    id_template: Option<&'a str>,
+    snapshots: &'a BTreeMap<String, ReferenceEntry>,
+}
+
+fn mac(
+    key: &str,
+    id_template: Option<&str>,
+    snapshots: &BTreeMap<String, ReferenceEntry>,
+) -> Hmac<Sha256> {
+    let payload = serde_json::to_vec(&Signed {
+        id_template,
+        snapshots,
+    })
+    .expect("manifest entries serialize to JSON");
+    let mut mac = Hmac::<Sha256>::new_from_slice(key.as_bytes()).expect("HMAC accepts any key");
+    mac.update(&payload);
+    mac
+}
+
+/// HMAC-SHA256 (hex) over the serialized entries and ID template.
+pub fn sign(
+    key: &str,
+    id_template: Option<&str>,
+    snapshots: &BTreeMap<String, ReferenceEntry>,
+) -> String {
+    mac(key, id_template, snapshots)
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// Whether `signature` is [`sign`]'s, compared in constant time.
+pub fn verify(
+    key: &str,
+    id_template: Option<&str>,
+    snapshots: &BTreeMap<String, ReferenceEntry>,
+    signature: &str,
+) -> bool {
+    unhex(signature).is_some_and(|bytes| {
+        mac(key, id_template, snapshots)
+            .verify_slice(&bytes)
+            .is_ok()
+    })
+}
+
+fn unhex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compare::diff::Mask;
+
+    #[test]
+    fn signature_covers_every_entry_field_and_the_template() {
+        let entry: ReferenceEntry = serde_json::from_value(serde_json::json!({
+            "hash": "ab", "format": "png", "width": 10, "height": 10,
+            "size": 100, "updated_at": 0,
+        }))
+        .unwrap();
+        let mut snapshots = BTreeMap::from([("button".to_string(), entry)]);
+        let signature = sign("key", None, &snapshots);
+        assert!(verify("key", None, &snapshots, &signature));
+        assert!(!verify("other", None, &snapshots, &signature));
+        assert!(!verify("key", Some("{story}"), &snapshots, &signature));
+        assert!(!verify("key", None, &snapshots, "not hex"));
+
+        snapshots.get_mut("button").unwrap().masks.push(Mask {
+            x: 0,
+            y: 0,
+            width: 10,
+            height: 10,
+        });
+        assert!(!verify("key", None, &snapshots, &signature));
+    }
+}
@@ -1,10 +1,16 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
 
 use anyhow::{Context, Result};
-use serde::Deserialize;
-use tracing::debug;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
 
-use super::Story;
+use super::ladle::MetaResponse;
+use super::nextjs::{self, NextjsOptions};
+use super::sitemap::{self, PageOptions};
+use super::{SourceKind, Story};
+use crate::store::{self, hash_bytes};
 
 #[derive(Deserialize)]
 struct IndexResponse {
@@ -31,13 +37,25 @@ impl From<StoryEntry> for Story {
             name: entry.name,
             title: entry.title,
             tags: entry.tags,
+            url: None,
         }
     }
 }
 
-/// A Storybook instance at a known URL.
+/// Longest pause between discovery retries.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// A story source (Storybook, Ladle, a sitemap or Next.js routes) at a
+/// known URL.
 pub struct Storybook {
+    kind: SourceKind,
     base_url: String,
+    local: bool,
+    client: reqwest::Client,
+    pages: PageOptions,
+    nextjs: NextjsOptions,
+    wait: Duration,
+    offline: bool,
 }
 
 impl Storybook {
@@ -46,53 +64,267 @@ impl Storybook {
     /// When `local` is false (Docker mode), rewrites `localhost` / `127.0.0.1`
     /// to the host's LAN IP so Chrome in a container can reach Storybook.
     /// Fails fast if the host IP cannot be detected.
-    pub fn new(base_url: &str, local: bool) -> Result<Self> {
+    ///
+    /// `headers` are sent with every request (e.g. `Authorization`);
+    /// `insecure` accepts invalid TLS certificates.
+    pub fn new(
+        kind: SourceKind,
+        base_url: &str,
+        local: bool,
+        headers: &BTreeMap<String, String>,
+        insecure: bool,
+    ) -> Result<Self> {
         let url = if local {
             base_url.to_string()
         } else {
             rewrite_localhost(base_url)?
         };
+        if url != base_url && url.starts_with("https://") && !insecure {
+            warn!(
+                "{base_url} is reached as {url} from remote Chrome; its certificate \
+                 likely doesn't cover that address. Set capture.ignore_https_errors \
+                 if captures fail with certificate errors."
+            );
+        }
+        let client = reqwest::Client::builder()
+            .default_headers(header_map(headers)?)
+            .danger_accept_invalid_certs(insecure)
+            .build()
+            .context("Failed to build HTTP client")?;
         Ok(Self {
+            kind,
             base_url: url.trim_end_matches('/').to_string(),
+            local,
+            client,
+            pages: PageOptions::default(),
+            nextjs: NextjsOptions::default(),
+            wait: Duration::ZERO,
+            offline: false,
         })
     }
 
+    /// Use the cached discovery result instead of contacting the server.
+    pub fn with_offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    /// Retry discovery for up to `wait` while the server starts.
+    pub fn with_wait(mut self, wait: Duration) -> Self {
+        self.wait = wait;
+        self
+    }
+
+    /// Page selection for sitemap and Next.js sources.
+    pub fn with_page_options(mut self, options: PageOptions) -> Self {
+        self.pages = options;
+        self
+    }
+
+    /// Route discovery for Next.js sources.
+    pub fn with_nextjs_options(mut self, options: NextjsOptions) -> Self {
+        self.nextjs = options;
+        self
+    }
+
     pub fn url(&self) -> &str {
         &self.base_url
     }
 
-    /// Build the iframe URL for a given story.
+    /// Build the iframe URL for a given story (the page URL for page sources).
     pub fn story_url(&self, story: &Story) -> String {
-        format!("{}/iframe.html?id={}", self.base_url, story.id)
+        if let Some(url) = &story.url {
+            return url.clone();
+        }
+        match self.kind {
+            SourceKind::Storybook | SourceKind::Sitemap | SourceKind::Nextjs => {
+                format!("{}/iframe.html?id={}", self.base_url, story.id)
+            }
+            SourceKind::Ladle => format!("{}/?story={}&mode=preview", self.base_url, story.id),
+        }
     }
 
-    /// Fetch index.json and return all stories.
+    /// Fetch the story index (`index.json`, Ladle's `meta.json`, the
+    /// sitemap, or Next.js routes on disk) and return all stories.
     ///
     /// Filters out non-story entries (e.g. docs).
-    /// Returns stories sorted by id for stable output.
+    /// Returns stories sorted by id for stable output. Failures are retried
+    /// with backoff until the `with_wait` deadline. Results are cached in
+    /// `.snapvrt/cache/`; offline, the cached result is used as-is.
     pub async fn discover(&self) -> Result<Vec<Story>> {
-        let index_url = format!("{}/index.json", self.base_url);
+        if self.offline {
+            return self.read_cache().map(|c| c.stories).with_context(|| {
+                format!(
+                    "No cached discovery for {}. Run once without --offline",
+                    self.base_url
+                )
+            });
+        }
+        let deadline = Instant::now() + self.wait;
+        let mut delay = Duration::from_millis(250);
+        let mut waiting = false;
+        loop {
+            match self.discover_once().await {
+                Ok(stories) => return Ok(stories),
+                Err(e) if Instant::now() + delay < deadline => {
+                    if !waiting {
+                        println!("Waiting for {} ...", self.base_url);
+                        waiting = true;
+                    }
+                    debug!(
+                        error = format!("{e:#}"),
+                        ?delay,
+                        "discovery failed, retrying"
+                    );
+                    tokio::time::sleep(delay).await;
+                    delay = (delay * 2).min(MAX_RETRY_DELAY);
+                }
+                Err(e) if waiting => {
+                    return Err(e).context(format!(
+                        "Gave up waiting for {} after {}s",
+                        self.base_url,
+                        self.wait.as_secs()
+                    ));
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    async fn discover_once(&self) -> Result<Vec<Story>> {
+        let cached = self.read_cache();
+        let etag = cached.as_ref().and_then(|c| c.etag.as_deref());
+        let (mut stories, etag): (Vec<Story>, _) = match self.kind {
+            SourceKind::Storybook => {
+                let Some((index, etag)) =
+                    self.fetch_json::<IndexResponse>("index.json", etag).await?
+                else {
+                    debug!("index.json not modified, using cached discovery");
+                    return Ok(cached.map(|c| c.stories).unwrap_or_default());
+                };
+                let stories = index
+                    .entries
+                    .into_values()
+                    .filter(|entry| entry.entry_type == "story")
+                    .map(Story::from)
+                    .collect();
+                (stories, etag)
+            }
+            SourceKind::Ladle => {
+                let Some((meta, etag)) = self.fetch_json::<MetaResponse>("meta.json", etag).await?
+                else {
+                    debug!("meta.json not modified, using cached discovery");
+                    return Ok(cached.map(|c| c.stories).unwrap_or_default());
+                };
+                (meta.into_stories(), etag)
+            }
+            SourceKind::Sitemap => {
+                let mut pages =
+                    sitemap::discover(&self.client, &self.base_url, &self.pages).await?;
+                // Sitemaps list absolute URLs; Chrome in Docker needs them rewritten too.
+                if !self.local {
+                    for page in &mut pages {
+                        if let Some(url) = &page.url {
+                            page.url = Some(rewrite_localhost(url)?);
+                        }
+                    }
+                }
+                (pages, None)
+            }
+            SourceKind::Nextjs => {
+                let urls: Vec<String> = nextjs::discover_routes(&self.nextjs)?
+                    .iter()
+                    .map(|route| format!("{}{route}", self.base_url))
+                    .collect();
+                (sitemap::select_pages(&urls, &self.pages), None)
+            }
+        };
 
-        let response = reqwest::get(&index_url)
+        stories.sort_by(|a, b| a.id.cmp(&b.id));
+
+        let cache = DiscoveryCache {
+            url: self.base_url.clone(),
+            etag,
+            stories,
+        };
+        if let Err(e) = self.write_cache(&cache) {
+            warn!("Failed to cache discovery: {e:#}");
+        }
+        Ok(cache.stories)
+    }
+
+    /// Fetch and parse `<base_url>/<file>`, revalidating against `etag`.
+    /// Returns `None` when the server answers 304 Not Modified.
+    async fn fetch_json<T: DeserializeOwned>(
+        &self,
+        file: &str,
+        etag: Option<&str>,
+    ) -> Result<Option<(T, Option<String>)>> {
+        let index_url = format!("{}/{file}", self.base_url);
+
+        let mut request = self.client.get(&index_url);
+        if let Some(etag) = etag {
+            request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+        }
+        let response = request
+            .send()
             .await
+            .with_context(|| format!("Failed to fetch {index_url}"))?
+            .error_for_status()
             .with_context(|| format!("Failed to fetch {index_url}"))?;
+        if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+            return Ok(None);
+        }
+        let etag = response
+            .headers()
+            .get(reqwest::header::ETAG)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
 
-        let index: IndexResponse = response
+        let parsed = response
             .json()
             .await
             .with_context(|| format!("Failed to parse {index_url}"))?;
+        Ok(Some((parsed, etag)))
+    }
 
-        let mut stories: Vec<Story> = index
-            .entries
-            .into_values()
-            .filter(|entry| entry.entry_type == "story")
-            .map(Story::from)
-            .collect();
+    /// Cache file name for this source (kind + URL).
+    fn cache_name(&self) -> String {
+        let key = format!("{:?} {}", self.kind, self.base_url);
+        format!("discovery-{}.json", &hash_bytes(key.as_bytes())[..16])
+    }
 
-        stories.sort_by(|a, b| a.id.cmp(&b.id));
+    fn read_cache(&self) -> Option<DiscoveryCache> {
+        let content = store::read_cache(&self.cache_name())?;
+        serde_json::from_str(&content).ok()
+    }
+
+    fn write_cache(&self, cache: &DiscoveryCache) -> Result<()> {
+        let content = serde_json::to_string(cache).context("Failed to serialize discovery")?;
+        store::write_cache(&self.cache_name(), &content)
+    }
+}
+
+/// Last discovery result for a source, in `.snapvrt/cache/`.
+#[derive(Serialize, Deserialize)]
+struct DiscoveryCache {
+    url: String,
+    /// `ETag` of the index, for revalidation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    etag: Option<String>,
+    stories: Vec<Story>,
+}
 
-        Ok(stories)
+fn header_map(headers: &BTreeMap<String, String>) -> Result<reqwest::header::HeaderMap> {
+    let mut map = reqwest::header::HeaderMap::new();
+    for (name, value) in headers {
+        let name = reqwest::header::HeaderName::from_bytes(name.as_bytes())
+            .with_context(|| format!("Invalid header name '{name}'"))?;
+        let value = reqwest::header::HeaderValue::from_str(value)
+            .with_context(|| format!("Invalid value for header '{name}'"))?;
+        map.insert(name, value);
     }
+    Ok(map)
 }
 
 // ---------------------------------------------------------------------------
@@ -0,0 +1,372 @@
+use std::fmt;
+use std::str::FromStr;
+
+use super::normalize_for_filter;
+use crate::config::matches_pattern;
+
+/// A `--filter` expression.
+///
+/// Bare words form a pattern, matched case-insensitively against the story
+/// ID, title and name, the viewport and the snapshot ID. A pattern without
+/// `*` matches anywhere in a field, as a substring, and runs of such words
+/// are one pattern, so `primary button` matches "Primary Button". With `*`
+/// a word is a pattern of its own that must match a whole field, so
+/// `button*` anchors at the start. `viewport:NAME` and `tag:NAME` match one
+/// viewport or story tag (`*` allowed). Terms combine with `and` (also
+/// implied between terms), `or`, `not` (or `!`) and parentheses; quote a
+/// pattern to match one of those words literally.
+///
+/// `button* and viewport:mobile and not tag:experimental`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Filter {
+    expr: Expr,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Expr {
+    Pattern(Pattern),
+    Viewport(Pattern),
+    Tag(Pattern),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+}
+
+/// A normalized pattern; see [`normalize_for_filter`].
+#[derive(Clone, Debug, PartialEq)]
+struct Pattern(String);
+
+impl Pattern {
+    fn new(text: &str) -> Self {
+        // Snapshot IDs copied from the review page end in `.png`.
+        let text = text.strip_suffix(".png").unwrap_or(text);
+        Self(normalize_for_filter(text))
+    }
+
+    fn matches(&self, field: &str) -> bool {
+        let field = normalize_for_filter(field);
+        if self.0.contains('*') {
+            matches_pattern(&self.0, &field)
+        } else {
+            field.contains(&self.0)
+        }
+    }
+
+    /// Viewport and tag names match exactly, or as a `*` pattern.
+    fn matches_whole(&self, name: &str) -> bool {
+        matches_pattern(&self.0, &normalize_for_filter(name))
+    }
+}
+
+/// What a filter is matched against.
+pub struct Candidate<'a> {
+    /// Fields matched by plain patterns.
+    pub fields: &'a [&'a str],
+    /// None where only snapshot IDs are known (`approve`, `optimize`).
+    pub viewport: Option<&'a str>,
+    pub tags: &'a [String],
+}
+
+impl Filter {
+    pub fn matches(&self, candidate: &Candidate) -> bool {
+        self.expr.matches(candidate)
+    }
+
+    /// Match a snapshot ID alone, for commands that work on stored
+    /// snapshots without discovering stories.
+    pub fn matches_id(&self, id: &str) -> bool {
+        self.matches(&Candidate {
+            fields: &[id],
+            viewport: None,
+            tags: &[],
+        })
+    }
+
+    /// Whether the filter uses `viewport:` or `tag:`, which need discovered
+    /// stories to match.
+    pub fn needs_stories(&self) -> bool {
+        self.expr.needs_stories()
+    }
+}
+
+impl Expr {
+    fn matches(&self, c: &Candidate) -> bool {
+        match self {
+            Self::Pattern(p) => c.fields.iter().any(|f| p.matches(f)),
+            Self::Viewport(p) => c.viewport.is_some_and(|v| p.matches_whole(v)),
+            Self::Tag(p) => c.tags.iter().any(|t| p.matches_whole(t)),
+            Self::Not(e) => !e.matches(c),
+            Self::And(a, b) => a.matches(c) && b.matches(c),
+            Self::Or(a, b) => a.matches(c) || b.matches(c),
+        }
+    }
+
+    fn needs_stories(&self) -> bool {
+        match self {
+            Self::Pattern(_) => false,
+            Self::Viewport(_) | Self::Tag(_) => true,
+            Self::Not(e) => e.needs_stories(),
+            Self::And(a, b) | Self::Or(a, b) => a.needs_stories() || b.needs_stories(),
+        }
+    }
+}
+
+impl FromStr for Filter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let tokens = tokenize(s)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let expr = parser.or()?;
+        if let Some(token) = parser.tokens.get(parser.pos) {
+            return Err(format!("unexpected {token} in filter"));
+        }
+        Ok(Self { expr })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    /// A bare or quoted word; quoted words are never keywords.
+    Word {
+        text: String,
+        quoted: bool,
+    },
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Word { text, .. } => write!(f, "'{text}'"),
+            Self::And => f.write_str("'and'"),
+            Self::Or => f.write_str("'or'"),
+            Self::Not => f.write_str("'not'"),
+            Self::Open => f.write_str("'('"),
+            Self::Close => f.write_str("')'"),
+        }
+    }
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = s.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' | ')' | '!' => {
+                chars.next();
+                tokens.push(match c {
+                    '(' => Token::Open,
+                    ')' => Token::Close,
+                    _ => Token::Not,
+                });
+            }
+            '"' | '\'' => {
+                chars.next();
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some(ch) if ch == c => break,
+                        Some(ch) => text.push(ch),
+                        None => return Err(format!("missing closing {c} in filter")),
+                    }
+                }
+                tokens.push(Token::Word { text, quoted: true });
+            }
+            _ => {
+                let mut text = String::new();
+                while let Some(&ch) = chars.peek() {
+                    if ch.is_whitespace() || ch == '(' || ch == ')' {
+                        break;
+                    }
+                    text.push(ch);
+                    chars.next();
+                }
+                tokens.push(match text.to_lowercase().as_str() {
+                    "and" => Token::And,
+                    "or" => Token::Or,
+                    "not" => Token::Not,
+                    _ => Token::Word {
+                        text,
+                        quoted: false,
+                    },
+                });
+            }
+        }
+    }
+    if tokens.is_empty() {
+        return Err("empty filter".to_string());
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent over `or := and ("or" and)*`,
+/// `and := unary ("and"? unary)*`, `unary := "not" unary | atom`.
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut expr = self.and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut expr = self.unary()?;
+        loop {
+            match self.peek() {
+                Some(Token::And) => self.pos += 1,
+                Some(Token::Word { .. } | Token::Not | Token::Open) => {}
+                _ => return Ok(expr),
+            }
+            expr = Expr::And(Box::new(expr), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.peek() == Some(&Token::Not) {
+            self.pos += 1;
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        self.atom()
+    }
+
+    fn atom(&mut self) -> Result<Expr, String> {
+        let token = self.peek().cloned();
+        self.pos += 1;
+        match token {
+            Some(Token::Open) => {
+                let expr = self.or()?;
+                if self.peek() != Some(&Token::Close) {
+                    return Err("missing ')' in filter".to_string());
+                }
+                self.pos += 1;
+                Ok(expr)
+            }
+            Some(Token::Word {
+                text,
+                quoted: false,
+            }) if is_predicate(&text) => {
+                let (key, value) = text.split_once(':').unwrap_or_default();
+                Ok(if key.eq_ignore_ascii_case("viewport") {
+                    Expr::Viewport(Pattern::new(value))
+                } else {
+                    Expr::Tag(Pattern::new(value))
+                })
+            }
+            Some(Token::Word { text, .. }) => Ok(Expr::Pattern(self.phrase(text))),
+            Some(token) => Err(format!("unexpected {token} in filter")),
+            None => Err("filter ends unexpectedly".to_string()),
+        }
+    }
+
+    /// `first` and the bare words right after it, joined with spaces, so
+    /// `-f "primary button"` still matches "Primary Button". A word with `*`
+    /// matches a whole field, so it is never part of a phrase.
+    fn phrase(&mut self, first: String) -> Pattern {
+        if first.contains('*') {
+            return Pattern::new(&first);
+        }
+        let mut words = vec![first];
+        while let Some(Token::Word {
+            text,
+            quoted: false,
+        }) = self.peek()
+        {
+            if is_predicate(text) || text.contains('*') {
+                break;
+            }
+            words.push(text.clone());
+            self.pos += 1;
+        }
+        Pattern::new(&words.join(" "))
+    }
+}
+
+fn is_predicate(word: &str) -> bool {
+    word.split_once(':').is_some_and(|(key, _)| {
+        key.eq_ignore_ascii_case("viewport") || key.eq_ignore_ascii_case("tag")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(filter: &str, fields: &[&str], viewport: &str, tags: &[&str]) -> bool {
+        let tags: Vec<String> = tags.iter().map(|t| t.to_string()).collect();
+        let filter: Filter = filter.parse().unwrap();
+        filter.matches(&Candidate {
+            fields,
+            viewport: Some(viewport),
+            tags: &tags,
+        })
+    }
+
+    #[test]
+    fn plain_patterns_match_substrings_like_before() {
+        let fields = ["button--primary", "Components/Button", "Primary Button"];
+        assert!(matches("primary_button", &fields, "desktop", &[]));
+        assert!(matches("Primary Button.png", &fields, "desktop", &[]));
+        assert!(!matches("secondary", &fields, "desktop", &[]));
+    }
+
+    #[test]
+    fn combines_globs_predicates_and_negation() {
+        let filter = "button* and viewport:mobile and not tag:experimental";
+        let fields = ["button--primary", "Button", "Primary"];
+        assert!(matches(filter, &fields, "mobile", &["stable"]));
+        assert!(!matches(filter, &fields, "desktop", &["stable"]));
+        assert!(!matches(filter, &fields, "mobile", &["experimental"]));
+        assert!(!matches(filter, &["forms--input"], "mobile", &[]));
+
+        assert!(matches(
+            "(input or !tag:x) viewport:mob*",
+            &fields,
+            "mobile",
+            &[]
+        ));
+        assert!(matches("'or'", &["color or size"], "desktop", &[]));
+    }
+
+    #[test]
+    fn star_words_are_terms_of_their_own() {
+        let fields = ["forms--input", "Forms/Button Group", "Input"];
+        assert!(matches("forms/button* input", &fields, "desktop", &[]));
+        assert!(matches("input forms*", &fields, "desktop", &[]));
+        assert!(!matches("button* input", &fields, "desktop", &[]));
+        assert!(matches("button group", &fields, "desktop", &[]));
+    }
+
+    #[test]
+    fn rejects_malformed_expressions() {
+        for bad in [
+            "",
+            "(button",
+            "button and",
+            "button )",
+            "not",
+            "'button",
+            "a \"b",
+        ] {
+            assert!(bad.parse::<Filter>().is_err(), "{bad:?}");
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+
+use super::Story;
+
+/// Ladle's `meta.json`.
+#[derive(Deserialize)]
+pub(super) struct MetaResponse {
+    pub stories: BTreeMap<String, MetaEntry>,
+}
+
+#[derive(Deserialize)]
+pub(super) struct MetaEntry {
+    pub name: String,
+    /// Title path segments (`["Forms", "Button"]`).
+    #[serde(default)]
+    pub levels: Vec<String>,
+    /// The story's `meta` export; `skip = true` is Ladle's own opt-out.
+    #[serde(default)]
+    pub meta: serde_json::Value,
+}
+
+impl MetaResponse {
+    pub fn into_stories(self) -> Vec<Story> {
+        self.stories
+            .into_iter()
+            .map(|(id, entry)| {
+                let mut tags = Vec::new();
+                if entry.meta["skip"] == true {
+                    tags.push("snapvrt-skip".to_string());
+                }
+                Story {
+                    id,
+                    name: entry.name,
+                    title: entry.levels.join("/"),
+                    tags,
+                    url: None,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_meta_json() {
+        let meta: MetaResponse = serde_json::from_str(
+            r#"{
+                "about": { "homepage": "https://www.ladle.dev", "version": 1 },
+                "stories": {
+                    "forms-button--primary": {
+                        "name": "Primary", "levels": ["Forms", "Button"],
+                        "locStart": 4, "locEnd": 4, "filePath": "src/button.stories.tsx",
+                        "meta": {}
+                    },
+                    "forms-button--flaky": {
+                        "name": "Flaky", "levels": ["Forms", "Button"],
+                        "meta": { "skip": true }
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+        let stories = meta.into_stories();
+        assert_eq!(stories.len(), 2);
+        assert_eq!(stories[1].id, "forms-button--primary");
+        assert_eq!(stories[1].title, "Forms/Button");
+        assert!(stories[0].is_skipped());
+        assert!(!stories[1].is_skipped());
+    }
+}
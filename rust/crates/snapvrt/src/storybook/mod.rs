@@ -1,6 +1,37 @@
 pub mod discovery;
+mod filter;
+mod ladle;
+mod nextjs;
+mod serve;
+mod sitemap;
+
+use serde::{Deserialize, Serialize};
 
 pub use self::discovery::Storybook;
+pub use self::filter::{Candidate, Filter};
+pub use self::nextjs::NextjsOptions;
+pub use self::serve::{StaticServer, static_dir};
+pub use self::sitemap::PageOptions;
+
+/// Story explorer a source is served by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceKind {
+    /// Storybook: `index.json`, `iframe.html?id=<id>`.
+    Storybook,
+    /// Ladle: `meta.json`, `?story=<id>&mode=preview`.
+    Ladle,
+    /// Plain pages listed in a `sitemap.xml`, captured whole.
+    Sitemap,
+    /// Next.js routes from the `app/` or `pages/` directory, captured whole.
+    Nextjs,
+}
+
+impl SourceKind {
+    /// Whether stories are whole pages rather than components in a story root.
+    pub fn captures_pages(self) -> bool {
+        matches!(self, Self::Sitemap | Self::Nextjs)
+    }
+}
 
 /// Normalize a string for filter comparison: lowercase + treat `_` and ` ` as equivalent.
 /// This lets users filter by either the raw story fields (spaces) or the
@@ -10,12 +41,16 @@ pub(crate) fn normalize_for_filter(s: &str) -> String {
 }
 
 /// A discovered story ready for capture.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Story {
     pub id: String,
     pub name: String,
     pub title: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub tags: Vec<String>,
+    /// Page URL, for sources without a story URL scheme (sitemaps).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
 }
 
 impl Story {
@@ -24,11 +59,13 @@ impl Story {
         self.tags.iter().any(|t| t == "snapvrt-skip")
     }
 
-    /// Check if any story field matches a case-insensitive pattern.
-    pub fn matches_filter(&self, pattern: &str) -> bool {
-        let p = normalize_for_filter(pattern);
-        normalize_for_filter(&self.id).contains(&p)
-            || normalize_for_filter(&self.title).contains(&p)
-            || normalize_for_filter(&self.name).contains(&p)
+    /// Check if the story, captured at `viewport` as `snapshot_id`, matches
+    /// a `--filter` expression.
+    pub fn matches_filter(&self, filter: &Filter, viewport: &str, snapshot_id: &str) -> bool {
+        filter.matches(&Candidate {
+            fields: &[&self.id, &self.title, &self.name, viewport, snapshot_id],
+            viewport: Some(viewport),
+            tags: &self.tags,
+        })
     }
 }
@@ -0,0 +1,184 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+/// Directories scanned when `dir` is not set, relative to the project root.
+const DEFAULT_DIRS: [&str; 4] = ["app", "src/app", "pages", "src/pages"];
+
+/// Page file extensions Next.js routes on.
+const PAGE_EXTENSIONS: [&str; 5] = ["tsx", "ts", "jsx", "js", "mdx"];
+
+/// Route discovery for `type = "nextjs"` sources.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NextjsOptions {
+    /// `app/` or `pages/` directory to scan (default: every one of `app`,
+    /// `src/app`, `pages`, `src/pages` that exists).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dir: Option<PathBuf>,
+    /// Values for dynamic segments, keyed by route:
+    /// `"/blog/[slug]" = { slug = ["hello", "world"] }`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub params: BTreeMap<String, BTreeMap<String, Vec<String>>>,
+}
+
+/// Enumerate routes (`/`, `/about`, `/blog/hello`) under the configured
+/// directories. Dynamic routes expand to every combination of their
+/// configured params and are skipped (with a warning) when none are set.
+pub(super) fn discover_routes(options: &NextjsOptions) -> Result<Vec<String>> {
+    let dirs: Vec<PathBuf> = match &options.dir {
+        Some(dir) => vec![dir.clone()],
+        None => DEFAULT_DIRS
+            .iter()
+            .map(PathBuf::from)
+            .filter(|d| d.is_dir())
+            .collect(),
+    };
+    if dirs.is_empty() {
+        anyhow::bail!(
+            "No Next.js app/ or pages/ directory found (looked for {}). Set `dir` on the source",
+            DEFAULT_DIRS.join(", ")
+        );
+    }
+
+    let mut patterns = Vec::new();
+    for dir in &dirs {
+        let app_router = dir.file_name().is_some_and(|n| n == "app");
+        debug!(dir = %dir.display(), app_router, "scanning Next.js routes");
+        scan(dir, &mut Vec::new(), app_router, &mut patterns)
+            .with_context(|| format!("Failed to scan {}", dir.display()))?;
+    }
+    patterns.sort();
+    patterns.dedup();
+
+    let mut routes = Vec::new();
+    for pattern in patterns {
+        if !pattern.contains('[') {
+            routes.push(pattern);
+            continue;
+        }
+        match options.params.get(&pattern) {
+            Some(params) => routes.extend(expand(&pattern, params)?),
+            None => warn!(route = %pattern, "dynamic Next.js route has no params, skipping"),
+        }
+    }
+    Ok(routes)
+}
+
+/// Collect route patterns under `dir`; `segments` is the URL path so far.
+fn scan(
+    dir: &Path,
+    segments: &mut Vec<String>,
+    app_router: bool,
+    out: &mut Vec<String>,
+) -> Result<()> {
+    let mut entries: Vec<_> = std::fs::read_dir(dir)?.collect::<Result<_, _>>()?;
+    entries.sort_by_key(|e| e.file_name());
+    for entry in entries {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let path = entry.path();
+        if path.is_dir() {
+            // Private folders, parallel and intercepting routes, API routes.
+            if name.starts_with('_')
+                || name.starts_with('@')
+                || name.starts_with("(.")
+                || (!app_router && segments.is_empty() && name == "api")
+            {
+                continue;
+            }
+            // Route groups don't appear in the URL.
+            let group = app_router && name.starts_with('(') && name.ends_with(')');
+            if !group {
+                segments.push(name);
+            }
+            scan(&path, segments, app_router, out)?;
+            if !group {
+                segments.pop();
+            }
+            continue;
+        }
+        let Some((stem, ext)) = name.rsplit_once('.') else {
+            continue;
+        };
+        if !PAGE_EXTENSIONS.contains(&ext) {
+            continue;
+        }
+        let route = if app_router {
+            if stem != "page" {
+                continue;
+            }
+            format!("/{}", segments.join("/"))
+        } else {
+            if stem.starts_with('_') || matches!(stem, "404" | "500") {
+                continue;
+            }
+            let mut parts = segments.clone();
+            if stem != "index" {
+                parts.push(stem.to_string());
+            }
+            format!("/{}", parts.join("/"))
+        };
+        out.push(route);
+    }
+    Ok(())
+}
+
+/// Expand a dynamic route pattern to concrete paths, one per combination of
+/// `params` values. `[[...name]]` also matches the route without the segment.
+fn expand(pattern: &str, params: &BTreeMap<String, Vec<String>>) -> Result<Vec<String>> {
+    let mut paths = vec![String::new()];
+    for segment in pattern.split('/').filter(|s| !s.is_empty()) {
+        let name = segment
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .trim_start_matches("...");
+        let values: Vec<Option<&str>> = if segment.starts_with('[') {
+            let values = params.get(name).with_context(|| {
+                format!("params for Next.js route \"{pattern}\" are missing \"{name}\"")
+            })?;
+            let mut values: Vec<Option<&str>> = values.iter().map(|v| Some(v.as_str())).collect();
+            if segment.starts_with("[[") {
+                values.insert(0, None);
+            }
+            values
+        } else {
+            vec![Some(segment)]
+        };
+        paths = paths
+            .iter()
+            .flat_map(|prefix| {
+                values.iter().map(move |value| match value {
+                    Some(v) => format!("{prefix}/{}", v.trim_matches('/')),
+                    None => prefix.clone(),
+                })
+            })
+            .collect();
+    }
+    Ok(paths
+        .into_iter()
+        .map(|p| if p.is_empty() { "/".to_string() } else { p })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expands_dynamic_segments() {
+        let mut params = BTreeMap::new();
+        params.insert("lang".to_string(), vec!["en".to_string(), "de".to_string()]);
+        params.insert("slug".to_string(), vec!["a/b".to_string()]);
+        assert_eq!(
+            expand("/[lang]/docs/[...slug]", &params).unwrap(),
+            ["/en/docs/a/b", "/de/docs/a/b"]
+        );
+        assert_eq!(
+            expand("/docs/[[...slug]]", &params).unwrap(),
+            ["/docs", "/docs/a/b"]
+        );
+        assert!(expand("/blog/[id]", &params).is_err());
+    }
+}
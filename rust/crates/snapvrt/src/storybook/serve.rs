@@ -0,0 +1,213 @@
+use std::convert::Infallible;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use http_body_util::Full;
+use hyper::body::{Bytes, Incoming};
+use hyper::header::{CACHE_CONTROL, CONTENT_TYPE, HeaderValue};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+use tracing::{debug, trace, warn};
+
+/// Pause after a failed `accept` (e.g. out of file descriptors), so the
+/// loop waits for connections to close instead of spinning.
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Local directory named by a source URL without a scheme
+/// (e.g. `storybook-static`), if it exists.
+pub fn static_dir(url: &str) -> Option<PathBuf> {
+    if url.contains("://") {
+        return None;
+    }
+    let path = PathBuf::from(url);
+    path.is_dir().then_some(path)
+}
+
+/// Minimal static file server for a built Storybook (`storybook-static/`).
+///
+/// Serves GET/HEAD over HTTP/1.1 (hyper) on an ephemeral port. Stops when
+/// dropped.
+pub struct StaticServer {
+    url: String,
+    task: JoinHandle<()>,
+}
+
+impl StaticServer {
+    /// Serve `dir` on an ephemeral port. With `public`, listen on all
+    /// interfaces so Chrome in Docker can reach it through the host IP (the
+    /// `localhost` URL is rewritten like any other).
+    pub async fn start(dir: &Path, public: bool) -> Result<Self> {
+        let root = dir
+            .canonicalize()
+            .with_context(|| format!("Failed to resolve {}", dir.display()))?;
+        let bind = if public { "0.0.0.0:0" } else { "127.0.0.1:0" };
+        let listener = TcpListener::bind(bind)
+            .await
+            .context("Failed to start static server")?;
+        let port = listener.local_addr()?.port();
+        debug!(root = %root.display(), port, "serving static Storybook");
+
+        let root = Arc::new(root);
+        let task = tokio::spawn(async move {
+            loop {
+                let stream = match listener.accept().await {
+                    Ok((stream, _)) => stream,
+                    Err(e) => {
+                        warn!(error = %e, "static server failed to accept a connection");
+                        tokio::time::sleep(ACCEPT_BACKOFF).await;
+                        continue;
+                    }
+                };
+                let root = root.clone();
+                let service = service_fn(move |request| {
+                    let root = root.clone();
+                    async move { Ok::<_, Infallible>(respond(request, &root).await) }
+                });
+                tokio::spawn(async move {
+                    if let Err(e) = http1::Builder::new()
+                        .serve_connection(TokioIo::new(stream), service)
+                        .await
+                    {
+                        trace!(error = %e, "static server connection ended");
+                    }
+                });
+            }
+        });
+
+        Ok(Self {
+            url: format!("http://localhost:{port}"),
+            task,
+        })
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+}
+
+impl Drop for StaticServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Answer one request with the file it names.
+async fn respond(request: Request<Incoming>, root: &Path) -> Response<Full<Bytes>> {
+    let method = request.method();
+    let target = request.uri().path();
+    let (status, content_type, body) = match *method {
+        Method::GET | Method::HEAD => match resolve(root, target) {
+            Some(path) => match tokio::fs::read(&path).await {
+                Ok(body) => (StatusCode::OK, content_type(&path), body),
+                Err(_) => (StatusCode::NOT_FOUND, "text/plain", b"Not Found".to_vec()),
+            },
+            None => (StatusCode::NOT_FOUND, "text/plain", b"Not Found".to_vec()),
+        },
+        _ => (
+            StatusCode::METHOD_NOT_ALLOWED,
+            "text/plain",
+            b"Method Not Allowed".to_vec(),
+        ),
+    };
+    trace!(%method, target, %status, "static request");
+
+    // hyper sets Content-Length, and leaves the body out for HEAD.
+    let mut response = Response::new(Full::new(Bytes::from(body)));
+    *response.status_mut() = status;
+    let headers = response.headers_mut();
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+    headers.insert(CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+    response
+}
+
+/// File for a request target under `root`: query stripped, percent-decoded,
+/// `index.html` for directories. `None` for paths escaping `root`.
+fn resolve(root: &Path, target: &str) -> Option<PathBuf> {
+    let path = target.split(['?', '#']).next().unwrap_or("/");
+    let path = percent_decode(path)?;
+    let mut file = root.to_path_buf();
+    for segment in path.split('/').filter(|s| !s.is_empty()) {
+        if segment == ".." || segment == "." || segment.contains('\\') {
+            return None;
+        }
+        file.push(segment);
+    }
+    if file.is_dir() {
+        file.push("index.html");
+    }
+    Some(file)
+}
+
+fn percent_decode(s: &str) -> Option<String> {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+            out.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(out).ok()
+}
+
+fn content_type(path: &Path) -> &'static str {
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    match ext.as_str() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "js" | "mjs" | "cjs" => "text/javascript; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "json" | "map" => "application/json",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "ico" => "image/x-icon",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "wasm" => "application/wasm",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        "txt" => "text/plain; charset=utf-8",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_targets_inside_root() {
+        let root = Path::new("/srv/storybook-static");
+        assert_eq!(
+            resolve(root, "/iframe.html?id=button--primary"),
+            Some(root.join("iframe.html"))
+        );
+        assert_eq!(
+            resolve(root, "/assets/My%20Font.woff2"),
+            Some(root.join("assets/My Font.woff2"))
+        );
+        assert_eq!(resolve(root, "/../etc/passwd"), None);
+        assert_eq!(resolve(root, "/%2e%2e/etc/passwd"), None);
+        assert_eq!(content_type(Path::new("a/b.WOFF2")), "font/woff2");
+    }
+}
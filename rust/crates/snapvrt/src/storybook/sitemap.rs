@@ -0,0 +1,212 @@
+use std::collections::HashSet;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use super::Story;
+use crate::config::matches_pattern;
+
+/// Nested `<sitemapindex>` levels followed before giving up.
+const MAX_INDEX_NESTING: u32 = 3;
+
+/// Page selection for `type = "sitemap"` and `type = "nextjs"` sources.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PageOptions {
+    /// Max URL path depth (`/` is 0, `/docs/intro` is 2).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub depth: Option<usize>,
+    /// Max pages captured, in sitemap order after filtering.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+    /// Only pages whose path matches one of these (`*` wildcards).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
+    /// Skip pages whose path matches one of these (`*` wildcards).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude: Vec<String>,
+}
+
+impl PageOptions {
+    fn accepts(&self, path: &str) -> bool {
+        let depth = path.split('/').filter(|s| !s.is_empty()).count();
+        self.depth.is_none_or(|max| depth <= max)
+            && (self.include.is_empty() || self.include.iter().any(|p| matches_pattern(p, path)))
+            && !self.exclude.iter().any(|p| matches_pattern(p, path))
+    }
+}
+
+/// Sitemap URL for a source `url`: used as-is when it names an `.xml` file,
+/// otherwise `<url>/sitemap.xml`.
+fn sitemap_url(url: &str) -> String {
+    if url.ends_with(".xml") {
+        url.to_string()
+    } else {
+        format!("{url}/sitemap.xml")
+    }
+}
+
+/// Fetch the sitemap (following sitemap indexes) and turn each selected page
+/// into a `Story`.
+pub(super) async fn discover(
+    client: &reqwest::Client,
+    url: &str,
+    options: &PageOptions,
+) -> Result<Vec<Story>> {
+    let mut pages = Vec::new();
+    let mut queue = vec![(sitemap_url(url), 0)];
+    while let Some((sitemap, nesting)) = queue.pop() {
+        let body = client
+            .get(&sitemap)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .with_context(|| format!("Failed to fetch {sitemap}"))?
+            .text()
+            .await
+            .with_context(|| format!("Failed to read {sitemap}"))?;
+        let locs = extract_locs(&body);
+        if body.contains("<sitemapindex") {
+            if nesting >= MAX_INDEX_NESTING {
+                warn!(sitemap, "sitemap indexes nested too deeply, skipping");
+                continue;
+            }
+            debug!(sitemap, children = locs.len(), "sitemap index");
+            // Reversed so children are fetched in document order.
+            queue.extend(locs.into_iter().rev().map(|loc| (loc, nesting + 1)));
+        } else {
+            pages.extend(locs);
+        }
+    }
+
+    Ok(select_pages(&pages, options))
+}
+
+/// Filter page URLs by `options` and turn them into stories, in order.
+pub(super) fn select_pages(pages: &[String], options: &PageOptions) -> Vec<Story> {
+    let mut seen = HashSet::new();
+    pages
+        .iter()
+        .filter(|page| options.accepts(page_path(page)))
+        .filter_map(|page| {
+            let story = page_story(page);
+            if seen.insert(story.id.clone()) {
+                Some(story)
+            } else {
+                warn!(page, id = %story.id, "page maps to a duplicate ID, skipping");
+                None
+            }
+        })
+        .take(options.limit.unwrap_or(usize::MAX))
+        .collect()
+}
+
+/// `<loc>` values, in document order, with XML entities decoded.
+fn extract_locs(xml: &str) -> Vec<String> {
+    let mut locs = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find("<loc>") {
+        let after = &rest[start + 5..];
+        let Some(end) = after.find("</loc>") else {
+            break;
+        };
+        let raw = after[..end].trim();
+        let raw = raw
+            .strip_prefix("<![CDATA[")
+            .and_then(|s| s.strip_suffix("]]>"))
+            .unwrap_or(raw);
+        locs.push(
+            raw.replace("&lt;", "<")
+                .replace("&gt;", ">")
+                .replace("&quot;", "\"")
+                .replace("&apos;", "'")
+                .replace("&amp;", "&"),
+        );
+        rest = &after[end + 6..];
+    }
+    locs
+}
+
+/// Path and query of a page URL (`/docs/intro?tab=2`).
+fn page_path(url: &str) -> &str {
+    let after_scheme = url.split_once("://").map_or(url, |(_, rest)| rest);
+    after_scheme.find('/').map_or("/", |i| &after_scheme[i..])
+}
+
+/// A page as a story: title is the host, name the path (`index` for `/`),
+/// ID the path with `/` as `-`.
+fn page_story(url: &str) -> Story {
+    let after_scheme = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let host = after_scheme.split('/').next().unwrap_or_default();
+    // Names become store paths: no empty, `.` or `..` segments.
+    let name = page_path(url)
+        .split('/')
+        .filter(|seg| !seg.is_empty())
+        .map(|seg| match seg {
+            "." | ".." => "_".to_string(),
+            _ => seg
+                .chars()
+                .map(|c| match c {
+                    'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '-' | '_' => c,
+                    _ => '_',
+                })
+                .collect(),
+        })
+        .collect::<Vec<String>>()
+        .join("/");
+    let name = if name.is_empty() {
+        "index".to_string()
+    } else {
+        name
+    };
+    Story {
+        id: name.replace('/', "-"),
+        name,
+        title: host.replace(':', "_"),
+        tags: Vec::new(),
+        url: Some(url.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_locs_and_maps_pages() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+            <urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+              <url><loc>https://example.com/</loc></url>
+              <url><loc> https://example.com/docs/intro?a=1&amp;b=2 </loc></url>
+            </urlset>"#;
+        let locs = extract_locs(xml);
+        assert_eq!(
+            locs,
+            [
+                "https://example.com/",
+                "https://example.com/docs/intro?a=1&b=2"
+            ]
+        );
+        let root = page_story(&locs[0]);
+        assert_eq!(
+            (root.title.as_str(), root.name.as_str()),
+            ("example.com", "index")
+        );
+        let page = page_story(&locs[1]);
+        assert_eq!(page.name, "docs/intro_a_1_b_2");
+        assert_eq!(page.id, "docs-intro_a_1_b_2");
+    }
+
+    #[test]
+    fn options_filter_by_depth_and_patterns() {
+        let options = PageOptions {
+            depth: Some(2),
+            exclude: vec!["/blog/*".into()],
+            ..Default::default()
+        };
+        assert!(options.accepts("/"));
+        assert!(options.accepts("/docs/intro"));
+        assert!(!options.accepts("/docs/guides/setup"));
+        assert!(!options.accepts("/blog/hello"));
+    }
+}
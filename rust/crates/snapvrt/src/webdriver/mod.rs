@@ -0,0 +1,4 @@
+pub mod protocol;
+pub mod session;
+
+pub use self::session::{TAB, WebDriver};
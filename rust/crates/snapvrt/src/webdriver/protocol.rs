@@ -0,0 +1,82 @@
+use anyhow::{Context, Result, bail};
+use serde_json::Value;
+
+/// A started session, from the new-session response.
+#[derive(Debug, PartialEq)]
+pub struct NewSession {
+    pub id: String,
+    /// `browserName/browserVersion` from the returned capabilities.
+    pub browser: String,
+}
+
+impl NewSession {
+    pub fn parse(resp: Value) -> Result<Self> {
+        let value = check("new session", resp)?;
+        let id = value["sessionId"]
+            .as_str()
+            .context("No sessionId in new session response")?
+            .to_string();
+        let caps = &value["capabilities"];
+        let browser = format!(
+            "{}/{}",
+            caps["browserName"].as_str().unwrap_or("unknown"),
+            caps["browserVersion"].as_str().unwrap_or("unknown")
+        );
+        Ok(Self { id, browser })
+    }
+}
+
+/// The `value` of a WebDriver response, or its error.
+pub fn check(what: &str, mut resp: Value) -> Result<Value> {
+    let value = resp["value"].take();
+    if let Some(error) = value["error"].as_str() {
+        let message = value["message"].as_str().unwrap_or_default();
+        bail!("WebDriver {what}: {error}: {message}");
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn new_session_reads_id_and_browser() {
+        let resp = json!({ "value": {
+            "sessionId": "4f1c",
+            "capabilities": { "browserName": "firefox", "browserVersion": "128.0" },
+        }});
+        assert_eq!(
+            NewSession::parse(resp).unwrap(),
+            NewSession {
+                id: "4f1c".to_string(),
+                browser: "firefox/128.0".to_string(),
+            }
+        );
+
+        let bare = json!({ "value": { "sessionId": "4f1c", "capabilities": {} } });
+        assert_eq!(NewSession::parse(bare).unwrap().browser, "unknown/unknown");
+        assert!(NewSession::parse(json!({ "value": {} })).is_err());
+    }
+
+    #[test]
+    fn error_envelope_becomes_an_error() {
+        let resp = json!({ "value": {
+            "error": "session not created",
+            "message": "No matching capabilities found",
+            "stacktrace": "",
+        }});
+        let err = NewSession::parse(resp).unwrap_err().to_string();
+        assert_eq!(
+            err,
+            "WebDriver new session: session not created: No matching capabilities found"
+        );
+
+        let ok = check("/url", json!({ "value": null })).unwrap();
+        assert!(ok.is_null());
+        let screenshot = check("/screenshot", json!({ "value": "iVBORw0KGgo=" })).unwrap();
+        assert_eq!(screenshot, "iVBORw0KGgo=");
+    }
+}
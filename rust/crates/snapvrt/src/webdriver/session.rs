@@ -0,0 +1,208 @@
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use reqwest::Method;
+use serde_json::{Value, json};
+use tracing::debug;
+
+use super::protocol::{NewSession, check};
+
+/// How long a Selenium Grid may queue a new-session request before a node
+/// picks it up.
+const NEW_SESSION_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Timeout for every other WebDriver command.
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// WebDriver key code of Tab (`\u{E004}`).
+pub const TAB: char = '\u{E004}';
+
+/// A W3C WebDriver session: one browser window driven over HTTP, e.g. on a
+/// Selenium Grid node. The fallback for environments without access to
+/// Chrome DevTools.
+pub struct WebDriver {
+    http: reqwest::Client,
+    /// `<server>/session/<id>`.
+    session_url: String,
+    id: String,
+    /// `browserName/browserVersion` from the returned capabilities.
+    browser: String,
+}
+
+impl WebDriver {
+    /// Start a session on the server at `server_url` (`http://grid:4444`,
+    /// or `.../wd/hub` for older servers) with `capabilities` as
+    /// `alwaysMatch`.
+    pub async fn start(server_url: &str, capabilities: Value) -> Result<Self> {
+        let http = reqwest::Client::new();
+        let server = server_url.trim_end_matches('/');
+        let url = format!("{server}/session");
+        debug!(url = %url, "starting WebDriver session");
+        let resp: Value = http
+            .post(&url)
+            .json(&json!({ "capabilities": { "alwaysMatch": capabilities } }))
+            .timeout(NEW_SESSION_TIMEOUT)
+            .send()
+            .await
+            .with_context(|| format!("Failed to reach WebDriver server at {server}"))?
+            .json()
+            .await
+            .context("Failed to parse new session response")?;
+        let NewSession { id, browser } = NewSession::parse(resp)?;
+        debug!(session_id = %id, browser = %browser, "WebDriver session started");
+        Ok(Self {
+            http,
+            session_url: format!("{server}/session/{id}"),
+            id,
+            browser,
+        })
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Browser product, e.g. `chrome/139.0.7258.138`.
+    pub fn browser(&self) -> &str {
+        &self.browser
+    }
+
+    /// Send a command to this session and return its `value`.
+    async fn command(&self, method: Method, path: &str, body: Option<Value>) -> Result<Value> {
+        let url = format!("{}{path}", self.session_url);
+        let mut request = self
+            .http
+            .request(method.clone(), &url)
+            .timeout(COMMAND_TIMEOUT);
+        // POST commands need a JSON body, even an empty one.
+        if method == Method::POST {
+            request = request.json(&body.unwrap_or_else(|| json!({})));
+        }
+        let resp: Value = request
+            .send()
+            .await
+            .with_context(|| format!("WebDriver {method} {path} failed"))?
+            .json()
+            .await
+            .with_context(|| format!("Failed to parse WebDriver {path} response"))?;
+        check(if path.is_empty() { "session" } else { path }, resp)
+    }
+
+    /// Limit how long scripts and page loads may take.
+    pub async fn set_timeouts(&self, script: Duration, page_load: Duration) -> Result<()> {
+        self.command(
+            Method::POST,
+            "/timeouts",
+            Some(json!({
+                "script": script.as_millis() as u64,
+                "pageLoad": page_load.as_millis() as u64,
+            })),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Navigate and wait for the load event.
+    pub async fn navigate(&self, url: &str) -> Result<()> {
+        self.command(Method::POST, "/url", Some(json!({ "url": url })))
+            .await
+            .with_context(|| format!("Failed to navigate to {url}"))?;
+        Ok(())
+    }
+
+    /// Run a script body (`return ...;`) and return its result.
+    pub async fn execute(&self, script: &str) -> Result<Value> {
+        self.command(
+            Method::POST,
+            "/execute/sync",
+            Some(json!({ "script": script, "args": [] })),
+        )
+        .await
+    }
+
+    /// Evaluate a JS expression, awaiting it when it is a promise, and
+    /// return its value.
+    pub async fn eval(&self, expression: &str) -> Result<Value> {
+        let script = format!(
+            "const done = arguments[arguments.length - 1];\n\
+             Promise.resolve().then(() => ({expression})).then(\n\
+             (value) => done({{ value }}),\n\
+             (e) => done({{ error: String((e && e.stack) || e) }}));"
+        );
+        let result = self
+            .command(
+                Method::POST,
+                "/execute/async",
+                Some(json!({ "script": script, "args": [] })),
+            )
+            .await
+            .context("JS evaluation failed")?;
+        if let Some(error) = result["error"].as_str() {
+            bail!("JS exception: {error}");
+        }
+        Ok(result["value"].clone())
+    }
+
+    /// Resize the window so the viewport (not the window) is `width` x
+    /// `height` CSS pixels, correcting for browser chrome.
+    pub async fn set_viewport(&self, width: u32, height: u32) -> Result<()> {
+        let frame = self
+            .execute(
+                "return [window.outerWidth - window.innerWidth, \
+                 window.outerHeight - window.innerHeight];",
+            )
+            .await?;
+        let extra = |i: usize| frame[i].as_f64().unwrap_or(0.0).max(0.0) as u32;
+        self.command(
+            Method::POST,
+            "/window/rect",
+            Some(json!({ "width": width + extra(0), "height": height + extra(1) })),
+        )
+        .await
+        .context("Failed to resize window")?;
+        Ok(())
+    }
+
+    /// Press and release `key` (a character, or a WebDriver key code like
+    /// [`TAB`]).
+    pub async fn press_key(&self, key: char) -> Result<()> {
+        let key = key.to_string();
+        self.command(
+            Method::POST,
+            "/actions",
+            Some(json!({
+                "actions": [{
+                    "type": "key",
+                    "id": "keyboard",
+                    "actions": [
+                        { "type": "keyDown", "value": key },
+                        { "type": "keyUp", "value": key },
+                    ],
+                }],
+            })),
+        )
+        .await
+        .context("Failed to press key")?;
+        Ok(())
+    }
+
+    /// PNG screenshot of the viewport.
+    pub async fn screenshot(&self) -> Result<Vec<u8>> {
+        let data = self
+            .command(Method::GET, "/screenshot", None)
+            .await
+            .context("Failed to capture screenshot")?;
+        let b64 = data.as_str().context("No screenshot data in response")?;
+        use base64::Engine;
+        base64::engine::general_purpose::STANDARD
+            .decode(b64)
+            .context("Failed to decode base64 screenshot")
+    }
+
+    /// End the session, closing the browser.
+    pub async fn quit(self) -> Result<()> {
+        self.command(Method::DELETE, "", None).await?;
+        debug!(session_id = %self.id, "WebDriver session ended");
+        Ok(())
+    }
+}